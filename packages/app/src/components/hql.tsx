@@ -176,14 +176,20 @@ export const HQL = (props: HQLProps) => {
     });
     setHqlStore("showResults", true);
 
+    // The tab may be edited while the query runs; the snapshot records what was run
+    const params = currentTab.params;
     const startTime = performance.now();
     try {
-      const result: any = await invoke("execute_dynamic_hql", {
+      const { result }: { result: any } = await invoke("execute_dynamic_hql", {
         url: getConnectionUrl(activeConnection()),
         code: codeToProcess,
-        params: currentTab.params,
+        params,
       });
 
+      invoke("snapshot_worksheet", { worksheetId: targetTabId, code: codeToProcess, params, reason: "execute" }).catch((e) =>
+        console.warn("Failed to snapshot worksheet:", e)
+      );
+
       const endTime = performance.now();
       const duration = Math.round(endTime - startTime);
       const tableData = extractTableData(result);
@@ -1,5 +1,6 @@
 import { createSignal, onMount, Show } from "solid-js";
 import { Sun, Moon, Monitor, Settings, X, Check } from "lucide-solid";
+import { invoke } from "@tauri-apps/api/core";
 
 export type Theme = "light" | "dark" | "system";
 
@@ -34,6 +35,9 @@ const applyTheme = (theme: Theme) => {
   }
 
   localStorage.setItem("theme", theme);
+
+  // Keep native title bar, window controls and material in sync with the CSS theme
+  invoke("set_window_appearance", { appearance: { theme } }).catch((err) => console.error("Failed to update window appearance", err));
 };
 
 // Listen for system theme changes
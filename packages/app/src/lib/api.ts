@@ -417,13 +417,13 @@ export class HelixApi {
 
     const requestPromise = (async () => {
       try {
-        const res = await invoke<any>("execute_dynamic_hql", {
+        const res = await invoke<{ result: any }>("execute_dynamic_hql", {
           url: this.baseUrl,
           code: hql,
           params,
           apiKey: this.apiKey,
         });
-        return res;
+        return res.result;
       } catch (e) {
         console.error("HQL execution failed:", e);
         throw e;
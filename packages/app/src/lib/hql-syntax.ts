@@ -41,6 +41,7 @@ export const HQL_TRAVERSALS = [
   "RANGE",
   "COUNT",
   "FIRST",
+  "DEDUP",
   "AGGREGATE_BY",
   "GROUP_BY",
   "ID",
@@ -133,6 +134,7 @@ const FUNCTION_SET = new Set(
     "UpsertV",
     "COUNT",
     "FIRST",
+    "DEDUP",
     "AGGREGATE_BY",
     "GROUP_BY",
   ].map((s) => s.toUpperCase())
@@ -1,5 +1,6 @@
 import { createStore } from "solid-js/store";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export type ConnectionType = "local" | "cloud";
 
@@ -12,12 +13,21 @@ export interface ConnectionInfo {
   cloudHost?: string;
   apiKey: string;
   localPath?: string;
+  /** Id of the sidebar group the connection is listed under. */
+  group?: string;
+}
+
+export interface ConnectionGroup {
+  id: string;
+  name: string;
+  collapsed: boolean;
 }
 
 export const CLOUD_URL = "https://cloud.helix-db.com";
 
 interface ConnectionState {
   connections: ConnectionInfo[];
+  groups: ConnectionGroup[];
   activeConnectionId: string | null;
   editingId: string | null;
 }
@@ -26,6 +36,7 @@ const isTauri = () => (window as any).__TAURI_INTERNALS__ !== undefined;
 
 const initialState: ConnectionState = {
   connections: [],
+  groups: [],
   activeConnectionId: null,
   editingId: null,
 };
@@ -38,17 +49,31 @@ if (isTauri()) {
       if (saved && saved.connections) {
         setConnectionStore({
           connections: saved.connections,
-          activeConnectionId: null,
-          editingId: saved.connections[0]?.id || null,
+          groups: saved.groups || [],
+          activeConnectionId: saved.current ?? null,
+          editingId: saved.current || saved.connections[0]?.id || null,
         });
       }
     })
     .catch((err) => console.error("Failed to load connections from Tauri", err));
+
+  // Switching config profiles replaces the whole connection list
+  listen<{ profile: string; config: any }>("config-profile-changed", (event) => {
+    const saved = event.payload.config;
+    setConnectionStore({
+      connections: saved.connections || [],
+      groups: saved.groups || [],
+      activeConnectionId: saved.current ?? null,
+      editingId: saved.current || saved.connections?.[0]?.id || null,
+    });
+  });
 }
 
 export const saveConnections = () => {
   const data = {
     connections: connectionStore.connections,
+    groups: connectionStore.groups,
+    current: connectionStore.activeConnectionId,
   };
 
   if (isTauri()) {
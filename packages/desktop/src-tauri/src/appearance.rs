@@ -0,0 +1,119 @@
+use tauri::window::Color;
+use tauri::{Theme, WebviewWindow};
+
+/// Solid backgrounds used where no translucent material is available.
+/// Dark matches `backgroundColor` in tauri.conf.json.
+const DARK_BACKGROUND: Color = Color(0x2a, 0x2a, 0x2a, 0xff);
+const LIGHT_BACKGROUND: Color = Color(0xf5, 0xf5, 0xf5, 0xff);
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowAppearance {
+    /// "light", "dark" or "system".
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// Use the platform's translucent material where supported.
+    #[serde(default = "default_true")]
+    pub vibrancy: bool,
+}
+
+fn default_theme() -> String {
+    "system".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for WindowAppearance {
+    fn default() -> Self {
+        Self { theme: default_theme(), vibrancy: true }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct AppliedAppearance {
+    /// The theme the window ended up with after resolving "system".
+    pub theme: String,
+    /// "vibrancy" (macOS), "mica" or "acrylic" (Windows), or "none".
+    pub effect: String,
+}
+
+fn requested_theme(theme: &str) -> Option<Theme> {
+    match theme {
+        "light" => Some(Theme::Light),
+        "dark" => Some(Theme::Dark),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_material(window: &WebviewWindow, _dark: bool) -> Result<&'static str, String> {
+    use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
+    apply_vibrancy(window, NSVisualEffectMaterial::Sidebar, None, None)
+        .map(|_| "vibrancy")
+        .map_err(|e| e.to_string())
+}
+
+/// Mica needs Windows 11; acrylic covers Windows 10.
+#[cfg(target_os = "windows")]
+fn apply_material(window: &WebviewWindow, dark: bool) -> Result<&'static str, String> {
+    use window_vibrancy::{apply_acrylic, apply_mica};
+    apply_mica(window, Some(dark))
+        .map(|_| "mica")
+        .or_else(|_| {
+            let tint = if dark { (0x2a, 0x2a, 0x2a, 0x80) } else { (0xf5, 0xf5, 0xf5, 0x80) };
+            apply_acrylic(window, Some(tint)).map(|_| "acrylic")
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Linux compositors expose no material API, so the window keeps a solid background.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn apply_material(_window: &WebviewWindow, _dark: bool) -> Result<&'static str, String> {
+    Err("Window materials are not supported on this platform".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn clear_material(window: &WebviewWindow) {
+    let _ = window_vibrancy::clear_vibrancy(window);
+}
+
+#[cfg(target_os = "windows")]
+fn clear_material(window: &WebviewWindow) {
+    let _ = window_vibrancy::clear_mica(window);
+    let _ = window_vibrancy::clear_acrylic(window);
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn clear_material(_window: &WebviewWindow) {}
+
+/// Applies theme and material to the native window chrome (title bar, controls,
+/// background), falling back to a solid theme color when no material applies.
+pub fn apply_appearance(window: &WebviewWindow, appearance: &WindowAppearance) -> Result<AppliedAppearance, String> {
+    window.set_theme(requested_theme(&appearance.theme)).map_err(|e| e.to_string())?;
+    let dark = window.theme().map(|t| t == Theme::Dark).unwrap_or(true);
+
+    let effect = if appearance.vibrancy {
+        match apply_material(window, dark) {
+            Ok(effect) => effect,
+            Err(e) => {
+                println!(">>> [Appearance] No window material: {}", e);
+                "none"
+            }
+        }
+    } else {
+        clear_material(window);
+        "none"
+    };
+
+    if effect == "none" {
+        let background = if dark { DARK_BACKGROUND } else { LIGHT_BACKGROUND };
+        window.set_background_color(Some(background)).map_err(|e| e.to_string())?;
+    }
+
+    Ok(AppliedAppearance {
+        theme: if dark { "dark" } else { "light" }.to_string(),
+        effect: effect.to_string(),
+    })
+}
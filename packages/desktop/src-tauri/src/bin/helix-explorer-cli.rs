@@ -0,0 +1,3 @@
+fn main() {
+    helixdb_explorer_lib::cli::main()
+}
@@ -0,0 +1,193 @@
+use crate::hql::tool_args::ToolArgs;
+use crate::gateway_auth::GatewayAuth;
+use crate::network_trace::SendTraced;
+
+/// MCP endpoints exposed by the gateway (`helix-db/src/helix_gateway/mcp/mcp.rs`).
+const MCP_ENDPOINTS: &[&str] = &[
+    "tool_call",
+    "collect",
+    "aggregate_by",
+    "group_by",
+    "schema_resource",
+    "search_keyword",
+    "search_vector",
+    "search_vector_text",
+];
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct ServerCapabilities {
+    pub version: Option<String>,
+    pub mcp_enabled: bool,
+    pub mcp_tools: Vec<String>,
+    pub bm25_enabled: bool,
+    pub vectors_enabled: bool,
+    pub write_api: bool,
+    /// False for an entry that only holds what executions learned before a probe ran.
+    #[serde(skip)]
+    pub probed: bool,
+    /// Whether `FilterItems` on `id` narrows a start step, learned by the first
+    /// execution that filters by id.
+    #[serde(skip)]
+    pub filters_ids: Option<bool>,
+}
+
+/// Capabilities per gateway URL, shared by the commands and the executors they start.
+pub type CapabilityCache = std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, ServerCapabilities>>>;
+
+/// The probed capabilities of `url`, if a probe has run.
+pub fn probed(cache: &CapabilityCache, url: &str) -> Option<ServerCapabilities> {
+    cache.lock().unwrap().get(url).filter(|caps| caps.probed).cloned()
+}
+
+impl ServerCapabilities {
+    pub fn has_tool(&self, endpoint: &str) -> bool {
+        self.mcp_tools.iter().any(|t| t == endpoint)
+    }
+
+    /// Rejects tools the connected server cannot run, instead of letting them 404.
+    pub fn ensure_supported(&self, tool: &ToolArgs) -> Result<(), String> {
+        if !self.mcp_enabled {
+            return Err(crate::i18n::t("hql.mcp_disabled", &[]));
+        }
+        match tool {
+            ToolArgs::SearchKeyword { .. } if !self.bm25_enabled => {
+                Err(crate::i18n::t("hql.bm25_unavailable", &[]))
+            }
+            ToolArgs::SearchVec { .. } if !self.vectors_enabled => {
+                Err(crate::i18n::t("hql.vector_unavailable", &[]))
+            }
+            ToolArgs::SearchVecText { .. } if !self.has_tool("search_vector_text") => {
+                Err(crate::i18n::t("hql.vector_text_unavailable", &[]))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub async fn probe_version(client: &reqwest::Client, url: &str, auth: &GatewayAuth) -> Option<String> {
+    let resp = auth.apply(client.get(format!("{}/version", url))).send_traced().await.ok()?;
+    let from_header = resp.headers().get("x-helix-version")
+        .or_else(|| resp.headers().get(reqwest::header::SERVER))
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    if !resp.status().is_success() {
+        return from_header;
+    }
+    let body = resp.text().await.ok()?;
+    let parsed = serde_json::from_str::<serde_json::Value>(&body).ok();
+    match parsed {
+        Some(serde_json::Value::String(v)) => Some(v),
+        Some(obj) => obj.get("version").and_then(|v| v.as_str()).map(|v| v.to_string()).or(from_header),
+        None if !body.trim().is_empty() => Some(body.trim().to_string()),
+        None => from_header,
+    }
+}
+
+/// Closes a connection opened only to probe the gateway, so probes do not leave
+/// sessions behind on the server.
+pub(crate) async fn close_probe_connection(client: &reqwest::Client, url: &str, auth: &GatewayAuth, connection_id: &str) {
+    let req = client.post(format!("{}/mcp/close", url))
+        .timeout(std::time::Duration::from_secs(5))
+        .json(&serde_json::json!({ "connection_id": connection_id }));
+    if let Err(e) = auth.apply(req).send_traced().await {
+        println!(">>> [Capabilities] Failed to close probe connection {}: {}", connection_id, e);
+    }
+}
+
+/// A route is considered present unless the gateway answers with a router-level 404.
+/// MCP handlers reply with other statuses (400/500) for the deliberately empty payload.
+async fn probe_endpoint(client: &reqwest::Client, url: &str, endpoint: &str, connection_id: &str, auth: &GatewayAuth) -> bool {
+    let req = client.post(format!("{}/mcp/{}", url, endpoint))
+        .json(&serde_json::json!({ "connection_id": connection_id }));
+    match auth.apply(req).send_traced().await {
+        Ok(resp) => {
+            if resp.status() != reqwest::StatusCode::NOT_FOUND {
+                return true;
+            }
+            let body = resp.text().await.unwrap_or_default().to_lowercase();
+            body.contains("connection")
+        }
+        Err(_) => false,
+    }
+}
+
+pub async fn probe_capabilities(client: &reqwest::Client, url: &str, auth: &GatewayAuth) -> Result<ServerCapabilities, String> {
+    let mut caps = ServerCapabilities {
+        version: probe_version(client, url, auth).await,
+        probed: true,
+        ..Default::default()
+    };
+
+    let init_resp = auth.apply(client.post(format!("{}/mcp/init", url))).send_traced().await
+        .map_err(|e| crate::commands::network::map_reqwest_error(e, "Capability probe failed"))?;
+
+    if !init_resp.status().is_success() {
+        // Gateway is reachable but MCP is off; nothing shows that compiled queries exist
+        return Ok(caps);
+    }
+
+    let init_body = init_resp.text().await.map_err(|e| format!("Failed to read init body: {}", e))?;
+    let connection_id: String = serde_json::from_str(&init_body)
+        .map_err(|e| format!("Failed to parse connection_id from '{}': {}", init_body, e))?;
+    caps.mcp_enabled = true;
+
+    for endpoint in MCP_ENDPOINTS {
+        if probe_endpoint(client, url, endpoint, &connection_id, auth).await {
+            caps.mcp_tools.push(endpoint.to_string());
+        }
+    }
+    caps.bm25_enabled = caps.has_tool("search_keyword");
+    caps.vectors_enabled = caps.has_tool("search_vector");
+
+    // Writes are only possible through compiled endpoints, so the write API is
+    // considered available when the schema advertises deployed queries.
+    let schema_req = client.post(format!("{}/mcp/schema_resource", url))
+        .json(&serde_json::json!({ "connection_id": connection_id }));
+    if let Ok(resp) = auth.apply(schema_req).send_traced().await {
+        if let Ok(val) = resp.json::<serde_json::Value>().await {
+            let schema = match &val {
+                serde_json::Value::String(s) => serde_json::from_str(s).unwrap_or(serde_json::Value::Null),
+                _ => val,
+            };
+            caps.write_api = schema.get("queries")
+                .and_then(|q| q.as_array())
+                .map(|q| !q.is_empty())
+                .unwrap_or(false);
+        }
+    }
+    close_probe_connection(client, url, auth, &connection_id).await;
+
+    Ok(caps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FakeGateway;
+
+    #[tokio::test]
+    async fn test_probe_closes_its_connection() {
+        let gateway = FakeGateway::start(|path| match path {
+            "/mcp/init" => (200, "\"probe-1\"".to_string()),
+            "/mcp/schema_resource" => (200, r#"{ "queries": [{ "name": "getUser" }] }"#.to_string()),
+            "/mcp/search_keyword" => (404, "Not Found".to_string()),
+            _ => (200, "null".to_string()),
+        }).await;
+        let caps = probe_capabilities(&reqwest::Client::new(), &gateway.url, &GatewayAuth::default()).await.unwrap();
+        assert!(caps.mcp_enabled && caps.write_api);
+        assert!(caps.has_tool("collect") && !caps.bm25_enabled);
+        assert_eq!(gateway.requests().last().map(String::as_str), Some("/mcp/close"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_init_means_no_write_api() {
+        let gateway = FakeGateway::start(|path| match path {
+            "/mcp/init" => (404, "Not Found".to_string()),
+            _ => (200, "null".to_string()),
+        }).await;
+        let caps = probe_capabilities(&reqwest::Client::new(), &gateway.url, &GatewayAuth::default()).await.unwrap();
+        assert!(!caps.mcp_enabled);
+        assert!(!caps.write_api);
+        assert!(!gateway.requests().contains(&"/mcp/close".to_string()));
+    }
+}
@@ -0,0 +1,269 @@
+//! `helix-explorer-cli`: the Explorer's execute, validate, format, sync and export paths
+//! without the GUI, reading the same connections, policy and library from the config
+//! directory.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Matches the `identifier` in tauri.conf.json, which names the app config directory.
+const APP_IDENTIFIER: &str = "com.helixdb.explorer";
+
+const USAGE: &str = "Usage: helix-explorer-cli <command> [options]
+
+Commands:
+  execute <file|->    Run a query; prints the result as JSON
+  export <file|->     Run a query and write the result (--format json|csv, --out <path>)
+  validate <file|->   Check a query for syntax errors
+  format <file|->     Print the formatted query, or rewrite the file with --write
+  sync <status|pull|push>
+                      Sync the saved query library with its git repository
+                      (pull: --take local|remote settles conflicts; push: --message <text>)
+
+Query options:
+  --url <url>         Connection to run against (required for execute and export)
+  --params <json>     Query parameters as a JSON object
+  --api-key <key>     Sent as x-api-key
+  --profile <name>    Translation profile, instead of the built-in one
+
+Global options:
+  --config-dir <dir>  Explorer config directory (default: the app's own)";
+
+/// Options that take no value.
+const FLAGS: &[&str] = &["write", "help"];
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.option(name).ok_or_else(|| format!("Missing --{}", name))
+    }
+
+    fn input(&self) -> Result<&str, String> {
+        self.positional.first().map(String::as_str).ok_or_else(|| format!("{} needs a query file, or - for stdin", self.command))
+    }
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            let (name, inline) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+            if FLAGS.contains(&name) {
+                parsed.flags.push(name.to_string());
+                continue;
+            }
+            let value = inline.or_else(|| args.next()).ok_or_else(|| format!("--{} needs a value", name))?;
+            parsed.options.insert(name.to_string(), value);
+        } else if parsed.command.is_empty() {
+            parsed.command = arg;
+        } else {
+            parsed.positional.push(arg);
+        }
+    }
+    Ok(parsed)
+}
+
+fn config_dir(args: &Args) -> Result<PathBuf, String> {
+    match args.option("config-dir") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => dirs::config_dir()
+            .map(|d| d.join(APP_IDENTIFIER))
+            .ok_or_else(|| "Could not find config directory".to_string()),
+    }
+}
+
+fn read_input(input: &str) -> Result<String, String> {
+    if input == "-" {
+        let mut code = String::new();
+        std::io::stdin().read_to_string(&mut code).map_err(|e| format!("Failed to read stdin: {}", e))?;
+        return Ok(code);
+    }
+    std::fs::read_to_string(input).map_err(|e| format!("Failed to read {}: {}", input, e))
+}
+
+/// 1-based line of a byte offset, for diagnostics.
+fn line_of(code: &str, offset: usize) -> usize {
+    code.get(..offset).unwrap_or(code).matches('\n').count() + 1
+}
+
+/// The connection `url` points at in the active config profile's connections.json.
+fn find_connection(dir: &Path, url: &str) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(crate::config_profiles::connections_file(dir).ok()?).ok()?;
+    crate::policy::connection_in(&crate::config::ConnectionsConfig::parse_resolved(&content).ok()?, Some(url))
+}
+
+async fn execute(args: &Args) -> Result<serde_json::Value, String> {
+    let dir = config_dir(args)?;
+    let url = args.required("url")?.trim_end_matches('/');
+    let code = read_input(args.input()?)?;
+    let params = args.option("params")
+        .map(|p| serde_json::from_str(p).map_err(|e| format!("Invalid --params: {}", e)))
+        .transpose()?;
+
+    let connection = find_connection(&dir, url);
+    let policy = crate::policy::policy_in(&dir, connection.as_ref(), Some(url))?;
+    let profile = crate::hql::profiles::select_profile(&crate::hql::profiles::profiles_in(Some(&dir)), None, args.option("profile"))?;
+
+    let client = crate::connection_clients::ClientSettings::of_connection(connection.as_ref())?.build()?;
+    let run = crate::commands::hql::run_detached(&client, url, &code, params, args.option("api-key").map(String::from), profile, connection.as_ref(), &policy);
+    let timeout = crate::config::connection_timeout(connection.as_ref(), None);
+    tokio::time::timeout(timeout, run).await.map_err(|_| crate::config::timed_out_error(timeout))?
+}
+
+async fn export(args: &Args) -> Result<String, String> {
+    let dir = config_dir(args)?;
+    let url = args.required("url")?;
+    let connection = find_connection(&dir, url);
+    crate::policy::policy_in(&dir, connection.as_ref(), Some(url))?.check_export()?;
+
+    let result = execute(args).await?;
+    let content = match args.option("format").unwrap_or("json") {
+        "json" => serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?,
+        "csv" => crate::local_api::rows_to_csv(&crate::local_api::result_rows(&result)),
+        other => return Err(format!("Unknown export format '{}'", other)),
+    };
+    match args.option("out") {
+        Some(path) => {
+            std::fs::write(path, &content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            Ok(format!("Exported to {}", path))
+        }
+        None => Ok(content),
+    }
+}
+
+async fn validate(args: &Args) -> Result<String, String> {
+    let input = args.input()?;
+    let code = read_input(input)?;
+    let diagnostics = crate::commands::hql::validate_hql(code.clone()).await?;
+    if diagnostics.is_empty() {
+        return Ok(format!("{}: ok", input));
+    }
+    let code = crate::hql::processor::preprocess_hql(&code);
+    let report: Vec<String> = diagnostics.iter()
+        .map(|d| format!("{}:{}: {}: {}", input, line_of(&code, d.from), d.severity, d.message))
+        .collect();
+    Err(report.join("\n"))
+}
+
+fn format_file(args: &Args) -> Result<String, String> {
+    let input = args.input()?;
+    let formatted = crate::commands::hql::format_hql(read_input(input)?)?;
+    if args.flag("write") && input != "-" {
+        std::fs::write(input, &formatted).map_err(|e| format!("Failed to write {}: {}", input, e))?;
+        return Ok(format!("Formatted {}", input));
+    }
+    Ok(formatted)
+}
+
+fn sync(args: &Args) -> Result<serde_json::Value, String> {
+    let dir = config_dir(args)?;
+    let result = match args.input()? {
+        "status" => serde_json::to_value(crate::library_sync::status(&dir)?),
+        "pull" => {
+            let mut result = crate::library_sync::pull(&dir, &Default::default())?;
+            if let (false, Some(take)) = (result.conflicts.is_empty(), args.option("take")) {
+                if take != "local" && take != "remote" {
+                    return Err("--take must be local or remote".to_string());
+                }
+                let resolutions = result.conflicts.iter().map(|c| (c.id.clone(), take.to_string())).collect();
+                result = crate::library_sync::pull(&dir, &resolutions)?;
+            }
+            serde_json::to_value(result)
+        }
+        "push" => serde_json::to_value(crate::library_sync::push(&dir, args.option("message"))?),
+        other => return Err(format!("Unknown sync action '{}'", other)),
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Entry point of the CLI binary. Results go to stdout and errors to stderr; the exit
+/// code is 1 on any error, including validation problems and unsettled conflicts.
+pub fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.command.is_empty() || args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let pretty = |value: serde_json::Value| serde_json::to_string_pretty(&value).map_err(|e| e.to_string());
+    let outcome = runtime.block_on(async {
+        match args.command.as_str() {
+            "execute" => pretty(execute(&args).await?),
+            "export" => export(&args).await,
+            "validate" => validate(&args).await,
+            "format" => format_file(&args),
+            "sync" => {
+                let result = sync(&args)?;
+                let conflicts = result.get("conflicts").and_then(|c| c.as_array()).map_or(0, |c| c.len());
+                let output = pretty(result)?;
+                if conflicts > 0 {
+                    return Err(format!("{}\n{} conflict(s); pull again with --take local or --take remote", output, conflicts));
+                }
+                Ok(output)
+            }
+            other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE)),
+        }
+    });
+    match outcome {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(args("execute q.hx --url http://localhost:6969 --profile=v1 --write")).unwrap();
+        assert_eq!(parsed.command, "execute");
+        assert_eq!(parsed.input(), Ok("q.hx"));
+        assert_eq!(parsed.required("url"), Ok("http://localhost:6969"));
+        assert_eq!(parsed.option("profile"), Some("v1"));
+        assert!(parsed.flag("write"));
+        assert_eq!(parsed.required("api-key"), Err("Missing --api-key".to_string()));
+
+        assert!(parse_args(args("execute q.hx --url")).is_err());
+        assert!(parse_args(args("format")).unwrap().input().is_err());
+        assert_eq!(line_of("a\nb\nc", 4), 3);
+    }
+}
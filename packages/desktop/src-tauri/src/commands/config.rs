@@ -1,11 +1,107 @@
-use crate::config;
+use crate::config::{self, ConnectionsConfig};
+use crate::connection_groups;
+use crate::helix_cli::{self, ImportedConnection};
+use crate::local_api::{self, LocalApiSettings};
+use crate::policy::{self, ConnectionPolicy};
 
 #[tauri::command]
-pub fn load_connection_config(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+pub fn load_connection_config(app: tauri::AppHandle) -> Result<ConnectionsConfig, String> {
     config::load_connection_config(app)
 }
 
+/// Saves what the window edits (connections, groups and the current connection) into
+/// the stored config, which keeps its version and the keys the window does not know.
 #[tauri::command]
 pub fn save_connection_config(app: tauri::AppHandle, config: serde_json::Value) -> Result<(), String> {
-    config::save_connection_config(app, config)
+    config::update_connection_config(&app, |stored| stored.apply_edit(config)).map(|_| ())
+}
+
+/// `default` and the profiles in ~/.helix-explorer/profiles, with which is active.
+#[tauri::command]
+pub fn list_config_profiles(app: tauri::AppHandle) -> Result<Vec<crate::config_profiles::ConfigProfile>, String> {
+    crate::config_profiles::list(&app)
+}
+
+/// Switches to the profile `name` (created empty if new) and returns its config; the
+/// window is also told through `config-profile-changed`.
+#[tauri::command]
+pub fn switch_config_profile(app: tauri::AppHandle, name: String) -> Result<ConnectionsConfig, String> {
+    crate::config_profiles::switch(&app, &name)
+}
+
+/// Adds an empty group after the others. Like the other group commands, returns the saved config.
+#[tauri::command]
+pub fn create_connection_group(app: tauri::AppHandle, name: String) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::create(config, &name).map(|_| ()))
+}
+
+#[tauri::command]
+pub fn rename_connection_group(app: tauri::AppHandle, id: String, name: String) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::rename(config, &id, &name))
+}
+
+#[tauri::command]
+pub fn set_connection_group_collapsed(app: tauri::AppHandle, id: String, collapsed: bool) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::set_collapsed(config, &id, collapsed))
+}
+
+/// Removes a group, leaving its connections ungrouped.
+#[tauri::command]
+pub fn delete_connection_group(app: tauri::AppHandle, id: String) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::delete(config, &id))
+}
+
+#[tauri::command]
+pub fn move_connection_group(app: tauri::AppHandle, id: String, index: usize) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::move_group(config, &id, index))
+}
+
+/// Moves a connection to `index` within `group`, or among the ungrouped ones.
+#[tauri::command]
+pub fn move_connection(app: tauri::AppHandle, id: String, group: Option<String>, index: usize) -> Result<ConnectionsConfig, String> {
+    config::update_connection_config(&app, |config| connection_groups::move_connection(config, &id, group.as_deref(), index))
+}
+
+#[tauri::command]
+pub fn import_from_helix_cli(app: tauri::AppHandle) -> Result<Vec<ImportedConnection>, String> {
+    let existing = config::load_connection_config(app.clone())?;
+    let mut workspaces = Vec::new();
+    if let Ok(path) = config::detect_workspace_path(&app, None) {
+        workspaces.push(std::path::PathBuf::from(path));
+    }
+    Ok(helix_cli::import_from_helix_cli(workspaces, &existing.connections))
+}
+
+/// The policy in force for `url` (or the current connection), so the UI can hide
+/// what it would refuse anyway.
+#[tauri::command]
+pub fn get_workspace_policy(app: tauri::AppHandle, url: Option<String>) -> Result<ConnectionPolicy, String> {
+    policy::effective_policy(&app, url.as_deref())
+}
+
+/// Localhost API settings, including the token scripts must send.
+#[tauri::command]
+pub fn get_local_api_settings(app: tauri::AppHandle) -> Result<LocalApiSettings, String> {
+    local_api::load_settings(&app)
+}
+
+/// Turns the localhost API on or off, or moves it to another port.
+#[tauri::command]
+pub async fn set_local_api_settings(app: tauri::AppHandle, enabled: bool, port: Option<u16>) -> Result<LocalApiSettings, String> {
+    let mut settings = local_api::load_settings(&app)?;
+    settings.enabled = enabled;
+    settings.port = port.unwrap_or(settings.port);
+    local_api::apply(&app, &settings).await?;
+    local_api::save_settings(&app, &settings)?;
+    Ok(settings)
+}
+
+/// Replaces the token, locking out scripts that use the old one.
+#[tauri::command]
+pub async fn regenerate_local_api_token(app: tauri::AppHandle) -> Result<LocalApiSettings, String> {
+    let mut settings = local_api::load_settings(&app)?;
+    settings.token = local_api::new_token();
+    local_api::save_settings(&app, &settings)?;
+    local_api::apply(&app, &settings).await?;
+    Ok(settings)
 }
@@ -1,8 +1,11 @@
 use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
 use helix_db::helixc::parser::types::*;
-use crate::hql::{processor, translator, executor};
-use crate::hql::tool_args::ToolArgs;
-use crate::commands::network::map_reqwest_error;
+use crate::hql::{processor, translator, executor, profiles, progress, compat, math, paging, explain, shape_check};
+use crate::hql::metrics::{count_rows, ExecutionMetrics, ExecutionPath, MetricsRecorder};
+use crate::i18n;
+use tauri::{Emitter, Manager};
+use crate::{environment, policy, redaction};
+use crate::network_trace::SendTraced;
 
 fn expression_to_json(expr: &Expression) -> Option<serde_json::Value> {
     match &expr.expr {
@@ -18,54 +21,159 @@ fn expression_to_json(expr: &Expression) -> Option<serde_json::Value> {
     }
 }
 
-#[tauri::command]
-pub async fn execute_dynamic_hql(
-    state: tauri::State<'_, crate::NetworkState>,
-    url: String, 
-    code: String, 
-    params: Option<serde_json::Value>, 
-    api_key: Option<String>
-) -> Result<serde_json::Value, String> {
-    let client = &state.client;
-    let code = processor::preprocess_hql(&code);
+/// Records one RETURN entry: plain variables are fetched as-is, math calls are
+/// evaluated afterwards, and aliased entries (`{ alias: expr }`) are fetched under
+/// their source variable and renamed at the end.
+fn collect_return<'a>(
+    expr: &'a Expression,
+    alias: Option<&str>,
+    output_order: &mut Vec<String>,
+    return_vars: &mut Vec<String>,
+    computed_returns: &mut Vec<(String, &'a Expression)>,
+    return_aliases: &mut Vec<(String, String)>,
+    variable_assignments: &mut std::collections::HashMap<String, &'a Traversal>,
+) {
+    match (&expr.expr, alias) {
+        (ExpressionType::Identifier(id), None) => {
+            output_order.push(id.clone());
+            return_vars.push(id.clone());
+        }
+        (ExpressionType::Identifier(id), Some(alias)) => {
+            output_order.push(alias.to_string());
+            return_aliases.push((alias.to_string(), id.clone()));
+        }
+        (ExpressionType::MathFunctionCall(_), _) => {
+            let name = alias.map(|a| a.to_string()).unwrap_or_else(|| math::describe(expr));
+            output_order.push(name.clone());
+            computed_returns.push((name, expr));
+        }
+        (ExpressionType::Traversal(t), Some(alias)) => {
+            let source = format!("_return_{}", alias);
+            variable_assignments.insert(source.clone(), &**t);
+            output_order.push(alias.to_string());
+            return_aliases.push((alias.to_string(), source));
+        }
+        (ExpressionType::Traversal(t), None) => {
+            // Run as an anonymous variable keyed by its source text, e.g. `user::Out<Authored>::COUNT`
+            let text = expr.loc.span.split_whitespace().collect::<Vec<_>>().join(" ");
+            let name = if text.is_empty() { format!("_return_{}", return_vars.len()) } else { text };
+            variable_assignments.insert(name.clone(), &**t);
+            output_order.push(name.clone());
+            if !return_vars.contains(&name) {
+                return_vars.push(name);
+            }
+        }
+        _ => {}
+    }
+}
 
+/// Parses worksheet code, wrapping bare statements in a temporary `QUERY`.
+fn parse_dynamic_source(code: &str) -> Result<Source, String> {
     fn try_parse(code: &str) -> Result<Source, String> {
         let content = write_to_temp_file(vec![code]);
         HelixParser::parse_source(&content).map_err(|e| format!("{:?}", e))
     }
 
-    let source = if code.trim().to_uppercase().starts_with("QUERY") {
-        try_parse(&code).map_err(|e| {
+    if code.trim().to_uppercase().starts_with("QUERY") {
+        try_parse(code).map_err(|e| {
             format!("Failed to parse Query: {}\nCode: '{}'", e, code)
-        })?
+        })
     } else {
-        match try_parse(&code) {
-            Ok(s) => s,
+        match try_parse(code) {
+            Ok(s) => Ok(s),
             Err(_) => {
                 let wrapped = format!("QUERY ExplorerTmp() => {}", code);
                 try_parse(&wrapped).map_err(|e| {
                     format!("Failed to parse HQL: {}", e)
-                })?
+                })
             }
         }
-    };
+    }
+}
 
-    if source.queries.len() > 1 {
-        return Err("Multiple queries detected in editor. Please select a specific query to execute, or ensure only one query exists.".to_string());
+/// The type name `compiled_queries` uses for a declared parameter type, e.g. `I64` or
+/// `[String]`. Schema types and objects are not checked.
+fn param_type_name(ty: &FieldType) -> Option<String> {
+    Some(match ty {
+        FieldType::String => "String".to_string(),
+        FieldType::Uuid => "ID".to_string(),
+        FieldType::Boolean => "Boolean".to_string(),
+        FieldType::Date => "Date".to_string(),
+        FieldType::I8 => "I8".to_string(),
+        FieldType::I16 => "I16".to_string(),
+        FieldType::I32 => "I32".to_string(),
+        FieldType::I64 => "I64".to_string(),
+        FieldType::U8 => "U8".to_string(),
+        FieldType::U16 => "U16".to_string(),
+        FieldType::U32 => "U32".to_string(),
+        FieldType::U64 => "U64".to_string(),
+        FieldType::U128 => "U128".to_string(),
+        FieldType::F32 => "F32".to_string(),
+        FieldType::F64 => "F64".to_string(),
+        FieldType::Array(inner) => format!("[{}]", param_type_name(inner)?),
+        _ => return None,
+    })
+}
+
+/// Converts the given parameters to the types the query declares, so `"30"` reaches an
+/// `I64` parameter as 30 on both the compiled and the MCP path. Every parameter that
+/// cannot be converted is reported. Environment references are left for `resolve`.
+fn coerce_params(query: &Query, params: serde_json::Value) -> Result<serde_json::Value, String> {
+    let serde_json::Value::Object(mut given) = params else {
+        return Ok(params);
+    };
+    let mut problems = Vec::new();
+    for param in &query.parameters {
+        let Some(ty) = param_type_name(&param.param_type.1) else {
+            continue;
+        };
+        let name = &param.name.1;
+        let Some(value) = given.get_mut(name) else {
+            continue;
+        };
+        if value.as_str().is_some_and(environment::is_reference) {
+            continue;
+        }
+        match crate::compiled_queries::coerce(&ty, value.take()) {
+            Ok(coerced) => *value = coerced,
+            Err(problem) => problems.push(format!("'{}' {}", name, problem)),
+        }
     }
-    
-    let query = source.queries.first().ok_or_else(|| "No query found in parsed source".to_string())?;
-    let mut params_val = params.unwrap_or(serde_json::json!({}));
+    if problems.is_empty() {
+        Ok(serde_json::Value::Object(given))
+    } else {
+        Err(i18n::t("hql.invalid_params", &[("name", &query.name), ("problems", &problems.join("; "))]))
+    }
+}
+
+/// What a worksheet query returns and where each returned variable comes from.
+struct QueryShape<'a> {
+    variable_assignments: std::collections::HashMap<String, &'a Traversal>,
+    variable_search_tools: std::collections::HashMap<String, translator::SearchCall>,
+    return_vars: Vec<String>,
+    computed_returns: Vec<(String, &'a Expression)>,
+    return_aliases: Vec<(String, String)>, // (alias, source variable)
+    /// Result keys in RETURN order.
+    output_order: Vec<String>,
+    hidden_vars: Vec<String>,
+    has_derived_returns: bool,
+}
 
+/// Walks the statements and RETURN of a read-only query. Literal assignments are
+/// added to `params_val`, which is then resolved against `env`.
+fn query_shape<'a>(query: &'a Query, params_val: &mut serde_json::Value, env: &environment::Environment) -> Result<QueryShape<'a>, String> {
     let mut variable_assignments = std::collections::HashMap::<String, &Traversal>::new();
-    let mut variable_search_tools = std::collections::HashMap::<String, ToolArgs>::new();
+    let mut variable_search_tools = std::collections::HashMap::<String, translator::SearchCall>::new();
     let mut return_vars = Vec::<String>::new();
+    let mut computed_returns = Vec::<(String, &Expression)>::new();
+    let mut return_aliases = Vec::<(String, String)>::new(); // (alias, source variable)
+    let mut output_order = Vec::<String>::new();
 
     for stmt in &query.statements {
         match &stmt.statement {
             StatementType::Assignment(assign) => {
                 if let Some(val) = expression_to_json(&assign.value) {
-                    if let serde_json::Value::Object(map) = &mut params_val {
+                    if let serde_json::Value::Object(map) = params_val {
                         map.insert(assign.variable.clone(), val);
                     }
                 }
@@ -75,37 +183,37 @@ pub async fn execute_dynamic_hql(
                         variable_assignments.insert(assign.variable.clone(), &**t);
                     },
                     ExpressionType::StringLiteral(s) => {
-                        if let serde_json::Value::Object(map) = &mut params_val {
+                        if let serde_json::Value::Object(map) = params_val {
                             map.insert(assign.variable.clone(), serde_json::Value::String(s.clone()));
                         }
                     },
                     ExpressionType::IntegerLiteral(i) => {
-                        if let serde_json::Value::Object(map) = &mut params_val {
+                        if let serde_json::Value::Object(map) = params_val {
                             map.insert(assign.variable.clone(), serde_json::Value::Number((*i).into()));
                         }
                     },
                     ExpressionType::FloatLiteral(f) => {
-                        if let serde_json::Value::Object(map) = &mut params_val {
+                        if let serde_json::Value::Object(map) = params_val {
                             if let Some(n) = serde_json::Number::from_f64(*f) {
                                 map.insert(assign.variable.clone(), serde_json::Value::Number(n));
                             }
                         }
                     },
                     ExpressionType::BooleanLiteral(b) => {
-                        if let serde_json::Value::Object(map) = &mut params_val {
+                        if let serde_json::Value::Object(map) = params_val {
                             map.insert(assign.variable.clone(), serde_json::Value::Bool(*b));
                         }
                     },
                     ExpressionType::AddNode(_) | ExpressionType::AddEdge(_) | ExpressionType::AddVector(_) => {
-                        return Err("Explorer Mode is Read-Only: The underlying MCP protocol does not support writing data. For more details, refer to https://docs.helix-db.com/guides/mcp-guide. To modify data (Add/Update/Delete), please use Migrations or the HTTP API.".to_string());
+                        return Err(i18n::t("hql.read_only", &[]));
                     },
                     ExpressionType::BM25Search(bm25) => {
                         let tool = translator::map_bm25_to_tool(bm25).map_err(|e| e.to_string())?;
-                        variable_search_tools.insert(assign.variable.clone(), tool);
+                        variable_search_tools.insert(assign.variable.clone(), tool.into());
                     },
                     ExpressionType::SearchVector(sv) => {
-                        let tool = translator::map_search_vector_to_tool(sv, &params_val).map_err(|e| e.to_string())?;
-                        variable_search_tools.insert(assign.variable.clone(), tool);
+                        let search = translator::map_search_vector_call(sv, params_val)?;
+                        variable_search_tools.insert(assign.variable.clone(), search);
                     },
                     _ => {}
                 }
@@ -116,39 +224,48 @@ pub async fn execute_dynamic_hql(
                          variable_assignments.insert("_implicit_".to_string(), &**t);
                     },
                     ExpressionType::AddNode(_) | ExpressionType::AddEdge(_) | ExpressionType::AddVector(_) => {
-                        return Err("Explorer Mode is Read-Only: The underlying MCP protocol does not support writing data. For more details, refer to https://docs.helix-db.com/guides/mcp-guide. To modify data (Add/Update/Delete), please use Migrations or the HTTP API.".to_string());
+                        return Err(i18n::t("hql.read_only", &[]));
                     },
                     ExpressionType::BM25Search(bm25) => {
                         let tool = translator::map_bm25_to_tool(bm25).map_err(|e| e.to_string())?;
-                        variable_search_tools.insert("_implicit_".to_string(), tool);
+                        variable_search_tools.insert("_implicit_".to_string(), tool.into());
                     },
                     ExpressionType::SearchVector(sv) => {
-                        let tool = translator::map_search_vector_to_tool(sv, &params_val).map_err(|e| e.to_string())?;
-                        variable_search_tools.insert("_implicit_".to_string(), tool);
+                        let search = translator::map_search_vector_call(sv, params_val)?;
+                        variable_search_tools.insert("_implicit_".to_string(), search);
                     },
                     _ => {}
                 }
             },
             StatementType::Drop(_) | StatementType::ForLoop(_) => {
-                return Err("Explorer Mode is Read-Only: The underlying MCP protocol does not support writing data or complex control flow. For more details, refer to https://docs.helix-db.com/guides/mcp-guide. To modify data (Add/Update/Delete), please use Migrations or the HTTP API.".to_string());
+                return Err(i18n::t("hql.read_only_control_flow", &[]));
             }
         }
     }
 
+    // Literal assignments may reference the environment too (`tenant <- "$env.tenant_id"`)
+    *params_val = environment::resolve(std::mem::take(params_val), env)?;
+
     if !query.return_values.is_empty() {
         for ret in &query.return_values {
             match ret {
                 ReturnType::Expression(expr) => {
-                    if let ExpressionType::Identifier(id) = &expr.expr {
-                        return_vars.push(id.clone());
-                    }
+                    collect_return(expr, None, &mut output_order, &mut return_vars, &mut computed_returns, &mut return_aliases, &mut variable_assignments);
                 },
                 ReturnType::Array(rets) => {
                     for r in rets {
                         if let ReturnType::Expression(expr) = r {
-                            if let ExpressionType::Identifier(id) = &expr.expr {
-                                return_vars.push(id.clone());
+                            collect_return(expr, None, &mut output_order, &mut return_vars, &mut computed_returns, &mut return_aliases, &mut variable_assignments);
+                        }
+                    }
+                },
+                ReturnType::Object(fields) => {
+                    for (alias, r) in fields {
+                        match r {
+                            ReturnType::Expression(expr) => {
+                                collect_return(expr, Some(alias.as_str()), &mut output_order, &mut return_vars, &mut computed_returns, &mut return_aliases, &mut variable_assignments);
                             }
+                            _ => return Err(format!("Nested objects or arrays in RETURN (field '{}') are not supported in Explorer mode", alias)),
                         }
                     }
                 },
@@ -157,7 +274,21 @@ pub async fn execute_dynamic_hql(
         }
     }
 
-    if return_vars.is_empty() {
+    // Variables only needed as inputs to computed or aliased returns are fetched, then dropped
+    let mut hidden_vars = Vec::<String>::new();
+    let mut deps = return_aliases.iter().map(|(_, source)| source.clone()).collect::<Vec<_>>();
+    for (_, expr) in &computed_returns {
+        math::referenced_identifiers(expr, &mut deps);
+    }
+    for dep in deps {
+        let fetchable = variable_assignments.contains_key(&dep) || variable_search_tools.contains_key(&dep);
+        if fetchable && !return_vars.contains(&dep) && !hidden_vars.contains(&dep) {
+            hidden_vars.push(dep);
+        }
+    }
+    let has_derived_returns = !computed_returns.is_empty() || !return_aliases.is_empty();
+
+    if return_vars.is_empty() && !has_derived_returns {
         if let Some(last_stmt) = query.statements.last() {
             match &last_stmt.statement {
                 StatementType::Assignment(assign) => {
@@ -172,180 +303,995 @@ pub async fn execute_dynamic_hql(
         return_vars.push("_implicit_".to_string());
     }
 
-    if return_vars.is_empty() {
-        return Err("No executable traversal or return statement found.".to_string());
+    if return_vars.is_empty() && !has_derived_returns {
+        return Err(i18n::t("hql.no_executable", &[]));
     }
+    return_vars.extend(hidden_vars.iter().cloned());
+
+    Ok(QueryShape {
+        variable_assignments,
+        variable_search_tools,
+        return_vars,
+        computed_returns,
+        return_aliases,
+        output_order,
+        hidden_vars,
+        has_derived_returns,
+    })
+}
 
-    let query_name = &query.name;
-    if query_name != "ExplorerTmp" && !query.parameters.is_empty() {
-        let compiled_url = format!("{}/{}", url, query_name);
-        let mut compiled_req = client.post(&compiled_url)
-            .json(&params_val);
-
-        if let Some(key) = &api_key {
-            compiled_req = compiled_req.header("x-api-key", key);
+/// Pairs each returned variable with its search call and/or resolved traversal.
+fn resolve_variables(shape: &QueryShape) -> Result<Vec<(String, Option<translator::SearchCall>, Option<Traversal>)>, String> {
+    let mut resolved_vars = Vec::new();
+    for var_name in &shape.return_vars {
+        let mut search_tool = shape.variable_search_tools.get(var_name).cloned();
+        let traversal = if search_tool.is_none() {
+            translator::resolve_traversal(var_name, &shape.variable_assignments)?
+        } else {
+            None
+        };
+        // Steps on a search result (`docs::In<Authored>`) run right after the search
+        if let Some(StartNode::Identifier(id)) = traversal.as_ref().map(|t| &t.start) {
+            let search = shape.variable_search_tools.get(id)
+                .ok_or_else(|| format!("Variable '{}' not found", id))?;
+            search_tool = Some(search.clone());
         }
+        if search_tool.is_some() || traversal.is_some() {
+            resolved_vars.push((var_name.clone(), search_tool, traversal));
+        }
+    }
+    Ok(resolved_vars)
+}
 
-        let compiled_resp = compiled_req.send()
-            .await;
+/// A worksheet result with how it was produced.
+#[derive(serde::Serialize)]
+pub struct HqlResponse {
+    pub result: serde_json::Value,
+    pub metrics: ExecutionMetrics,
+}
 
-        if let Ok(resp) = compiled_resp {
-            if resp.status().is_success() {
-                if let Ok(json) = resp.json::<serde_json::Value>().await {
-                    return Ok(translator::normalize_value(json));
-                }
-            }
-        }
+/// Whether the query adds, updates, upserts or drops data, or loops, none of which MCP
+/// tool calls can do. Every traversal step is walked, including those inside `WHERE`.
+fn writes_data(query: &Query) -> bool {
+    query.statements.iter().any(|stmt| match &stmt.statement {
+        StatementType::Assignment(assign) => expression_writes(&assign.value),
+        StatementType::Expression(expr) => expression_writes(expr),
+        StatementType::Drop(_) | StatementType::ForLoop(_) => true,
+    })
+}
+
+fn expression_writes(expr: &Expression) -> bool {
+    match &expr.expr {
+        ExpressionType::AddNode(_) | ExpressionType::AddEdge(_) | ExpressionType::AddVector(_) => true,
+        ExpressionType::Traversal(traversal) => traversal_writes(traversal),
+        ExpressionType::ArrayLiteral(exprs) | ExpressionType::And(exprs) | ExpressionType::Or(exprs) => exprs.iter().any(expression_writes),
+        ExpressionType::Not(inner) => expression_writes(inner),
+        _ => false,
     }
+}
 
-    let mut final_map = serde_json::Map::new();
+fn traversal_writes(traversal: &Traversal) -> bool {
+    traversal.steps.iter().any(|step| match &step.step {
+        StepType::Update(_) | StepType::Upsert(_) | StepType::UpsertN(_) | StepType::UpsertE(_) | StepType::UpsertV(_) | StepType::AddEdge(_) => true,
+        StepType::Where(expr) => expression_writes(expr),
+        _ => false,
+    })
+}
+
+/// Named queries with parameters are tried against their compiled endpoint first.
+/// Compiled endpoints may write, so under a no-writes policy the read-only dynamic path is used.
+fn uses_compiled_endpoint(query: &Query, policy: &policy::ConnectionPolicy) -> bool {
+    query.name != "ExplorerTmp" && !query.parameters.is_empty() && policy.check_compiled_query(&query.name).is_ok()
+}
+
+/// Runs worksheet HQL once the connection's queue lets it start. Gives up after
+/// `timeout_ms` (or the connection's default); connections still in use are closed
+/// rather than reused. With `count_only`, every
+/// pipeline returns its item count instead of its items and computed RETURN values are
+/// left out; the compiled endpoint and the result cache are skipped. A named query that
+/// adds or drops data is sent to its compiled endpoint, only with `confirm_write`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_dynamic_hql(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String, 
+    code: String, 
+    params: Option<serde_json::Value>, 
+    api_key: Option<String>,
+    profile: Option<String>,
+    stream: Option<bool>,
+    timeout_ms: Option<u64>,
+    cache_ttl_ms: Option<u64>,
+    count_only: Option<bool>,
+    confirm_write: Option<bool>,
+) -> Result<HqlResponse, String> {
+    let timeout = crate::config::query_timeout(&app, &url, timeout_ms);
+    // Queued time does not count against the timeout
+    let mut ticket = state.query_queue.enqueue(&url, &code);
+    ticket.wait_turn(crate::hql::queue::max_concurrency(&app, &url)).await?;
+    let recording = url.clone();
+    let run = run_recorded(&app, &window, state.inner(), timeout, url, code, params, api_key, profile, stream, cache_ttl_ms, count_only.unwrap_or(false), confirm_write.unwrap_or(false));
+    let run = crate::network_trace::record(&recording, run);
+    let (result, metrics) = tokio::select! {
+        outcome = run => outcome,
+        _ = ticket.cancelled() => return Err(i18n::t("hql.cancelled", &[])),
+    };
+    Ok(HqlResponse { result: result?, metrics })
+}
+
+/// Worksheet executions waiting for or holding a slot, optionally only those against `url`.
+#[tauri::command]
+pub fn list_query_queue(state: tauri::State<'_, crate::NetworkState>, url: Option<String>) -> Vec<crate::hql::queue::QueuedQuery> {
+    state.query_queue.list(url.as_deref())
+}
+
+/// Cancels a queued or running execution; false when it already finished.
+#[tauri::command]
+pub fn cancel_queued_query(state: tauri::State<'_, crate::NetworkState>, id: u64) -> bool {
+    state.query_queue.cancel(id)
+}
 
-    // Try to get connection_id from cache first
-    let mut connection_id = {
-        let cache = state.mcp_connections.lock().unwrap();
-        cache.get(&url).cloned()
+/// Runs `code` like a worksheet execution without a calling window, for the local API.
+/// Progress events still go to the main window, so it must be open.
+pub(crate) async fn run_headless(
+    app: &tauri::AppHandle,
+    url: String,
+    code: String,
+    params: Option<serde_json::Value>,
+    api_key: Option<String>,
+) -> Result<HqlResponse, String> {
+    let window = app.get_webview_window("main")
+        .map(|w| w.as_ref().window())
+        .ok_or("The Explorer window is not open")?;
+    let state = app.state::<crate::NetworkState>();
+    let timeout = crate::config::query_timeout(app, &url, None);
+    let mut ticket = state.query_queue.enqueue(&url, &code);
+    ticket.wait_turn(crate::hql::queue::max_concurrency(app, &url)).await?;
+    let run = run_recorded(app, &window, state.inner(), timeout, url, code, params, api_key, None, Some(false), None, false, false);
+    let (result, metrics) = tokio::select! {
+        outcome = run => outcome,
+        _ = ticket.cancelled() => return Err(i18n::t("hql.cancelled", &[])),
     };
+    Ok(HqlResponse { result: result?, metrics })
+}
 
-    if connection_id.is_none() {
-        let mut init_req = client.post(format!("{}/mcp/init", url));
-        if let Some(key) = &api_key {
-            init_req = init_req.header("x-api-key", key);
+/// Runs `code` through MCP outside the app, for the command line. `connection` is the
+/// connection's entry in connections.json, whose environment and redaction apply.
+/// Writes need the Explorer's confirmation and are refused.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_detached(
+    client: &reqwest::Client,
+    url: &str,
+    code: &str,
+    params: Option<serde_json::Value>,
+    api_key: Option<String>,
+    profile: profiles::TranslationProfile,
+    connection: Option<&serde_json::Value>,
+    policy: &policy::ConnectionPolicy,
+) -> Result<serde_json::Value, String> {
+    let code = processor::preprocess_hql(code);
+    let source = parse_dynamic_source(&code)?;
+    if source.queries.len() > 1 {
+        return Err(i18n::t("hql.multiple_queries", &[]));
+    }
+    let query = source.queries.first().ok_or_else(|| i18n::t("hql.no_query", &[]))?;
+    if query.name != "ExplorerTmp" && writes_data(query) {
+        return Err(format!("Query '{}' writes data; run it from the Explorer to confirm the write", query.name));
+    }
+
+    let env = connection.map(environment::environment_of).unwrap_or_default();
+    let mut params_val = environment::resolve(coerce_params(query, params.unwrap_or(serde_json::json!({})))?, &env)?;
+    let shape = query_shape(query, &mut params_val, &env)?;
+    let resolved_vars = resolve_variables(&shape)?;
+    check_labels(policy, &resolved_vars, &params_val)?;
+
+    let auth = crate::gateway_auth::GatewayAuth::of_connection(connection, api_key);
+    let target = executor::McpTarget::new(client.clone(), url, auth, profile)
+        .with_limits(crate::hql::guardrails::ResultLimits::from_connection(connection))
+        .with_rate_limit(crate::rate_limit::RateLimits::default().for_connection(url, connection));
+    let value = execute_shape(&target, &shape, resolved_vars, &params_val, false).await?;
+    let rules = connection.map(redaction::rules_of).unwrap_or_default();
+    Ok(redaction::redact(policy.limit_rows(translator::normalize_value(value)), &rules))
+}
+
+/// Runs one worksheet execution under `timeout`, recording it in the history and,
+/// past the connection's threshold, in the slow query log.
+#[allow(clippy::too_many_arguments)]
+async fn run_recorded(
+    app: &tauri::AppHandle,
+    window: &tauri::Window,
+    state: &crate::NetworkState,
+    timeout: std::time::Duration,
+    url: String,
+    code: String,
+    params: Option<serde_json::Value>,
+    api_key: Option<String>,
+    profile: Option<String>,
+    stream: Option<bool>,
+    cache_ttl_ms: Option<u64>,
+    count_only: bool,
+    confirm_write: bool,
+) -> (Result<serde_json::Value, String>, ExecutionMetrics) {
+    let metrics = MetricsRecorder::default();
+    let mut record = crate::history::ExecutionRecord {
+        started_at: chrono::Utc::now().to_rfc3339(),
+        url: url.clone(),
+        code: code.clone(),
+        params: params.clone(),
+        duration_ms: 0,
+        error: None,
+        entity_ids: Vec::new(),
+    };
+    let started = std::time::Instant::now();
+    let result = tokio::time::timeout(timeout, run_dynamic_hql(app, window, state, &metrics, url, code, params, api_key, profile, stream, cache_ttl_ms, count_only, confirm_write))
+        .await
+        .map_err(|_| crate::config::timed_out_error(timeout))
+        .and_then(|r| r);
+    record.duration_ms = started.elapsed().as_millis() as u64;
+    record.error = result.as_ref().err().cloned();
+    record.entity_ids = result.as_ref().map(crate::history::entity_ids).unwrap_or_default();
+    if let Err(e) = crate::history::record(app, &record) {
+        println!(">>> [History] Failed to record execution: {}", e);
+    }
+    let metrics = metrics.finish(started.elapsed());
+    // Cached results never reached the server
+    if metrics.path != ExecutionPath::Cache {
+        crate::usage::record_result(app, &record.url, result.as_ref().ok());
+    }
+    if record.duration_ms >= crate::slow_queries::threshold_ms(app, &record.url) {
+        let slow = crate::slow_queries::SlowQuery {
+            plan: explain_code(app, &record.code, record.params.clone(), Some(&record.url)).ok()
+                .and_then(|plan| serde_json::to_value(plan).ok()),
+            metrics: serde_json::to_value(&metrics).ok(),
+            rows: metrics.rows,
+            started_at: record.started_at,
+            url: record.url,
+            code: record.code,
+            params: record.params,
+            duration_ms: record.duration_ms,
+            error: record.error,
+        };
+        if let Err(e) = crate::slow_queries::record(app, &slow) {
+            println!(">>> [SlowQueries] Failed to record slow query: {}", e);
         }
+    }
+    (result, metrics)
+}
 
-        let init_resp = init_req.send()
-            .await
-            .map_err(|e| map_reqwest_error(e, "Init failed"))?;
-        
-        if !init_resp.status().is_success() {
-            let status = init_resp.status();
-            let err_text = init_resp.text().await.unwrap_or_else(|_| String::new());
-            return Err(format!("Init request failed ({}): {}", status, err_text));
+/// The outcome of one query in a batch: its result and metrics, or its error.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchOutcome {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ExecutionMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs every `QUERY` in `code`, `concurrency` at a time (default one after another).
+/// Returns query name -> outcome in source order; one failing query does not stop the rest.
+/// Each query gets its own timeout and history entry.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_hql_batch(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    code: String,
+    params_by_query: Option<std::collections::HashMap<String, serde_json::Value>>,
+    api_key: Option<String>,
+    profile: Option<String>,
+    concurrency: Option<usize>,
+    timeout_ms: Option<u64>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    use futures::StreamExt;
+
+    let code = processor::preprocess_hql(&code);
+    let source = parse_dynamic_source(&code)?;
+    if source.queries.is_empty() {
+        return Err(i18n::t("hql.no_query", &[]));
+    }
+    // Bare statements parse as one temporary query whose range covers the wrapper, not `code`
+    let queries: Vec<(String, String)> = if source.queries.len() == 1 {
+        vec![(source.queries[0].name.clone(), code.clone())]
+    } else {
+        source.queries.iter().map(|q| (q.name.clone(), code[q.loc.byte_range()].trim().to_string())).collect()
+    };
+
+    let timeout = crate::config::query_timeout(&app, &url, timeout_ms);
+    let mut params_by_query = params_by_query.unwrap_or_default();
+    let runs = queries.into_iter().map(|(name, query_code)| {
+        let params = params_by_query.remove(&name);
+        let (app, window, state, url, api_key, profile) = (&app, &window, state.inner(), url.clone(), api_key.clone(), profile.clone());
+        async move {
+            let (result, metrics) = run_recorded(app, window, state, timeout, url, query_code, params, api_key, profile, Some(false), None, false, false).await;
+            let outcome = match result {
+                Ok(result) => BatchOutcome { result: Some(result), metrics: Some(metrics), error: None },
+                Err(e) => BatchOutcome { result: None, metrics: None, error: Some(e) },
+            };
+            (name, outcome)
         }
+    });
+    let outcomes: Vec<(String, BatchOutcome)> = futures::stream::iter(runs)
+        .buffered(concurrency.unwrap_or(1).max(1))
+        .collect()
+        .await;
+
+    outcomes.into_iter()
+        .map(|(name, outcome)| serde_json::to_value(outcome).map(|v| (name, v)).map_err(|e| e.to_string()))
+        .collect()
+}
 
-        let init_body = init_resp.text().await.map_err(|e| format!("Failed to read init body: {}", e))?;
-        let id: String = serde_json::from_str(&init_body)
-            .map_err(|e| format!("Failed to parse connection_id from '{}': {}", init_body, e))?;
-        
-        // Update cache
-        let mut cache = state.mcp_connections.lock().unwrap();
-        cache.insert(url.clone(), id.clone());
-        connection_id = Some(id);
+/// Logged slow executions grouped per query, ranked by total (default) or average time.
+#[tauri::command]
+pub fn get_slow_queries(
+    app: tauri::AppHandle,
+    order: Option<crate::slow_queries::SlowQueryOrder>,
+    limit: Option<usize>,
+) -> Result<Vec<crate::slow_queries::SlowQueryStats>, String> {
+    let mut ranked = crate::slow_queries::rank(crate::slow_queries::load(&app)?, order.unwrap_or_default());
+    if let Some(limit) = limit {
+        ranked.truncate(limit);
     }
+    Ok(ranked)
+}
 
-    let conn_id = connection_id.unwrap();
+#[tauri::command]
+pub fn clear_slow_queries(app: tauri::AppHandle) -> Result<(), String> {
+    crate::slow_queries::clear(&app)
+}
 
-    // 1. Resolve all traversals upfront (CPU-only, no async)
-    let mut resolved_vars: Vec<(String, Option<ToolArgs>, Option<helix_db::helixc::parser::types::Traversal>)> = Vec::new();
-    for var_name in &return_vars {
-        let search_tool = variable_search_tools.get(var_name).cloned();
-        let traversal = if search_tool.is_none() {
-            translator::resolve_traversal(var_name, &variable_assignments)?
+/// Queries, rows and bytes fetched per day from `url` (or every connection), over the
+/// last `days` days recorded.
+#[tauri::command]
+pub fn get_usage_stats(app: tauri::AppHandle, url: Option<String>, days: Option<usize>) -> Result<Vec<crate::usage::ConnectionUsage>, String> {
+    crate::usage::usage_stats(&app, url.as_deref(), days)
+}
+
+#[tauri::command]
+pub fn reset_usage_stats(app: tauri::AppHandle, url: Option<String>) -> Result<(), String> {
+    crate::usage::reset(&app, url.as_deref())
+}
+
+/// Replayed queries running at once; later ones wait past their scheduled time.
+const REPLAY_CONCURRENCY: usize = 16;
+
+/// The first query in `code` a replay could write with: one that adds or drops data,
+/// or a named query sent to its compiled endpoint, which may write as well.
+fn replay_write(code: &str, policy: &policy::ConnectionPolicy) -> Option<String> {
+    let source = parse_dynamic_source(&processor::preprocess_hql(code)).ok()?;
+    source.queries.iter()
+        .find(|query| writes_data(query) || uses_compiled_endpoint(query, policy))
+        .map(|query| query.name.clone())
+}
+
+/// Replays recorded executions (started within `from`..`to`, optionally only those
+/// against `source_url`) against `url`. Original spacing is kept, divided by `speed`;
+/// a speed of 0 sends everything as soon as `REPLAY_CONCURRENCY` allows. Queries that
+/// may write are refused unless `allow_writes` is set and the policy allows writes.
+/// Replays are not recorded or cached.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn replay_load(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    from: Option<String>,
+    to: Option<String>,
+    source_url: Option<String>,
+    speed: Option<f64>,
+    api_key: Option<String>,
+    allow_writes: Option<bool>,
+) -> Result<crate::history::ReplayReport, String> {
+    let mut records = crate::history::load(&app, from.as_deref(), to.as_deref(), source_url.as_deref())?;
+    if records.is_empty() {
+        return Err("No recorded executions in that window".to_string());
+    }
+    records.truncate(crate::history::MAX_REPLAY);
+    let allow_writes = allow_writes.unwrap_or(false);
+    let policy = policy::effective_policy(&app, Some(&url))?;
+    if allow_writes {
+        policy.check_writes()?;
+    } else if let Some(name) = records.iter().find_map(|record| replay_write(&record.code, &policy)) {
+        return Err(format!("Replaying '{}' could write to {}; allow writes to replay it", name, url));
+    }
+    let offsets = crate::history::schedule(&records, speed.unwrap_or(1.0));
+    let timeout = crate::config::query_timeout(&app, &url, None);
+    let start = tokio::time::Instant::now();
+    let running = tokio::sync::Semaphore::new(REPLAY_CONCURRENCY);
+
+    let outcomes = futures::future::join_all(records.iter().zip(offsets).map(|(record, offset)| {
+        let (app, window, state, url, api_key, running) = (&app, &window, state.inner(), url.clone(), api_key.clone(), &running);
+        async move {
+            tokio::time::sleep_until(start + std::time::Duration::from_millis(offset)).await;
+            let _permit = running.acquire().await;
+            let started = std::time::Instant::now();
+            let run = run_dynamic_hql(app, window, state, &MetricsRecorder::default(), url, record.code.clone(), record.params.clone(), api_key, None, Some(false), Some(0), false, allow_writes);
+            let result = tokio::time::timeout(timeout, run).await
+                .map_err(|_| crate::config::timed_out_error(timeout))
+                .and_then(|r| r);
+            (started.elapsed().as_millis() as u64, result.err())
+        }
+    })).await;
+
+    Ok(crate::history::compare(&records, outcomes))
+}
+
+/// A compiled-endpoint call that did not produce a result.
+struct CompiledFailure {
+    endpoint: String,
+    /// `None` when no response arrived.
+    status: Option<u16>,
+    body: String,
+}
+
+impl CompiledFailure {
+    /// Both errors, for when the MCP fallback failed as well. The compiled endpoint's
+    /// message usually names the real problem, such as a missing parameter.
+    fn with_fallback(self, fallback: &str) -> String {
+        let status = self.status.map_or_else(|| "no response".to_string(), |s| s.to_string());
+        i18n::t("hql.compiled_and_fallback_failed", &[
+            ("endpoint", &self.endpoint),
+            ("status", &status),
+            ("body", self.body.trim()),
+            ("fallback", fallback),
+        ])
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_dynamic_hql(
+    app: &tauri::AppHandle,
+    window: &tauri::Window,
+    state: &crate::NetworkState,
+    metrics: &MetricsRecorder,
+    url: String, 
+    code: String, 
+    params: Option<serde_json::Value>, 
+    api_key: Option<String>,
+    profile: Option<String>,
+    stream: Option<bool>,
+    cache_ttl_ms: Option<u64>,
+    count_only: bool,
+    confirm_write: bool,
+) -> Result<serde_json::Value, String> {
+    let client = &state.client_for(app, &url)?;
+    let code = processor::preprocess_hql(&code);
+    // Counts are cheap and keyed like the full result, so they are never cached
+    let cache = if count_only { None } else { crate::hql::cache::settings(app, &url, cache_ttl_ms) };
+    let cache_key = std::cell::OnceCell::<crate::hql::cache::CacheKey>::new();
+    let policy = policy::effective_policy(app, Some(&url))?;
+    let redaction_rules = redaction::rules_for(app, &url);
+    let redact = |value: serde_json::Value| redaction::redact(translator::normalize_value(value), &redaction_rules);
+    // Cached results are already redacted, so they only get row limits and demo data
+    let finish_redacted = |value: serde_json::Value| {
+        let value = crate::demo::apply(policy.limit_rows(value));
+        metrics.set_rows(count_rows(&value));
+        if stream.unwrap_or(false) {
+            crate::hql::stream::emit_chunks(window, value)
         } else {
-            None
-        };
-        if search_tool.is_some() || traversal.is_some() {
-            resolved_vars.push((var_name.clone(), search_tool, traversal));
+            value
+        }
+    };
+    // Every result leaves through here, so row limits, redaction and demo data cannot be skipped
+    let finish = |value: serde_json::Value| finish_redacted(redact(value));
+    // Results read through MCP are cached redacted; compiled endpoints may write and are
+    // not. Truncated and partly failed results are not cached, as a cache hit could not
+    // report either.
+    let store = |value: serde_json::Value| {
+        let value = redact(value);
+        if let (Some(cache), Some(key), false) = (&cache, cache_key.get(), metrics.is_truncated() || metrics.is_partial()) {
+            state.query_cache.put(key.clone(), value.clone(), cache.disk.as_deref());
         }
+        finish_redacted(value)
+    };
+
+    let source = parse_dynamic_source(&code)?;
+
+    if source.queries.len() > 1 {
+        return Err(i18n::t("hql.multiple_queries", &[]));
     }
+    
+    let query = source.queries.first().ok_or_else(|| i18n::t("hql.no_query", &[]))?;
 
-    // 2. Execute: parallel for multiple vars, serial for single
-    if resolved_vars.len() <= 1 {
-        // Single variable — use cached connection, no extra overhead
-        for (var_name, search_tool, traversal) in resolved_vars {
-            let result_res = if let Some(tool) = &search_tool {
-                executor::execute_search_tool(&client, &url, &conn_id, tool, api_key.clone()).await
-            } else if let Some(t) = &traversal {
-                executor::execute_pipeline(&client, &url, &conn_id, t, &params_val, api_key.clone()).await
-            } else {
-                Ok(serde_json::Value::Null)
-            };
+    // MCP is read-only, but a deployed named query can still write through its endpoint
+    if !count_only && query.name != "ExplorerTmp" && writes_data(query) {
+        if !confirm_write {
+            return Err(i18n::t("hql.confirm_write", &[("name", &query.name)]));
+        }
+        policy.check_writes()?;
+        let started = std::time::Instant::now();
+        let result = crate::commands::network::call_compiled_query(app, state, &url, &query.name, coerce_params(query, params.unwrap_or(serde_json::json!({})))?, api_key, None).await?;
+        metrics.set_path(ExecutionPath::Compiled);
+        metrics.for_variable(&query.name).tool_call("compiled", started.elapsed());
+        metrics.set_rows(count_rows(&result));
+        let disk = crate::hql::cache::cache_dir(app).ok();
+        state.query_cache.invalidate(Some(&url), disk.as_deref());
+        return Ok(result);
+    }
+
+    let env = environment::connection_environment(app, &url);
+    let mut params_val = environment::resolve(coerce_params(query, params.unwrap_or(serde_json::json!({})))?, &env)?;
+
+    let shape = query_shape(query, &mut params_val, &env)?;
+
+    if let Some(cache) = &cache {
+        let auth = crate::gateway_auth::GatewayAuth::for_url(app, &url, api_key.clone());
+        let identity = crate::hql::cache::identity(&auth, profile.as_deref(), &redaction_rules);
+        let key = crate::hql::cache::CacheKey::new(&url, &identity, &code, &params_val);
+        if let Some(hit) = state.query_cache.get(&key, cache.ttl, cache.disk.as_deref()) {
+            metrics.set_path(ExecutionPath::Cache);
+            return Ok(finish_redacted(hit));
+        }
+        let _ = cache_key.set(key);
+    }
 
-            let result = match result_res {
-                Ok(val) => val,
-                Err(e) => {
-                    let err_msg = e.to_string();
-                    if err_msg.contains("Connection not found") || err_msg.contains("expired") {
-                        let mut cache = state.mcp_connections.lock().unwrap();
-                        cache.remove(&url);
+    let query_name = &query.name;
+    // A failed compiled call is kept, so its message can be shown if the fallback fails too
+    let mut compiled_failure = None;
+    if !count_only && uses_compiled_endpoint(query, &policy) {
+        // The compiled endpoint runs the same query, so its labels are checked up front
+        if policy.allowed_labels.is_some() {
+            check_labels(&policy, &resolve_variables(&shape)?, &params_val)?;
+        }
+        let compiled_url = format!("{}/{}", url, query_name);
+        let compiled_req = crate::gateway_auth::GatewayAuth::for_url(app, &url, api_key.clone())
+            .apply(client.post(&compiled_url).json(&params_val));
+
+        let compiled_started = std::time::Instant::now();
+        let compiled_resp = compiled_req.send_traced()
+            .await;
+
+        let failure = |status: Option<u16>, body: String| CompiledFailure { endpoint: compiled_url.clone(), status, body };
+        match compiled_resp {
+            Ok(resp) if resp.status().is_success() => {
+                let status = resp.status().as_u16();
+                match resp.json::<serde_json::Value>().await {
+                    Ok(json) => {
+                        policy.check_result_labels(&json)?;
+                        metrics.set_path(ExecutionPath::Compiled);
+                        metrics.for_variable(query_name).tool_call("compiled", compiled_started.elapsed());
+                        return Ok(finish(json));
                     }
-                    return Err(e);
+                    Err(e) => compiled_failure = Some(failure(Some(status), format!("invalid JSON: {}", e))),
                 }
-            };
+            }
+            Ok(resp) => {
+                let status = resp.status().as_u16();
+                compiled_failure = Some(failure(Some(status), resp.text().await.unwrap_or_default()));
+            }
+            Err(e) => compiled_failure = Some(failure(None, e.to_string())),
+        }
+    }
 
-            if var_name == "_implicit_" && final_map.is_empty() {
-                return Ok(translator::normalize_value(result));
+    let fallback = async {
+        // Resolve all traversals upfront (CPU-only, no async)
+        let resolved_vars = resolve_variables(&shape)?;
+        check_labels(&policy, &resolved_vars, &params_val)?;
+
+        // Fail early with a readable message when a probed server lacks a feature
+        let cached_caps = crate::capabilities::probed(&state.server_capabilities, &url);
+        if let Some(caps) = &cached_caps {
+            if !caps.mcp_enabled {
+                return Err(i18n::t("hql.mcp_disabled", &[]));
+            }
+            for (_, search_tool, _) in &resolved_vars {
+                if let Some(search) = search_tool {
+                    caps.ensure_supported(&search.tool)?;
+                }
             }
-            final_map.insert(var_name, result);
         }
-    } else {
-        // Multiple variables — execute in parallel, each with its own connection
-        let mut handles = Vec::new();
 
-        for (var_name, search_tool, traversal) in resolved_vars {
-            let client = client.clone();
-            let url = url.clone();
-            let api_key = api_key.clone();
-            let params_val = params_val.clone();
-
-            let handle = tokio::spawn(async move {
-                // Each parallel task inits its own MCP connection
-                let init_resp = client.post(format!("{}/mcp/init", url))
-                    .send().await
-                    .map_err(|e| format!("Parallel init failed: {}", e))?;
-
-                if !init_resp.status().is_success() {
-                    let status = init_resp.status();
-                    let err_text = init_resp.text().await.unwrap_or_default();
-                    return Err(format!("Parallel init failed ({}): {}", status, err_text));
+        let profile = profiles::select_profile(
+            &profiles::load_profiles(app),
+            cached_caps.as_ref().and_then(|c| c.version.as_deref()),
+            profile.as_deref(),
+        )?;
+        let progress_window = window.clone();
+        let progress = progress::ProgressReporter::new(move |event| {
+            if let Err(e) = progress_window.emit("query-progress", event) {
+                println!(">>> [Progress] Failed to emit progress: {}", e);
+            }
+        });
+        let recovered_window = window.clone();
+        let auth = crate::gateway_auth::GatewayAuth::for_url(app, &url, api_key.clone());
+        let target = executor::McpTarget::new(client.clone(), &url, auth, profile)
+            .with_pool(state.mcp_pool.clone())
+            .with_capabilities(state.server_capabilities.clone())
+            .with_rate_limit(state.rate_limit_for(app, &url))
+            .with_progress(progress)
+            .on_recovered(move |event| {
+                if let Err(e) = recovered_window.emit("connection-recovered", event) {
+                    println!(">>> [Executor] Failed to emit connection recovery: {}", e);
                 }
+            })
+            .with_metrics(metrics.clone())
+            .with_count_only(count_only)
+            .with_limits(crate::hql::guardrails::ResultLimits::for_connection(app, &url));
 
-                let init_body = init_resp.text().await
-                    .map_err(|e| format!("Failed to read init body: {}", e))?;
-                let par_conn_id: String = serde_json::from_str(&init_body)
-                    .map_err(|e| format!("Failed to parse connection_id: {}", e))?;
+        execute_shape(&target, &shape, resolved_vars, &params_val, count_only).await.map(store)
+    }.await;
 
-                let result = if let Some(tool) = &search_tool {
-                    executor::execute_search_tool(&client, &url, &par_conn_id, tool, api_key.clone()).await?
-                } else if let Some(t) = &traversal {
-                    executor::execute_pipeline(&client, &url, &par_conn_id, t, &params_val, api_key.clone()).await?
-                } else {
-                    serde_json::Value::Null
-                };
+    match (fallback, compiled_failure) {
+        (Err(e), Some(compiled)) => Err(compiled.with_fallback(&e)),
+        (result, _) => result,
+    }
+}
 
-                Ok::<(String, serde_json::Value), String>((var_name, result))
-            });
+/// Refuses variables that touch labels outside the policy's `allowedLabels`.
+fn check_labels(
+    policy: &policy::ConnectionPolicy,
+    resolved_vars: &[(String, Option<translator::SearchCall>, Option<Traversal>)],
+    params_val: &serde_json::Value,
+) -> Result<(), String> {
+    if policy.allowed_labels.is_none() {
+        return Ok(());
+    }
+    for (_, search_tool, traversal) in resolved_vars {
+        let plan = traversal.as_ref().map(|t| translator::map_traversal_to_tools(t, params_val)).transpose()?;
+        let mut labels = Vec::new();
+        if let Some(search) = search_tool {
+            policy::tool_labels(&search.tool, &mut labels);
+        }
+        if let Some(plan) = &plan {
+            labels.extend(policy::plan_labels(plan));
+        }
+        policy.check_labels(labels)?;
+    }
+    Ok(())
+}
 
-            handles.push(handle);
+/// Runs the resolved variables of `shape` through MCP and assembles the result in
+/// RETURN order, with computed returns and aliases applied.
+async fn execute_shape(
+    target: &executor::McpTarget,
+    shape: &QueryShape<'_>,
+    resolved_vars: Vec<(String, Option<translator::SearchCall>, Option<Traversal>)>,
+    params_val: &serde_json::Value,
+    count_only: bool,
+) -> Result<serde_json::Value, String> {
+    let mut final_map = serde_json::Map::new();
+    let mut partial = false;
+
+    // 2. Execute: parallel for multiple vars, serial for single
+    if resolved_vars.len() <= 1 {
+        for (var_name, search_tool, traversal) in resolved_vars {
+            let result = executor::execute_variable(target, &var_name, search_tool.as_ref(), traversal.as_ref(), params_val).await?;
+            if var_name == "_implicit_" && final_map.is_empty() && !shape.has_derived_returns {
+                return Ok(result);
+            }
+            final_map.insert(var_name, result);
+        }
+    } else {
+        // Multiple variables — execute concurrently, each on its own connection so
+        // pipelines cannot interleave, then assemble in RETURN order.
+        let mut results = futures::future::join_all(resolved_vars.iter().map(|(var_name, search_tool, traversal)| {
+            async move {
+                let result = executor::execute_variable(target, var_name, search_tool.as_ref(), traversal.as_ref(), params_val).await;
+                (var_name.clone(), result)
+            }
+        })).await;
+
+        // A variable that came back as the wrong kind of value is run again on its own
+        for ((var_name, result), (_, search_tool, traversal)) in results.iter_mut().zip(&resolved_vars) {
+            if let Ok(value) = result {
+                shape_check::verify(target, var_name, search_tool.as_ref(), traversal.as_ref(), params_val, value).await;
+            }
         }
 
-        // Await all parallel tasks
-        for handle in handles {
-            let task_result = handle.await
-                .map_err(|e| format!("Task join error: {}", e))?;
-
-            let (var_name, result) = match task_result {
-                Ok(v) => v,
-                Err(e) => {
-                    if e.contains("Connection not found") || e.contains("expired") {
-                        let mut cache = state.mcp_connections.lock().unwrap();
-                        cache.remove(&url);
+        // A failed variable carries its error in place of its value, so the others
+        // are not lost. Only when every variable failed is the first failure (in
+        // RETURN order) reported on its own.
+        if results.iter().all(|(_, result)| result.is_err()) {
+            if let Some((_, Err(e))) = results.into_iter().next() {
+                return Err(e);
+            }
+        } else {
+            for (var_name, result) in results {
+                let value = result.unwrap_or_else(|e| {
+                    partial = true;
+                    if let Some(metrics) = &target.metrics {
+                        metrics.for_variable(&var_name).variable_failed();
                     }
-                    return Err(e);
-                }
+                    variable_error(&e)
+                });
+                final_map.insert(var_name, value);
+            }
+        }
+    }
+
+    if !count_only && !shape.computed_returns.is_empty() {
+        // Evaluate over normalized values so numbers are plain JSON numbers
+        let resolved = match translator::normalize_value(serde_json::Value::Object(final_map.clone())) {
+            serde_json::Value::Object(map) => map,
+            _ => serde_json::Map::new(),
+        };
+        for (name, expr) in &shape.computed_returns {
+            let value = match math::evaluate(expr, &resolved, params_val) {
+                Ok(value) => value,
+                // Most likely computed from a variable that failed
+                Err(e) if partial => variable_error(&e),
+                Err(e) => return Err(e),
             };
+            final_map.insert(name.clone(), value);
+        }
+    }
+    for (alias, source) in &shape.return_aliases {
+        let value = final_map.get(source).cloned().unwrap_or(serde_json::Value::Null);
+        final_map.insert(alias.clone(), value);
+    }
+    for var in &shape.hidden_vars {
+        if !shape.return_aliases.iter().any(|(alias, _)| alias == var) {
+            final_map.shift_remove(var);
+        }
+    }
+    let final_map = translator::order_by_return(final_map, &shape.output_order);
+
+    if final_map.len() == 1 && final_map.contains_key("_implicit_") {
+        return Ok(final_map.get("_implicit_").unwrap().clone());
+    }
+
+    Ok(serde_json::Value::Object(final_map))
+}
+
+/// What a failed RETURN variable holds in a partly failed result.
+fn variable_error(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+/// The MCP plan `execute_dynamic_hql` would run for `code`, without contacting the
+/// server. `url` selects the connection whose policy and environment apply.
+#[tauri::command]
+pub fn explain_hql(app: tauri::AppHandle, code: String, params: Option<serde_json::Value>, url: Option<String>) -> Result<explain::QueryExplanation, String> {
+    explain_code(&app, &code, params, url.as_deref())
+}
+
+fn explain_code(app: &tauri::AppHandle, code: &str, params: Option<serde_json::Value>, url: Option<&str>) -> Result<explain::QueryExplanation, String> {
+    let code = processor::preprocess_hql(code);
+    let source = parse_dynamic_source(&code)?;
+    if source.queries.len() > 1 {
+        return Err(i18n::t("hql.multiple_queries", &[]));
+    }
+    let query = source.queries.first().ok_or_else(|| i18n::t("hql.no_query", &[]))?;
+    let policy = policy::effective_policy(app, url)?;
+    let env = url.map(|u| environment::connection_environment(app, u)).unwrap_or_default();
+    let mut params_val = environment::resolve(coerce_params(query, params.unwrap_or(serde_json::json!({})))?, &env)?;
+
+    let shape = query_shape(query, &mut params_val, &env)?;
+    let variables = resolve_variables(&shape)?.iter()
+        .map(|(name, search, traversal)| explain::explain_variable(name, search.as_ref(), traversal.as_ref(), &params_val))
+        .collect();
+    Ok(explain::QueryExplanation {
+        query_name: query.name.clone(),
+        compiled_fast_path: uses_compiled_endpoint(query, &policy),
+        params: params_val,
+        variables,
+        computed_returns: shape.computed_returns.iter().map(|(name, _)| name.clone()).collect(),
+        aliases: shape.return_aliases.clone(),
+    })
+}
+
+/// Recommends `INDEX` annotations from the WHERE filters of recorded executions against
+/// `url`, weighted by each label's current size. Fields already marked `INDEX` in the
+/// connection's project schema are skipped.
+#[tauri::command]
+pub async fn suggest_indexes(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+    schema: Option<crate::hql::cookbook::CookbookSchema>,
+) -> Result<Vec<crate::hql::index_advisor::IndexSuggestion>, String> {
+    let mut usage = crate::hql::index_advisor::FilterUsage::default();
+    for record in crate::history::load(&app, None, None, Some(&url))? {
+        let Ok(explained) = explain_code(&app, &record.code, record.params.clone(), Some(&url)) else {
+            continue;
+        };
+        for plan in explained.variables.iter().filter_map(|v| v.plan.as_ref()) {
+            usage.observe(plan, schema.as_ref());
+        }
+    }
+
+    // A label that cannot be counted simply gets no estimate
+    let mut sizes = std::collections::HashMap::new();
+    for label in usage.candidate_labels() {
+        let start = match &schema {
+            Some(s) if s.edges.iter().any(|e| e.name == label) => "E",
+            Some(s) if s.vectors.iter().any(|v| v.name == label) => "V",
+            _ => "N",
+        };
+        let code = format!("{}<{}>::COUNT", start, label);
+        let count = run_dynamic_hql(&app, &window, state.inner(), &MetricsRecorder::default(), url.clone(), code, None, api_key.clone(), None, Some(false), Some(0), false, false).await;
+        if let Some(n) = count.ok().and_then(|v| v.as_u64()) {
+            sizes.insert(label, n);
+        }
+    }
+
+    let indexed = policy::find_connection(&app, Some(&url))
+        .and_then(|c| c.get("localPath")?.as_str().map(String::from))
+        .and_then(|path| std::fs::read_to_string(std::path::Path::new(&path).join("db").join("schema.hx")).ok())
+        .map(|content| crate::hql::index_advisor::indexed_fields(&content))
+        .unwrap_or_default();
+    Ok(usage.suggest(&sizes, &indexed, schema.as_ref()))
+}
+
+/// Past worksheet executions whose result contained the entity `id`, newest first,
+/// optionally only those against `url`.
+#[tauri::command]
+pub fn find_entity_occurrences(
+    app: tauri::AppHandle,
+    id: String,
+    url: Option<String>,
+) -> Result<Vec<crate::history::EntityOccurrence>, String> {
+    let records = crate::history::load(&app, None, None, url.as_deref())?;
+    Ok(crate::history::occurrences(&records, &id))
+}
+
+/// Drops cached results for `url`, or for every connection.
+#[tauri::command]
+pub fn invalidate_query_cache(app: tauri::AppHandle, state: tauri::State<'_, crate::NetworkState>, url: Option<String>) -> usize {
+    let disk = crate::hql::cache::cache_dir(&app).ok();
+    state.query_cache.invalidate(url.as_deref(), disk.as_deref())
+}
 
-            if var_name == "_implicit_" && final_map.is_empty() {
-                return Ok(translator::normalize_value(result));
+/// The traversal a single-result query returns, with literal assignments added to
+/// `params`. Anything else (searches, several returns) cannot be paged.
+fn pageable_traversal(query: &Query, params: &mut serde_json::Value) -> Result<Traversal, String> {
+    let mut assignments = std::collections::HashMap::<String, &Traversal>::new();
+    let mut last_var = None;
+    for stmt in &query.statements {
+        let (name, value) = match &stmt.statement {
+            StatementType::Assignment(assign) => (assign.variable.clone(), &assign.value),
+            StatementType::Expression(expr) => ("_implicit_".to_string(), expr),
+            StatementType::Drop(_) | StatementType::ForLoop(_) => return Err(i18n::t("hql.read_only_control_flow", &[])),
+        };
+        match &value.expr {
+            ExpressionType::Traversal(t) => {
+                assignments.insert(name.clone(), &**t);
+            }
+            ExpressionType::AddNode(_) | ExpressionType::AddEdge(_) | ExpressionType::AddVector(_) => return Err(i18n::t("hql.read_only", &[])),
+            ExpressionType::BM25Search(_) | ExpressionType::SearchVector(_) => return Err("Search results cannot be paged; run the query without paging".to_string()),
+            _ => {
+                if let (Some(val), serde_json::Value::Object(map)) = (expression_to_json(value), &mut *params) {
+                    map.insert(name.clone(), val);
+                }
             }
-            final_map.insert(var_name, result);
         }
+        last_var = Some(name);
     }
 
-    if final_map.len() == 1 && final_map.contains_key("_implicit_") {
-        return Ok(translator::normalize_value(final_map.get("_implicit_").unwrap().clone()));
+    let returned = match query.return_values.as_slice() {
+        [] => last_var,
+        [ReturnType::Expression(Expression { expr: ExpressionType::Identifier(id), .. })] => Some(id.clone()),
+        _ => return Err("Paging needs a query that returns a single variable".to_string()),
+    };
+    let name = returned.ok_or_else(|| i18n::t("hql.no_executable", &[]))?;
+    let traversal = translator::resolve_traversal(&name, &assignments)?
+        .ok_or_else(|| i18n::t("hql.no_executable", &[]))?;
+    if let StartNode::Identifier(id) = &traversal.start {
+        return Err(format!("'{}' is not a traversal and cannot be paged", id));
+    }
+    Ok(traversal)
+}
+
+/// Starts the single traversal of `code` on its own connection, leaving its pipeline open.
+/// Returns the rows to read as `start..end`, with the policy's row limit applied.
+#[allow(clippy::too_many_arguments)]
+async fn open_single_traversal(
+    app: &tauri::AppHandle,
+    state: &crate::NetworkState,
+    policy: &policy::ConnectionPolicy,
+    url: &str,
+    code: String,
+    params: Option<serde_json::Value>,
+    api_key: Option<String>,
+    profile: Option<String>,
+) -> Result<(executor::McpTarget, crate::hql::pool::PooledConnection, usize, Option<usize>), String> {
+    let code = processor::preprocess_hql(&code);
+    let source = parse_dynamic_source(&code)?;
+    if source.queries.len() > 1 {
+        return Err(i18n::t("hql.multiple_queries", &[]));
+    }
+    let query = source.queries.first().ok_or_else(|| i18n::t("hql.no_query", &[]))?;
+    let env = environment::connection_environment(app, url);
+    let mut params_val = environment::resolve(coerce_params(query, params.unwrap_or(serde_json::json!({})))?, &env)?;
+    let traversal = pageable_traversal(query, &mut params_val)?;
+    let params_val = environment::resolve(params_val, &env)?;
+    if policy.allowed_labels.is_some() {
+        let plan = translator::map_traversal_to_tools(&traversal, &params_val)?;
+        policy.check_labels(policy::plan_labels(&plan))?;
     }
 
-    Ok(translator::normalize_value(serde_json::Value::Object(final_map)))
+    let cached_caps = crate::capabilities::probed(&state.server_capabilities, url);
+    if cached_caps.as_ref().is_some_and(|c| !c.mcp_enabled) {
+        return Err(i18n::t("hql.mcp_disabled", &[]));
+    }
+    let profile = profiles::select_profile(
+        &profiles::load_profiles(app),
+        cached_caps.as_ref().and_then(|c| c.version.as_deref()),
+        profile.as_deref(),
+    )?;
+    let auth = crate::gateway_auth::GatewayAuth::for_url(app, url, api_key);
+    let target = executor::McpTarget::new(state.client_for(app, url)?, url, auth, profile)
+        .with_pool(state.mcp_pool.clone())
+        .with_capabilities(state.server_capabilities.clone())
+        .with_rate_limit(state.rate_limit_for(app, url));
+
+    let conn = executor::acquire_connection(&target).await?;
+    let range = conn.lend(executor::open_pipeline(&target, &conn, &traversal, &params_val)).await?;
+    let (start, end) = range.unwrap_or((0, None));
+    // The policy's row limit counts from the first returned row
+    let end = match (end, policy.max_rows) {
+        (Some(end), Some(max)) => Some(end.min(start + max)),
+        (end, max) => end.or(max.map(|m| start + m)),
+    };
+    Ok((target, conn, start, end))
+}
+
+/// One page of a single-traversal query, for infinite scroll. Without a cursor the
+/// traversal is started and its pipeline kept open on its own connection; pass
+/// `next_cursor` back for the following page. The last page has no cursor. Without a
+/// `page_size`, the connection's default (or `paging::DEFAULT_PAGE_SIZE`) applies.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_dynamic_hql_page(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    code: String,
+    params: Option<serde_json::Value>,
+    cursor: Option<String>,
+    page_size: Option<usize>,
+    api_key: Option<String>,
+    profile: Option<String>,
+) -> Result<paging::HqlPage, String> {
+    let policy = policy::effective_policy(&app, Some(&url))?;
+    let page_size = page_size
+        .or_else(|| crate::config::ConnectionDefaults::of_connection(policy::find_connection(&app, Some(&url)).as_ref()).page_size)
+        .unwrap_or(paging::DEFAULT_PAGE_SIZE);
+    let redaction_rules = redaction::rules_for(&app, &url);
+    let finish = |mut page: paging::HqlPage| {
+        page.rows = crate::demo::apply(redaction::redact(translator::normalize_value(page.rows), &redaction_rules));
+        page
+    };
+
+    if let Some(cursor) = cursor {
+        return state.page_sessions.next_page(&cursor, page_size).await.map(finish);
+    }
+
+    let (target, conn, start, end) = open_single_traversal(&app, state.inner(), &policy, &url, code, params, api_key, profile).await?;
+    let session = paging::PageSession::new(target, conn, start, end);
+    state.page_sessions.first_page(session, page_size).await.map(finish)
+}
+
+/// A uniform random sample of `sample_size` rows from a single-traversal query. The
+/// traversal is read in RANGE windows and sampled as it streams in, so only the sample
+/// is kept; the response is flagged as sampled.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_dynamic_hql_sample(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    code: String,
+    params: Option<serde_json::Value>,
+    sample_size: usize,
+    api_key: Option<String>,
+    profile: Option<String>,
+) -> Result<crate::hql::sample::HqlSample, String> {
+    let policy = policy::effective_policy(&app, Some(&url))?;
+    let redaction_rules = redaction::rules_for(&app, &url);
+    let (target, conn, start, end) = open_single_traversal(&app, state.inner(), &policy, &url, code, params, api_key, profile).await?;
+    let mut sample = crate::hql::sample::sample_pipeline(&target, &conn, start, end, sample_size).await?;
+    sample.rows = crate::demo::apply(redaction::redact(translator::normalize_value(sample.rows), &redaction_rules));
+    Ok(sample)
 }
 
 #[tauri::command]
@@ -448,7 +1394,7 @@ pub fn get_hql_completion(code: String, cursor: usize, schema: Option<SchemaSumm
         "ShortestPath", "ShortestPathDijkstras", "ShortestPathBFS", "ShortestPathAStar",
         "PREFILTER", "RerankRRF", "RerankMMR", "Embed",
         "AddN", "AddE", "AddV", "BatchAddV", "UpsertN", "UpsertE", "UpsertV",
-        "WHERE", "ORDER", "RANGE", "COUNT", "FIRST", "AGGREGATE_BY", "GROUP_BY", "ID",
+        "WHERE", "ORDER", "RANGE", "COUNT", "FIRST", "DEDUP", "AGGREGATE_BY", "GROUP_BY", "ID",
         "AND", "OR", "GT", "GTE", "LT", "LTE", "EQ", "NEQ", "IS_IN", "CONTAINS",
         "Asc", "Desc"
     ];
@@ -1203,3 +2149,193 @@ pub fn get_vector_projections(vectors: Vec<Vec<f64>>) -> Result<Vec<Vec<f64>>, S
     }
     Ok(projection)
 }
+
+#[tauri::command]
+pub fn list_translation_profiles(app: tauri::AppHandle) -> Vec<profiles::TranslationProfile> {
+    profiles::load_profiles(&app)
+}
+
+/// Compares the local `ToolArgs` mirror against what the connected server accepts.
+#[tauri::command]
+pub async fn check_protocol_compat(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+    profile: Option<String>
+) -> Result<compat::CompatReport, String> {
+    let server_version = crate::capabilities::probed(&state.server_capabilities, &url).and_then(|c| c.version);
+    let profile = profiles::select_profile(&profiles::load_profiles(&app), server_version.as_deref(), profile.as_deref())?;
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let target = executor::McpTarget::new(state.client_for(&app, &url)?, &url, auth, profile);
+    compat::check_protocol_compat(&target, server_version).await
+}
+
+/// Samples `sample_size` edges of each edge type in `schema` and reports those whose
+/// endpoints do not match the schema's direction, plus missing counterparts of
+/// reciprocal edge types (inferred unless `reciprocal` pairs are given). Edge types
+/// the workspace policy hides are skipped.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn audit_edge_directions(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+    schema: crate::hql::cookbook::CookbookSchema,
+    reciprocal: Option<Vec<(String, String)>>,
+    sample_size: Option<usize>,
+    profile: Option<String>,
+) -> Result<crate::hql::edge_audit::EdgeAuditReport, String> {
+    let policy = policy::effective_policy(&app, Some(&url))?;
+    let mut schema = schema;
+    schema.edges.retain(|e| {
+        let labels = [Some(&e.name), e.from.as_ref(), e.to.as_ref()];
+        policy.check_labels(labels.into_iter().flatten().map(String::as_str)).is_ok()
+    });
+
+    let server_version = crate::capabilities::probed(&state.server_capabilities, &url).and_then(|c| c.version);
+    let profile = profiles::select_profile(&profiles::load_profiles(&app), server_version.as_deref(), profile.as_deref())?;
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let target = executor::McpTarget::new(state.client_for(&app, &url)?, &url, auth, profile)
+        .with_pool(state.mcp_pool.clone())
+        .with_capabilities(state.server_capabilities.clone())
+        .with_rate_limit(state.rate_limit_for(&app, &url));
+    let sample_size = sample_size.unwrap_or(crate::hql::edge_audit::DEFAULT_SAMPLE_SIZE);
+    Ok(crate::hql::edge_audit::audit_edges(&target, &schema, reciprocal.as_deref(), sample_size).await)
+}
+
+/// Keeps `result` under `label` across sessions, for comparing later.
+#[tauri::command]
+pub fn pin_result(app: tauri::AppHandle, label: String, url: String, code: Option<String>, result: serde_json::Value) -> Result<crate::pinned::PinnedSummary, String> {
+    crate::pinned::pin(&app, label, url, code, result)
+}
+
+#[tauri::command]
+pub fn list_pinned_results(app: tauri::AppHandle) -> Result<Vec<crate::pinned::PinnedSummary>, String> {
+    crate::pinned::list(&app)
+}
+
+#[tauri::command]
+pub fn get_pinned_result(app: tauri::AppHandle, id: String) -> Result<crate::pinned::PinnedResult, String> {
+    crate::pinned::get(&app, &id)
+}
+
+#[tauri::command]
+pub fn relabel_pinned_result(app: tauri::AppHandle, id: String, label: String) -> Result<(), String> {
+    crate::pinned::relabel(&app, &id, label)
+}
+
+/// Returns false when no pin had `id`.
+#[tauri::command]
+pub fn unpin_result(app: tauri::AppHandle, id: String) -> Result<bool, String> {
+    crate::pinned::unpin(&app, &id)
+}
+
+/// Aligns two pinned results on `key` (default `id`) for the split compare view. For
+/// multi-return results, `variable` picks the list to compare.
+#[tauri::command]
+pub fn compare_pinned(
+    app: tauri::AppHandle,
+    left_id: String,
+    right_id: String,
+    key: Option<String>,
+    variable: Option<String>,
+) -> Result<crate::pinned::Comparison, String> {
+    crate::pinned::compare(&app, &left_id, &right_id, key.as_deref(), variable.as_deref())
+}
+
+/// Screen-reader friendly description of a result set.
+#[tauri::command]
+pub fn summarize_result(result: serde_json::Value) -> crate::summary::ResultSummary {
+    crate::summary::summarize_result(&result)
+}
+
+/// Evaluates a worksheet's `// @expect` comments against the result it produced.
+#[tauri::command]
+pub fn check_hql_expectations(code: String, result: serde_json::Value) -> Vec<crate::hql::assertions::ExpectationResult> {
+    crate::hql::assertions::check_expectations(&code, &result)
+}
+
+/// Turns the literals of a query into parameters; returns the rewritten query and the
+/// parameter JSON that reproduces the original.
+#[tauri::command]
+pub fn parameterize_query(code: String) -> Result<crate::hql::parameterize::ParameterizedQuery, String> {
+    crate::hql::parameterize::parameterize(&code)
+}
+
+/// A seed query that builds `scenario` from the schema's labels. Writes need a compiled
+/// query, so it is synced into the project and called like the onboarding seed.
+#[tauri::command]
+pub fn generate_seed_query(
+    schema: crate::hql::cookbook::CookbookSchema,
+    scenario: crate::hql::seed::SeedScenario,
+    size: Option<usize>,
+    edge: Option<String>,
+) -> Result<crate::hql::seed::SeedQuery, String> {
+    crate::hql::seed::generate_seed(&schema, scenario, size, edge.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hql::tool_args::ToolArgs;
+
+    fn first_query(code: &str) -> Query {
+        parse_dynamic_source(code).expect("test query should parse").queries.remove(0)
+    }
+
+    fn shape_of(query: &Query) -> Result<QueryShape<'_>, String> {
+        query_shape(query, &mut serde_json::json!({}), &environment::Environment::new())
+    }
+
+    #[test]
+    fn test_writes_data_walks_steps() {
+        assert!(!writes_data(&first_query("QUERY Read(id: ID) =>\n    user <- N<User>(id)\n    RETURN user")));
+        assert!(writes_data(&first_query("QUERY Add() =>\n    user <- AddN<User>({ name: \"Ann\" })\n    RETURN user")));
+        assert!(writes_data(&first_query(
+            "QUERY Rename(id: ID) =>\n    user <- N<User>(id)::UPDATE({ name: \"Ann\" })\n    RETURN user"
+        )));
+        assert!(writes_data(&first_query(
+            "QUERY Ensure(name: String) =>\n    user <- N<User>::WHERE(_::{name}::EQ(name))::UpsertN({ name: name })\n    RETURN user"
+        )));
+    }
+
+    #[test]
+    fn test_object_return_keys_by_alias() {
+        let query = first_query("QUERY T() =>\n    user <- N<User>\n    RETURN { author: user, posts: user::Out<Authored> }");
+        let shape = shape_of(&query).unwrap();
+        assert_eq!(shape.output_order, vec!["author", "posts"]);
+        assert_eq!(shape.return_aliases, vec![
+            ("author".to_string(), "user".to_string()),
+            ("posts".to_string(), "_return_posts".to_string()),
+        ]);
+        assert!(shape.has_derived_returns);
+        assert_eq!(shape.hidden_vars, vec!["user", "_return_posts"]);
+        assert_eq!(shape.return_vars, shape.hidden_vars);
+        let (_, _, posts) = resolve_variables(&shape).unwrap().into_iter().find(|(name, _, _)| name == "_return_posts").unwrap();
+        let plan = translator::map_traversal_to_tools(&posts.unwrap(), &serde_json::json!({})).unwrap();
+        assert!(matches!(plan.tools[..], [ToolArgs::NFromType { .. }, ToolArgs::OutStep { .. }]));
+
+        let nested = first_query("QUERY T() =>\n    user <- N<User>\n    RETURN { author: { who: user } }");
+        assert!(shape_of(&nested).err().unwrap().contains("field 'author'"));
+    }
+
+    #[test]
+    fn test_inline_return_traversals_keyed_by_source() {
+        let query = first_query("QUERY T() =>\n    user <- N<User>\n    RETURN user, user::Out<Authored>::COUNT");
+        let shape = shape_of(&query).unwrap();
+        assert_eq!(shape.output_order, vec!["user", "user::Out<Authored>::COUNT"]);
+        assert_eq!(shape.return_vars, shape.output_order);
+        let resolved = resolve_variables(&shape).unwrap();
+        let (_, _, count) = resolved.iter().find(|(name, _, _)| name == "user::Out<Authored>::COUNT").unwrap();
+        let plan = translator::map_traversal_to_tools(count.as_ref().unwrap(), &serde_json::json!({})).unwrap();
+        assert!(matches!(plan.tools[..], [ToolArgs::NFromType { .. }, ToolArgs::OutStep { .. }]));
+        assert_eq!(plan.final_action, translator::FinalAction::Count);
+
+        let query = first_query("QUERY T() =>\n    user <- N<User>\n    RETURN user::COUNT::Out<Authored>");
+        let shape = shape_of(&query).unwrap();
+        let (_, _, misplaced) = resolve_variables(&shape).unwrap().remove(0);
+        assert!(translator::map_traversal_to_tools(&misplaced.unwrap(), &serde_json::json!({})).is_err());
+    }
+}
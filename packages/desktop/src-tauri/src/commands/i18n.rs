@@ -0,0 +1,13 @@
+use crate::i18n::{self, LocaleMessages};
+
+/// The active catalog, or the catalog for `locale` when previewing another language.
+#[tauri::command]
+pub fn get_locale_messages(app: tauri::AppHandle, locale: Option<String>) -> LocaleMessages {
+    i18n::get_messages(&app, locale.as_deref())
+}
+
+/// Switches the language of backend messages and remembers it across restarts.
+#[tauri::command]
+pub fn set_locale(app: tauri::AppHandle, locale: String) -> Result<LocaleMessages, String> {
+    i18n::set_locale(&app, &locale)
+}
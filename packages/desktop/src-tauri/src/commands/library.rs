@@ -0,0 +1,92 @@
+use crate::hql::cookbook::{self, CookbookSchema};
+use crate::library::{self, SavedQuery};
+use crate::library_sync::{self, ConflictResolutions, LibraryRepoSettings, LibraryRepoStatus, LibrarySyncResult};
+use crate::share::{self, QueryLink, SharedQuery};
+use crate::versions;
+
+#[tauri::command]
+pub fn list_saved_queries(app: tauri::AppHandle) -> Result<Vec<SavedQuery>, String> {
+    Ok(library::load_library(&app)?.queries)
+}
+
+#[tauri::command]
+pub fn save_query(app: tauri::AppHandle, query: SavedQuery) -> Result<(), String> {
+    if let Err(e) = versions::snapshot(&app, &query.id, "save", &query.code, query.params.clone()) {
+        println!(">>> [Versions] Failed to snapshot {}: {}", query.id, e);
+    }
+    let mut lib = library::load_library(&app)?;
+    lib.upsert(query);
+    library::save_library(&app, &lib)
+}
+
+#[tauri::command]
+pub fn delete_query(app: tauri::AppHandle, id: String) -> Result<bool, String> {
+    let mut lib = library::load_library(&app)?;
+    let removed = lib.remove(&id);
+    library::save_library(&app, &lib)?;
+    Ok(removed)
+}
+
+/// Regenerates the "Examples" folder from the connected schema.
+#[tauri::command]
+pub fn generate_cookbook(app: tauri::AppHandle, schema: CookbookSchema) -> Result<Vec<SavedQuery>, String> {
+    let examples = cookbook::generate_cookbook(&schema);
+    let mut lib = library::load_library(&app)?;
+    lib.queries.retain(|q| !cookbook::is_example(q));
+    for example in &examples {
+        lib.upsert(example.clone());
+    }
+    library::save_library(&app, &lib)?;
+    Ok(examples)
+}
+
+/// Packs a query into a deep link; with `path`, also writes it as a `.helixq` file.
+#[tauri::command]
+pub fn encode_query_link(app: tauri::AppHandle, query: SharedQuery, path: Option<String>) -> Result<QueryLink, String> {
+    crate::policy::effective_policy(&app, None)?.check_export()?;
+    let link = share::encode_link(&query)?;
+    if let Some(path) = path {
+        std::fs::write(&path, format!("{}\n", link.link))
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(link)
+}
+
+/// Opens a link, a bare payload, or a `.helixq` file at `path`.
+#[tauri::command]
+pub fn decode_query_link(link: Option<String>, path: Option<String>) -> Result<SharedQuery, String> {
+    let input = match (link, path) {
+        (Some(link), _) => link,
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+        (None, None) => return Err("No link or file given".to_string()),
+    };
+    share::decode_link(&input)
+}
+
+/// Backs the library with a git repository, cloning it and merging its queries in.
+#[tauri::command]
+pub async fn configure_library_repo(app: tauri::AppHandle, settings: LibraryRepoSettings) -> Result<LibrarySyncResult, String> {
+    tauri::async_runtime::spawn_blocking(move || library_sync::configure(&library_sync::config_dir(&app)?, settings))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn library_repo_status(app: tauri::AppHandle) -> Result<LibraryRepoStatus, String> {
+    library_sync::status(&library_sync::config_dir(&app)?)
+}
+
+#[tauri::command]
+pub async fn pull_library_repo(app: tauri::AppHandle, resolutions: Option<ConflictResolutions>) -> Result<LibrarySyncResult, String> {
+    tauri::async_runtime::spawn_blocking(move || library_sync::pull(&library_sync::config_dir(&app)?, &resolutions.unwrap_or_default()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn push_library_repo(app: tauri::AppHandle, message: Option<String>) -> Result<LibrarySyncResult, String> {
+    tauri::async_runtime::spawn_blocking(move || library_sync::push(&library_sync::config_dir(&app)?, message.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
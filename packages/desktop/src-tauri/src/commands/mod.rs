@@ -5,6 +5,11 @@ pub mod config;
 pub mod sync;
 pub mod stats;
 pub mod ui;
+pub mod onboarding;
+pub mod tutorials;
+pub mod library;
+pub mod i18n;
+pub mod versions;
 
 pub use system::*;
 pub use network::*;
@@ -13,3 +18,8 @@ pub use config::*;
 pub use sync::*;
 pub use stats::*;
 pub use ui::*;
+pub use onboarding::*;
+pub use tutorials::*;
+pub use library::*;
+pub use i18n::*;
+pub use versions::*;
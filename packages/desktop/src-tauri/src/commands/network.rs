@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use tauri::Manager;
+use crate::network_trace::SendTraced;
 
 pub fn map_reqwest_error(e: reqwest::Error, prefix: &str) -> String {
     if e.is_connect() {
@@ -17,8 +19,56 @@ pub fn map_reqwest_error(e: reqwest::Error, prefix: &str) -> String {
     format!("{}: {}", prefix, err_str)
 }
 
+/// Connecting gives up after this on every request path; reading is bounded by the
+/// query timeout.
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Every MCP connection in use can have a tool call in flight, each on its own socket;
+/// keeping that many idle lets the next burst reuse them instead of reconnecting (and,
+/// for TLS gateways, redoing the handshake).
+const POOL_MAX_IDLE_PER_HOST: usize = crate::hql::pool::MAX_CONNECTIONS_PER_TARGET;
+/// Idle sockets are dropped before common server keep-alive limits close them, so a
+/// tool call does not land on a socket the gateway has just shut.
+const POOL_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+const TCP_KEEPALIVE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The one HTTP client behind `NetworkState` and the CLI. Gateways are local or reached
+/// directly, so system proxies are ignored; connections with a `proxy` get their own
+/// client (see `connection_clients`).
+pub fn build_client() -> Result<reqwest::Client, String> {
+    client_builder()
+        .no_proxy()
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Pooling and timeouts shared by every client. Tool calls are small requests sent in
+/// quick succession, so Nagle's delay is turned off.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(Some(TCP_KEEPALIVE))
+        .tcp_nodelay(true)
+        .connect_timeout(CONNECT_TIMEOUT)
+}
+
+/// The compiled query a request calls: any POST outside the built-in gateway routes.
+fn compiled_query_name<'a>(method: &str, url: &'a str) -> Option<&'a str> {
+    if !method.eq_ignore_ascii_case("POST") {
+        return None;
+    }
+    let path = url.split("://").nth(1).unwrap_or(url);
+    let path = path.split_once('/').map(|(_, p)| p).unwrap_or("");
+    let path = path.split('?').next().unwrap_or_default().trim_matches('/');
+    if path.is_empty() || path.starts_with("mcp/") || path.contains('/') {
+        return None;
+    }
+    Some(path)
+}
+
 #[tauri::command]
 pub async fn helix_request(
+    app: tauri::AppHandle,
     state: tauri::State<'_, crate::NetworkState>,
     method: String,
     url: String,
@@ -26,8 +76,16 @@ pub async fn helix_request(
     body: Option<String>,
     timeout_ms: Option<u64>,
 ) -> Result<String, String> {
-    let client = &state.client;
-    let timeout = std::time::Duration::from_millis(timeout_ms.unwrap_or(60000));
+    let client = &state.client_for(&app, &url)?;
+    let timeout = crate::config::query_timeout(&app, &url, timeout_ms);
+    let compiled_policy = match compiled_query_name(&method, &url) {
+        Some(query_name) => {
+            let policy = crate::policy::effective_policy(&app, Some(&url))?;
+            policy.check_compiled_query(query_name)?;
+            Some(policy)
+        }
+        None => None,
+    };
 
     let method_type = match method.to_uppercase().as_str() {
         "GET" => reqwest::Method::GET,
@@ -37,7 +95,9 @@ pub async fn helix_request(
         _ => return Err(format!("Unsupported method: {}", method)),
     };
 
-    let mut req = client.request(method_type, &url).timeout(timeout);
+    // The frontend sends the API key itself; the connection's own headers fill in the rest
+    let set: Vec<&str> = headers.keys().map(String::as_str).collect();
+    let mut req = crate::gateway_auth::GatewayAuth::for_url(&app, &url, None).apply_except(client.request(method_type, &url).timeout(timeout), &set);
 
     for (key, value) in headers {
         req = req.header(key, value);
@@ -47,15 +107,27 @@ pub async fn helix_request(
         req = req.body(b);
     }
 
-    let resp = req.send().await.map_err(|e| {
-        map_reqwest_error(e, "Request error")
-    })?;
+    let resp = req.send_traced().await
+        .map_err(|e| if e.is_timeout() { crate::config::timed_out_error(timeout) } else { map_reqwest_error(e, "Request error") })?;
 
     let status = resp.status();
-    let text = resp.text().await.unwrap_or_default();
+    let text = resp.text().await
+        .map_err(|e| if e.is_timeout() { crate::config::timed_out_error(timeout) } else { format!("Failed to read response: {}", e) })?;
     
     if status.is_success() {
-        Ok(text)
+        if let Some(policy) = compiled_policy.filter(|p| p.allowed_labels.is_some()) {
+            let json = serde_json::from_str(&text).map_err(|e| format!("Failed to parse response: {}", e))?;
+            policy.check_result_labels(&json)?;
+        }
+        let rules = crate::redaction::rules_for(&app, &url);
+        if rules.is_empty() && !crate::demo::is_enabled() {
+            return Ok(text);
+        }
+        // Node details and graph data come through here too, so JSON bodies are redacted
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => serde_json::to_string(&crate::demo::apply(crate::redaction::redact(json, &rules))).map_err(|e| e.to_string()),
+            Err(_) => Ok(text),
+        }
     } else {
         Err(format!("Server responded with status {}: {}", status, text))
     }
@@ -63,32 +135,62 @@ pub async fn helix_request(
 
 #[tauri::command]
 pub async fn execute_query(
+    app: tauri::AppHandle,
     state: tauri::State<'_, crate::NetworkState>,
     url: String, 
     query_name: String, 
     args: serde_json::Value, 
-    api_key: Option<String>
+    api_key: Option<String>,
+    timeout_ms: Option<u64>,
 ) -> Result<serde_json::Value, String> {
-    let client = &state.client;
-    
-    let url = format!("{}/{}", url, query_name);
-    
-    let mut req = client.post(url)
-        .json(&args);
+    let result = crate::network_trace::record(&url, call_compiled_query(&app, state.inner(), &url, &query_name, args, api_key, timeout_ms)).await;
+    crate::usage::record_result(&app, &url, result.as_ref().ok());
+    result
+}
 
-    if let Some(key) = api_key {
-        req = req.header("x-api-key", key);
+/// Calls the compiled endpoint of `query_name` under the connection's policy, environment,
+/// row limit and redaction. Arguments are checked first when `list_compiled_queries` has
+/// described the query.
+pub async fn call_compiled_query(
+    app: &tauri::AppHandle,
+    state: &crate::NetworkState,
+    url: &str,
+    query_name: &str,
+    args: serde_json::Value,
+    api_key: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+    let policy = crate::policy::effective_policy(app, Some(url))?;
+    policy.check_compiled_query(query_name)?;
+    let redaction_rules = crate::redaction::rules_for(app, url);
+    
+    let args = crate::environment::resolve(args, &crate::environment::connection_environment(app, url))?;
+    let described = state.compiled_queries.lock().unwrap().get(url)
+        .and_then(|queries| queries.iter().find(|q| q.name == query_name).cloned());
+    if let Some(query) = described {
+        query.validate_args(&args)?;
+    }
+    let timeout = crate::config::query_timeout(app, url, timeout_ms);
+    let endpoint = format!("{}/{}", url, query_name);
+    if let Some(limiter) = state.rate_limit_for(app, url) {
+        limiter.wait().await;
     }
     
-    let resp = req.send()
+    let req = state.client_for(app, url)?.post(endpoint)
+        .timeout(timeout)
+        .json(&args);
+    let resp = crate::gateway_auth::GatewayAuth::for_url(app, url, api_key).apply(req).send_traced()
         .await
-        .map_err(|e| map_reqwest_error(e, "Request failed"))?;
+        .map_err(|e| if e.is_timeout() { crate::config::timed_out_error(timeout) } else { map_reqwest_error(e, "Request failed") })?;
 
     if resp.status().is_success() {
         let json: serde_json::Value = resp.json()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-        Ok(json)
+            .map_err(|e| if e.is_timeout() { crate::config::timed_out_error(timeout) } else { format!("Failed to parse response: {}", e) })?;
+        policy.check_result_labels(&json)?;
+        Ok(crate::demo::apply(crate::redaction::redact(policy.limit_rows(json), &redaction_rules)))
+    } else if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        Err(crate::i18n::t("hql.no_compiled_endpoint", &[("name", query_name)]))
     } else {
         let status = resp.status();
         let err_text = resp.text().await.unwrap_or_else(|_| String::new());
@@ -98,18 +200,42 @@ pub async fn execute_query(
 
 #[tauri::command]
 pub async fn fetch_mcp_schema(
-    state: tauri::State<'_, crate::NetworkState>,
+    app: tauri::AppHandle,
     url: String, 
     api_key: Option<String>
 ) -> Result<serde_json::Value, String> {
-    let client = &state.client;
+    cached_schema(&app, &url, api_key.as_deref()).await
+}
 
-    let mut init_req = client.post(format!("{}/mcp/init", url));
-    if let Some(key) = &api_key {
-        init_req = init_req.header("x-api-key", key);
+/// Drops the cached schema for `url` and fetches it again.
+#[tauri::command]
+pub async fn refresh_schema(
+    app: tauri::AppHandle,
+    url: String,
+    api_key: Option<String>,
+) -> Result<serde_json::Value, String> {
+    app.state::<crate::NetworkState>().schema_cache.invalidate(Some(&url));
+    cached_schema(&app, &url, api_key.as_deref()).await
+}
+
+/// `url`'s schema from the cache while it is fresh, else from the server.
+pub async fn cached_schema(app: &tauri::AppHandle, url: &str, api_key: Option<&str>) -> Result<serde_json::Value, String> {
+    let state = app.state::<crate::NetworkState>();
+    let ttl = crate::schema_cache::ttl_for(app, url);
+    if let Some(schema) = state.schema_cache.get(url, ttl) {
+        return Ok(schema);
+    }
+    let auth = crate::gateway_auth::GatewayAuth::for_url(app, url, api_key.map(String::from));
+    let schema = mcp_schema(&state.client_for(app, url)?, url, &auth).await?;
+    if !ttl.is_zero() {
+        state.schema_cache.put(url, schema.clone());
     }
+    Ok(schema)
+}
 
-    let init_resp = init_req.send()
+/// The server's schema through MCP `schema_resource`, parsed from its string form.
+pub async fn mcp_schema(client: &reqwest::Client, url: &str, auth: &crate::gateway_auth::GatewayAuth) -> Result<serde_json::Value, String> {
+    let init_resp = auth.apply(client.post(format!("{}/mcp/init", url))).send_traced()
         .await
         .map_err(|e| {
             map_reqwest_error(e, "Init failed")
@@ -125,14 +251,9 @@ pub async fn fetch_mcp_schema(
     let connection_id: String = serde_json::from_str(&init_body)
         .map_err(|e| format!("Failed to parse connection_id from '{}': {}", init_body, e))?;
 
-    let mut schema_req = client.post(format!("{}/mcp/schema_resource", url))
+    let schema_req = client.post(format!("{}/mcp/schema_resource", url))
         .json(&serde_json::json!({ "connection_id": connection_id }));
-    
-    if let Some(key) = &api_key {
-        schema_req = schema_req.header("x-api-key", key);
-    }
-
-    let schema_resp = schema_req.send()
+    let schema_resp = auth.apply(schema_req).send_traced()
         .await
         .map_err(|e| map_reqwest_error(e, "Schema request failed"))?;
 
@@ -156,3 +277,144 @@ pub async fn fetch_mcp_schema(
     
     Ok(val)
 }
+
+#[tauri::command]
+pub async fn get_server_capabilities(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+    refresh: Option<bool>,
+) -> Result<crate::capabilities::ServerCapabilities, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(caps) = crate::capabilities::probed(&state.server_capabilities, &url) {
+            return Ok(caps);
+        }
+    }
+
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let mut caps = crate::capabilities::probe_capabilities(&state.client_for(&app, &url)?, &url, &auth).await?;
+    let mut cache = state.server_capabilities.lock().unwrap();
+    caps.filters_ids = cache.get(&url).and_then(|old| old.filters_ids);
+    cache.insert(url, caps.clone());
+    Ok(caps)
+}
+
+/// Forwards `url`'s instance events to the window as `instance-event` until
+/// `unsubscribe_instance_events` is called with the returned id.
+#[tauri::command]
+pub fn subscribe_instance_events(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+) -> Result<u64, String> {
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    Ok(crate::instance_events::subscribe(&app, state.client_for(&app, &url)?, &url, auth))
+}
+
+#[tauri::command]
+pub fn unsubscribe_instance_events(app: tauri::AppHandle, id: u64) -> bool {
+    crate::instance_events::unsubscribe(&app, id)
+}
+
+/// Turns the network trace on or off. Turning it off keeps what was recorded.
+#[tauri::command]
+pub fn set_network_trace(enabled: bool) {
+    crate::network_trace::set_enabled(enabled);
+}
+
+/// The last `limit` traced requests (all kept ones by default), oldest first, with each
+/// connection's redaction applied to the bodies.
+#[tauri::command]
+pub fn get_network_trace(app: tauri::AppHandle, limit: Option<usize>) -> Vec<crate::network_trace::TraceEntry> {
+    let mut entries = crate::network_trace::entries(limit);
+    for entry in &mut entries {
+        crate::network_trace::redact_entry(entry, &crate::redaction::rules_for(&app, &entry.url));
+    }
+    entries
+}
+
+#[tauri::command]
+pub fn clear_network_trace() {
+    crate::network_trace::clear();
+}
+
+/// Puts the last executed query on the clipboard as a curl script that replays its
+/// requests, and returns the script. Secrets are read from environment variables.
+#[tauri::command]
+pub fn copy_last_request_as_curl(app: tauri::AppHandle) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+    let execution = crate::network_trace::last_execution().ok_or("No query has been executed yet")?;
+    let script = crate::curl::render(&execution);
+    app.clipboard().write_text(script.clone()).map_err(|e| format!("Failed to copy to the clipboard: {}", e))?;
+    Ok(script)
+}
+
+/// The SSH tunnel of the connection `url` points at: its local port, whether its session
+/// is up and why forwarding last failed. None without a tunnel or before its first request.
+#[tauri::command]
+pub async fn get_ssh_tunnel_status(app: tauri::AppHandle, url: String) -> Result<Option<crate::ssh_tunnel::TunnelStatus>, String> {
+    let connection = crate::policy::find_connection(&app, Some(&url));
+    match crate::ssh_tunnel::TunnelSettings::of_connection(connection.as_ref())? {
+        Some(settings) => Ok(crate::ssh_tunnel::status(&settings).await),
+        None => Ok(None),
+    }
+}
+
+/// Trusts the host key the SSH tunnel of `url` was refused for, after the user checked
+/// `fingerprint`, as shown by `get_ssh_tunnel_status`, against the server's.
+#[tauri::command]
+pub fn trust_ssh_host_key(app: tauri::AppHandle, url: String, fingerprint: String) -> Result<(), String> {
+    let connection = crate::policy::find_connection(&app, Some(&url));
+    let settings = crate::ssh_tunnel::TunnelSettings::of_connection(connection.as_ref())?
+        .ok_or("This connection has no SSH tunnel")?;
+    crate::ssh_tunnel::trust_host_key(&settings, &fingerprint)
+}
+
+/// Pings the gateway, MCP and a compiled endpoint of `url` and times each, for the
+/// connection list's status dots.
+#[tauri::command]
+pub async fn check_connection(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+) -> Result<crate::health::ConnectionHealth, String> {
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let known_query = state.compiled_queries.lock().unwrap().get(&url)
+        .and_then(|queries| queries.first())
+        .map(|q| q.name.clone());
+    Ok(crate::health::check(&state.client_for(&app, &url)?, &url, &auth, known_query).await)
+}
+
+/// Queries deployed on the gateway with their parameters, from `/introspect`. The list is
+/// kept so `execute_query` can check arguments before sending them.
+#[tauri::command]
+pub async fn list_compiled_queries(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    api_key: Option<String>,
+) -> Result<Vec<crate::compiled_queries::CompiledQuery>, String> {
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let queries = crate::compiled_queries::fetch(&state.client_for(&app, &url)?, &url, &auth).await?;
+    state.compiled_queries.lock().unwrap().insert(url, queries.clone());
+    Ok(queries)
+}
+
+#[tauri::command]
+pub async fn describe_query(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    url: String,
+    name: String,
+    api_key: Option<String>,
+) -> Result<crate::compiled_queries::CompiledQuery, String> {
+    let auth = crate::gateway_auth::GatewayAuth::for_url(&app, &url, api_key);
+    let queries = crate::compiled_queries::fetch(&state.client_for(&app, &url)?, &url, &auth).await?;
+    state.compiled_queries.lock().unwrap().insert(url, queries.clone());
+    queries.into_iter()
+        .find(|q| q.name == name)
+        .ok_or_else(|| crate::i18n::t("hql.no_compiled_endpoint", &[("name", &name)]))
+}
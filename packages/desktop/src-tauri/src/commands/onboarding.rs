@@ -0,0 +1,15 @@
+use crate::onboarding::{self, OnboardingEnvironment, OnboardingOptions, OnboardingResult};
+
+#[tauri::command]
+pub async fn detect_onboarding_environment(app: tauri::AppHandle) -> Result<OnboardingEnvironment, String> {
+    Ok(onboarding::detect_environment(&app).await)
+}
+
+#[tauri::command]
+pub async fn run_onboarding(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    options: Option<OnboardingOptions>
+) -> Result<OnboardingResult, String> {
+    onboarding::run_onboarding(&app, &state.client, options.unwrap_or_default()).await
+}
@@ -1,3 +1,5 @@
+use crate::network_trace::SendTraced;
+
 #[tauri::command]
 pub async fn get_local_db_stats(path: String, instance_name: Option<String>) -> Result<crate::stats::LocalStorageStats, String> {
     crate::stats::get_local_db_stats(&path, instance_name.as_deref())
@@ -7,3 +9,36 @@ pub async fn get_local_db_stats(path: String, instance_name: Option<String>) ->
 pub async fn validate_helix_workspace(path: String) -> Result<bool, String> {
     crate::stats::validate_helix_workspace(&path)
 }
+
+#[tauri::command]
+pub async fn verify_local_db(path: String, instance_name: Option<String>) -> Result<crate::db_verify::VerificationReport, String> {
+    tauri::async_runtime::spawn_blocking(move || crate::db_verify::verify_local_db(&path, instance_name.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn compact_local_db(path: String, instance_name: Option<String>) -> Result<crate::stats::CompactionReport, String> {
+    tauri::async_runtime::spawn_blocking(move || crate::stats::compact_local_db(&path, instance_name.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Swaps in the compacted copy. `url` is the workspace's connection; the swap goes ahead
+/// only when nothing listens there, so a slow or erroring instance still counts as running.
+#[tauri::command]
+pub async fn swap_compacted_db(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::NetworkState>,
+    path: String,
+    instance_name: Option<String>,
+    url: String,
+) -> Result<crate::stats::CompactionReport, String> {
+    let probe = state.client_for(&app, &url)?.get(&url).timeout(std::time::Duration::from_secs(2)).send_traced().await;
+    if !matches!(&probe, Err(e) if e.is_connect()) {
+        return Err(format!("The instance at {} is still running; stop it before swapping in the compacted database", url));
+    }
+    tauri::async_runtime::spawn_blocking(move || crate::stats::swap_compacted_db(&path, instance_name.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -4,7 +4,7 @@ use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
 use helix_db::helixc::parser::types::*;
 use crate::hql::analyzer::{self, LitType};
 
-#[derive(serde::Serialize, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct PendingSyncItem {
     pub query_name: String,
     pub old_code: String,
@@ -19,30 +19,65 @@ pub enum SyncResponse {
     Pending(Vec<PendingSyncItem>),
 }
 
+/// Unified diffs (with word-level changes) for the items of a pending sync, in order.
+#[tauri::command]
+pub fn compute_sync_diff(items: Vec<PendingSyncItem>, context: Option<usize>) -> Vec<crate::diff::UnifiedDiff> {
+    let context = context.unwrap_or(crate::diff::DEFAULT_CONTEXT);
+    items.iter()
+        .map(|item| crate::diff::unified_diff(&item.query_name, &item.old_code, &item.new_code, context))
+        .collect()
+}
+
+/// The outcome of merging queries into a project's queries.hx, before anything is written.
+enum PreparedSync {
+    Pending(Vec<PendingSyncItem>),
+    Ready { path: std::path::PathBuf, old_content: String, content: String },
+}
+
+fn log(logs: &mut String, msg: &str) {
+    logs.push_str(msg);
+    logs.push('\n');
+}
+
+fn write_prepared(path: &std::path::Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-pub async fn sync_hql_to_project(code: String, local_path: String, force: bool) -> Result<SyncResponse, String> {
+pub async fn sync_hql_to_project(app: tauri::AppHandle, code: String, local_path: String, force: bool) -> Result<SyncResponse, String> {
+    crate::policy::project_policy(&app, std::path::Path::new(&local_path))?.check_writes()?;
     let mut logs = String::new();
-    fn log(logs: &mut String, msg: &str) {
-        logs.push_str(msg);
-        logs.push('\n');
+    match prepare_sync(&code, &local_path, force, &mut logs)? {
+        PreparedSync::Pending(items) => Ok(SyncResponse::Pending(items)),
+        PreparedSync::Ready { path, content, .. } => {
+            write_prepared(&path, &content)?;
+            Ok(SyncResponse::Success(logs))
+        }
     }
+}
 
-    log(&mut logs, &format!(">>> [Sync] Starting HQL Sync to project: {}", local_path));
+/// Merges the queries in `code` into the project's queries.hx in memory. Queries that
+/// already exist there are reported as conflicts unless `force` is set.
+fn prepare_sync(code: &str, local_path: &str, force: bool, logs: &mut String) -> Result<PreparedSync, String> {
+    log(logs, &format!(">>> [Sync] Starting HQL Sync to project: {}", local_path));
     
-    let root_path = std::path::Path::new(&local_path);
+    let root_path = std::path::Path::new(local_path);
     if !root_path.exists() {
         return Err(format!("Local path does not exist: {}", local_path));
     }
 
     let queries_path = root_path.join("db").join("queries.hx");
-    log(&mut logs, &format!(">>> [Sync] Target queries file resolved to: {:?}", queries_path));
+    log(logs, &format!(">>> [Sync] Target queries file resolved to: {:?}", queries_path));
 
     let re_purify = regex::Regex::new(r#"(?x)
         (\w+\s*:\s*[A-Za-z0-9_<>]+)
         \s*=\s*
         ('[^']*'|"[^"]*"|[\d\.]+|true|false)
     "#).unwrap();
-    let purified_code = re_purify.replace_all(&code, "$1").to_string();
+    let purified_code = re_purify.replace_all(code, "$1").to_string();
 
     let incoming_content = write_to_temp_file(vec![&purified_code]);
     let incoming_source = HelixParser::parse_source(&incoming_content)
@@ -67,7 +102,7 @@ pub async fn sync_hql_to_project(code: String, local_path: String, force: bool)
             .collect();
 
         if !unused_params.is_empty() && !literals.is_empty() {
-            log(&mut logs, &format!(">>> [Sync] DWIM ({}): Found {} unused params and {} candidate literals", 
+            log(logs, &format!(">>> [Sync] DWIM ({}): Found {} unused params and {} candidate literals", 
                 query.name, unused_params.len(), literals.len()));
             
             literals.sort_by(|a, b| a.0.start.cmp(&b.0.start));
@@ -103,19 +138,17 @@ pub async fn sync_hql_to_project(code: String, local_path: String, force: bool)
         fs::read_to_string(&queries_path)
             .map_err(|e| format!("Failed to read queries.hx: {}", e))?
     } else {
-        log(&mut logs, ">>> [Sync] Creating new queries.hx file");
-        if let Some(parent) = queries_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-        }
+        log(logs, ">>> [Sync] Creating new queries.hx file");
         String::new()
     };
+    let old_content = target_file_content.clone();
 
     let temp_target = write_to_temp_file(vec![&target_file_content]);
     let existing_source = if !target_file_content.trim().is_empty() {
         match HelixParser::parse_source(&temp_target) {
             Ok(src) => Some(src),
             Err(e) => {
-                log(&mut logs, &format!(">>> [Sync] Warning: Failed to parse existing queries.hx: {}. This usually means there are syntax errors or duplicate query names in the file.", e));
+                log(logs, &format!(">>> [Sync] Warning: Failed to parse existing queries.hx: {}. This usually means there are syntax errors or duplicate query names in the file.", e));
                 None
             }
         }
@@ -123,7 +156,7 @@ pub async fn sync_hql_to_project(code: String, local_path: String, force: bool)
         None
     };
 
-    let sync_marker = "// Synced from Helix Explorer";
+    let sync_marker = crate::project_queries::SYNC_MARKER;
     let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
     
     struct Change {
@@ -201,7 +234,7 @@ pub async fn sync_hql_to_project(code: String, local_path: String, force: bool)
     }
 
     if !force && !pending_items.is_empty() {
-        return Ok(SyncResponse::Pending(pending_items));
+        return Ok(PreparedSync::Pending(pending_items));
     }
 
     if !replacements.is_empty() {
@@ -238,7 +271,122 @@ pub async fn sync_hql_to_project(code: String, local_path: String, force: bool)
         target_file_content.push_str(&snippet);
     }
 
-    fs::write(&queries_path, target_file_content).map_err(|e| e.to_string())?;
-    
-    Ok(SyncResponse::Success(logs))
+    Ok(PreparedSync::Ready { path: queries_path, old_content, content: target_file_content })
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkSyncConflict {
+    pub saved_query_id: Option<String>,
+    pub item: PendingSyncItem,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkSyncReport {
+    /// Saved queries included, in the order they were written.
+    pub saved_query_ids: Vec<String>,
+    /// Every query that already exists in the project; nothing is written while any remain
+    /// unless `force` is set.
+    pub conflicts: Vec<BulkSyncConflict>,
+    /// Line diff of queries.hx, empty when there are conflicts.
+    pub diff: Vec<crate::versions::DiffLine>,
+    pub written: bool,
+    pub logs: String,
+}
+
+/// Saved queries may be bare worksheets; those become a query named after the entry.
+fn as_query_source(query: &crate::library::SavedQuery) -> String {
+    if query.code.trim_start().to_uppercase().starts_with("QUERY") {
+        return query.code.trim().to_string();
+    }
+    let mut name: String = query.name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w[..1].to_uppercase() + &w[1..])
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name = format!("Query{}", name);
+    }
+    format!("QUERY {}() =>\n{}", name, query.code.trim())
+}
+
+/// Syncs a folder (or a chosen list) of saved queries to the project's queries.hx in
+/// one write. With `preview`, only reports conflicts and the resulting diff.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sync_library_to_project(
+    app: tauri::AppHandle,
+    local_path: String,
+    folder: Option<String>,
+    query_ids: Option<Vec<String>>,
+    order: Option<crate::library::SelectionOrder>,
+    preview: bool,
+    force: bool,
+) -> Result<BulkSyncReport, String> {
+    let library = crate::library::load_library(&app)?;
+    let selected = library.select(folder.as_deref(), query_ids.as_deref(), order.unwrap_or_default());
+    if selected.is_empty() {
+        return Err("No saved queries match the selection".to_string());
+    }
+
+    let sources: Vec<(String, String)> = selected.iter().map(|q| (q.id.clone(), as_query_source(q))).collect();
+    let code = sources.iter().map(|(_, src)| src.as_str()).collect::<Vec<_>>().join("\n\n");
+    let query_re = regex::Regex::new(r"(?i)QUERY\s+(\w+)").unwrap();
+    let owner = |name: &str| sources.iter()
+        .find(|(_, src)| query_re.captures_iter(src).any(|c| &c[1] == name))
+        .map(|(id, _)| id.clone());
+
+    let mut logs = String::new();
+    let mut report = BulkSyncReport {
+        saved_query_ids: sources.iter().map(|(id, _)| id.clone()).collect(),
+        conflicts: Vec::new(),
+        diff: Vec::new(),
+        written: false,
+        logs: String::new(),
+    };
+    match prepare_sync(&code, &local_path, force, &mut logs)? {
+        PreparedSync::Pending(items) => {
+            report.conflicts = items.into_iter()
+                .map(|item| BulkSyncConflict { saved_query_id: owner(&item.query_name), item })
+                .collect();
+        }
+        PreparedSync::Ready { path, old_content, content } => {
+            report.diff = crate::versions::diff_lines(&old_content, &content);
+            if !preview {
+                crate::policy::project_policy(&app, std::path::Path::new(&local_path))?.check_writes()?;
+                write_prepared(&path, &content)?;
+                report.written = true;
+            }
+        }
+    }
+    report.logs = logs;
+    Ok(report)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanReport {
+    pub changed: bool,
+    pub diff: Vec<crate::versions::DiffLine>,
+    pub written: bool,
+}
+
+/// Normalizes sync markers and blank lines in the project's queries.hx, optionally
+/// sorting queries by name. With `dry_run`, only returns the diff.
+#[tauri::command]
+pub fn clean_project_queries(app: tauri::AppHandle, local_path: String, sort_by_name: bool, dry_run: bool) -> Result<CleanReport, String> {
+    let path = std::path::Path::new(&local_path).join("db").join("queries.hx");
+    if !path.exists() {
+        return Err(format!("No queries.hx found at {:?}", path));
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read queries.hx: {}", e))?;
+    let cleaned = crate::project_queries::clean_queries_file(&content, sort_by_name)?;
+    let changed = cleaned != content;
+    let mut report = CleanReport { changed, diff: crate::versions::diff_lines(&content, &cleaned), written: false };
+    if changed && !dry_run {
+        crate::policy::project_policy(&app, std::path::Path::new(&local_path))?.check_writes()?;
+        fs::write(&path, cleaned).map_err(|e| e.to_string())?;
+        report.written = true;
+    }
+    Ok(report)
 }
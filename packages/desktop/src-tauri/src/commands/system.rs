@@ -16,3 +16,10 @@ pub fn terminate_app(app: tauri::AppHandle) {
 pub fn detect_workspace_path(app: tauri::AppHandle, port: Option<String>) -> Result<String, String> {
     config::detect_workspace_path(&app, port)
 }
+
+/// Checks that a connection's project path still holds a helix project, and suggests
+/// where it went; with `apply`, saves the suggestion in place of a stale path.
+#[tauri::command]
+pub fn revalidate_workspace(app: tauri::AppHandle, connection_id: String, apply: Option<bool>) -> Result<crate::workspace::WorkspaceCheck, String> {
+    crate::workspace::revalidate(&app, &connection_id, apply.unwrap_or(false))
+}
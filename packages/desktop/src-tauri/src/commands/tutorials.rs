@@ -0,0 +1,50 @@
+use crate::tutorials::{self, AttemptResult, Lesson, LessonSummary};
+
+#[tauri::command]
+pub fn list_tutorials(app: tauri::AppHandle) -> Vec<LessonSummary> {
+    tutorials::summarize(&tutorials::load_lessons(&app), &tutorials::load_progress(&app))
+}
+
+#[tauri::command]
+pub fn get_tutorial(app: tauri::AppHandle, id: String) -> Result<Lesson, String> {
+    tutorials::load_lessons(&app)
+        .into_iter()
+        .find(|l| l.id == id)
+        .ok_or_else(|| format!("Tutorial '{}' not found", id))
+}
+
+#[tauri::command]
+pub fn check_tutorial_attempt(
+    app: tauri::AppHandle,
+    id: String,
+    step: usize,
+    code: String,
+    result: Option<serde_json::Value>
+) -> Result<AttemptResult, String> {
+    let lesson = get_tutorial(app.clone(), id)?;
+    let lesson_step = lesson.steps.get(step)
+        .ok_or_else(|| format!("Tutorial '{}' has no step {}", lesson.id, step))?;
+
+    let mut attempt = tutorials::check_attempt(lesson_step, &code, result.as_ref());
+    if attempt.passed {
+        let mut progress = tutorials::load_progress(&app);
+        let done = progress.entry(lesson.id.clone()).or_default();
+        if !done.contains(&step) {
+            done.push(step);
+            done.sort_unstable();
+        }
+        attempt.lesson_completed = done.len() == lesson.steps.len();
+        tutorials::save_progress(&app, &progress)?;
+    }
+    Ok(attempt)
+}
+
+#[tauri::command]
+pub fn reset_tutorial_progress(app: tauri::AppHandle, id: Option<String>) -> Result<(), String> {
+    let mut progress = tutorials::load_progress(&app);
+    match id {
+        Some(id) => { progress.remove(&id); }
+        None => progress.clear(),
+    }
+    tutorials::save_progress(&app, &progress)
+}
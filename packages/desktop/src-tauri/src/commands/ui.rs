@@ -42,13 +42,13 @@ pub fn show_grid_context_menu(
     }
 
     let copy_label = if rows.len() > 1 {
-        format!("Copy {} rows", rows.len())
+        crate::i18n::t("grid.copy_rows", &[("count", &rows.len().to_string())])
     } else {
-        "Copy".to_string()
+        crate::i18n::t("grid.copy", &[])
     };
 
     let copy_item = MenuItem::with_id(&app, "grid-copy", copy_label, true, None::<&str>).map_err(|e| e.to_string())?;
-    let copy_json_item = MenuItem::with_id(&app, "grid-copy-json", "Copy as JSON", true, None::<&str>).map_err(|e| e.to_string())?;
+    let copy_json_item = MenuItem::with_id(&app, "grid-copy-json", crate::i18n::t("grid.copy_json", &[]), true, None::<&str>).map_err(|e| e.to_string())?;
 
     let menu = Menu::with_items(&app, &[
         &copy_item,
@@ -61,3 +61,94 @@ pub fn show_grid_context_menu(
 
     Ok(())
 }
+
+/// Formats a batch of numbers, dates and byte sizes for display so every view
+/// uses the same locale rules.
+#[tauri::command]
+pub fn format_values(
+    values: Vec<crate::format::FormatRequest>,
+    settings: Option<crate::format::FormatSettings>,
+) -> Result<Vec<String>, String> {
+    crate::format::format_values(&values, &settings.unwrap_or_default())
+}
+
+/// Updates native window chrome (title bar, controls, material) when the theme
+/// changes in settings.
+#[tauri::command]
+pub fn set_window_appearance(
+    window: tauri::WebviewWindow,
+    appearance: crate::appearance::WindowAppearance,
+) -> Result<crate::appearance::AppliedAppearance, String> {
+    crate::appearance::apply_appearance(&window, &appearance)
+}
+
+/// Sets the webview zoom factor for the calling window and persists it.
+#[tauri::command]
+pub fn set_webview_zoom(window: tauri::WebviewWindow, zoom: f64) -> Result<(), String> {
+    crate::window_state::set_zoom(&window, zoom)
+}
+
+/// Renders a result table or report as print-friendly HTML and opens the OS print dialog.
+#[tauri::command]
+pub fn print_view(app: tauri::AppHandle, request: crate::print::PrintRequest) -> Result<(), String> {
+    crate::policy::effective_policy(&app, None)?.check_export()?;
+    let html = crate::print::render_print_html(&request);
+    crate::print::open_print_dialog(&app, &html)
+}
+
+/// Switches demo mode, which replaces result strings with consistent fake data for
+/// recording against real instances.
+#[tauri::command]
+pub fn set_demo_mode(enabled: bool) -> bool {
+    crate::demo::set_enabled(enabled);
+    enabled
+}
+
+#[tauri::command]
+pub fn get_demo_mode() -> bool {
+    crate::demo::is_enabled()
+}
+
+/// Finds or replaces text across the given worksheet tabs; returns matches per tab
+/// and, unless previewing, each changed tab's new contents.
+#[tauri::command]
+pub fn find_replace_in_tabs(
+    tabs: Vec<crate::find_replace::TabContent>,
+    options: crate::find_replace::FindReplaceOptions,
+) -> Result<Vec<crate::find_replace::TabReplaceResult>, String> {
+    crate::find_replace::find_replace_in_tabs(&tabs, &options)
+}
+
+/// Saved grid layout for `label` on the connection at `url`. With `columns` (the keys in
+/// the current result), `order` is returned already arranged for them.
+#[tauri::command]
+pub fn get_grid_prefs(
+    app: tauri::AppHandle,
+    url: String,
+    label: String,
+    columns: Option<Vec<String>>,
+) -> Option<crate::grid_prefs::GridPrefs> {
+    let mut prefs = crate::grid_prefs::load(&app, &url, &label)?;
+    if let Some(columns) = columns {
+        prefs.order = prefs.arrange(&columns);
+    }
+    Some(prefs)
+}
+
+/// Saves the grid layout for `label`; `None` restores the default layout.
+#[tauri::command]
+pub fn set_grid_prefs(
+    app: tauri::AppHandle,
+    url: String,
+    label: String,
+    prefs: Option<crate::grid_prefs::GridPrefs>,
+) -> Result<(), String> {
+    crate::grid_prefs::save(&app, &url, &label, prefs)
+}
+
+/// .hx files opened through the file association or the command line since the last
+/// call, split into tabs. Called at startup and on `hx-files-opened`.
+#[tauri::command]
+pub fn take_opened_files(app: tauri::AppHandle) -> Vec<crate::file_open::OpenedFile> {
+    crate::file_open::take(&app)
+}
@@ -0,0 +1,57 @@
+use crate::versions::{self, DiffLine, VersionSummary, WorksheetVersion};
+
+#[tauri::command]
+pub fn snapshot_worksheet(
+    app: tauri::AppHandle,
+    worksheet_id: String,
+    code: String,
+    params: Option<serde_json::Value>,
+    reason: Option<String>,
+) -> Result<Option<WorksheetVersion>, String> {
+    versions::snapshot(&app, &worksheet_id, reason.as_deref().unwrap_or("execute"), &code, params)
+}
+
+#[tauri::command]
+pub fn list_worksheet_versions(app: tauri::AppHandle, worksheet_id: String) -> Result<Vec<VersionSummary>, String> {
+    Ok(versions::load_history(&app, &worksheet_id)?.summaries())
+}
+
+/// Returns the stored version for the editor to load. The current contents are
+/// snapshotted first, so a restore can itself be rolled back.
+#[tauri::command]
+pub fn restore_worksheet_version(
+    app: tauri::AppHandle,
+    worksheet_id: String,
+    version_id: u64,
+    current_code: Option<String>,
+) -> Result<WorksheetVersion, String> {
+    let mut history = versions::load_history(&app, &worksheet_id)?;
+    let version = history.get(version_id).cloned()
+        .ok_or_else(|| format!("Version {} not found", version_id))?;
+    if let Some(code) = current_code {
+        if history.record("restore", &code, None).is_some() {
+            versions::save_history(&app, &worksheet_id, &history)?;
+        }
+    }
+    Ok(version)
+}
+
+/// Diffs version `from` against version `to`, or against `current_code` when `to` is omitted.
+#[tauri::command]
+pub fn diff_worksheet_versions(
+    app: tauri::AppHandle,
+    worksheet_id: String,
+    from: u64,
+    to: Option<u64>,
+    current_code: Option<String>,
+) -> Result<Vec<DiffLine>, String> {
+    let history = versions::load_history(&app, &worksheet_id)?;
+    let code_of = |id: u64| history.get(id).map(|v| v.code.clone()).ok_or_else(|| format!("Version {} not found", id));
+    let old = code_of(from)?;
+    let new = match (to, current_code) {
+        (Some(id), _) => code_of(id)?,
+        (None, Some(code)) => code,
+        (None, None) => return Err("Nothing to compare against".to_string()),
+    };
+    Ok(versions::diff_lines(&old, &new))
+}
@@ -0,0 +1,230 @@
+use crate::network_trace::SendTraced;
+
+/// One parameter of a deployed query.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamSpec {
+    pub name: String,
+    /// HQL type as reported by the gateway, e.g. `String`, `I32` or `[ID]`.
+    pub param_type: String,
+}
+
+/// A query deployed on the gateway, as described by its `/introspect` endpoint.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompiledQuery {
+    pub name: String,
+    pub parameters: Vec<ParamSpec>,
+    pub returns: Vec<String>,
+}
+
+/// Reads the `queries` of an `/introspect` response. Queries may come as an array or a map,
+/// and parameters as `[{ name, param_type }]` or `{ name: type }`.
+pub fn parse_introspection(value: &serde_json::Value) -> Vec<CompiledQuery> {
+    let queries: Vec<&serde_json::Value> = match value.get("queries") {
+        Some(serde_json::Value::Array(items)) => items.iter().collect(),
+        Some(serde_json::Value::Object(map)) => map.values().collect(),
+        _ => Vec::new(),
+    };
+    let mut parsed: Vec<CompiledQuery> = queries.into_iter()
+        .filter_map(|q| {
+            let name = q.get("name").or_else(|| q.get("query_name"))?.as_str()?.to_string();
+            let parameters = match q.get("parameters") {
+                Some(serde_json::Value::Array(params)) => params.iter()
+                    .filter_map(|p| Some(ParamSpec {
+                        name: p.get("name")?.as_str()?.to_string(),
+                        param_type: p.get("param_type").or_else(|| p.get("type")).and_then(|t| t.as_str()).unwrap_or("String").to_string(),
+                    }))
+                    .collect(),
+                Some(serde_json::Value::Object(params)) => params.iter()
+                    .map(|(name, ty)| ParamSpec { name: name.clone(), param_type: ty.as_str().map(String::from).unwrap_or_else(|| ty.to_string()) })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let returns = q.get("returns").and_then(|r| r.as_array())
+                .map(|r| r.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            Some(CompiledQuery { name, parameters, returns })
+        })
+        .collect();
+    parsed.sort_by(|a, b| a.name.cmp(&b.name));
+    parsed
+}
+
+pub async fn fetch(client: &reqwest::Client, url: &str, auth: &crate::gateway_auth::GatewayAuth) -> Result<Vec<CompiledQuery>, String> {
+    let req = client.get(format!("{}/introspect", url.trim_end_matches('/')));
+    let resp = auth.apply(req).send_traced().await
+        .map_err(|e| crate::commands::network::map_reqwest_error(e, "Introspection failed"))?;
+    if !resp.status().is_success() {
+        return Err(format!("Introspection failed ({}): {}", resp.status(), resp.text().await.unwrap_or_default()));
+    }
+    let body: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to read introspection: {}", e))?;
+    Ok(parse_introspection(&body))
+}
+
+/// Whether `value` can be sent for a parameter of HQL type `ty`. Types this does not
+/// know, such as custom objects, accept anything.
+fn matches_type(ty: &str, value: &serde_json::Value) -> bool {
+    let ty = ty.trim();
+    if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return value.as_array().is_some_and(|items| items.iter().all(|v| matches_type(inner, v)));
+    }
+    match ty {
+        "String" | "ID" => value.is_string(),
+        "Date" => value.is_string() || value.is_number(),
+        "Boolean" => value.is_boolean(),
+        "I8" | "I16" | "I32" | "I64" | "U8" | "U16" | "U32" | "U64" | "U128" => value.is_i64() || value.is_u64(),
+        "F32" | "F64" => value.is_number(),
+        _ => true,
+    }
+}
+
+/// Smallest and largest value of an integer HQL type. JSON has no 128-bit integers,
+/// so `U128` is held to `u64`.
+fn int_bounds(ty: &str) -> Option<(i128, i128)> {
+    Some(match ty {
+        "I8" => (i8::MIN.into(), i8::MAX.into()),
+        "I16" => (i16::MIN.into(), i16::MAX.into()),
+        "I32" => (i32::MIN.into(), i32::MAX.into()),
+        "I64" => (i64::MIN.into(), i64::MAX.into()),
+        "U8" => (0, u8::MAX.into()),
+        "U16" => (0, u16::MAX.into()),
+        "U32" => (0, u32::MAX.into()),
+        "U64" | "U128" => (0, u64::MAX.into()),
+        _ => return None,
+    })
+}
+
+fn int_value(n: i128) -> serde_json::Value {
+    match i64::try_from(n) {
+        Ok(n) => serde_json::Value::from(n),
+        Err(_) => serde_json::Value::from(n as u64),
+    }
+}
+
+/// Converts `value` to HQL type `ty` where nothing is lost: numeric or boolean text
+/// becomes a number or boolean, whole floats become integers, and numbers or
+/// booleans become text for `String`. The error says what was given instead.
+pub fn coerce(ty: &str, value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let ty = ty.trim();
+    let mismatch = |value: &serde_json::Value| format!("should be {}, got {} {}", ty, json_type(value), value);
+    if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return match value {
+            serde_json::Value::Array(items) => items.into_iter()
+                .map(|v| coerce(inner, v))
+                .collect::<Result<Vec<_>, _>>()
+                .map(serde_json::Value::Array),
+            other => Err(mismatch(&other)),
+        };
+    }
+    if let Some((min, max)) = int_bounds(ty) {
+        let n = match &value {
+            serde_json::Value::Number(n) => n.as_i64().map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from))
+                .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0 && f.abs() < 1e19).map(|f| f as i128)),
+            serde_json::Value::String(s) => s.trim().parse::<i128>().ok(),
+            _ => None,
+        };
+        return match n {
+            Some(n) if (min..=max).contains(&n) => Ok(int_value(n)),
+            Some(_) => Err(format!("is out of range for {}, got {}", ty, value)),
+            None => Err(mismatch(&value)),
+        };
+    }
+    if matches_type(ty, &value) {
+        return Ok(value);
+    }
+    let coerced = match (ty, &value) {
+        ("F32" | "F64", serde_json::Value::String(s)) => s.trim().parse::<f64>().ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number),
+        ("Boolean", serde_json::Value::String(s)) => match s.trim() {
+            "true" => Some(serde_json::Value::Bool(true)),
+            "false" => Some(serde_json::Value::Bool(false)),
+            _ => None,
+        },
+        ("String", serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => Some(serde_json::Value::String(value.to_string())),
+        _ => None,
+    };
+    coerced.ok_or_else(|| mismatch(&value))
+}
+
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+impl CompiledQuery {
+    /// Checks `args` against the parameters, reporting every problem at once.
+    pub fn validate_args(&self, args: &serde_json::Value) -> Result<(), String> {
+        let empty = serde_json::Map::new();
+        let given = args.as_object().unwrap_or(&empty);
+        let mut problems = Vec::new();
+        for param in &self.parameters {
+            match given.get(&param.name) {
+                None => problems.push(format!("missing parameter '{}' ({})", param.name, param.param_type)),
+                Some(value) if !matches_type(&param.param_type, value) => {
+                    problems.push(format!("'{}' should be {}, got {}", param.name, param.param_type, json_type(value)))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in given.keys() {
+            if !self.parameters.iter().any(|p| &p.name == name) {
+                problems.push(format!("unknown parameter '{}'", name));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid arguments for '{}': {}", self.name, problems.join("; ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let introspection = serde_json::json!({
+            "queries": [
+                { "name": "getUser", "parameters": { "id": "ID" }, "returns": ["user"] },
+                { "name": "addPosts", "parameters": [{ "name": "titles", "param_type": "[String]" }, { "name": "score", "param_type": "I32" }] },
+            ]
+        });
+        let queries = parse_introspection(&introspection);
+        assert_eq!(queries.iter().map(|q| q.name.as_str()).collect::<Vec<_>>(), vec!["addPosts", "getUser"]);
+        assert_eq!(queries[1].returns, vec!["user"]);
+
+        let add = &queries[0];
+        assert!(add.validate_args(&serde_json::json!({ "titles": ["a", "b"], "score": 3 })).is_ok());
+        let err = add.validate_args(&serde_json::json!({ "titles": ["a", 1], "extra": true })).unwrap_err();
+        assert!(err.contains("'titles' should be [String], got array"), "{}", err);
+        assert!(err.contains("missing parameter 'score' (I32)"), "{}", err);
+        assert!(err.contains("unknown parameter 'extra'"), "{}", err);
+        assert!(add.validate_args(&serde_json::json!({ "titles": [], "score": 1.5 })).is_err());
+    }
+
+    #[test]
+    fn test_coerce() {
+        assert_eq!(coerce("I64", serde_json::json!("30")), Ok(serde_json::json!(30)));
+        assert_eq!(coerce("U8", serde_json::json!(4.0)), Ok(serde_json::json!(4)));
+        assert_eq!(coerce("F64", serde_json::json!(" 1.5")), Ok(serde_json::json!(1.5)));
+        assert_eq!(coerce("Boolean", serde_json::json!("true")), Ok(serde_json::json!(true)));
+        assert_eq!(coerce("String", serde_json::json!(7)), Ok(serde_json::json!("7")));
+        assert_eq!(coerce("[I32]", serde_json::json!(["1", 2])), Ok(serde_json::json!([1, 2])));
+        assert_eq!(coerce("I64", serde_json::json!("thirty")), Err("should be I64, got string \"thirty\"".to_string()));
+        assert_eq!(coerce("I8", serde_json::json!(300)), Err("is out of range for I8, got 300".to_string()));
+        assert!(coerce("U32", serde_json::json!(-1)).is_err());
+        assert!(coerce("I64", serde_json::json!(1.5)).is_err());
+        assert!(coerce("[String]", serde_json::json!("a")).is_err());
+    }
+}
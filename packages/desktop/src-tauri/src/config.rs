@@ -1,39 +1,434 @@
 
 use std::path::PathBuf;
 use std::fs;
-use tauri::Manager;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{Emitter, Manager};
 
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// The connections.json schema version this build writes. Older files are migrated
+/// when read: `MIGRATIONS[n]` takes a version `n` file to version `n + 1`.
+pub const CONFIG_VERSION: u64 = 3;
+/// Query timeout when neither the request nor the connection sets one.
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Last connections.json content known to the app, used to tell external edits
+/// apart from our own writes.
+#[derive(Default)]
+pub struct ConfigWatchState {
+    pub last_content: Mutex<Option<String>>,
+}
+
+#[derive(serde::Serialize, Clone)]
+pub struct ConfigChangedEvent {
+    pub config: Option<ConnectionsConfig>,
+    pub error: Option<String>,
+}
+
+/// connections.json. Connections stay JSON objects, since each module reads the keys
+/// it owns (`apiKey`, `headers`, `proxy`, `policy`, ...); each has at least a string `id`.
+/// They are listed in the order the sidebar shows them, each under its `group`, if any.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct ConnectionsConfig {
+    pub version: u64,
+    pub connections: Vec<serde_json::Value>,
+    /// Id of the connection used when a command names none.
+    #[serde(default)]
+    pub current: Option<String>,
+    /// Sidebar folders, in order.
+    #[serde(default)]
+    pub groups: Vec<ConnectionGroup>,
+    /// Keys this version does not know, kept as they are.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl Default for ConnectionsConfig {
+    fn default() -> Self {
+        Self { version: CONFIG_VERSION, connections: Vec::new(), current: None, groups: Vec::new(), other: serde_json::Map::new() }
+    }
+}
+
+type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+/// `connections` must be an array of objects with a string `id`.
+fn check_connections(connections: Option<&serde_json::Value>) -> Result<(), String> {
+    let connections = connections
+        .and_then(|c| c.as_array())
+        .ok_or_else(|| "'connections' must be an array".to_string())?;
+    for (i, conn) in connections.iter().enumerate() {
+        if conn.get("id").and_then(|id| id.as_str()).is_none() {
+            return Err(format!("Connection at index {} is missing a string 'id'", i));
+        }
+    }
+    Ok(())
+}
+
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [
+    // 0 -> 1: the selected connection was saved as `activeConnectionId`
+    |config| {
+        if let Some(active) = config.remove("activeConnectionId") {
+            if config.get("current").is_none_or(|c| c.is_null()) {
+                config.insert("current".to_string(), active);
+            }
+        }
+    },
+    // 1 -> 2: ports are strings, as the connection form edits them
+    |config| {
+        let connections = config.get_mut("connections").and_then(|c| c.as_array_mut());
+        for connection in connections.into_iter().flatten() {
+            if let Some(port) = connection.get_mut("port").filter(|p| p.is_number()) {
+                *port = serde_json::Value::String(port.to_string());
+            }
+        }
+    },
+    // 2 -> 3: `timeoutMs` and `maxConcurrentQueries` move under `defaults`
+    |config| {
+        let connections = config.get_mut("connections").and_then(|c| c.as_array_mut());
+        for connection in connections.into_iter().flatten().filter_map(|c| c.as_object_mut()) {
+            let moves = [("timeoutMs", "timeoutMs", "timeout_ms"), ("maxConcurrentQueries", "maxConcurrency", "max_concurrency")];
+            for (old, new, snake) in moves {
+                let Some(value) = connection.remove(old) else { continue };
+                let defaults = connection.entry("defaults").or_insert_with(|| serde_json::json!({}));
+                // A default already set wins
+                if let Some(defaults) = defaults.as_object_mut().filter(|d| !d.contains_key(new) && !d.contains_key(snake)) {
+                    defaults.insert(new.to_string(), value);
+                }
+            }
+        }
+    },
+];
+
+impl ConnectionsConfig {
+    /// Reads a config of any known version, migrating it to `CONFIG_VERSION`. A config
+    /// without `version` predates versioning.
+    pub fn migrate(config: serde_json::Value) -> Result<Self, String> {
+        let serde_json::Value::Object(mut config) = config else {
+            return Err("The config must be an object".to_string());
+        };
+        let version = match config.get("version") {
+            None => 0,
+            Some(v) => v.as_u64().ok_or_else(|| "'version' must be a number".to_string())?,
+        };
+        if version > CONFIG_VERSION {
+            return Err(format!("The config is version {}, newer than this app reads ({}); update the app", version, CONFIG_VERSION));
+        }
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut config);
+        }
+        config.insert("version".to_string(), CONFIG_VERSION.into());
+
+        check_connections(config.get("connections"))?;
+        serde_json::from_value(serde_json::Value::Object(config)).map_err(|e| e.to_string())
+    }
+
+    /// Applies what the window edits (`connections`, `groups` and `current`) to this
+    /// config, keeping its version and the keys the window does not know.
+    pub fn apply_edit(&mut self, edit: serde_json::Value) -> Result<(), String> {
+        let serde_json::Value::Object(mut edit) = edit else {
+            return Err("The config must be an object".to_string());
+        };
+        if let Some(connections) = edit.remove("connections") {
+            check_connections(Some(&connections))?;
+            self.connections = serde_json::from_value(connections).map_err(|e| e.to_string())?;
+        }
+        if let Some(groups) = edit.remove("groups") {
+            self.groups = serde_json::from_value(groups).map_err(|e| e.to_string())?;
+        }
+        if let Some(current) = edit.remove("current") {
+            self.current = serde_json::from_value(current).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        Self::migrate(serde_json::from_str(content).map_err(|e| e.to_string())?)
+    }
+
+    /// `parse`, with `${NAME}` placeholders in the connections replaced by environment variables.
+    pub fn parse_resolved(content: &str) -> Result<Self, String> {
+        let mut config = Self::parse(content)?;
+        config.resolve_env(&env_var);
+        Ok(config)
+    }
+
+    pub fn connection(&self, id: &str) -> Option<&serde_json::Value> {
+        self.connections.iter().find(|c| c.get("id").and_then(|v| v.as_str()) == Some(id))
+    }
+
+    pub fn resolve_env(&mut self, lookup: &dyn Fn(&str) -> Option<String>) {
+        self.connections.iter_mut().for_each(|c| resolve_env_in(c, lookup));
+    }
+
+    /// Puts the placeholders of `raw`, the file as written, back wherever a connection
+    /// setting still holds what its placeholder resolved to, so saving a loaded config
+    /// does not write the variables' values into the file.
+    pub fn restore_env(&mut self, raw: &ConnectionsConfig, lookup: &dyn Fn(&str) -> Option<String>) {
+        for connection in &mut self.connections {
+            let id = connection.get("id").and_then(|v| v.as_str()).map(String::from);
+            if let Some(written) = id.and_then(|id| raw.connection(&id)) {
+                restore_env_in(connection, written, lookup);
+            }
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Replaces each `${NAME}` in `text` with the variable `NAME`. Unset variables, and
+/// `${...}` that is not a variable name (such as `${env.name}` in parameters), stay as written.
+fn interpolate_env(text: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after.find('}').map(|end| &after[..end])
+            .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+            .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        match name.and_then(|name| Some((name, lookup(name)?))) {
+            Some((name, value)) => {
+                out.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn resolve_env_in(value: &mut serde_json::Value, lookup: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        serde_json::Value::String(s) if s.contains("${") => *s = interpolate_env(s, lookup),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| resolve_env_in(v, lookup)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|v| resolve_env_in(v, lookup)),
+        _ => {}
+    }
+}
+
+fn restore_env_in(value: &mut serde_json::Value, written: &serde_json::Value, lookup: &dyn Fn(&str) -> Option<String>) {
+    match (value, written) {
+        (serde_json::Value::String(s), serde_json::Value::String(w)) if w.contains("${") && interpolate_env(w, lookup) == *s => {
+            *s = w.clone();
+        }
+        (serde_json::Value::Array(items), serde_json::Value::Array(written)) => {
+            items.iter_mut().zip(written).for_each(|(v, w)| restore_env_in(v, w, lookup));
+        }
+        (serde_json::Value::Object(map), serde_json::Value::Object(written)) => {
+            for (key, v) in map.iter_mut() {
+                if let Some(w) = written.get(key) {
+                    restore_env_in(v, w, lookup);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The connections.json of the active config profile.
 pub fn get_config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let mut path = app.path().app_config_dir()
+    let dir = app.path().app_config_dir()
         .map_err(|e| format!("Could not find config directory: {}", e))?;
-    if !path.exists() {
-        fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    let path = crate::config_profiles::connections_file(&dir)?;
+    if let Some(parent) = path.parent().filter(|p| !p.exists()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
-    path.push("connections.json");
     Ok(path)
 }
 
-pub fn load_connection_config(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+/// connections.json, migrated to the current version and with `${NAME}` placeholders
+/// resolved from the environment. The file itself is rewritten in the current version
+/// on the next save.
+pub fn load_connection_config(app: tauri::AppHandle) -> Result<ConnectionsConfig, String> {
     let path = get_config_path(&app)?;
     if !path.exists() {
-        return Ok(serde_json::json!({
-            "connections": [],
-            "current": null
-        }));
+        return Ok(ConnectionsConfig::default());
     }
     let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let config: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-    Ok(config)
+    ConnectionsConfig::parse_resolved(&content)
 }
 
-pub fn save_connection_config(app: tauri::AppHandle, config: serde_json::Value) -> Result<(), String> {
+/// Writes `config`, keeping the file's `${NAME}` placeholders for settings that were not changed.
+pub fn save_connection_config(app: tauri::AppHandle, mut config: ConnectionsConfig) -> Result<(), String> {
     let path = get_config_path(&app)?;
+    crate::ssh_tunnel::close_unused(&config.connections);
+    let written = fs::read_to_string(&path).ok().and_then(|c| ConnectionsConfig::parse(&c).ok());
+    if let Some(written) = written {
+        config.restore_env(&written, &env_var);
+    }
     let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())?;
+    fs::write(path, &content).map_err(|e| e.to_string())?;
+    if let Some(state) = app.try_state::<ConfigWatchState>() {
+        *state.last_content.lock().unwrap() = Some(content);
+    }
     Ok(())
 }
 
-fn get_docker_path() -> String {
+/// A connection's `defaults`, for what a request leaves unset. Keys may also be written
+/// in snake_case, and numbers as strings, as `${NAME}` placeholders resolve to strings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionDefaults {
+    pub timeout_ms: Option<u64>,
+    /// Queries run at once; more wait in the queue.
+    pub max_concurrency: Option<usize>,
+    /// Rows per page of a paged query.
+    pub page_size: Option<usize>,
+    /// Refuses writes, as a policy with `allowWrites: false` does.
+    pub read_only: bool,
+}
+
+impl ConnectionDefaults {
+    /// The connection's `defaults`, or the first invalid value among them.
+    pub fn parse(connection: Option<&serde_json::Value>) -> Result<Self, String> {
+        let (defaults, errors) = Self::read(connection);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(defaults),
+        }
+    }
+
+    /// The connection's `defaults`, an invalid value counting as unset, except for
+    /// `readOnly`: one that is there but unreadable counts as set, so a typo never
+    /// allows writes.
+    pub fn of_connection(connection: Option<&serde_json::Value>) -> Self {
+        Self::read(connection).0
+    }
+
+    /// Each key is read on its own, so one bad value does not discard the others.
+    fn read(connection: Option<&serde_json::Value>) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let fields = match connection.and_then(|c| c.get("defaults")) {
+            None | Some(serde_json::Value::Null) => return (Self::default(), errors),
+            Some(serde_json::Value::Object(fields)) => fields,
+            Some(_) => {
+                errors.push("`defaults` must be an object".to_string());
+                return (Self { read_only: true, ..Default::default() }, errors);
+            }
+        };
+        let value = |camel: &str, snake: &str| fields.get(camel).or_else(|| fields.get(snake)).filter(|v| !v.is_null());
+        let mut number = |camel: &str, snake: &str| {
+            let v = value(camel, snake)?;
+            let n = v.as_u64().or_else(|| v.as_str()?.trim().parse().ok());
+            if n.is_none() {
+                errors.push(format!("`defaults.{}` must be a whole number, not {}", camel, v));
+            }
+            n
+        };
+        let timeout_ms = number("timeoutMs", "timeout_ms");
+        let max_concurrency = number("maxConcurrency", "max_concurrency").map(|n| n as usize);
+        let page_size = number("pageSize", "page_size").map(|n| n as usize);
+        let read_only = match value("readOnly", "read_only") {
+            None => false,
+            Some(v) => v.as_bool().or_else(|| v.as_str()?.trim().parse().ok()).unwrap_or_else(|| {
+                errors.push(format!("`defaults.readOnly` must be true or false, not {}", v));
+                true
+            }),
+        };
+        (Self { timeout_ms, max_concurrency, page_size, read_only }, errors)
+    }
+}
+
+/// Loads connections.json, applies `change` and saves it, returning the saved config.
+pub fn update_connection_config(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut ConnectionsConfig) -> Result<(), String>,
+) -> Result<ConnectionsConfig, String> {
+    let mut config = load_connection_config(app.clone())?;
+    change(&mut config)?;
+    save_connection_config(app.clone(), config.clone())?;
+    Ok(config)
+}
+
+/// The timeout for a query: the requested one, else the connection's default
+/// `timeoutMs`, else `DEFAULT_QUERY_TIMEOUT`.
+pub fn query_timeout(app: &tauri::AppHandle, url: &str, timeout_ms: Option<u64>) -> Duration {
+    connection_timeout(crate::policy::find_connection(app, Some(url)).as_ref(), timeout_ms)
+}
+
+/// `query_timeout` for an already loaded connection.
+pub fn connection_timeout(connection: Option<&serde_json::Value>, timeout_ms: Option<u64>) -> Duration {
+    timeout_ms
+        .or_else(|| ConnectionDefaults::of_connection(connection).timeout_ms)
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_QUERY_TIMEOUT)
+}
+
+/// The error reported when a query runs past its timeout.
+pub fn timed_out_error(timeout: Duration) -> String {
+    let seconds = format!("{}", (timeout.as_millis() as f64 / 1000.0 * 10.0).round() / 10.0);
+    crate::i18n::t("hql.timed_out", &[("seconds", &seconds)])
+}
+
+/// Records `content` as the latest known config. Returns false if it was already known.
+pub(crate) fn mark_config_content(app: &tauri::AppHandle, content: &Option<String>) -> bool {
+    let state = app.state::<ConfigWatchState>();
+    let mut last = state.last_content.lock().unwrap();
+    if *last == *content {
+        return false;
+    }
+    *last = content.clone();
+    true
+}
+
+/// Polls connections.json and emits `connections-changed` with the re-validated
+/// config whenever it is modified outside of `save_connection_config`.
+pub fn watch_connection_config(app: tauri::AppHandle) {
+    if let Ok(path) = get_config_path(&app) {
+        mark_config_content(&app, &fs::read_to_string(path).ok());
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut last_modified: Option<SystemTime> = None;
+        loop {
+            tokio::time::sleep(CONFIG_POLL_INTERVAL).await;
+
+            let path = match get_config_path(&app) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            let content = fs::read_to_string(&path).ok();
+            if !mark_config_content(&app, &content) {
+                continue;
+            }
+
+            let event = match content {
+                Some(c) => match ConnectionsConfig::parse_resolved(&c) {
+                    Ok(config) => {
+                        crate::ssh_tunnel::close_unused(&config.connections);
+                        ConfigChangedEvent { config: Some(config), error: None }
+                    }
+                    Err(e) => ConfigChangedEvent { config: None, error: Some(format!("Invalid connections.json: {}", e)) },
+                },
+                None => ConfigChangedEvent { config: Some(ConnectionsConfig::default()), error: None },
+            };
+            let _ = app.emit("connections-changed", event);
+        }
+    });
+}
+
+pub fn get_docker_path() -> String {
     use std::process::Command;
     if let Ok(output) = Command::new("which").arg("docker").output() {
         if output.status.success() {
@@ -47,7 +442,13 @@ fn get_docker_path() -> String {
         .unwrap_or_else(|| "docker".to_string())
 }
 
-fn try_detect_from_docker(target_port: &str) -> Option<String> {
+/// Whether `path` holds a helix project.
+pub fn is_project_dir(path: &std::path::Path) -> bool {
+    path.join("helix.toml").exists() || path.join("helix.hx").exists()
+}
+
+/// The project mounted into the running container that publishes `target_port`.
+pub(crate) fn try_detect_from_docker(target_port: &str) -> Option<String> {
     use std::process::Command;
     let docker_path = get_docker_path();
 
@@ -90,7 +491,7 @@ fn try_detect_from_docker(target_port: &str) -> Option<String> {
                     if let Some(source) = mount.get("Source").and_then(|s| s.as_str()) {
                         let mut current_path = std::path::PathBuf::from(source);
                         loop {
-                            if current_path.join("helix.toml").exists() || current_path.join("helix.hx").exists() {
+                            if is_project_dir(&current_path) {
                                 return Some(current_path.to_string_lossy().into_owned());
                             }
                             if !current_path.pop() { break; }
@@ -135,3 +536,119 @@ pub fn detect_workspace_path(app: &tauri::AppHandle, target_port: Option<String>
 
     Err("Could not detect workspace path. Please set it manually in settings.".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_migrations() {
+        let legacy = serde_json::json!({
+            "connections": [{ "id": "a", "host": "localhost", "port": 6969 }, { "id": "b", "port": "7000" }],
+            "activeConnectionId": "b",
+            "theme": "dark"
+        });
+        let config = ConnectionsConfig::migrate(legacy).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.current.as_deref(), Some("b"));
+        assert_eq!(config.connection("a").unwrap()["port"], "6969");
+        assert_eq!(config.connection("b").unwrap()["port"], "7000");
+        assert_eq!(config.other.get("theme").unwrap(), "dark");
+        assert!(!config.other.contains_key("activeConnectionId"));
+
+        // Migrating is idempotent, and a current version file is read as is
+        let saved = serde_json::to_value(&config).unwrap();
+        assert_eq!(ConnectionsConfig::migrate(saved.clone()).unwrap(), config);
+        let kept = serde_json::json!({ "version": 1, "connections": [], "current": "x", "activeConnectionId": "y" });
+        assert_eq!(ConnectionsConfig::migrate(kept).unwrap().current.as_deref(), Some("x"));
+
+        assert!(ConnectionsConfig::migrate(serde_json::json!({ "version": CONFIG_VERSION + 1, "connections": [] })).is_err());
+        assert!(ConnectionsConfig::migrate(serde_json::json!({ "connections": [{ "name": "no id" }] })).is_err());
+        assert!(ConnectionsConfig::parse(r#"{ "connections": {} }"#).is_err());
+
+        let v2 = serde_json::json!({ "version": 2, "connections": [
+            { "id": "a", "timeoutMs": 5000, "maxConcurrentQueries": 2 },
+            { "id": "b", "timeoutMs": 5000, "defaults": { "timeout_ms": 9000, "page_size": 50 } }
+        ] });
+        let config = ConnectionsConfig::migrate(v2).unwrap();
+        let defaults = |id| ConnectionDefaults::of_connection(config.connection(id));
+        assert_eq!(defaults("a"), ConnectionDefaults { timeout_ms: Some(5000), max_concurrency: Some(2), ..Default::default() });
+        assert_eq!(defaults("b").timeout_ms, Some(9000));
+        assert_eq!(defaults("b").page_size, Some(50));
+        assert!(config.connection("a").unwrap().get("timeoutMs").is_none());
+    }
+
+    #[test]
+    fn test_window_edit_keeps_version_and_unknown_keys() {
+        let stored = r#"{ "version": 3, "connections": [{ "id": "a", "port": "6969" }], "current": "a", "theme": "dark" }"#;
+        let mut config = ConnectionsConfig::parse(stored).unwrap();
+        config.apply_edit(serde_json::json!({
+            "connections": [{ "id": "a", "port": "6969" }, { "id": "b", "port": "7000" }],
+            "groups": [{ "id": "g", "name": "Prod" }],
+            "current": "b",
+        })).unwrap();
+        let saved = ConnectionsConfig::parse(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(saved, config);
+        assert_eq!(saved.version, CONFIG_VERSION);
+        assert_eq!(saved.current.as_deref(), Some("b"));
+        assert_eq!(saved.other.get("theme").unwrap(), "dark");
+        assert_eq!(saved.connections.len(), 2);
+        assert_eq!(saved.groups[0].name, "Prod");
+
+        // What the window leaves out stays as it was
+        config.apply_edit(serde_json::json!({ "current": null })).unwrap();
+        assert_eq!(config.current, None);
+        assert_eq!(config.connections.len(), 2);
+        assert!(config.apply_edit(serde_json::json!({ "connections": [{ "name": "no id" }] })).is_err());
+    }
+
+    #[test]
+    fn test_defaults_read_per_key() {
+        let connection = |defaults: serde_json::Value| serde_json::json!({ "id": "a", "defaults": defaults });
+        let c = connection(serde_json::json!({ "pageSize": "fifty", "readOnly": true, "timeout_ms": "2000" }));
+        let defaults = ConnectionDefaults::of_connection(Some(&c));
+        assert!(defaults.read_only);
+        assert_eq!(defaults.timeout_ms, Some(2000));
+        assert_eq!(defaults.page_size, None);
+        assert!(ConnectionDefaults::parse(Some(&c)).unwrap_err().contains("defaults.pageSize"));
+
+        // Present but unreadable, read-only fails closed
+        let c = connection(serde_json::json!({ "readOnly": "yes", "maxConcurrency": 4 }));
+        let defaults = ConnectionDefaults::of_connection(Some(&c));
+        assert!(defaults.read_only);
+        assert_eq!(defaults.max_concurrency, Some(4));
+        assert!(ConnectionDefaults::parse(Some(&c)).unwrap_err().contains("defaults.readOnly"));
+        assert!(ConnectionDefaults::of_connection(Some(&connection(serde_json::json!([])))).read_only);
+
+        assert_eq!(ConnectionDefaults::parse(Some(&connection(serde_json::Value::Null))), Ok(ConnectionDefaults::default()));
+        assert_eq!(ConnectionDefaults::parse(None), Ok(ConnectionDefaults::default()));
+    }
+
+    #[test]
+    fn test_env_placeholders() {
+        let lookup = |name: &str| match name {
+            "HELIX_URL" => Some("db.example.com".to_string()),
+            "HELIX_API_KEY" => Some("secret".to_string()),
+            _ => None,
+        };
+        assert_eq!(interpolate_env("https://${HELIX_URL}/", &lookup), "https://db.example.com/");
+        assert_eq!(interpolate_env("${UNSET} ${env.tenant} ${HELIX_URL", &lookup), "${UNSET} ${env.tenant} ${HELIX_URL");
+
+        let written = ConnectionsConfig::migrate(serde_json::json!({ "version": CONFIG_VERSION, "connections": [
+            { "id": "a", "cloudHost": "${HELIX_URL}", "apiKey": "${HELIX_API_KEY}", "headers": { "X-Key": "k-${HELIX_API_KEY}" } }
+        ] })).unwrap();
+        let mut config = written.clone();
+        config.resolve_env(&lookup);
+        let connection = config.connection("a").unwrap();
+        assert_eq!(connection["apiKey"], "secret");
+        assert_eq!(connection["headers"]["X-Key"], "k-secret");
+
+        // Unchanged settings get their placeholders back; edited ones keep the new value
+        config.connections[0]["cloudHost"] = "other.example.com".into();
+        config.restore_env(&written, &lookup);
+        let connection = config.connection("a").unwrap();
+        assert_eq!(connection["apiKey"], "${HELIX_API_KEY}");
+        assert_eq!(connection["headers"]["X-Key"], "k-${HELIX_API_KEY}");
+        assert_eq!(connection["cloudHost"], "other.example.com");
+    }
+}
@@ -0,0 +1,143 @@
+//! Named sets of connections, say one for work and one for personal instances. The
+//! `default` profile is the app's own connections.json; the others are kept in
+//! `~/.helix-explorer/profiles/<name>.json`. The active profile is recorded in the app
+//! config directory, so the CLI reads the same connections as the app.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
+use crate::config::{self, ConnectionsConfig};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const ACTIVE_FILE: &str = "config-profile.json";
+const CONNECTIONS_FILE: &str = "connections.json";
+pub const PROFILE_CHANGED_EVENT: &str = "config-profile-changed";
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct ActiveProfile {
+    active: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigProfile {
+    pub name: String,
+    pub active: bool,
+    pub path: String,
+    /// None when the profile's file cannot be read.
+    pub connections: Option<usize>,
+}
+
+/// Sent as `config-profile-changed` with the connections of the profile switched to.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileChanged {
+    pub profile: String,
+    pub config: ConnectionsConfig,
+}
+
+pub fn profiles_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find the home directory")?;
+    Ok(home.join(".helix-explorer").join("profiles"))
+}
+
+/// Names become file names: letters, digits, `-` and `_` only.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid profile name '{}'; use letters, digits, - and _", name));
+    }
+    Ok(())
+}
+
+/// The active profile recorded in `config_dir`, `default` when none is.
+pub fn active_in(config_dir: &Path) -> String {
+    fs::read_to_string(config_dir.join(ACTIVE_FILE)).ok()
+        .and_then(|content| serde_json::from_str::<ActiveProfile>(&content).ok())
+        .and_then(|file| file.active)
+        .filter(|name| check_name(name).is_ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn profile_file(config_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    match name {
+        DEFAULT_PROFILE => Ok(config_dir.join(CONNECTIONS_FILE)),
+        name => Ok(profiles_dir()?.join(format!("{}.json", name))),
+    }
+}
+
+/// The connections.json of the active profile.
+pub fn connections_file(config_dir: &Path) -> Result<PathBuf, String> {
+    profile_file(config_dir, &active_in(config_dir))
+}
+
+/// Profile names: `default`, then the files in the profiles directory, sorted.
+fn profile_names(dir: Option<&Path>) -> Vec<String> {
+    let mut names: Vec<String> = dir.and_then(|dir| fs::read_dir(dir).ok()).into_iter().flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(".json").map(String::from))
+        .filter(|name| name != DEFAULT_PROFILE && check_name(name).is_ok())
+        .collect();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+pub fn list(app: &tauri::AppHandle) -> Result<Vec<ConfigProfile>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    let active = active_in(&config_dir);
+    profile_names(profiles_dir().ok().as_deref()).into_iter()
+        .map(|name| {
+            let path = profile_file(&config_dir, &name)?;
+            let connections = match fs::read_to_string(&path) {
+                Ok(content) => ConnectionsConfig::parse(&content).ok().map(|c| c.connections.len()),
+                Err(_) => Some(0),
+            };
+            Ok(ConfigProfile { active: name == active, path: path.to_string_lossy().into_owned(), name, connections })
+        })
+        .collect()
+}
+
+/// Makes `name` the active profile, starting it empty if it is new, and emits
+/// `config-profile-changed` so the window reloads its connections.
+pub fn switch(app: &tauri::AppHandle, name: &str) -> Result<ConnectionsConfig, String> {
+    check_name(name)?;
+    let config_dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
+    let active = ActiveProfile { active: Some(name.to_string()) };
+    let content = serde_json::to_string_pretty(&active).map_err(|e| e.to_string())?;
+    fs::write(config_dir.join(ACTIVE_FILE), content).map_err(|e| e.to_string())?;
+
+    let config = config::load_connection_config(app.clone())?;
+    crate::ssh_tunnel::close_unused(&config.connections);
+    // The event carries the new connections; the file watcher need not report them again
+    config::mark_config_content(app, &fs::read_to_string(config::get_config_path(app)?).ok());
+    let _ = app.emit(PROFILE_CHANGED_EVENT, ProfileChanged { profile: name.to_string(), config: config.clone() });
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names_and_active() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let profiles = tempfile::tempdir().unwrap();
+        for file in ["work.json", "personal.json", "default.json", "bad name.json", "notes.txt"] {
+            fs::write(profiles.path().join(file), "{}").unwrap();
+        }
+        assert_eq!(profile_names(Some(profiles.path())), vec!["default", "personal", "work"]);
+        assert_eq!(profile_names(None), vec!["default"]);
+
+        assert_eq!(active_in(config_dir.path()), DEFAULT_PROFILE);
+        assert_eq!(connections_file(config_dir.path()).unwrap(), config_dir.path().join(CONNECTIONS_FILE));
+        fs::write(config_dir.path().join(ACTIVE_FILE), r#"{ "active": "work" }"#).unwrap();
+        assert_eq!(active_in(config_dir.path()), "work");
+        fs::write(config_dir.path().join(ACTIVE_FILE), r#"{ "active": "../etc" }"#).unwrap();
+        assert_eq!(active_in(config_dir.path()), DEFAULT_PROFILE);
+
+        assert!(check_name("work-2").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("a/b").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! HTTP clients for connections that need their own. reqwest sets client certificates
+//! and proxies on the client rather than the request, so a connection with either (or
+//! an SSH tunnel, reached as a proxy) gets a client built for it; the others share
+//! `NetworkState`'s.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use crate::client_identity::ClientIdentity;
+use crate::proxy::ProxySetting;
+use crate::ssh_tunnel::TunnelSettings;
+
+/// What a connection's client is built with; connections with the same settings share one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClientSettings {
+    pub identity: Option<ClientIdentity>,
+    pub proxy: Option<ProxySetting>,
+    pub tunnel: Option<TunnelSettings>,
+}
+
+impl ClientSettings {
+    pub fn of_connection(connection: Option<&serde_json::Value>) -> Result<Self, String> {
+        let settings = Self {
+            identity: ClientIdentity::of_connection(connection)?,
+            proxy: ProxySetting::of_connection(connection)?,
+            tunnel: TunnelSettings::of_connection(connection)?,
+        };
+        if settings.proxy.is_some() && settings.tunnel.is_some() {
+            return Err("A connection can set `proxy` or `sshTunnel`, not both".to_string());
+        }
+        Ok(settings)
+    }
+
+    /// When the certificate files last changed, so a renewed one replaces the cached client.
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.identity.iter().flat_map(|identity| identity.files())
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    /// The tunnel's proxy URL, which changes when a closed tunnel is opened again.
+    fn tunnel_url(&self) -> Result<Option<String>, String> {
+        self.tunnel.as_ref().map(crate::ssh_tunnel::proxy_url).transpose()
+    }
+
+    pub fn build(&self) -> Result<reqwest::Client, String> {
+        let builder = crate::commands::network::client_builder();
+        let builder = match (&self.proxy, self.tunnel_url()?) {
+            (Some(proxy), _) => proxy.apply(builder)?,
+            (None, Some(url)) => builder.proxy(reqwest::Proxy::all(url).map_err(|e| e.to_string())?),
+            // Gateways are local or reached directly
+            (None, None) => builder.no_proxy(),
+        };
+        let builder = match &self.identity {
+            Some(identity) => builder.identity(identity.load()?),
+            None => builder,
+        };
+        builder.build().map_err(|e| format!("Failed to build HTTP client: {}", e))
+    }
+}
+
+struct CachedClient {
+    modified: Vec<Option<SystemTime>>,
+    tunnel_url: Option<String>,
+    client: reqwest::Client,
+}
+
+/// Clients built on first use, per settings.
+#[derive(Default)]
+pub struct ConnectionClients(Mutex<HashMap<ClientSettings, CachedClient>>);
+
+impl ConnectionClients {
+    /// The client for requests to `connection`: `shared` unless it needs one of its own.
+    pub fn client_for(&self, shared: &reqwest::Client, connection: Option<&serde_json::Value>) -> Result<reqwest::Client, String> {
+        let settings = ClientSettings::of_connection(connection)?;
+        if settings == ClientSettings::default() {
+            return Ok(shared.clone());
+        }
+        let modified = settings.modified();
+        let tunnel_url = settings.tunnel_url()?;
+        if let Some(cached) = self.0.lock().unwrap().get(&settings).filter(|c| c.modified == modified && c.tunnel_url == tunnel_url) {
+            return Ok(cached.client.clone());
+        }
+        let client = settings.build()?;
+        self.0.lock().unwrap().insert(settings, CachedClient { modified, tunnel_url, client: client.clone() });
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clients_per_settings() {
+        let clients = ConnectionClients::default();
+        let shared = reqwest::Client::new();
+        clients.client_for(&shared, Some(&serde_json::json!({ "url": "http://h" }))).unwrap();
+        assert!(clients.0.lock().unwrap().is_empty());
+
+        let proxied = serde_json::json!({ "url": "http://h", "proxy": "http://proxy:3128" });
+        clients.client_for(&shared, Some(&proxied)).unwrap();
+        clients.client_for(&shared, Some(&proxied)).unwrap();
+        assert_eq!(clients.0.lock().unwrap().len(), 1);
+
+        let bad_cert = serde_json::json!({ "clientCert": { "cert": "/missing.pem", "key": "/missing.key" } });
+        assert!(clients.client_for(&shared, Some(&bad_cert)).is_err());
+
+        let both = serde_json::json!({ "proxy": "system", "sshTunnel": { "host": "vm", "user": "u", "keyPath": "k" } });
+        assert!(ClientSettings::of_connection(Some(&both)).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Sidebar folders for connections. A connection belongs to the group named by its
+//! `group` key; the order of `connections` is the order within each group, and the order
+//! of `groups` the order of the folders. A `group` naming no folder counts as none.
+
+use crate::config::{ConnectionGroup, ConnectionsConfig};
+
+fn check_name(config: &ConnectionsConfig, name: &str, except: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("A group needs a name".to_string());
+    }
+    if config.groups.iter().any(|g| Some(g.id.as_str()) != except && g.name.eq_ignore_ascii_case(name)) {
+        return Err(format!("There is already a group named '{}'", name));
+    }
+    Ok(name.to_string())
+}
+
+fn group_index(config: &ConnectionsConfig, id: &str) -> Result<usize, String> {
+    config.groups.iter().position(|g| g.id == id).ok_or_else(|| format!("No connection group '{}'", id))
+}
+
+/// The group `connection` is shown under.
+pub fn group_of<'a>(config: &ConnectionsConfig, connection: &'a serde_json::Value) -> Option<&'a str> {
+    connection.get("group")?.as_str().filter(|id| config.groups.iter().any(|g| g.id == *id))
+}
+
+pub fn create(config: &mut ConnectionsConfig, name: &str) -> Result<ConnectionGroup, String> {
+    let name = check_name(config, name, None)?;
+    let mut millis = chrono::Utc::now().timestamp_millis();
+    while config.groups.iter().any(|g| g.id == format!("group-{}", millis)) {
+        millis += 1;
+    }
+    let group = ConnectionGroup { id: format!("group-{}", millis), name, collapsed: false };
+    config.groups.push(group.clone());
+    Ok(group)
+}
+
+pub fn rename(config: &mut ConnectionsConfig, id: &str, name: &str) -> Result<(), String> {
+    let name = check_name(config, name, Some(id))?;
+    let index = group_index(config, id)?;
+    config.groups[index].name = name;
+    Ok(())
+}
+
+pub fn set_collapsed(config: &mut ConnectionsConfig, id: &str, collapsed: bool) -> Result<(), String> {
+    let index = group_index(config, id)?;
+    config.groups[index].collapsed = collapsed;
+    Ok(())
+}
+
+/// Removes the group; its connections stay, ungrouped.
+pub fn delete(config: &mut ConnectionsConfig, id: &str) -> Result<(), String> {
+    let index = group_index(config, id)?;
+    config.groups.remove(index);
+    for connection in &mut config.connections {
+        if connection.get("group").and_then(|g| g.as_str()) == Some(id) {
+            connection.as_object_mut().map(|c| c.remove("group"));
+        }
+    }
+    Ok(())
+}
+
+/// Moves the group to `index` among the groups (the end when past it).
+pub fn move_group(config: &mut ConnectionsConfig, id: &str, index: usize) -> Result<(), String> {
+    let from = group_index(config, id)?;
+    let group = config.groups.remove(from);
+    config.groups.insert(index.min(config.groups.len()), group);
+    Ok(())
+}
+
+/// Moves a connection into `group` (or out of any), at `index` among that group's
+/// connections (the end when past it).
+pub fn move_connection(config: &mut ConnectionsConfig, id: &str, group: Option<&str>, index: usize) -> Result<(), String> {
+    if let Some(group) = group {
+        group_index(config, group)?;
+    }
+    let from = config.connections.iter()
+        .position(|c| c.get("id").and_then(|v| v.as_str()) == Some(id))
+        .ok_or_else(|| format!("No connection '{}'", id))?;
+    let mut connection = config.connections.remove(from);
+    let fields = connection.as_object_mut().ok_or_else(|| format!("Connection '{}' is not an object", id))?;
+    match group {
+        Some(group) => fields.insert("group".to_string(), group.into()),
+        None => fields.remove("group"),
+    };
+
+    let members: Vec<usize> = config.connections.iter().enumerate()
+        .filter(|(_, c)| group_of(config, c) == group)
+        .map(|(i, _)| i)
+        .collect();
+    let at = match members.get(index) {
+        Some(&i) => i,
+        None => members.last().map_or(config.connections.len(), |&i| i + 1),
+    };
+    config.connections.insert(at, connection);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_and_ordering() {
+        let mut config = ConnectionsConfig {
+            connections: ["a", "b", "c", "d"].iter().map(|id| serde_json::json!({ "id": id })).collect(),
+            ..Default::default()
+        };
+        let staging = create(&mut config, "Staging").unwrap();
+        let prod = create(&mut config, " Prod ").unwrap();
+        assert_eq!(prod.name, "Prod");
+        assert!(create(&mut config, "prod").is_err());
+        assert!(rename(&mut config, &staging.id, "PROD").is_err());
+        rename(&mut config, &staging.id, "Stage").unwrap();
+
+        move_connection(&mut config, "c", Some(&prod.id), 0).unwrap();
+        move_connection(&mut config, "a", Some(&prod.id), 0).unwrap();
+        move_connection(&mut config, "d", Some(&staging.id), 5).unwrap();
+        let ids = |config: &ConnectionsConfig| config.connections.iter()
+            .map(|c| format!("{}:{}", c["id"].as_str().unwrap(), group_of(config, c).unwrap_or("-")))
+            .collect::<Vec<_>>();
+        let (s, p) = (staging.id.as_str(), prod.id.as_str());
+        assert_eq!(ids(&config), vec!["b:-".to_string(), format!("a:{}", p), format!("c:{}", p), format!("d:{}", s)]);
+
+        // Within a group, and back out of it
+        move_connection(&mut config, "c", Some(&prod.id), 0).unwrap();
+        move_connection(&mut config, "d", None, 0).unwrap();
+        assert_eq!(ids(&config), vec!["d:-".to_string(), "b:-".to_string(), format!("c:{}", p), format!("a:{}", p)]);
+
+        move_group(&mut config, &prod.id, 0).unwrap();
+        assert_eq!(config.groups[0].id, prod.id);
+        delete(&mut config, &prod.id).unwrap();
+        assert_eq!(config.groups.len(), 1);
+        assert!(config.connections.iter().all(|c| c.get("group").is_none()));
+        assert!(move_connection(&mut config, "a", Some(&prod.id), 0).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Renders the last execution's requests as a curl script to share a repro. MCP
+//! connection ids are replaced by fresh `mcp/init` calls, and credentials by shell
+//! variables (`$HELIX_API_KEY` for the API key) the script lists at the top.
+
+use crate::network_trace::{is_secret_header, LastExecution, RecordedRequest};
+
+/// Quotes `s` for a POSIX shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// The shell variable standing in for a secret header.
+fn secret_variable(header: &str) -> String {
+    if header.eq_ignore_ascii_case("x-api-key") {
+        return "HELIX_API_KEY".to_string();
+    }
+    let name: String = header.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    format!("HELIX_{}", name)
+}
+
+fn connection_id(request: &RecordedRequest) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_str(request.body.as_deref()?).ok()?;
+    body.get("connection_id")?.as_str().map(String::from)
+}
+
+/// `{url}/mcp/`, the MCP base a request was sent under.
+fn mcp_base(url: &str) -> Option<&str> {
+    url.find("/mcp/").map(|i| &url[..i + "/mcp/".len()])
+}
+
+/// `-H` arguments for the request's headers, except those named in `skip`.
+fn header_args(request: &RecordedRequest, skip: &[&str], secrets: &mut Vec<String>) -> Vec<String> {
+    request.headers.iter()
+        .filter(|(name, _)| !skip.iter().any(|s| s.eq_ignore_ascii_case(name)))
+        .map(|(name, value)| {
+            if is_secret_header(name) {
+                let variable = secret_variable(name);
+                if !secrets.contains(&variable) {
+                    secrets.push(variable.clone());
+                }
+                format!("-H \"{}: ${}\"", name, variable)
+            } else {
+                format!("-H {}", quote(&format!("{}: {}", name, value)))
+            }
+        })
+        .collect()
+}
+
+/// One curl command per request. MCP requests without a connection id are the
+/// execution's own `init` calls; each connection the others use is opened afresh instead.
+pub fn render(execution: &LastExecution) -> String {
+    let mut secrets = Vec::new();
+    let mut connections: Vec<(String, String)> = Vec::new();
+    let mut lines = Vec::new();
+
+    let init_url = execution.requests.iter()
+        .find(|r| mcp_base(&r.url).is_some() && connection_id(r).is_none())
+        .map(|r| r.url.clone());
+    for request in &execution.requests {
+        let id = connection_id(request);
+        if id.is_none() && mcp_base(&request.url).is_some() {
+            continue;
+        }
+        if let Some(id) = id.as_ref().filter(|id| !connections.iter().any(|(known, _)| known == *id)) {
+            let variable = format!("CONN{}", connections.len() + 1);
+            let url = init_url.clone()
+                .or_else(|| mcp_base(&request.url).map(|base| format!("{}init", base)))
+                .unwrap_or_default();
+            let mut init = vec![format!("{}=$(curl -s -X POST {}", variable, quote(&url))];
+            init.extend(header_args(request, &["content-length", "content-type"], &mut secrets));
+            lines.push(format!("{} | tr -d '\"')", init.join(" ")));
+            connections.push((id.clone(), variable));
+        }
+
+        let mut parts = vec![format!("curl -s -X {} {}", request.method, quote(&request.url))];
+        parts.extend(header_args(request, &["content-length"], &mut secrets));
+        if let Some(body) = &request.body {
+            let mut body = quote(body);
+            for (id, variable) in &connections {
+                body = body.replace(&format!("\"{}\"", id), &format!("\"'\"${}\"'\"", variable));
+            }
+            parts.push(format!("-d {}", body));
+        }
+        lines.push(parts.join(" \\\n  "));
+    }
+
+    let mut script = vec![format!("# {} request(s) against {} at {}", lines.len() - connections.len(), execution.url, execution.at)];
+    if !secrets.is_empty() {
+        script.push(format!("# Set {} before running", secrets.join(", ")));
+    }
+    script.extend(lines);
+    script.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, body: Option<&str>) -> RecordedRequest {
+        RecordedRequest {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers: vec![("content-type".to_string(), "application/json".to_string()), ("x-api-key".to_string(), "secret".to_string())],
+            body: body.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_render_pipeline() {
+        let execution = LastExecution {
+            url: "http://h:6969".to_string(),
+            at: "2026-01-01T00:00:00Z".to_string(),
+            requests: vec![
+                request("http://h:6969/mcp/init", None),
+                request("http://h:6969/mcp/tool_call", Some(r#"{"connection_id":"c-1","tool":{"n_from_type":{"node_type":"User's"}}}"#)),
+                request("http://h:6969/mcp/collect", Some(r#"{"connection_id":"c-1","range":null,"drop":true}"#)),
+            ],
+        };
+        let script = render(&execution);
+        assert!(!script.contains("secret"));
+        assert!(!script.contains("c-1"));
+        let lines: Vec<&str> = script.lines().collect();
+        assert_eq!(lines[0], "# 2 request(s) against http://h:6969 at 2026-01-01T00:00:00Z");
+        assert_eq!(lines[1], "# Set HELIX_API_KEY before running");
+        assert_eq!(lines[2], r#"CONN1=$(curl -s -X POST 'http://h:6969/mcp/init' -H "x-api-key: $HELIX_API_KEY" | tr -d '"')"#);
+        assert!(script.contains(r#"-d '{"connection_id":"'"$CONN1"'","tool":{"n_from_type":{"node_type":"User'\''s"}}}'"#));
+
+        let compiled = LastExecution { requests: vec![request("http://h:6969/getUsers", Some("{}"))], ..execution };
+        assert_eq!(render(&compiled).lines().filter(|l| l.starts_with("curl")).count(), 1);
+        assert_eq!(secret_variable("Authorization"), "HELIX_AUTHORIZATION");
+    }
+}
@@ -0,0 +1,256 @@
+//! Read-only integrity check of a local instance's data.mdb: every entry of the core
+//! databases is checked for the layout HelixDB writes, and a sample of the edge
+//! indexes is checked for endpoints that no longer exist.
+
+use heed3::{Database, RoTxn, types::Bytes};
+use serde::Serialize;
+use crate::stats::BM25Metadata;
+
+/// Anomalies listed in the report; the counts keep going past this.
+const MAX_ANOMALIES: usize = 200;
+/// Edge index entries whose endpoints are looked up.
+const EDGE_SAMPLE: usize = 1000;
+
+const ID_LEN: usize = 16;
+/// Edge index keys are the node id followed by a 4-byte label hash.
+const EDGE_KEY_LEN: usize = ID_LEN + 4;
+/// Edge index values are the edge id followed by the other node's id.
+const EDGE_VALUE_LEN: usize = ID_LEN * 2;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Anomaly {
+    pub database: String,
+    /// The entry's key, hex encoded.
+    pub key: String,
+    pub problem: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct DatabaseCheck {
+    pub name: String,
+    pub entries: usize,
+    /// Entries whose layout was checked; databases without a known layout check none.
+    pub checked: usize,
+    pub problems: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VerificationReport {
+    pub db_path: String,
+    pub databases: Vec<DatabaseCheck>,
+    pub anomalies: Vec<Anomaly>,
+    /// Problems found in total, including those past the listed anomalies.
+    pub problem_count: usize,
+    /// Edge index entries whose endpoints were looked up.
+    pub sampled_edges: usize,
+    pub healthy: bool,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Nodes and edges are stored as their label (a length-prefixed string), a version
+/// byte, then the rest of the item; the id lives in the key.
+fn check_item(key: &[u8], value: &[u8]) -> Result<(), String> {
+    if key.len() != ID_LEN {
+        return Err(format!("key is {} bytes, expected a {}-byte id", key.len(), ID_LEN));
+    }
+    let len_bytes: [u8; 8] = value.get(..8)
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| format!("value is {} bytes, too short for a label", value.len()))?;
+    let label_len = u64::from_le_bytes(len_bytes) as usize;
+    let label = value.get(8..8usize.saturating_add(label_len))
+        .ok_or_else(|| format!("label length {} runs past the {}-byte value", label_len, value.len()))?;
+    std::str::from_utf8(label).map_err(|_| "label is not valid UTF-8".to_string())?;
+    if value.len() < 8 + label_len + 1 {
+        return Err("value ends before the version byte".to_string());
+    }
+    Ok(())
+}
+
+/// An `out_edges`/`in_edges` entry: the node it is keyed by, the edge, and the node
+/// at the other end.
+struct EdgeEntry<'a> {
+    node: &'a [u8],
+    edge: &'a [u8],
+    other: &'a [u8],
+}
+
+fn edge_entry<'a>(key: &'a [u8], value: &'a [u8]) -> Result<EdgeEntry<'a>, String> {
+    if key.len() != EDGE_KEY_LEN {
+        return Err(format!("key is {} bytes, expected {}", key.len(), EDGE_KEY_LEN));
+    }
+    if value.len() != EDGE_VALUE_LEN {
+        return Err(format!("value is {} bytes, expected {}", value.len(), EDGE_VALUE_LEN));
+    }
+    Ok(EdgeEntry { node: &key[..ID_LEN], edge: &value[..ID_LEN], other: &value[ID_LEN..] })
+}
+
+fn check_entry(name: &str, key: &[u8], value: &[u8]) -> Option<Result<(), String>> {
+    match name {
+        "nodes" | "edges" => Some(check_item(key, value)),
+        "out_edges" | "in_edges" => Some(edge_entry(key, value).map(|_| ())),
+        _ if name.starts_with("bm25_metadata") && key == b"metadata" => Some(
+            bincode::deserialize::<BM25Metadata>(value).map(|_| ()).map_err(|e| format!("BM25 metadata does not deserialize: {}", e)),
+        ),
+        "vectors" | "vector_data" | "hnsw_out_nodes" => Some(if key.is_empty() || value.is_empty() {
+            Err("empty vector entry".to_string())
+        } else {
+            Ok(())
+        }),
+        _ => None,
+    }
+}
+
+struct Findings {
+    anomalies: Vec<Anomaly>,
+    count: usize,
+}
+
+impl Findings {
+    fn add(&mut self, database: &str, key: &[u8], problem: String) {
+        self.count += 1;
+        if self.anomalies.len() < MAX_ANOMALIES {
+            self.anomalies.push(Anomaly { database: database.to_string(), key: hex(key), problem });
+        }
+    }
+}
+
+fn check_database(txn: &RoTxn, db: Database<Bytes, Bytes>, name: &str, entries: usize, findings: &mut Findings) -> Result<DatabaseCheck, String> {
+    let mut check = DatabaseCheck { name: name.to_string(), entries, ..Default::default() };
+    for item in db.iter(txn).map_err(|e| e.to_string())? {
+        let (key, value) = match item {
+            Ok(entry) => entry,
+            Err(e) => {
+                check.problems += 1;
+                findings.add(name, &[], format!("entry could not be read: {}", e));
+                continue;
+            }
+        };
+        let Some(result) = check_entry(name, key, value) else {
+            continue;
+        };
+        check.checked += 1;
+        if let Err(problem) = result {
+            check.problems += 1;
+            findings.add(name, key, problem);
+        }
+    }
+    Ok(check)
+}
+
+/// Looks up the endpoints of every `stride`-th entry of an edge index. In `out_edges`
+/// the key holds the source node; in `in_edges` the target.
+fn sample_edges(
+    txn: &RoTxn,
+    index: Database<Bytes, Bytes>,
+    name: &str,
+    nodes: Database<Bytes, Bytes>,
+    edges: Database<Bytes, Bytes>,
+    stride: usize,
+    findings: &mut Findings,
+) -> Result<usize, String> {
+    let mut sampled = 0;
+    for item in index.iter(txn).map_err(|e| e.to_string())?.step_by(stride.max(1)) {
+        let Ok((key, value)) = item else {
+            continue;
+        };
+        let Ok(entry) = edge_entry(key, value) else {
+            continue;
+        };
+        sampled += 1;
+        let exists = |db: &Database<Bytes, Bytes>, id: &[u8]| db.get(txn, id).map(|v| v.is_some()).unwrap_or(false);
+        if !exists(&nodes, entry.node) {
+            findings.add(name, key, format!("node {} does not exist", hex(entry.node)));
+        }
+        if !exists(&nodes, entry.other) {
+            findings.add(name, key, format!("node {} at the other end does not exist", hex(entry.other)));
+        }
+        if !exists(&edges, entry.edge) {
+            findings.add(name, key, format!("edge {} does not exist", hex(entry.edge)));
+        }
+    }
+    Ok(sampled)
+}
+
+/// Walks every sub-database of the workspace's data.mdb in one read transaction.
+pub fn verify_local_db(path: &str, instance_name: Option<&str>) -> Result<VerificationReport, String> {
+    let db_path = crate::stats::resolve_db_path(path, instance_name);
+    if !db_path.join("data.mdb").exists() {
+        return Err(format!("No data.mdb found for {}", path));
+    }
+    let env = crate::stats::open_env(&db_path)?;
+    let counts = crate::stats::entry_counts(&env)?;
+    let txn = env.read_txn().map_err(|e| format!("Failed to start read transaction: {}", e))?;
+    let open = |name: &str| env.open_database::<Bytes, Bytes>(&txn, Some(name)).ok().flatten();
+
+    let mut findings = Findings { anomalies: Vec::new(), count: 0 };
+    let mut names: Vec<&String> = counts.keys().collect();
+    names.sort();
+    let mut databases = Vec::new();
+    for name in names {
+        match open(name) {
+            Some(db) => databases.push(check_database(&txn, db, name, counts[name], &mut findings)?),
+            None => findings.add(name, &[], "listed but cannot be opened".to_string()),
+        }
+    }
+
+    let mut sampled_edges = 0;
+    if let (Some(nodes), Some(edges)) = (open("nodes"), open("edges")) {
+        for name in ["out_edges", "in_edges"] {
+            if let (Some(index), Some(entries)) = (open(name), counts.get(name)) {
+                sampled_edges += sample_edges(&txn, index, name, nodes, edges, entries / EDGE_SAMPLE, &mut findings)?;
+            }
+        }
+    }
+
+    Ok(VerificationReport {
+        db_path: db_path.to_string_lossy().into_owned(),
+        databases,
+        healthy: findings.count == 0,
+        problem_count: findings.count,
+        anomalies: findings.anomalies,
+        sampled_edges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str) -> Vec<u8> {
+        let mut value = (label.len() as u64).to_le_bytes().to_vec();
+        value.extend_from_slice(label.as_bytes());
+        value.push(1);
+        value
+    }
+
+    #[test]
+    fn test_verify_reports_dangling_edges() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, missing, edge) = ([1u8; 16], [2u8; 16], [9u8; 16], [3u8; 16]);
+        {
+            let env = crate::stats::open_env(dir.path()).unwrap();
+            let mut wtxn = env.write_txn().unwrap();
+            let create = |wtxn: &mut heed3::RwTxn, name: &str| env.create_database::<Bytes, Bytes>(wtxn, Some(name)).unwrap();
+            let (nodes, edges, out_edges) = (create(&mut wtxn, "nodes"), create(&mut wtxn, "edges"), create(&mut wtxn, "out_edges"));
+            nodes.put(&mut wtxn, &a, &item("User")).unwrap();
+            nodes.put(&mut wtxn, &b, &item("User")).unwrap();
+            nodes.put(&mut wtxn, &[4u8; 3], &item("User")).unwrap();
+            edges.put(&mut wtxn, &edge, &item("Follows")).unwrap();
+            let key = |node: [u8; 16]| [node.as_slice(), &[0u8; 4]].concat();
+            out_edges.put(&mut wtxn, &key(a), &[edge, b].concat()).unwrap();
+            out_edges.put(&mut wtxn, &key(b), &[edge, missing].concat()).unwrap();
+            wtxn.commit().unwrap();
+        }
+
+        let report = verify_local_db(dir.path().to_str().unwrap(), None).unwrap();
+        assert!(!report.healthy);
+        assert_eq!(report.sampled_edges, 2);
+        assert_eq!(report.problem_count, 2);
+        let nodes = report.databases.iter().find(|d| d.name == "nodes").unwrap();
+        assert_eq!((nodes.entries, nodes.checked, nodes.problems), (3, 3, 1));
+        assert!(report.anomalies.iter().any(|a| a.database == "out_edges" && a.problem.contains(&hex(&missing))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Off at startup; demo mode lasts for the session it was switched on in.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Structural fields that results and the graph view link by. Faking them would break
+/// navigation, and they carry no user data.
+const KEPT_KEYS: &[&str] = &["id", "label", "from_node", "to_node", "from", "to", "score", "distance"];
+
+const FIRST_NAMES: &[&str] = &["Alex", "Jordan", "Sam", "Taylor", "Morgan", "Casey", "Riley", "Jamie", "Avery", "Quinn", "Drew", "Robin"];
+const LAST_NAMES: &[&str] = &["Rivera", "Chen", "Okafor", "Novak", "Silva", "Haddad", "Larsen", "Kimura", "Moreau", "Patel", "Fischer", "Brooks"];
+const WORDS: &[&str] = &["lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod", "tempor", "incididunt", "labore", "magna", "aliqua"];
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// FNV-1a over the key and value, so a value always fakes to the same output and
+/// repeated values still line up across rows and queries.
+fn seed(key: &str, value: &str) -> u64 {
+    key.bytes().chain([0]).chain(value.bytes())
+        .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn pick<'a>(list: &[&'a str], seed: u64, salt: u32) -> &'a str {
+    list[(seed.rotate_left(salt * 11) % list.len() as u64) as usize]
+}
+
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+        && groups.iter().all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Replaces every hex digit, keeping hyphens and letter case.
+fn fake_uuid(s: &str, seed: u64) -> String {
+    let mut state = seed;
+    s.chars().map(|c| {
+        if !c.is_ascii_hexdigit() {
+            return c;
+        }
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let digit = std::char::from_digit(((state >> 60) & 0xf) as u32, 16).unwrap();
+        if c.is_ascii_uppercase() { digit.to_ascii_uppercase() } else { digit }
+    }).collect()
+}
+
+/// A stand-in for `value` chosen by what the property name and the value look like.
+pub fn fake_string(key: &str, value: &str) -> String {
+    let seed = seed(key, value);
+    let key = key.to_lowercase();
+    let first = pick(FIRST_NAMES, seed, 1);
+    let last = pick(LAST_NAMES, seed, 2);
+
+    if value.is_empty() || crate::hql::translator::parse_date(value).is_some() {
+        value.to_string()
+    } else if is_uuid(value) {
+        fake_uuid(value, seed)
+    } else if key.contains("email") || (value.contains('@') && !value.contains(' ')) {
+        format!("{}.{}{}@example.com", first.to_lowercase(), last.to_lowercase(), seed % 100)
+    } else if key.contains("phone") || key.contains("mobile") {
+        format!("+1 555-01{:02}", seed % 100)
+    } else if value.starts_with("http://") || value.starts_with("https://") {
+        format!("https://example.com/{}", pick(WORDS, seed, 3))
+    } else if key.contains("username") || key.contains("handle") || key.contains("login") {
+        format!("{}{}", first.to_lowercase(), seed % 1000)
+    } else if key.contains("first") {
+        first.to_string()
+    } else if key.contains("last") || key.contains("surname") {
+        last.to_string()
+    } else if key.contains("name") || key.contains("author") {
+        format!("{} {}", first, last)
+    } else {
+        // Same number of words, so column widths and wrapping look like the real data
+        let count = value.split_whitespace().count().max(1);
+        (0..count).map(|i| pick(WORDS, seed, i as u32 + 4)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn fake(key: &str, value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(fake_string(key, &s)),
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(|v| fake(key, v)).collect()),
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter()
+            .map(|(k, v)| {
+                let v = if KEPT_KEYS.contains(&k.as_str()) { v } else { fake(&k, v) };
+                (k, v)
+            })
+            .collect()),
+        other => other,
+    }
+}
+
+/// Replaces string values in a (normalized) result when demo mode is on. Numbers,
+/// booleans, dates and structural fields are left alone.
+pub fn apply(value: serde_json::Value) -> serde_json::Value {
+    if !is_enabled() {
+        return value;
+    }
+    fake("", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_values_are_deterministic_and_shaped() {
+        let value = serde_json::json!([
+            { "id": "1f0e2a3b-4c5d-6e7f-8091-a2b3c4d5e6f7", "label": "User", "name": "Ada Lovelace", "email": "ada@math.org", "age": 36 },
+            { "id": "2f0e2a3b-4c5d-6e7f-8091-a2b3c4d5e6f7", "label": "User", "name": "Ada Lovelace", "bio": "Wrote the first program",
+              "accountId": "ABCDEF01-2345-6789-ABCD-EF0123456789", "createdAt": "2024-01-01T00:00:00Z" }
+        ]);
+        let faked = fake("", value);
+        let (a, b) = (&faked[0], &faked[1]);
+        assert_eq!(a["id"], "1f0e2a3b-4c5d-6e7f-8091-a2b3c4d5e6f7");
+        assert_eq!(a["label"], "User");
+        assert_ne!(a["name"], "Ada Lovelace");
+        assert_eq!(a["name"], b["name"]);
+        assert!(a["email"].as_str().unwrap().ends_with("@example.com"));
+        assert_eq!(a["age"], 36);
+        assert_eq!(b["bio"].as_str().unwrap().split(' ').count(), 4);
+        let account = b["accountId"].as_str().unwrap();
+        assert!(is_uuid(account) && account != "ABCDEF01-2345-6789-ABCD-EF0123456789");
+        assert!(!account.chars().any(|c| c.is_ascii_lowercase()));
+        assert_eq!(b["createdAt"], "2024-01-01T00:00:00Z");
+    }
+}
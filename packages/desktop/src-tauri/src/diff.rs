@@ -0,0 +1,188 @@
+use crate::versions::{diff_tokens, DiffKind};
+
+/// Unchanged lines shown around each change.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// Part of a changed line; `Same` parts are shared with the line it replaced.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct WordSegment {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkLine {
+    pub kind: DiffKind,
+    pub text: String,
+    /// 1-based line numbers; `None` on the side the line does not exist.
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    /// Word-level changes, for lines replaced by (or replacing) another line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<WordSegment>>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UnifiedDiff {
+    pub name: String,
+    /// The diff as `diff -u` would print it; empty when nothing changed.
+    pub unified: String,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Words, runs of whitespace and single punctuation characters.
+fn tokenize(line: &str) -> Vec<&str> {
+    let class = |c: char| if c.is_alphanumeric() || c == '_' { 0 } else if c.is_whitespace() { 1 } else { 2 };
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<u8> = None;
+    for (i, c) in line.char_indices() {
+        let kind = class(c);
+        if prev.is_some() && (prev != Some(kind) || kind == 2) {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+        prev = Some(kind);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+/// Word diff of one replaced line: the removed side's segments and the added side's.
+fn word_segments(old: &str, new: &str) -> (Vec<WordSegment>, Vec<WordSegment>) {
+    let (a, b) = (tokenize(old), tokenize(new));
+    let mut removed: Vec<WordSegment> = Vec::new();
+    let mut added: Vec<WordSegment> = Vec::new();
+    let push = |side: &mut Vec<WordSegment>, kind: DiffKind, text: &str| match side.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => side.push(WordSegment { kind, text: text.to_string() }),
+    };
+    for (kind, text) in diff_tokens(&a, &b) {
+        match kind {
+            DiffKind::Same => {
+                push(&mut removed, DiffKind::Same, text);
+                push(&mut added, DiffKind::Same, text);
+            }
+            DiffKind::Removed => push(&mut removed, DiffKind::Removed, text),
+            DiffKind::Added => push(&mut added, DiffKind::Added, text),
+        }
+    }
+    (removed, added)
+}
+
+/// Pairs each block of removed lines with the added lines right after it, in order.
+fn add_word_segments(lines: &mut [HunkLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        let removed_end = i + lines[i..].iter().take_while(|l| l.kind == DiffKind::Removed).count();
+        let added_end = removed_end + lines[removed_end..].iter().take_while(|l| l.kind == DiffKind::Added).count();
+        let pairs = (removed_end - i).min(added_end - removed_end);
+        for k in 0..pairs {
+            let (old, new) = word_segments(&lines[i + k].text, &lines[removed_end + k].text);
+            lines[i + k].segments = Some(old);
+            lines[removed_end + k].segments = Some(new);
+        }
+        i = added_end.max(i + 1);
+    }
+}
+
+/// Unified diff from `old` to `new`, with `context` unchanged lines around each hunk.
+pub fn unified_diff(name: &str, old: &str, new: &str, context: usize) -> UnifiedDiff {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    let (mut old_no, mut new_no) = (0, 0);
+    let lines: Vec<HunkLine> = diff_tokens(&a, &b).into_iter()
+        .map(|(kind, text)| {
+            let (old_line, new_line) = match kind {
+                DiffKind::Same => { old_no += 1; new_no += 1; (Some(old_no), Some(new_no)) }
+                DiffKind::Removed => { old_no += 1; (Some(old_no), None) }
+                DiffKind::Added => { new_no += 1; (None, Some(new_no)) }
+            };
+            HunkLine { kind, text: text.to_string(), old_line, new_line, segments: None }
+        })
+        .collect();
+
+    // Changes closer than two contexts apart share a hunk
+    let changes: Vec<usize> = lines.iter().enumerate().filter(|(_, l)| l.kind != DiffKind::Same).map(|(i, _)| i).collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let (start, end) = (i.saturating_sub(context), (i + context + 1).min(lines.len()));
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut unified = String::new();
+    let mut hunks = Vec::new();
+    for (start, end) in ranges {
+        let mut hunk_lines = lines[start..end].to_vec();
+        add_word_segments(&mut hunk_lines);
+        let old_lines = hunk_lines.iter().filter(|l| l.kind != DiffKind::Added).count();
+        let new_lines = hunk_lines.iter().filter(|l| l.kind != DiffKind::Removed).count();
+        // Lines before the hunk on each side; an empty side starts at that line, as in `diff -u`
+        let old_before = lines[..start].iter().filter(|l| l.kind != DiffKind::Added).count();
+        let new_before = lines[..start].iter().filter(|l| l.kind != DiffKind::Removed).count();
+        let hunk = DiffHunk {
+            old_start: if old_lines == 0 { old_before } else { old_before + 1 },
+            old_lines,
+            new_start: if new_lines == 0 { new_before } else { new_before + 1 },
+            new_lines,
+            lines: hunk_lines,
+        };
+        unified.push_str(&format!("@@ -{},{} +{},{} @@\n", hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines));
+        for line in &hunk.lines {
+            let prefix = match line.kind {
+                DiffKind::Same => ' ',
+                DiffKind::Removed => '-',
+                DiffKind::Added => '+',
+            };
+            unified.push(prefix);
+            unified.push_str(&line.text);
+            unified.push('\n');
+        }
+        hunks.push(hunk);
+    }
+    if !unified.is_empty() {
+        unified = format!("--- a/{name}\n+++ b/{name}\n{unified}");
+    }
+
+    UnifiedDiff { name: name.to_string(), unified, hunks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_with_word_segments() {
+        let old = "QUERY Get() =>\n    users <- N<User>\n    RETURN users\n";
+        let new = "QUERY Get() =>\n    users <- N<User>::RANGE(0, 10)\n    RETURN users\n";
+        let diff = unified_diff("Get", old, new, 1);
+        assert_eq!(diff.unified, "--- a/Get\n+++ b/Get\n@@ -1,3 +1,3 @@\n QUERY Get() =>\n-    users <- N<User>\n+    users <- N<User>::RANGE(0, 10)\n     RETURN users\n");
+
+        let added = &diff.hunks[0].lines[2];
+        assert_eq!(added.new_line, Some(2));
+        let segments = added.segments.as_ref().unwrap();
+        assert_eq!(segments.last().unwrap(), &WordSegment { kind: DiffKind::Added, text: "::RANGE(0, 10)".to_string() });
+
+        assert!(unified_diff("Get", old, old, 3).hunks.is_empty());
+        let appended = unified_diff("Get", "", "a", 3);
+        assert_eq!((appended.hunks[0].old_start, appended.hunks[0].new_start), (0, 1));
+    }
+}
@@ -0,0 +1,90 @@
+/// Per-connection environment variables, referenced from parameters and literal
+/// assignments as `$env.name` (the whole value) or `${env.name}` (inside text).
+///
+/// A connection defines them in connections.json either directly:
+/// `"environment": { "tenant_id": "acme" }`
+/// or as named sets with one active:
+/// `"environments": { "staging": {...}, "prod": {...} }, "activeEnvironment": "staging"`.
+pub type Environment = serde_json::Map<String, serde_json::Value>;
+
+const WHOLE_PREFIX: &str = "$env.";
+const INLINE_OPEN: &str = "${env.";
+
+/// The active environment of the connection `url` points at.
+pub fn connection_environment(app: &tauri::AppHandle, url: &str) -> Environment {
+    let Some(connection) = crate::policy::find_connection(app, Some(url)) else {
+        return Environment::new();
+    };
+    environment_of(&connection)
+}
+
+pub(crate) fn environment_of(connection: &serde_json::Value) -> Environment {
+    let active = connection.get("activeEnvironment").and_then(|v| v.as_str());
+    let named = active.and_then(|name| connection.get("environments")?.get(name));
+    named.or_else(|| connection.get("environment"))
+        .and_then(|env| env.as_object())
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn lookup<'a>(env: &'a Environment, name: &str) -> Result<&'a serde_json::Value, String> {
+    env.get(name).ok_or_else(|| format!("Unknown environment variable '{}'", name))
+}
+
+fn interpolate(text: &str, env: &Environment) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(INLINE_OPEN) {
+        let after = &rest[start + INLINE_OPEN.len()..];
+        let end = after.find('}').ok_or_else(|| format!("Unclosed environment reference in '{}'", text))?;
+        out.push_str(&rest[..start]);
+        match lookup(env, &after[..end])? {
+            serde_json::Value::String(s) => out.push_str(s),
+            other => out.push_str(&other.to_string()),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Whether `text` refers to an environment variable, so its type is only known once resolved.
+pub fn is_reference(text: &str) -> bool {
+    text.starts_with(WHOLE_PREFIX) || text.contains(INLINE_OPEN)
+}
+
+/// Replaces environment references anywhere in `value`. Whole-value references keep
+/// the variable's JSON type, so `"$env.limit"` can stand for a number.
+pub fn resolve(value: serde_json::Value, env: &Environment) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        serde_json::Value::String(s) => match s.strip_prefix(WHOLE_PREFIX) {
+            Some(name) if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') => lookup(env, name)?.clone(),
+            _ if s.contains(INLINE_OPEN) => serde_json::Value::String(interpolate(&s, env)?),
+            _ => serde_json::Value::String(s),
+        },
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(|v| resolve(v, env)).collect::<Result<_, _>>()?),
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter()
+            .map(|(k, v)| Ok((k, resolve(v, env)?)))
+            .collect::<Result<_, String>>()?),
+        other => other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_environment_references() {
+        let connection = serde_json::json!({
+            "environment": { "tenant_id": "fallback" },
+            "environments": { "prod": { "tenant_id": "acme", "limit": 50 } },
+            "activeEnvironment": "prod"
+        });
+        let env = environment_of(&connection);
+        let params = serde_json::json!({ "tenant": "$env.tenant_id", "limit": "$env.limit", "path": "/t/${env.tenant_id}/${env.limit}", "plain": "$5" });
+        let resolved = resolve(params, &env).unwrap();
+        assert_eq!(resolved, serde_json::json!({ "tenant": "acme", "limit": 50, "path": "/t/acme/50", "plain": "$5" }));
+        assert!(resolve(serde_json::json!(["$env.missing"]), &env).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! .hx files handed to the app by the OS, through the file association or as a path
+//! on the command line. Each file opens as one worksheet tab per query.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+use tauri::{Emitter, Manager};
+use crate::commands::hql::Diagnostic;
+
+/// Tells the frontend to call `take_opened_files`.
+pub const OPENED_EVENT: &str = "hx-files-opened";
+
+/// Opening a file should not hang on an unreachable default connection.
+const SCHEMA_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedTab {
+    pub name: String,
+    pub code: String,
+    /// Syntax errors, plus labels the connected schema does not define.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedFile {
+    pub path: String,
+    pub tabs: Vec<OpenedTab>,
+    /// The connection the tabs were checked against, when one is set.
+    pub connection_id: Option<String>,
+    /// Why the schema check was skipped, when the connection could not be reached.
+    pub schema_error: Option<String>,
+}
+
+/// Opened files waiting for the frontend, which may not be listening yet at launch.
+#[derive(Default)]
+pub struct OpenedFiles(pub Mutex<Vec<OpenedFile>>);
+
+fn is_hx(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("hx"))
+}
+
+/// The .hx files among launch arguments, after the executable itself.
+pub fn hx_paths<I: IntoIterator<Item = String>>(args: I) -> Vec<PathBuf> {
+    args.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .filter(|path| is_hx(path))
+        .collect()
+}
+
+/// One `(name, code)` per query, with the comments above it. A file without queries,
+/// or one that does not parse, opens whole under the file's name so nothing is lost.
+pub fn split_queries(content: &str, file_name: &str) -> Vec<(String, String)> {
+    let mut queries: Vec<_> = match HelixParser::parse_source(&write_to_temp_file(vec![content])) {
+        Ok(source) => source.queries.iter().map(|q| (q.name.clone(), q.loc.byte_range())).collect(),
+        Err(_) => Vec::new(),
+    };
+    if queries.is_empty() {
+        return vec![(file_name.to_string(), content.trim().to_string())];
+    }
+    queries.sort_by_key(|(_, range)| range.start);
+
+    let mut cursor = 0;
+    let mut tabs = Vec::new();
+    for (name, range) in queries {
+        let code = content[cursor..range.end].trim().to_string();
+        cursor = range.end;
+        tabs.push((name, code));
+    }
+    tabs
+}
+
+/// The labels a connection's schema defines, from `fetch_mcp_schema`.
+#[derive(Debug, Default)]
+pub struct SchemaLabels {
+    nodes: HashSet<String>,
+    edges: HashSet<String>,
+    vectors: HashSet<String>,
+}
+
+impl SchemaLabels {
+    /// Reads both the MCP shape (`{ schema: { nodes, ... } }`) and a bare `{ nodes, ... }`.
+    pub fn from_schema(value: &serde_json::Value) -> Self {
+        let schema = value.get("schema").filter(|s| s.get("nodes").is_some()).unwrap_or(value);
+        let names = |key: &str| -> HashSet<String> {
+            schema.get(key).and_then(|v| v.as_array()).into_iter().flatten()
+                .filter_map(|item| item.get("name").unwrap_or(item).as_str())
+                .map(String::from)
+                .collect()
+        };
+        Self { nodes: names("nodes"), edges: names("edges"), vectors: names("vectors") }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty() && self.vectors.is_empty()
+    }
+
+    /// Warnings for labels in `code` the schema does not define. An empty schema
+    /// (a server without one) checks nothing.
+    pub fn unknown_labels(&self, code: &str) -> Vec<Diagnostic> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let re = regex::Regex::new(r"\b(AddN|UpsertN|SearchBM25|N|AddE|UpsertE|OutE|InE|Out|In|E|AddV|UpsertV|SearchV|V)\s*<\s*(\w+)\s*>").unwrap();
+        re.captures_iter(code)
+            .filter_map(|caps| {
+                let known = match &caps[1] {
+                    "AddN" | "UpsertN" | "SearchBM25" | "N" => &self.nodes,
+                    "AddV" | "UpsertV" | "SearchV" | "V" => &self.vectors,
+                    _ => &self.edges,
+                };
+                let label = caps.get(2)?;
+                if known.contains(label.as_str()) {
+                    return None;
+                }
+                Some(Diagnostic {
+                    from: label.start(),
+                    to: label.end(),
+                    severity: "warning".to_string(),
+                    message: crate::i18n::t("hql.unknown_label", &[("label", label.as_str())]),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The current connection's id and schema labels; no labels when no connection is set.
+async fn current_schema(app: &tauri::AppHandle) -> (Option<String>, Result<Option<SchemaLabels>, String>) {
+    let Some(connection) = crate::policy::find_connection(app, None) else {
+        return (None, Ok(None));
+    };
+    let id = connection.get("id").and_then(|v| v.as_str()).map(String::from);
+    let url = crate::policy::connection_url(&connection);
+    let api_key = connection.get("apiKey").and_then(|v| v.as_str()).filter(|k| !k.is_empty());
+    let fetched = tokio::time::timeout(SCHEMA_TIMEOUT, crate::commands::network::cached_schema(app, &url, api_key)).await;
+    let labels = match fetched {
+        Ok(Ok(schema)) => Ok(Some(SchemaLabels::from_schema(&schema))),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(crate::config::timed_out_error(SCHEMA_TIMEOUT)),
+    };
+    (id, labels)
+}
+
+/// Reads `path` into tabs, checking each for syntax and, when a connection is set,
+/// against its schema.
+pub async fn open_file(app: &tauri::AppHandle, path: &Path) -> Result<OpenedFile, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file_name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let (connection_id, labels) = current_schema(app).await;
+    let (labels, schema_error) = match labels {
+        Ok(labels) => (labels, None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let mut tabs = Vec::new();
+    for (name, code) in split_queries(&content, &file_name) {
+        let mut diagnostics = crate::commands::hql::validate_hql(code.clone()).await?;
+        if let Some(labels) = &labels {
+            diagnostics.extend(labels.unknown_labels(&code));
+        }
+        tabs.push(OpenedTab { name, code, diagnostics });
+    }
+    Ok(OpenedFile { path: path.to_string_lossy().into_owned(), tabs, connection_id, schema_error })
+}
+
+/// Opens `paths` in the background, queues them for `take_opened_files` and emits
+/// `hx-files-opened`.
+pub fn open_paths(app: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    let paths: Vec<PathBuf> = paths.into_iter().filter(|p| is_hx(p)).collect();
+    if paths.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for path in paths {
+            match open_file(&app, &path).await {
+                Ok(file) => app.state::<OpenedFiles>().0.lock().unwrap().push(file),
+                Err(e) => println!(">>> [FileOpen] {}", e),
+            }
+        }
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.emit(OPENED_EVENT, ());
+        }
+    });
+}
+
+/// Drains the opened files, oldest first.
+pub fn take(app: &tauri::AppHandle) -> Vec<OpenedFile> {
+    std::mem::take(&mut *app.state::<OpenedFiles>().0.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hx_paths_and_labels() {
+        let args = ["explorer", "--flag", "/tmp/a.hx", "/tmp/b.HX", "/tmp/c.json"].map(String::from);
+        assert_eq!(hx_paths(args), vec![PathBuf::from("/tmp/a.hx"), PathBuf::from("/tmp/b.HX")]);
+
+        let labels = SchemaLabels::from_schema(&serde_json::json!({
+            "queries": [],
+            "schema": { "nodes": [{ "name": "User" }], "edges": [{ "name": "Follows" }], "vectors": ["Doc"] }
+        }));
+        let code = "N<User>::Out<Follows>::In<Likes>  SearchV<Doc>  AddN<Post>";
+        let unknown: Vec<&str> = labels.unknown_labels(code).iter().map(|d| &code[d.from..d.to]).collect();
+        assert_eq!(unknown, vec!["Likes", "Post"]);
+        assert!(SchemaLabels::default().unknown_labels(code).is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+
+/// Longest line excerpt returned per match in previews.
+const PREVIEW_CONTEXT: usize = 120;
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReplaceOptions {
+    pub find: String,
+    #[serde(default)]
+    pub replace: String,
+    /// Treat `find` as a regular expression; `replace` may then use `$1`/`${name}`.
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Only report matches, leaving every tab unchanged.
+    #[serde(default)]
+    pub preview: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct TabContent {
+    pub id: String,
+    pub code: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TextMatch {
+    /// 1-based line and column of the match start.
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+    pub matched: String,
+    pub replacement: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TabReplaceResult {
+    pub id: String,
+    pub matches: Vec<TextMatch>,
+    /// New contents, or `None` in preview mode and for tabs without matches.
+    pub code: Option<String>,
+}
+
+fn build_regex(options: &FindReplaceOptions) -> Result<Regex, String> {
+    if options.find.is_empty() {
+        return Err("Search text is empty".to_string());
+    }
+    let mut pattern = if options.regex { options.find.clone() } else { regex::escape(&options.find) };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+fn find_matches(re: &Regex, options: &FindReplaceOptions, code: &str) -> Vec<TextMatch> {
+    re.captures_iter(code)
+        .filter_map(|caps| {
+            let m = caps.get(0).filter(|m| !m.as_str().is_empty())?;
+            let line_start = code[..m.start()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = code[m.start()..].find('\n').map(|i| m.start() + i).unwrap_or(code.len());
+            let replacement = if options.regex {
+                let mut expanded = String::new();
+                caps.expand(&options.replace, &mut expanded);
+                expanded
+            } else {
+                options.replace.clone()
+            };
+            Some(TextMatch {
+                line: code[..m.start()].matches('\n').count() + 1,
+                column: code[line_start..m.start()].chars().count() + 1,
+                line_text: code[line_start..line_end].chars().take(PREVIEW_CONTEXT).collect(),
+                matched: m.as_str().to_string(),
+                replacement,
+            })
+        })
+        .collect()
+}
+
+/// Finds (and unless previewing, replaces) across all tabs. The pattern is validated
+/// before any tab is touched, so either every tab gets its new contents or none does.
+pub fn find_replace_in_tabs(tabs: &[TabContent], options: &FindReplaceOptions) -> Result<Vec<TabReplaceResult>, String> {
+    let re = build_regex(options)?;
+    Ok(tabs.iter()
+        .map(|tab| {
+            let matches = find_matches(&re, options, &tab.code);
+            let code = if options.preview || matches.is_empty() {
+                None
+            } else if options.regex {
+                Some(re.replace_all(&tab.code, options.replace.as_str()).into_owned())
+            } else {
+                Some(re.replace_all(&tab.code, NoExpand(&options.replace)).into_owned())
+            };
+            TabReplaceResult { id: tab.id.clone(), matches, code }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs() -> Vec<TabContent> {
+        vec![
+            TabContent { id: "a".to_string(), code: "N<User>::WHERE(_::{name}::EQ(username))\nN<Users>".to_string() },
+            TabContent { id: "b".to_string(), code: "N<Post>".to_string() },
+        ]
+    }
+
+    #[test]
+    fn test_whole_word_literal_replace() {
+        let options = FindReplaceOptions { find: "user".to_string(), replace: "Account".to_string(), whole_word: true, ..Default::default() };
+        let results = find_replace_in_tabs(&tabs(), &options).unwrap();
+        assert_eq!(results[0].matches.len(), 1);
+        assert_eq!(results[0].matches[0].column, 3);
+        assert_eq!(results[0].code.as_deref(), Some("N<Account>::WHERE(_::{name}::EQ(username))\nN<Users>"));
+        assert!(results[1].code.is_none());
+    }
+
+    #[test]
+    fn test_regex_preview_and_invalid_pattern() {
+        let options = FindReplaceOptions { find: r"N<(\w+)>".to_string(), replace: "V<$1>".to_string(), regex: true, case_sensitive: true, preview: true, ..Default::default() };
+        let results = find_replace_in_tabs(&tabs(), &options).unwrap();
+        assert_eq!(results[0].matches[1].line, 2);
+        assert_eq!(results[0].matches[1].replacement, "V<Users>");
+        assert!(results[0].code.is_none());
+
+        let invalid = FindReplaceOptions { find: "(".to_string(), regex: true, ..Default::default() };
+        assert!(find_replace_in_tabs(&tabs(), &invalid).is_err());
+    }
+}
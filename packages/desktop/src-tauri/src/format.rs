@@ -0,0 +1,181 @@
+use std::str::FromStr;
+use fixed_decimal::FixedDecimal;
+use icu::calendar::DateTime;
+use icu::datetime::{options::length, DateTimeFormatter};
+use icu::decimal::{options::FixedDecimalFormatterOptions, FixedDecimalFormatter};
+use icu::locid::Locale;
+
+const DEFAULT_LOCALE: &str = "en-US";
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatSettings {
+    /// BCP-47 tag such as `de-DE`. Falls back to the system locale.
+    pub locale: Option<String>,
+    /// Fraction digits for non-integer numbers (default 2).
+    pub decimals: Option<u8>,
+    /// "short", "medium" (default), "long" or "full".
+    pub date_style: Option<String>,
+    /// Use 1024-based units (KiB, MiB) for byte sizes.
+    #[serde(default)]
+    pub binary_units: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    Number,
+    Date,
+    Bytes,
+    Percent,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct FormatRequest {
+    pub kind: ValueKind,
+    pub value: serde_json::Value,
+}
+
+/// Formatters are built once per batch and reused for every value.
+pub struct Formatter {
+    decimal: FixedDecimalFormatter,
+    datetime: DateTimeFormatter,
+    decimals: usize,
+    binary_units: bool,
+}
+
+/// The locale from LC_ALL/LANG (`de_DE.UTF-8` -> `de-DE`), if it parses.
+pub(crate) fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_NUMERIC", "LANG"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|v| v.split('.').next().unwrap_or_default().replace('_', "-"))
+        .find(|v| !v.is_empty() && v != "C" && v != "POSIX" && v.parse::<Locale>().is_ok())
+}
+
+impl Formatter {
+    pub fn new(settings: &FormatSettings) -> Result<Self, String> {
+        let tag = settings.locale.clone()
+            .or_else(system_locale)
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        let locale: Locale = tag.parse().map_err(|e| format!("Invalid locale '{}': {:?}", tag, e))?;
+
+        let decimal = FixedDecimalFormatter::try_new(&(&locale).into(), FixedDecimalFormatterOptions::default())
+            .map_err(|e| format!("No number formatting data for '{}': {:?}", tag, e))?;
+
+        let date_style = match settings.date_style.as_deref() {
+            Some("short") => length::Date::Short,
+            Some("long") => length::Date::Long,
+            Some("full") => length::Date::Full,
+            _ => length::Date::Medium,
+        };
+        let bag = length::Bag::from_date_time_style(date_style, length::Time::Short);
+        let datetime = DateTimeFormatter::try_new(&(&locale).into(), bag.into())
+            .map_err(|e| format!("No date formatting data for '{}': {:?}", tag, e))?;
+
+        Ok(Self {
+            decimal,
+            datetime,
+            decimals: settings.decimals.unwrap_or(2) as usize,
+            binary_units: settings.binary_units,
+        })
+    }
+
+    pub fn number(&self, n: f64) -> String {
+        let text = if n.fract() == 0.0 && n.abs() < 1e15 {
+            format!("{}", n as i64)
+        } else {
+            format!("{:.*}", self.decimals, n)
+        };
+        match FixedDecimal::from_str(&text) {
+            Ok(fd) => self.decimal.format_to_string(&fd),
+            Err(_) => text,
+        }
+    }
+
+    pub fn percent(&self, ratio: f64) -> String {
+        format!("{}%", self.number(ratio * 100.0))
+    }
+
+    pub fn bytes(&self, size: f64) -> String {
+        let (base, units): (f64, &[&str]) = if self.binary_units {
+            (1024.0, &["B", "KiB", "MiB", "GiB", "TiB", "PiB"])
+        } else {
+            (1000.0, &["B", "KB", "MB", "GB", "TB", "PB"])
+        };
+        let mut value = size;
+        let mut unit = 0;
+        while value.abs() >= base && unit < units.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        let rounded = if unit == 0 { value.round() } else { (value * 10.0).round() / 10.0 };
+        format!("{} {}", self.number(rounded), units[unit])
+    }
+
+    /// Accepts RFC 3339 strings, `YYYY-MM-DD` dates and Unix timestamps (s or ms).
+    pub fn date(&self, value: &serde_json::Value) -> Option<String> {
+        let parsed = match value {
+            serde_json::Value::String(s) => chrono::DateTime::parse_from_rfc3339(s)
+                .map(|d| d.naive_utc())
+                .ok()
+                .or_else(|| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0))),
+            serde_json::Value::Number(n) => n.as_i64().and_then(|ts| {
+                let secs = if ts.abs() > 100_000_000_000 { ts / 1000 } else { ts };
+                chrono::DateTime::from_timestamp(secs, 0).map(|d| d.naive_utc())
+            }),
+            _ => None,
+        }?;
+
+        use chrono::{Datelike, Timelike};
+        let dt = DateTime::try_new_iso_datetime(
+            parsed.year(),
+            parsed.month() as u8,
+            parsed.day() as u8,
+            parsed.hour() as u8,
+            parsed.minute() as u8,
+            parsed.second() as u8,
+        ).ok()?;
+        self.datetime.format_to_string(&dt.to_any()).ok()
+    }
+
+    /// Values that cannot be interpreted as `kind` are returned as plain text.
+    pub fn format(&self, request: &FormatRequest) -> String {
+        let as_number = request.value.as_f64()
+            .or_else(|| request.value.as_str().and_then(|s| s.trim().parse().ok()));
+        let formatted = match request.kind {
+            ValueKind::Number => as_number.map(|n| self.number(n)),
+            ValueKind::Percent => as_number.map(|n| self.percent(n)),
+            ValueKind::Bytes => as_number.map(|n| self.bytes(n)),
+            ValueKind::Date => self.date(&request.value),
+        };
+        formatted.unwrap_or_else(|| match &request.value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        })
+    }
+}
+
+pub fn format_values(values: &[FormatRequest], settings: &FormatSettings) -> Result<Vec<String>, String> {
+    let formatter = Formatter::new(settings)?;
+    Ok(values.iter().map(|v| formatter.format(v)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter(locale: &str) -> Formatter {
+        Formatter::new(&FormatSettings { locale: Some(locale.to_string()), ..Default::default() }).unwrap()
+    }
+
+    #[test]
+    fn test_localized_numbers_and_bytes() {
+        assert_eq!(formatter("en-US").number(1234567.0), "1,234,567");
+        assert_eq!(formatter("de-DE").number(1234.5), "1.234,50");
+        assert_eq!(formatter("en-US").bytes(1_500_000.0), "1.5 MB");
+        let mut binary = formatter("en-US");
+        binary.binary_units = true;
+        assert_eq!(binary.bytes(2048.0), "2 KiB");
+    }
+}
@@ -0,0 +1,73 @@
+//! How requests to a gateway authenticate. Cloud instances want `x-api-key`; a gateway
+//! behind a proxy may also need headers of its own, set per connection as `headers`
+//! in connections.json.
+
+/// The API key and extra headers sent with every request to one gateway.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GatewayAuth {
+    pub api_key: Option<String>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl GatewayAuth {
+    /// `api_key` when the caller has one, else the connection's stored `apiKey`, plus
+    /// the connection's `headers`. Headers that are not strings are skipped.
+    pub fn of_connection(connection: Option<&serde_json::Value>, api_key: Option<String>) -> Self {
+        let api_key = api_key.filter(|k| !k.is_empty())
+            .or_else(|| connection?.get("apiKey")?.as_str().filter(|k| !k.is_empty()).map(String::from));
+        let headers = connection
+            .and_then(|c| c.get("headers")?.as_object())
+            .map(|headers| headers.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect())
+            .unwrap_or_default();
+        Self { api_key, headers }
+    }
+
+    /// `of_connection` for the connection `url` points at.
+    pub fn for_url(app: &tauri::AppHandle, url: &str, api_key: Option<String>) -> Self {
+        Self::of_connection(crate::policy::find_connection(app, Some(url)).as_ref(), api_key)
+    }
+
+    /// Whether the request already sets `name`, which then wins over the connection's.
+    fn overridden(name: &str, set: &[&str]) -> bool {
+        set.iter().any(|s| s.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds the headers, skipping any named in `set` (those the caller sends itself).
+    pub fn apply_except(&self, mut req: reqwest::RequestBuilder, set: &[&str]) -> reqwest::RequestBuilder {
+        for (name, value) in &self.headers {
+            if !Self::overridden(name, set) {
+                req = req.header(name, value);
+            }
+        }
+        match &self.api_key {
+            Some(key) if !Self::overridden("x-api-key", set) => req.header("x-api-key", key),
+            _ => req,
+        }
+    }
+
+    pub fn apply(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        self.apply_except(req, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_auth() {
+        let connection = serde_json::json!({
+            "apiKey": "stored",
+            "headers": { "Authorization": "Bearer t", "X-Retries": 3 }
+        });
+        let auth = GatewayAuth::of_connection(Some(&connection), None);
+        assert_eq!(auth.api_key.as_deref(), Some("stored"));
+        assert_eq!(auth.headers, vec![("Authorization".to_string(), "Bearer t".to_string())]);
+        assert_eq!(GatewayAuth::of_connection(Some(&connection), Some("given".into())).api_key.as_deref(), Some("given"));
+        assert_eq!(GatewayAuth::of_connection(None, Some(String::new())), GatewayAuth::default());
+
+        let req = auth.apply_except(reqwest::Client::new().get("http://localhost"), &["authorization"]).build().unwrap();
+        assert_eq!(req.headers().get("x-api-key").unwrap(), "stored");
+        assert!(req.headers().get("authorization").is_none());
+    }
+}
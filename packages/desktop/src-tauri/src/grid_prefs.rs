@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+const PREFS_FILE: &str = "grid_prefs.json";
+
+/// How the grid lays out one label's data. Columns not mentioned anywhere (new
+/// properties) are shown after the ordered ones.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GridPrefs {
+    /// Column keys in display order.
+    #[serde(default)]
+    pub order: Vec<String>,
+    #[serde(default)]
+    pub hidden: Vec<String>,
+    /// Widths in CSS pixels.
+    #[serde(default)]
+    pub widths: HashMap<String, u32>,
+    /// Columns kept at the left edge while scrolling, in order.
+    #[serde(default)]
+    pub pinned: Vec<String>,
+}
+
+impl GridPrefs {
+    /// The visible columns among `columns`: pinned first, then the saved order, then
+    /// columns the preferences do not know yet, in their given order.
+    pub fn arrange(&self, columns: &[String]) -> Vec<String> {
+        let mut arranged: Vec<String> = Vec::new();
+        for key in self.pinned.iter().chain(&self.order).chain(columns) {
+            if columns.contains(key) && !self.hidden.contains(key) && !arranged.contains(key) {
+                arranged.push(key.clone());
+            }
+        }
+        arranged
+    }
+}
+
+/// Connection URLs with and without a trailing slash share preferences.
+fn prefs_key(url: &str, label: &str) -> String {
+    format!("{}#{}", url.trim_end_matches('/'), label)
+}
+
+fn prefs_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(PREFS_FILE))
+}
+
+fn load_all(app: &tauri::AppHandle) -> HashMap<String, GridPrefs> {
+    prefs_path(app).ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+pub fn load(app: &tauri::AppHandle, url: &str, label: &str) -> Option<GridPrefs> {
+    load_all(app).remove(&prefs_key(url, label))
+}
+
+/// Saves `prefs` for the label, or forgets them with `None`.
+pub fn save(app: &tauri::AppHandle, url: &str, label: &str, prefs: Option<GridPrefs>) -> Result<(), String> {
+    let mut all = load_all(app);
+    match prefs {
+        Some(prefs) => all.insert(prefs_key(url, label), prefs),
+        None => all.remove(&prefs_key(url, label)),
+    };
+    let content = serde_json::to_string_pretty(&all).map_err(|e| e.to_string())?;
+    fs::write(prefs_path(app)?, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_arrange_columns() {
+        let prefs = GridPrefs {
+            order: keys(&["name", "age", "removed", "email"]),
+            hidden: keys(&["email"]),
+            widths: HashMap::new(),
+            pinned: keys(&["id"]),
+        };
+        let columns = keys(&["email", "created_at", "age", "id", "name"]);
+        assert_eq!(prefs.arrange(&columns), keys(&["id", "name", "age", "created_at"]));
+        assert_eq!(GridPrefs::default().arrange(&columns), columns);
+        assert_eq!(prefs_key("http://localhost:6969/", "User"), prefs_key("http://localhost:6969", "User"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Connection health check behind the live status dots in the connection list: the
+//! gateway root, `mcp/init` (and the schema behind it) and one compiled endpoint are
+//! pinged and timed.
+
+use std::time::{Duration, Instant};
+use crate::gateway_auth::GatewayAuth;
+use crate::network_trace::SendTraced;
+
+/// Each ping gives up after this; a dot should not take a query timeout to turn red.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Probe {
+    pub ok: bool,
+    pub latency_ms: u64,
+    /// The HTTP status, when the server answered.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum HealthStatus {
+    Online,
+    /// The gateway answers but MCP or the compiled endpoint does not.
+    Degraded,
+    /// The gateway refused the connection's credentials (401 or 403).
+    Unauthorized,
+    Offline,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHealth {
+    pub url: String,
+    pub status: HealthStatus,
+    pub reachable: bool,
+    /// Round trip to the gateway root.
+    pub latency_ms: Option<u64>,
+    pub version: Option<String>,
+    pub gateway: Probe,
+    pub mcp: Option<Probe>,
+    pub schema_available: bool,
+    /// The compiled endpoint that was pinged, if the gateway has any.
+    pub compiled_query: Option<String>,
+    pub compiled: Option<Probe>,
+    pub checked_at: String,
+}
+
+fn refused(status: u16) -> bool {
+    matches!(status, 401 | 403)
+}
+
+/// Online when every ping that ran passed, offline when the gateway did not answer,
+/// unauthorized when any ping was refused.
+pub fn status_of(gateway: &Probe, mcp: Option<&Probe>, compiled: Option<&Probe>) -> HealthStatus {
+    let probes = || std::iter::once(gateway).chain(mcp).chain(compiled);
+    if !gateway.ok {
+        HealthStatus::Offline
+    } else if probes().any(|p| p.status.is_some_and(refused)) {
+        HealthStatus::Unauthorized
+    } else if probes().all(|p| p.ok) {
+        HealthStatus::Online
+    } else {
+        HealthStatus::Degraded
+    }
+}
+
+/// Sends `req`, passing when the status satisfies `ok`. The response is kept for reading.
+async fn ping(req: reqwest::RequestBuilder, ok: impl Fn(reqwest::StatusCode) -> bool) -> (Probe, Option<reqwest::Response>) {
+    let started = Instant::now();
+    match req.timeout(PROBE_TIMEOUT).send_traced().await {
+        Ok(resp) => {
+            let status = resp.status();
+            let probe = Probe {
+                ok: ok(status),
+                latency_ms: started.elapsed().as_millis() as u64,
+                status: Some(status.as_u16()),
+                error: (!ok(status)).then(|| format!("Answered {}", status)),
+            };
+            (probe, Some(resp))
+        }
+        Err(e) => {
+            let probe = Probe {
+                ok: false,
+                latency_ms: started.elapsed().as_millis() as u64,
+                status: None,
+                error: Some(crate::commands::network::map_reqwest_error(e, "Ping failed")),
+            };
+            (probe, None)
+        }
+    }
+}
+
+/// Whether the schema can be read on the connection `mcp/init` answered with.
+async fn schema_available(client: &reqwest::Client, url: &str, auth: &GatewayAuth, connection_id: &str) -> bool {
+    let req = client.post(format!("{}/mcp/schema_resource", url))
+        .timeout(PROBE_TIMEOUT)
+        .json(&serde_json::json!({ "connection_id": connection_id }));
+    match auth.apply(req).send_traced().await {
+        Ok(resp) if resp.status().is_success() => resp.json::<serde_json::Value>().await.is_ok(),
+        _ => false,
+    }
+}
+
+/// Pings `url`. `compiled_query` names the endpoint to ping; without one the first
+/// query `/introspect` lists is used. Compiled endpoints only take POST, so a GET is
+/// answered by the router (405) without running the query.
+pub async fn check(client: &reqwest::Client, url: &str, auth: &GatewayAuth, compiled_query: Option<String>) -> ConnectionHealth {
+    let url = url.trim_end_matches('/');
+    let (gateway, _) = ping(auth.apply(client.get(format!("{}/", url))), |_| true).await;
+    let mut health = ConnectionHealth {
+        url: url.to_string(),
+        status: HealthStatus::Offline,
+        reachable: gateway.ok,
+        latency_ms: gateway.ok.then_some(gateway.latency_ms),
+        version: None,
+        gateway,
+        mcp: None,
+        schema_available: false,
+        compiled_query: None,
+        compiled: None,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if !health.reachable {
+        return health;
+    }
+
+    health.version = crate::capabilities::probe_version(client, url, auth).await;
+    let (mcp, init) = ping(auth.apply(client.post(format!("{}/mcp/init", url))), |s| s.is_success()).await;
+    if let Some(init) = init.filter(|_| mcp.ok) {
+        if let Some(connection_id) = init.text().await.ok().and_then(|body| serde_json::from_str::<String>(&body).ok()) {
+            health.schema_available = schema_available(client, url, auth, &connection_id).await;
+            crate::capabilities::close_probe_connection(client, url, auth, &connection_id).await;
+        }
+    }
+    health.mcp = Some(mcp);
+
+    let compiled_query = match compiled_query {
+        Some(name) => Some(name),
+        None => crate::compiled_queries::fetch(client, url, auth).await.ok()
+            .and_then(|queries| queries.into_iter().next())
+            .map(|q| q.name),
+    };
+    if let Some(name) = &compiled_query {
+        let (probe, _) = ping(auth.apply(client.get(format!("{}/{}", url, name))), |s| s != reqwest::StatusCode::NOT_FOUND && !refused(s.as_u16())).await;
+        health.compiled = Some(probe);
+    }
+    health.compiled_query = compiled_query;
+    health.status = status_of(&health.gateway, health.mcp.as_ref(), health.compiled.as_ref());
+    health
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::FakeGateway;
+
+    #[test]
+    fn test_status_of() {
+        let up = Probe { ok: true, latency_ms: 3, status: Some(200), error: None };
+        let down = Probe { ok: false, ..Probe::default() };
+        assert_eq!(status_of(&up, None, None), HealthStatus::Online);
+        assert_eq!(status_of(&up, Some(&up), Some(&up)), HealthStatus::Online);
+        assert_eq!(status_of(&up, Some(&down), Some(&up)), HealthStatus::Degraded);
+        assert_eq!(status_of(&up, Some(&up), Some(&down)), HealthStatus::Degraded);
+        assert_eq!(status_of(&down, Some(&up), None), HealthStatus::Offline);
+        let refused = Probe { ok: false, status: Some(401), ..Probe::default() };
+        assert_eq!(status_of(&up, Some(&refused), Some(&up)), HealthStatus::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_check_closes_probe_connection() {
+        let gateway = FakeGateway::start(|path| match path {
+            "/mcp/init" => (200, "\"probe-1\"".to_string()),
+            "/mcp/schema_resource" => (200, "{}".to_string()),
+            _ => (200, "null".to_string()),
+        }).await;
+        let health = check(&reqwest::Client::new(), &gateway.url, &GatewayAuth::default(), Some("getUser".to_string())).await;
+        assert_eq!(health.status, HealthStatus::Online);
+        assert!(health.schema_available);
+        let requests = gateway.requests();
+        let init = requests.iter().position(|p| p == "/mcp/init").unwrap();
+        assert!(requests[init..].contains(&"/mcp/close".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_refused_credentials() {
+        let gateway = FakeGateway::start(|path| match path {
+            "/" => (200, String::new()),
+            _ => (401, "Unauthorized".to_string()),
+        }).await;
+        let health = check(&reqwest::Client::new(), &gateway.url, &GatewayAuth::default(), Some("getUser".to_string())).await;
+        assert_eq!(health.status, HealthStatus::Unauthorized);
+        assert_eq!(health.mcp.unwrap().status, Some(401));
+        assert!(!gateway.requests().contains(&"/mcp/close".to_string()));
+    }
+}
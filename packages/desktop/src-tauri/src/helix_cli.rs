@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_LOCAL_PORT: &str = "6969";
+
+/// A connection proposal built from the helix CLI's own configuration.
+/// Field names match the frontend `ConnectionInfo` shape so it can be saved as-is.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedConnection {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub conn_type: String,
+    pub host: String,
+    pub port: String,
+    pub cloud_host: Option<String>,
+    pub api_key: String,
+    pub local_path: Option<String>,
+    pub already_configured: bool,
+}
+
+type TomlSections = BTreeMap<String, BTreeMap<String, String>>;
+
+/// `line` without its `#` comment; a `#` inside a quoted value is kept.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Minimal reader for the flat `[section]` / `key = value` layout used by helix.toml
+/// and the CLI credentials file. Nested tables and arrays are not needed here.
+pub fn parse_sections(content: &str) -> TomlSections {
+    let mut sections = TomlSections::new();
+    let mut current = String::new();
+
+    for line in content.lines() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            current = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            sections.entry(current.clone()).or_default();
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"').trim_matches('\'').to_string();
+            sections.entry(current.clone()).or_default().insert(key.trim().to_string(), value);
+        }
+    }
+    sections
+}
+
+fn read_cli_api_key() -> Option<String> {
+    let path = dirs::home_dir()?.join(".helix").join("credentials");
+    let content = fs::read_to_string(path).ok()?;
+    let sections = parse_sections(&content);
+    sections.values()
+        .find_map(|kv| kv.get("helix_user_key").or_else(|| kv.get("api_key")).cloned())
+        .filter(|k| !k.is_empty())
+}
+
+fn connections_from_workspace(workspace: &Path, api_key: &Option<String>) -> Vec<ImportedConnection> {
+    let content = match fs::read_to_string(workspace.join("helix.toml")) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+    let sections = parse_sections(&content);
+    let project = sections.get("project")
+        .and_then(|p| p.get("name").cloned())
+        .or_else(|| workspace.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "helix".to_string());
+    let local_path = workspace.to_string_lossy().to_string();
+
+    let mut out = Vec::new();
+    for (section, values) in &sections {
+        let mut parts = section.splitn(3, '.');
+        let kind = parts.next().unwrap_or("");
+        let instance = match parts.next() {
+            Some(i) if !i.is_empty() => i,
+            _ => continue,
+        };
+
+        match kind {
+            "local" => {
+                out.push(ImportedConnection {
+                    id: format!("cli-{}-{}", project, instance),
+                    name: format!("{} ({})", project, instance),
+                    conn_type: "local".to_string(),
+                    host: "127.0.0.1".to_string(),
+                    port: values.get("port").cloned().unwrap_or_else(|| DEFAULT_LOCAL_PORT.to_string()),
+                    cloud_host: None,
+                    api_key: String::new(),
+                    local_path: Some(local_path.clone()),
+                    already_configured: false,
+                });
+            }
+            "cloud" => {
+                // Cloud entries are only importable when the CLI recorded a reachable URL.
+                let url = match values.get("url").or_else(|| values.get("host")) {
+                    Some(u) if !u.is_empty() => u.clone(),
+                    _ => continue,
+                };
+                out.push(ImportedConnection {
+                    id: format!("cli-{}-{}", project, instance),
+                    name: format!("{} ({})", project, instance),
+                    conn_type: "cloud".to_string(),
+                    host: String::new(),
+                    port: String::new(),
+                    cloud_host: Some(url),
+                    api_key: api_key.clone().unwrap_or_default(),
+                    local_path: Some(local_path.clone()),
+                    already_configured: false,
+                });
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Builds connection proposals from helix.toml files in `workspaces` (plus every
+/// `localPath` already in connections.json) and the CLI credentials, flagging those
+/// that already exist among the `existing` connections.
+pub fn import_from_helix_cli(mut workspaces: Vec<PathBuf>, existing: &[serde_json::Value]) -> Vec<ImportedConnection> {
+    let api_key = read_cli_api_key();
+
+    let mut known = HashSet::new();
+    for conn in existing {
+        if let Some(path) = conn.get("localPath").and_then(|v| v.as_str()).filter(|p| !p.is_empty()) {
+            workspaces.push(PathBuf::from(path));
+        }
+        let host = conn.get("host").and_then(|v| v.as_str()).unwrap_or("");
+        let port = conn.get("port").and_then(|v| v.as_str()).unwrap_or("");
+        let cloud = conn.get("cloudHost").and_then(|v| v.as_str()).unwrap_or("");
+        known.insert(format!("{}:{}|{}", host, port, cloud));
+    }
+
+    let mut seen_workspaces = HashSet::new();
+    let mut seen_ids = HashSet::new();
+    let mut out = Vec::new();
+    for ws in workspaces {
+        let ws = ws.canonicalize().unwrap_or(ws);
+        if !seen_workspaces.insert(ws.clone()) {
+            continue;
+        }
+        for mut conn in connections_from_workspace(&ws, &api_key) {
+            if !seen_ids.insert(conn.id.clone()) {
+                continue;
+            }
+            let key = format!("{}:{}|{}", conn.host, conn.port, conn.cloud_host.clone().unwrap_or_default());
+            conn.already_configured = known.contains(&key)
+                || (conn.host == "127.0.0.1" && known.contains(&format!("localhost:{}|", conn.port)));
+            out.push(conn);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_helix_toml_sections() {
+        let content = r#"
+            [project]
+            name = "social"   # comment
+            queries = "./db/"
+
+            [local.dev]
+            port = 6970
+
+            [cloud.prod]
+            url = "https://prod.example.com"
+        "#;
+        let sections = parse_sections(content);
+        assert_eq!(sections["project"]["name"], "social");
+        assert_eq!(sections["local.dev"]["port"], "6970");
+        assert_eq!(sections["cloud.prod"]["url"], "https://prod.example.com");
+
+        let sections = parse_sections(r#"
+            [cloud.prod]
+            api_key = "ab#cd"  # the key
+            url = 'https://prod.example.com/#/x' # single quotes
+            note = "say \"hi\" # not a comment" # a comment
+        "#);
+        assert_eq!(sections["cloud.prod"]["api_key"], "ab#cd");
+        assert_eq!(sections["cloud.prod"]["url"], "https://prod.example.com/#/x");
+        assert_eq!(sections["cloud.prod"]["note"], r#"say \"hi\" # not a comment"#);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::Manager;
+
+const HISTORY_FILE: &str = "execution-history.jsonl";
+/// Once the log grows past this, the older half is dropped.
+const MAX_HISTORY_BYTES: u64 = 8 * 1024 * 1024;
+/// Executions replayed per run at most.
+pub const MAX_REPLAY: usize = 1000;
+/// Entity IDs kept per execution, so large results do not bloat the log.
+const MAX_RECORDED_IDS: usize = 1000;
+
+/// One worksheet execution, appended to the history log as a JSON line.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionRecord {
+    pub started_at: String,
+    pub url: String,
+    pub code: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// IDs of the nodes, edges and vectors in the result, for `find_entity_occurrences`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entity_ids: Vec<String>,
+}
+
+impl ExecutionRecord {
+    fn started_ms(&self) -> Option<i64> {
+        chrono::DateTime::parse_from_rfc3339(&self.started_at).ok().map(|d| d.timestamp_millis())
+    }
+}
+
+fn history_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+pub fn record(app: &tauri::AppHandle, entry: &ExecutionRecord) -> Result<(), String> {
+    let path = history_path(app)?;
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+
+    if file.metadata().map(|m| m.len() > MAX_HISTORY_BYTES).unwrap_or(false) {
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let lines: Vec<&str> = content.lines().collect();
+        let kept = lines[lines.len() / 2..].join("\n");
+        fs::write(&path, format!("{}\n", kept)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Recorded executions started within `[from, to]` (RFC 3339, either end open), oldest
+/// first, optionally only those against `url`. Unreadable lines are skipped.
+pub fn load(app: &tauri::AppHandle, from: Option<&str>, to: Option<&str>, url: Option<&str>) -> Result<Vec<ExecutionRecord>, String> {
+    let path = history_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let bound = |s: Option<&str>| -> Result<Option<i64>, String> {
+        s.map(|s| chrono::DateTime::parse_from_rfc3339(s)
+            .map(|d| d.timestamp_millis())
+            .map_err(|e| format!("Invalid timestamp '{}': {}", s, e)))
+            .transpose()
+    };
+    let (from, to) = (bound(from)?, bound(to)?);
+    let url = url.map(|u| u.trim_end_matches('/'));
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str::<ExecutionRecord>(line).ok())
+        .filter(|r| url.is_none_or(|u| r.url.trim_end_matches('/') == u))
+        .filter(|r| match r.started_ms() {
+            Some(ms) => from.is_none_or(|f| ms >= f) && to.is_none_or(|t| ms <= t),
+            None => false,
+        })
+        .collect())
+}
+
+/// Milliseconds after the first record each record started, divided by `speed`.
+/// A speed of 0 replays everything at once.
+pub fn schedule(records: &[ExecutionRecord], speed: f64) -> Vec<u64> {
+    let first = records.iter().filter_map(|r| r.started_ms()).min().unwrap_or(0);
+    records.iter()
+        .map(|r| {
+            let offset = (r.started_ms().unwrap_or(first) - first).max(0) as f64;
+            if speed > 0.0 { (offset / speed) as u64 } else { 0 }
+        })
+        .collect()
+}
+
+/// The `id` of every object in a result, in order of appearance, without repeats.
+pub fn entity_ids(value: &serde_json::Value) -> Vec<String> {
+    fn walk(value: &serde_json::Value, ids: &mut Vec<String>) {
+        if ids.len() >= MAX_RECORDED_IDS {
+            return;
+        }
+        match value {
+            serde_json::Value::Object(map) => {
+                if let Some(id) = map.get("id").and_then(|v| v.as_str()) {
+                    if !ids.iter().any(|known| known == id) {
+                        ids.push(id.to_string());
+                    }
+                }
+                map.values().for_each(|v| walk(v, ids));
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, ids)),
+            _ => {}
+        }
+    }
+    let mut ids = Vec::new();
+    walk(value, &mut ids);
+    ids.truncate(MAX_RECORDED_IDS);
+    ids
+}
+
+/// A past execution whose result contained the entity.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityOccurrence {
+    pub started_at: String,
+    pub url: String,
+    pub code: String,
+    pub params: Option<serde_json::Value>,
+}
+
+/// Executions whose result contained `id`, newest first.
+pub fn occurrences(records: &[ExecutionRecord], id: &str) -> Vec<EntityOccurrence> {
+    records.iter().rev()
+        .filter(|r| r.entity_ids.iter().any(|known| known == id))
+        .map(|r| EntityOccurrence { started_at: r.started_at.clone(), url: r.url.clone(), code: r.code.clone(), params: r.params.clone() })
+        .collect()
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub count: usize,
+    pub errors: usize,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyStats {
+    /// Latencies of successful runs only; failures are counted separately.
+    pub fn from_runs<'a>(runs: impl IntoIterator<Item = (u64, Option<&'a str>)>) -> Self {
+        let mut ok = Vec::new();
+        let mut errors = 0;
+        for (ms, error) in runs {
+            if error.is_some() {
+                errors += 1;
+            } else {
+                ok.push(ms);
+            }
+        }
+        ok.sort_unstable();
+        let percentile = |p: usize| ok.get((ok.len() * p / 100).min(ok.len().saturating_sub(1))).copied().unwrap_or(0);
+        Self {
+            count: ok.len() + errors,
+            errors,
+            mean_ms: if ok.is_empty() { 0 } else { ok.iter().sum::<u64>() / ok.len() as u64 },
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: ok.last().copied().unwrap_or(0),
+        }
+    }
+}
+
+/// One recorded execution next to its replay.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayEntry {
+    pub started_at: String,
+    pub code: String,
+    pub original_ms: u64,
+    pub replay_ms: u64,
+    pub delta_ms: i64,
+    pub original_error: Option<String>,
+    pub replay_error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub original: LatencyStats,
+    pub replay: LatencyStats,
+    /// Executions that succeeded originally and failed on replay.
+    pub new_errors: usize,
+    /// Executions that failed originally and succeeded on replay.
+    pub resolved_errors: usize,
+    pub entries: Vec<ReplayEntry>,
+}
+
+/// Pairs each record with its replay outcome (`(duration_ms, error)`, in the same order).
+pub fn compare(records: &[ExecutionRecord], outcomes: Vec<(u64, Option<String>)>) -> ReplayReport {
+    let entries: Vec<ReplayEntry> = records.iter().zip(outcomes)
+        .map(|(r, (replay_ms, replay_error))| ReplayEntry {
+            started_at: r.started_at.clone(),
+            code: r.code.clone(),
+            original_ms: r.duration_ms,
+            replay_ms,
+            delta_ms: replay_ms as i64 - r.duration_ms as i64,
+            original_error: r.error.clone(),
+            replay_error,
+        })
+        .collect();
+    ReplayReport {
+        original: LatencyStats::from_runs(entries.iter().map(|e| (e.original_ms, e.original_error.as_deref()))),
+        replay: LatencyStats::from_runs(entries.iter().map(|e| (e.replay_ms, e.replay_error.as_deref()))),
+        new_errors: entries.iter().filter(|e| e.original_error.is_none() && e.replay_error.is_some()).count(),
+        resolved_errors: entries.iter().filter(|e| e.original_error.is_some() && e.replay_error.is_none()).count(),
+        entries,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(started_at: &str, duration_ms: u64, error: Option<&str>) -> ExecutionRecord {
+        ExecutionRecord {
+            started_at: started_at.to_string(),
+            url: "http://localhost:6969".to_string(),
+            code: "N<User>".to_string(),
+            params: None,
+            duration_ms,
+            error: error.map(String::from),
+            entity_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_schedule_and_stats() {
+        let records = vec![
+            run("2024-05-01T10:00:00Z", 10, None),
+            run("2024-05-01T10:00:02Z", 30, None),
+            run("2024-05-01T10:00:10Z", 5, Some("timed out")),
+        ];
+        assert_eq!(schedule(&records, 1.0), vec![0, 2000, 10000]);
+        assert_eq!(schedule(&records, 4.0), vec![0, 500, 2500]);
+        assert_eq!(schedule(&records, 0.0), vec![0, 0, 0]);
+
+        let stats = LatencyStats::from_runs(records.iter().map(|r| (r.duration_ms, r.error.as_deref())));
+        assert_eq!((stats.count, stats.errors, stats.mean_ms, stats.max_ms), (3, 1, 20, 30));
+
+        let report = compare(&records, vec![(15, None), (30, Some("boom".to_string())), (7, None)]);
+        assert_eq!((report.new_errors, report.resolved_errors), (1, 1));
+        assert_eq!(report.entries[0].delta_ms, 5);
+    }
+
+    #[test]
+    fn test_entity_occurrences() {
+        let result = serde_json::json!({ "user": [{ "id": "u1", "name": "Ada" }, { "id": "u2" }], "edges": [{ "id": "e1", "from_node": "u1" }], "again": { "id": "u1" } });
+        assert_eq!(entity_ids(&result), vec!["u1", "u2", "e1"]);
+
+        let mut first = run("2024-05-01T10:00:00Z", 10, None);
+        first.entity_ids = entity_ids(&result);
+        let mut second = run("2024-05-01T11:00:00Z", 10, None);
+        second.entity_ids = vec!["u1".to_string()];
+        let found = occurrences(&[first, second, run("2024-05-01T12:00:00Z", 10, None)], "u1");
+        assert_eq!(found.iter().map(|o| o.started_at.as_str()).collect::<Vec<_>>(), vec!["2024-05-01T11:00:00Z", "2024-05-01T10:00:00Z"]);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::hql::executor::compare_json;
+use crate::hql::tool_args::Operator;
+
+const MARKER: &str = "@expect";
+
+/// What an `// @expect` comment checks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpectTarget {
+    /// `rows`, or `rows.<var>` when the result has several lists.
+    Rows(Option<String>),
+    /// `field user.name`: dotted path into the result; lists step into their first row
+    /// unless the segment is an index.
+    Field(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expectation {
+    /// 1-based line of the comment.
+    pub line: usize,
+    pub source: String,
+    pub target: ExpectTarget,
+    pub operator: Operator,
+    pub expected: serde_json::Value,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpectationResult {
+    pub line: usize,
+    pub source: String,
+    pub passed: bool,
+    pub actual: Option<serde_json::Value>,
+    pub message: Option<String>,
+}
+
+fn parse_operator(s: &str) -> Option<Operator> {
+    Some(match s {
+        "==" => Operator::Eq,
+        "!=" => Operator::Neq,
+        ">" => Operator::Gt,
+        ">=" => Operator::Gte,
+        "<" => Operator::Lt,
+        "<=" => Operator::Lte,
+        _ => return None,
+    })
+}
+
+fn parse_expectation(line: usize, text: &str) -> Result<Expectation, String> {
+    let mut parts = text.split_whitespace();
+    let target = match parts.next() {
+        Some("rows") => ExpectTarget::Rows(None),
+        Some(t) if t.starts_with("rows.") => ExpectTarget::Rows(Some(t["rows.".len()..].to_string())),
+        Some("field") => {
+            let path = parts.next().ok_or("Missing field path after 'field'")?;
+            ExpectTarget::Field(path.split('.').map(String::from).collect())
+        }
+        Some(other) => return Err(format!("Unknown expectation '{}'; use 'rows' or 'field <path>'", other)),
+        None => return Err("Empty expectation".to_string()),
+    };
+    let op = parts.next().ok_or("Missing comparison operator")?;
+    let operator = parse_operator(op).ok_or_else(|| format!("Unknown operator '{}'", op))?;
+    let rest = parts.collect::<Vec<_>>().join(" ");
+    if rest.is_empty() {
+        return Err("Missing expected value".to_string());
+    }
+    // Bare words are taken as strings, so `== alice` works as well as `== "alice"`
+    let expected = serde_json::from_str(&rest).unwrap_or(serde_json::Value::String(rest));
+    Ok(Expectation { line, source: text.to_string(), target, operator, expected })
+}
+
+/// Finds `// @expect ...` comments. Malformed ones are returned as errors with their
+/// line so they show up as failures instead of being ignored.
+pub fn parse_expectations(code: &str) -> Vec<Result<Expectation, (usize, String, String)>> {
+    code.lines().enumerate()
+        .filter_map(|(i, line)| {
+            let comment = &line[line.find("//")? + 2..];
+            let text = comment.trim_start().strip_prefix(MARKER)?.trim();
+            Some(parse_expectation(i + 1, text).map_err(|e| (i + 1, text.to_string(), e)))
+        })
+        .collect()
+}
+
+fn count_rows(result: &serde_json::Value, var: Option<&str>) -> Result<usize, String> {
+    match (result, var) {
+        (serde_json::Value::Array(rows), None) => Ok(rows.len()),
+        (serde_json::Value::Object(map), Some(var)) => match map.get(var) {
+            Some(serde_json::Value::Array(rows)) => Ok(rows.len()),
+            Some(serde_json::Value::Null) | None => Err(format!("'{}' is not in the result", var)),
+            Some(_) => Ok(1),
+        },
+        (serde_json::Value::Object(map), None) => {
+            let mut lists = map.values().filter_map(|v| v.as_array());
+            match (lists.next(), lists.next()) {
+                (Some(rows), None) => Ok(rows.len()),
+                (None, _) => Ok(if map.is_empty() { 0 } else { 1 }),
+                _ => Err("The result has several lists; use rows.<var>".to_string()),
+            }
+        }
+        (serde_json::Value::Null, _) => Ok(0),
+        (_, Some(var)) => Err(format!("'{}' is not in the result", var)),
+        (_, None) => Ok(1),
+    }
+}
+
+fn lookup<'a>(result: &'a serde_json::Value, path: &[String]) -> Option<&'a serde_json::Value> {
+    path.iter().try_fold(result, |current, segment| match current {
+        serde_json::Value::Array(rows) => match segment.parse::<usize>() {
+            Ok(i) => rows.get(i),
+            Err(_) => rows.first()?.get(segment),
+        },
+        other => other.get(segment),
+    })
+}
+
+fn evaluate(expectation: &Expectation, result: &serde_json::Value) -> ExpectationResult {
+    let actual = match &expectation.target {
+        ExpectTarget::Rows(var) => count_rows(result, var.as_deref()).map(|n| serde_json::Value::from(n as u64)),
+        ExpectTarget::Field(path) => lookup(result, path).cloned().ok_or_else(|| format!("'{}' is not in the result", path.join("."))),
+    };
+    match actual {
+        Ok(actual) => {
+            let passed = compare_json(expectation.operator, &actual, &expectation.expected);
+            ExpectationResult {
+                line: expectation.line,
+                source: expectation.source.clone(),
+                passed,
+                message: (!passed).then(|| format!("Expected {}, got {}", expectation.source, actual)),
+                actual: Some(actual),
+            }
+        }
+        Err(message) => ExpectationResult { line: expectation.line, source: expectation.source.clone(), passed: false, actual: None, message: Some(message) },
+    }
+}
+
+/// Checks every `// @expect` comment in `code` against an executed result.
+pub fn check_expectations(code: &str, result: &serde_json::Value) -> Vec<ExpectationResult> {
+    parse_expectations(code).into_iter()
+        .map(|parsed| match parsed {
+            Ok(expectation) => evaluate(&expectation, result),
+            Err((line, source, message)) => ExpectationResult { line, source, passed: false, actual: None, message: Some(message) },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_expectations() {
+        let code = "// @expect rows.users > 0\n// @expect field users.name == \"alice\"\nusers <- N<User>\n// @expect field users.age >= 40\n// @expect rows ~ 1\nRETURN users, posts";
+        let result = serde_json::json!({ "users": [{ "name": "alice", "age": 30 }], "posts": [] });
+        let checks = check_expectations(code, &result);
+        let outcome: Vec<_> = checks.iter().map(|c| (c.line, c.passed)).collect();
+        assert_eq!(outcome, vec![(1, true), (2, true), (4, false), (5, false)]);
+        assert_eq!(checks[2].actual, Some(serde_json::json!(30)));
+        assert_eq!(checks[3].message.as_deref(), Some("Unknown operator '~'"));
+    }
+}
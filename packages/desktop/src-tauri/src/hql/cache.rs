@@ -0,0 +1,225 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Entries kept in memory; the oldest is evicted first.
+const MAX_ENTRIES: usize = 100;
+
+/// Identifies a result: the same connection, caller identity, query text (ignoring
+/// layout) and params.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct CacheKey {
+    pub url: String,
+    /// Digest from `identity`, so callers with other credentials never share results.
+    pub identity: String,
+    pub code: String,
+    pub params: String,
+}
+
+/// A digest of what decides which results a caller sees: the API key and headers sent,
+/// the translation profile and the redaction rules the cached value was redacted with.
+/// Only the digest is kept, as keys are written to disk.
+pub fn identity(auth: &crate::gateway_auth::GatewayAuth, profile: Option<&str>, rules: &[crate::redaction::RedactionRule]) -> String {
+    let mut hasher = DefaultHasher::new();
+    auth.api_key.hash(&mut hasher);
+    auth.headers.hash(&mut hasher);
+    profile.hash(&mut hasher);
+    serde_json::to_string(rules).unwrap_or_default().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Runs of whitespace outside string literals become one space.
+fn collapse_whitespace(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut in_string = false;
+    let mut pending_space = false;
+    for c in code.trim().chars() {
+        if !in_string && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            out.push(' ');
+            pending_space = false;
+        }
+        if c == '"' {
+            in_string = !in_string;
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl CacheKey {
+    pub fn new(url: &str, identity: &str, code: &str, params: &serde_json::Value) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            identity: identity.to_string(),
+            code: collapse_whitespace(code),
+            params: params.to_string(),
+        }
+    }
+
+    fn file_name(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        format!("{:016x}.json", hasher.finish())
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    key: CacheKey,
+    /// Milliseconds since the epoch, so disk entries survive restarts.
+    stored_at: u64,
+    value: serde_json::Value,
+}
+
+/// How results for one connection are cached.
+pub struct CacheSettings {
+    pub ttl: Duration,
+    pub disk: Option<std::path::PathBuf>,
+}
+
+pub fn cache_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    use tauri::Manager;
+    Ok(app.path().app_cache_dir()
+        .map_err(|e| format!("Could not find cache directory: {}", e))?
+        .join("query-results"))
+}
+
+/// Caching for `url`: `ttl_ms` if given, else the connection's `resultCacheTtlMs`;
+/// `None` (or 0) means results are not cached. `resultCacheOnDisk` keeps them
+/// across restarts.
+pub fn settings(app: &tauri::AppHandle, url: &str, ttl_ms: Option<u64>) -> Option<CacheSettings> {
+    let connection = crate::policy::find_connection(app, Some(url));
+    let field = |k: &str| connection.as_ref().and_then(|c| c.get(k).cloned());
+    let ttl = ttl_ms.or_else(|| field("resultCacheTtlMs")?.as_u64()).filter(|ms| *ms > 0)?;
+    let disk = match field("resultCacheOnDisk").and_then(|v| v.as_bool()) {
+        Some(true) => cache_dir(app).ok(),
+        _ => None,
+    };
+    Some(CacheSettings { ttl: Duration::from_millis(ttl), disk })
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Query results as normalized and redacted, so redacted fields never reach the disk;
+/// row limits are applied on the way out, so changes to them apply to cached results
+/// too. Disk entries are written only when a directory is given.
+#[derive(Default)]
+pub struct QueryCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl QueryCache {
+    pub fn get(&self, key: &CacheKey, ttl: Duration, disk: Option<&Path>) -> Option<serde_json::Value> {
+        let fresh = |entry: &CacheEntry| now_ms().saturating_sub(entry.stored_at) < ttl.as_millis() as u64;
+        if let Some(entry) = self.entries.lock().unwrap().get(key).filter(|e| fresh(e)) {
+            return Some(entry.value.clone());
+        }
+        let content = fs::read_to_string(disk?.join(key.file_name())).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        if entry.key != *key || !fresh(&entry) {
+            return None;
+        }
+        let value = entry.value.clone();
+        self.insert(entry);
+        Some(value)
+    }
+
+    pub fn put(&self, key: CacheKey, value: serde_json::Value, disk: Option<&Path>) {
+        let entry = CacheEntry { key, stored_at: now_ms(), value };
+        if let Some(dir) = disk {
+            let written = fs::create_dir_all(dir)
+                .and_then(|_| fs::write(dir.join(entry.key.file_name()), serde_json::to_string(&entry).unwrap_or_default()));
+            if let Err(e) = written {
+                println!(">>> [QueryCache] Failed to write cache entry: {}", e);
+            }
+        }
+        self.insert(entry);
+    }
+
+    fn insert(&self, entry: CacheEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&entry.key) {
+            let oldest = entries.values().min_by_key(|e| e.stored_at).map(|e| e.key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(entry.key.clone(), entry);
+    }
+
+    /// Drops cached results for `url`, or all of them, from memory and disk. Returns
+    /// how many results were removed.
+    pub fn invalidate(&self, url: Option<&str>, disk: Option<&Path>) -> usize {
+        let url = url.map(|u| u.trim_end_matches('/'));
+        let wanted = |key: &CacheKey| match url {
+            Some(u) => key.url == u,
+            None => true,
+        };
+        let mut removed: HashSet<CacheKey> = HashSet::new();
+        self.entries.lock().unwrap().retain(|key, _| {
+            let drop = wanted(key);
+            if drop {
+                removed.insert(key.clone());
+            }
+            !drop
+        });
+
+        let files = disk.and_then(|d| fs::read_dir(d).ok()).into_iter().flatten().flatten();
+        for file in files {
+            let Some(entry) = fs::read_to_string(file.path()).ok().and_then(|c| serde_json::from_str::<CacheEntry>(&c).ok()) else {
+                continue;
+            };
+            if wanted(&entry.key) && fs::remove_file(file.path()).is_ok() {
+                removed.insert(entry.key);
+            }
+        }
+        removed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_ttl_and_invalidate() {
+        let cache = QueryCache::default();
+        let key = CacheKey::new("http://localhost:6969/", "id", "N<User>\n   ::RANGE(0, 10)", &serde_json::json!({ "a": 1 }));
+        assert_eq!(key, CacheKey::new("http://localhost:6969", "id", "N<User> ::RANGE(0, 10)", &serde_json::json!({ "a": 1 })));
+        assert_ne!(CacheKey::new("u", "id", "WHERE(_::{name}::EQ(\"a  b\"))", &serde_json::Value::Null).code,
+            CacheKey::new("u", "id", "WHERE(_::{name}::EQ(\"a b\"))", &serde_json::Value::Null).code);
+        cache.put(key.clone(), serde_json::json!([1, 2]), None);
+        assert_eq!(cache.get(&key, Duration::from_secs(60), None), Some(serde_json::json!([1, 2])));
+        assert_eq!(cache.get(&key, Duration::ZERO, None), None);
+        assert_eq!(cache.invalidate(Some("http://other:1"), None), 0);
+        assert_eq!(cache.invalidate(Some("http://localhost:6969"), None), 1);
+        assert_eq!(cache.get(&key, Duration::from_secs(60), None), None);
+    }
+
+    #[test]
+    fn test_identity_separates_callers() {
+        use crate::gateway_auth::GatewayAuth;
+        let auth = |key: &str| GatewayAuth { api_key: Some(key.to_string()), headers: Vec::new() };
+        let a = identity(&auth("key-a"), None, &[]);
+        assert_eq!(a, identity(&auth("key-a"), None, &[]));
+        assert!(!a.contains("key-a"));
+        assert_ne!(a, identity(&auth("key-b"), None, &[]));
+        assert_ne!(a, identity(&auth("key-a"), Some("v1"), &[]));
+
+        let cache = QueryCache::default();
+        let code = "N<User>";
+        cache.put(CacheKey::new("u", &a, code, &serde_json::Value::Null), serde_json::json!([1]), None);
+        let other = CacheKey::new("u", &identity(&auth("key-b"), None, &[]), code, &serde_json::Value::Null);
+        assert_eq!(cache.get(&other, Duration::from_secs(60), None), None);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::hql::executor::{acquire_connection, search_request, McpTarget};
+use crate::network_trace::SendTraced;
+use crate::hql::tool_args::{EdgeType, FilterProperties, FilterTraversal, Operator, Order, ToolArgs};
+use helix_db::protocol::value::Value;
+
+/// Label that should never exist in a real schema, so probes traverse nothing.
+const PROBE_LABEL: &str = "__explorer_compat_probe__";
+
+/// Server error fragments that indicate the request body itself was rejected,
+/// as opposed to the (harmless) failure of running a probe against an empty label.
+const SHAPE_ERRORS: &[&str] = &[
+    "unknown variant",
+    "unknown field",
+    "missing field",
+    "invalid type",
+    "deserializ",
+    "failed to parse",
+];
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CompatStatus {
+    Ok,
+    Mismatch,
+    Unsupported,
+    Error,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CompatCheck {
+    pub tool_name: String,
+    pub local_round_trip: bool,
+    pub server: CompatStatus,
+    pub detail: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct CompatReport {
+    pub server_version: Option<String>,
+    pub profile: String,
+    pub in_sync: bool,
+    pub checks: Vec<CompatCheck>,
+}
+
+/// The canonical `tool_name` of a variant. The match is exhaustive on purpose:
+/// adding a variant to `ToolArgs` without a probe here fails to compile.
+fn tool_name(tool: &ToolArgs) -> &'static str {
+    match tool {
+        ToolArgs::OutStep { .. } => "out_step",
+        ToolArgs::OutEStep { .. } => "out_e_step",
+        ToolArgs::InStep { .. } => "in_step",
+        ToolArgs::InEStep { .. } => "in_e_step",
+        ToolArgs::NFromType { .. } => "n_from_type",
+        ToolArgs::VFromType { .. } => "v_from_type",
+        ToolArgs::EFromType { .. } => "e_from_type",
+        ToolArgs::FilterItems { .. } => "filter_items",
+        ToolArgs::OrderBy { .. } => "order_by",
+        ToolArgs::SearchKeyword { .. } => "search_keyword",
+        ToolArgs::SearchVecText { .. } => "search_vec_text",
+        ToolArgs::SearchVec { .. } => "search_vec",
+    }
+}
+
+/// One synthetic instance of every `ToolArgs` variant, with every optional field set
+/// so the full shape is exercised.
+pub fn sample_tools() -> Vec<ToolArgs> {
+    let filter = FilterTraversal {
+        properties: Some(vec![vec![FilterProperties {
+            key: "name".to_string(),
+            value: Value::String("probe".to_string()),
+            operator: Some(Operator::Eq),
+        }]]),
+        filter_traversals: Some(vec![ToolArgs::OutEStep { edge_label: PROBE_LABEL.to_string(), filter: None }]),
+    };
+    let label = || PROBE_LABEL.to_string();
+
+    vec![
+        ToolArgs::NFromType { node_type: label() },
+        ToolArgs::EFromType { edge_type: label() },
+        ToolArgs::VFromType { vector_type: label(), filter: Some(filter.clone()) },
+        ToolArgs::OutStep { edge_label: label(), edge_type: EdgeType::Node, filter: Some(filter.clone()) },
+        ToolArgs::OutEStep { edge_label: label(), filter: Some(filter.clone()) },
+        ToolArgs::InStep { edge_label: label(), edge_type: EdgeType::Vec, filter: Some(filter.clone()) },
+        ToolArgs::InEStep { edge_label: label(), filter: Some(filter.clone()) },
+        ToolArgs::FilterItems { filter },
+        ToolArgs::OrderBy { properties: "name".to_string(), order: Order::Desc },
+        ToolArgs::SearchKeyword { query: "probe".to_string(), limit: 1, label: label() },
+        ToolArgs::SearchVecText { query: "probe".to_string(), label: label(), k: 1 },
+        ToolArgs::SearchVec { vector: vec![0.0; 4], k: 1, min_score: Some(0.0), cutoff: Some(1), label: label() },
+    ]
+}
+
+/// Serializes and deserializes a tool, catching serde attribute mistakes in the mirror.
+fn local_round_trip(tool: &ToolArgs) -> Result<(), String> {
+    let json = serde_json::to_value(tool).map_err(|e| e.to_string())?;
+    let back: ToolArgs = serde_json::from_value(json.clone()).map_err(|e| e.to_string())?;
+    if &back != tool {
+        return Err(format!("round trip changed the value: {}", json));
+    }
+    Ok(())
+}
+
+fn classify_response(status: reqwest::StatusCode, body: &str) -> (CompatStatus, Option<String>) {
+    let lower = body.to_lowercase();
+    if status.is_success() {
+        return (CompatStatus::Ok, None);
+    }
+    if SHAPE_ERRORS.iter().any(|p| lower.contains(p)) {
+        return (CompatStatus::Mismatch, Some(body.to_string()));
+    }
+    if status == reqwest::StatusCode::NOT_FOUND && !lower.contains("connection") {
+        return (CompatStatus::Unsupported, Some(format!("Endpoint not served ({})", status)));
+    }
+    // Anything else means the body was accepted and the probe itself failed,
+    // which is expected for a label that does not exist.
+    (CompatStatus::Ok, Some(format!("Accepted, probe returned {}", status)))
+}
+
+async fn probe_tool(target: &McpTarget, tool: &ToolArgs) -> Result<(CompatStatus, Option<String>), String> {
+    // Each probe gets its own connection so a rejected step cannot poison the next one.
+    // Never released: a probed connection is closed rather than reused.
+    let conn = acquire_connection(target).await?;
+    let conn: &str = &conn;
+    let (endpoint, body) = match search_request(tool, conn) {
+        Some(request) => request,
+        None => ("tool_call", serde_json::json!({ "connection_id": conn, "tool": target.profile.encode_tool(tool)? })),
+    };
+    let resp = target.post(endpoint).json(&body).send_traced().await
+        .map_err(|e| crate::commands::network::map_reqwest_error(e, "Compat probe failed"))?;
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    Ok(classify_response(status, &text))
+}
+
+/// Checks every mirrored tool locally and against the server and reports drift.
+pub async fn check_protocol_compat(target: &McpTarget, server_version: Option<String>) -> Result<CompatReport, String> {
+    let mut checks = Vec::new();
+    for tool in sample_tools() {
+        let local = local_round_trip(&tool);
+        let (server, detail) = match probe_tool(target, &tool).await {
+            Ok(res) => res,
+            Err(e) => (CompatStatus::Error, Some(e)),
+        };
+        let detail = match (&local, detail) {
+            (Err(e), Some(d)) => Some(format!("Local: {}; Server: {}", e, d)),
+            (Err(e), None) => Some(format!("Local: {}", e)),
+            (Ok(()), d) => d,
+        };
+        checks.push(CompatCheck {
+            tool_name: tool_name(&tool).to_string(),
+            local_round_trip: local.is_ok(),
+            server,
+            detail,
+        });
+    }
+
+    let in_sync = checks.iter().all(|c| c.local_round_trip && !matches!(c.server, CompatStatus::Mismatch));
+    Ok(CompatReport {
+        server_version,
+        profile: target.profile.name.clone(),
+        in_sync,
+        checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_tools_round_trip() {
+        let samples = sample_tools();
+        for tool in &samples {
+            assert!(local_round_trip(tool).is_ok(), "{} failed", tool_name(tool));
+            let json = serde_json::to_value(tool).unwrap();
+            assert_eq!(json["tool_name"], tool_name(tool));
+        }
+        assert_eq!(samples.len(), 12);
+    }
+}
@@ -0,0 +1,108 @@
+use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+use crate::library::SavedQuery;
+
+pub const EXAMPLES_FOLDER: &str = "Examples";
+const EXAMPLE_ID_PREFIX: &str = "example-";
+const PAGE: usize = 50;
+
+/// The parts of the connected schema the cookbook needs (same shape as the frontend `SchemaInfo`).
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct CookbookSchema {
+    #[serde(default)]
+    pub nodes: Vec<CookbookItem>,
+    #[serde(default)]
+    pub edges: Vec<CookbookItem>,
+    #[serde(default)]
+    pub vectors: Vec<CookbookItem>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct CookbookItem {
+    pub name: String,
+    #[serde(default, alias = "from_node")]
+    pub from: Option<String>,
+    #[serde(default, alias = "to_node")]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+pub fn is_example(query: &SavedQuery) -> bool {
+    query.id.starts_with(EXAMPLE_ID_PREFIX)
+}
+
+fn example(slug: &str, name: &str, code: String) -> SavedQuery {
+    SavedQuery {
+        id: format!("{}{}", EXAMPLE_ID_PREFIX, slug.to_lowercase()),
+        name: name.to_string(),
+        folder: Some(EXAMPLES_FOLDER.to_string()),
+        code,
+        params: None,
+        description: None,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
+
+fn is_numeric_type(ty: &str) -> bool {
+    matches!(ty, "I8" | "I16" | "I32" | "I64" | "U8" | "U16" | "U32" | "U64" | "U128" | "F32" | "F64")
+}
+
+/// A filter on the first property we know how to compare, if any.
+fn filter_for(node: &CookbookItem) -> Option<String> {
+    node.properties.iter().find_map(|(prop, ty)| {
+        let ty = ty.as_str().unwrap_or_default();
+        if is_numeric_type(ty) {
+            Some(format!("N<{}>::WHERE(_::{{{}}}::GT(0))::RANGE(0, {})", node.name, prop, PAGE))
+        } else if ty == "Boolean" {
+            Some(format!("N<{}>::WHERE(_::{{{}}}::EQ(true))::RANGE(0, {})", node.name, prop, PAGE))
+        } else {
+            None
+        }
+    })
+}
+
+fn parses(code: &str) -> bool {
+    let wrapped = format!("QUERY ExplorerTmp() => {}", code);
+    let content = write_to_temp_file(vec![&wrapped]);
+    HelixParser::parse_source(&content).is_ok()
+}
+
+/// Builds ready-to-run Explorer queries for the schema: one fetch and count per
+/// label, both directions of every edge, a property filter and search examples.
+/// Every example is checked with the HQL parser before it is returned.
+pub fn generate_cookbook(schema: &CookbookSchema) -> Vec<SavedQuery> {
+    let mut out = Vec::new();
+
+    for node in &schema.nodes {
+        let n = &node.name;
+        out.push(example(&format!("all-{}", n), &format!("All {}", n), format!("// First {} {} nodes\nN<{}>::RANGE(0, {})", PAGE, n, n, PAGE)));
+        out.push(example(&format!("count-{}", n), &format!("Count {}", n), format!("// How many {} nodes exist\nN<{}>::COUNT", n, n)));
+    }
+
+    for edge in &schema.edges {
+        let e = &edge.name;
+        out.push(example(&format!("edges-{}", e), &format!("All {} edges", e), format!("// First {} {} edges\nE<{}>::RANGE(0, {})", PAGE, e, e, PAGE)));
+        if let Some(from) = &edge.from {
+            out.push(example(&format!("out-{}", e), &format!("{} via {}", from, e), format!("// Follow {} from {} nodes\nN<{}>::Out<{}>::RANGE(0, {})", e, from, from, e, PAGE)));
+        }
+        if let Some(to) = &edge.to {
+            out.push(example(&format!("in-{}", e), &format!("{} via incoming {}", to, e), format!("// Walk {} backwards from {} nodes\nN<{}>::In<{}>::RANGE(0, {})", e, to, to, e, PAGE)));
+        }
+    }
+
+    if let Some((node, code)) = schema.nodes.iter().find_map(|n| filter_for(n).map(|c| (n, c))) {
+        out.push(example(&format!("filter-{}", node.name), &format!("Filter {}", node.name), format!("// Filter {} by a property\n{}", node.name, code)));
+    }
+
+    if let Some(node) = schema.nodes.iter().find(|n| n.properties.values().any(|t| t.as_str() == Some("String"))) {
+        out.push(example(&format!("bm25-{}", node.name), &format!("Keyword search {}", node.name), format!("// Full-text search (requires BM25 on the server)\nSearchBM25<{}>(\"example\", 10)", node.name)));
+    }
+    if let Some(vector) = schema.vectors.first() {
+        out.push(example(&format!("vector-{}", vector.name), &format!("Vector search {}", vector.name), format!("// Semantic search (requires an embedding model on the server)\nSearchV<{}>(Embed(\"example\"), 10)", vector.name)));
+    }
+
+    out.into_iter()
+        .filter(|q| parses(&q.code))
+        .collect()
+}
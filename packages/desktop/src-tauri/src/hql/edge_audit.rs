@@ -0,0 +1,278 @@
+//! Audit of stored edges against the schema's edge directions: a sample of each edge
+//! type is checked for endpoints under the wrong labels (often a reversed `AddE`) and
+//! for missing counterparts of reciprocal edge types.
+
+use std::collections::{HashMap, HashSet};
+use crate::hql::cookbook::{CookbookItem, CookbookSchema};
+use crate::hql::executor::{self, McpTarget};
+use crate::hql::tool_args::ToolArgs;
+
+pub const DEFAULT_SAMPLE_SIZE: usize = 200;
+/// Examples listed per edge type; the counts keep going past this.
+const MAX_EXAMPLES: usize = 10;
+/// Endpoint ids looked up per request.
+const ID_CHUNK: usize = 200;
+/// Edges of a reciprocal type read at most; past this missing counterparts are not reported.
+const MAX_RECIPROCAL_SCAN: usize = 50_000;
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EdgeProblem {
+    /// The source is a node of the schema's target label and the target one of its source label.
+    Reversed,
+    WrongFromLabel,
+    WrongToLabel,
+    /// No edge of the reciprocal type runs back from the target to the source.
+    MissingReciprocal,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeExample {
+    pub problem: EdgeProblem,
+    pub edge_id: String,
+    pub from_id: String,
+    pub to_id: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeTypeAudit {
+    pub edge_type: String,
+    pub from_label: String,
+    pub to_label: String,
+    pub sampled: usize,
+    pub reversed: usize,
+    /// Edges with an endpoint under neither of the schema's labels.
+    pub mismatched: usize,
+    /// The edge type expected to run the other way, when there is one.
+    pub reciprocal: Option<String>,
+    /// Whether every edge of the reciprocal type could be read to check against.
+    pub reciprocal_checked: bool,
+    pub missing_reciprocal: usize,
+    pub examples: Vec<EdgeExample>,
+    /// Why the type could not be audited.
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeAuditReport {
+    pub edge_types: Vec<EdgeTypeAudit>,
+    pub problem_count: usize,
+    pub healthy: bool,
+}
+
+/// An edge's id and endpoints as collected from the server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampledEdge {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl SampledEdge {
+    pub fn from_value(value: &serde_json::Value) -> Option<Self> {
+        let field = |keys: [&str; 2]| keys.iter().find_map(|k| value.get(*k)?.as_str()).map(String::from);
+        Some(Self {
+            id: value.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            from: field(["from_node", "from"])?,
+            to: field(["to_node", "to"])?,
+        })
+    }
+}
+
+/// Edge types expected to run both ways, keyed by edge type. Without explicit pairs,
+/// two types between the same labels in opposite directions (`Wrote: User -> Post`,
+/// `WrittenBy: Post -> User`) pair up. A self-referential type is only checked
+/// against itself when listed, as most (`Follows`) are not symmetric.
+pub fn reciprocal_pairs(schema: &CookbookSchema, explicit: Option<&[(String, String)]>) -> HashMap<String, String> {
+    let mut pairs = HashMap::new();
+    if let Some(explicit) = explicit {
+        for (a, b) in explicit {
+            pairs.insert(a.clone(), b.clone());
+            pairs.insert(b.clone(), a.clone());
+        }
+        return pairs;
+    }
+    for a in &schema.edges {
+        let inverse = schema.edges.iter().find(|b| {
+            b.name != a.name && a.from.is_some() && a.from != a.to && b.from == a.to && b.to == a.from
+        });
+        if let Some(b) = inverse {
+            pairs.insert(a.name.clone(), b.name.clone());
+        }
+    }
+    pairs
+}
+
+/// Checks an edge's endpoints against the ids found under the schema's source and
+/// target labels.
+pub fn classify(edge: &SampledEdge, from_ids: &HashSet<String>, to_ids: &HashSet<String>) -> Option<EdgeProblem> {
+    match (from_ids.contains(&edge.from), to_ids.contains(&edge.to)) {
+        (true, true) => None,
+        _ if to_ids.contains(&edge.from) && from_ids.contains(&edge.to) => Some(EdgeProblem::Reversed),
+        (false, _) => Some(EdgeProblem::WrongFromLabel),
+        (true, false) => Some(EdgeProblem::WrongToLabel),
+    }
+}
+
+/// The sampled edges with no edge running back among `reverse`.
+pub fn missing_reciprocals<'a>(sample: &'a [SampledEdge], reverse: &[SampledEdge]) -> Vec<&'a SampledEdge> {
+    let back: HashSet<(&str, &str)> = reverse.iter().map(|e| (e.from.as_str(), e.to.as_str())).collect();
+    sample.iter().filter(|e| !back.contains(&(e.to.as_str(), e.from.as_str()))).collect()
+}
+
+impl EdgeTypeAudit {
+    fn problem_count(&self) -> usize {
+        self.reversed + self.mismatched + self.missing_reciprocal + usize::from(self.error.is_some())
+    }
+
+    fn record(&mut self, problem: EdgeProblem, edge: &SampledEdge) {
+        match problem {
+            EdgeProblem::Reversed => self.reversed += 1,
+            EdgeProblem::WrongFromLabel | EdgeProblem::WrongToLabel => self.mismatched += 1,
+            EdgeProblem::MissingReciprocal => self.missing_reciprocal += 1,
+        }
+        if self.examples.len() < MAX_EXAMPLES {
+            self.examples.push(EdgeExample { problem, edge_id: edge.id.clone(), from_id: edge.from.clone(), to_id: edge.to.clone() });
+        }
+    }
+}
+
+fn edges_of(rows: &serde_json::Value) -> Vec<SampledEdge> {
+    rows.as_array().into_iter().flatten().filter_map(SampledEdge::from_value).collect()
+}
+
+/// Reads the edges of `edge_type`, a uniform sample of `size` when given, otherwise
+/// up to `MAX_RECIPROCAL_SCAN + 1` of them.
+async fn read_edges(target: &McpTarget, edge_type: &str, size: Option<usize>) -> Result<Vec<SampledEdge>, String> {
+    let conn = executor::acquire_connection(target).await?;
+    executor::open_tools(target, &conn, &[ToolArgs::EFromType { edge_type: edge_type.to_string() }]).await?;
+    let rows = match size {
+        Some(size) => crate::hql::sample::sample_pipeline(target, &conn, 0, None, size).await?.rows,
+        None => executor::collect_window(target, &conn, 0, MAX_RECIPROCAL_SCAN + 1, false).await?,
+    };
+    Ok(edges_of(&rows))
+}
+
+/// The ids among `ids` that exist under `label`, a node or vector label of the schema.
+async fn ids_under(target: &McpTarget, schema: &CookbookSchema, label: &str, ids: &[String]) -> Result<HashSet<String>, String> {
+    let start = if schema.vectors.iter().any(|v| v.name == label) {
+        ToolArgs::VFromType { vector_type: label.to_string(), filter: None }
+    } else {
+        ToolArgs::NFromType { node_type: label.to_string() }
+    };
+    let mut found = HashSet::new();
+    for chunk in ids.chunks(ID_CHUNK) {
+        let items = executor::fetch_by_ids(target, &start, chunk).await?;
+        found.extend(items.as_array().into_iter().flatten().filter_map(|i| i.get("id")?.as_str().map(String::from)));
+    }
+    Ok(found)
+}
+
+async fn audit_edge_type(
+    target: &McpTarget,
+    schema: &CookbookSchema,
+    edge: &CookbookItem,
+    reciprocal: Option<&String>,
+    sample_size: usize,
+) -> Result<EdgeTypeAudit, String> {
+    let (Some(from_label), Some(to_label)) = (&edge.from, &edge.to) else {
+        return Err(format!("The schema does not give the endpoints of edge type '{}'", edge.name));
+    };
+    let mut audit = EdgeTypeAudit {
+        edge_type: edge.name.clone(),
+        from_label: from_label.clone(),
+        to_label: to_label.clone(),
+        reciprocal: reciprocal.cloned(),
+        ..Default::default()
+    };
+    let sample = read_edges(target, &edge.name, Some(sample_size)).await?;
+    audit.sampled = sample.len();
+
+    let mut ids: Vec<String> = sample.iter().flat_map(|e| [e.from.clone(), e.to.clone()]).collect();
+    ids.sort();
+    ids.dedup();
+    let from_ids = ids_under(target, schema, from_label, &ids).await?;
+    let to_ids = if to_label == from_label { from_ids.clone() } else { ids_under(target, schema, to_label, &ids).await? };
+    for sampled in &sample {
+        if let Some(problem) = classify(sampled, &from_ids, &to_ids) {
+            audit.record(problem, sampled);
+        }
+    }
+
+    if let Some(reciprocal) = reciprocal {
+        let reverse = if *reciprocal == edge.name { sample.clone() } else { read_edges(target, reciprocal, None).await? };
+        // Reading the whole type back is what makes a missing counterpart certain
+        audit.reciprocal_checked = *reciprocal == edge.name || reverse.len() <= MAX_RECIPROCAL_SCAN;
+        if audit.reciprocal_checked {
+            for missing in missing_reciprocals(&sample, &reverse) {
+                audit.record(EdgeProblem::MissingReciprocal, missing);
+            }
+        }
+    }
+    Ok(audit)
+}
+
+/// Audits every edge type of `schema`. A type that cannot be read is reported with its
+/// error rather than failing the audit.
+pub async fn audit_edges(
+    target: &McpTarget,
+    schema: &CookbookSchema,
+    reciprocal: Option<&[(String, String)]>,
+    sample_size: usize,
+) -> EdgeAuditReport {
+    let pairs = reciprocal_pairs(schema, reciprocal);
+    let mut edge_types = Vec::new();
+    for edge in &schema.edges {
+        let audit = match audit_edge_type(target, schema, edge, pairs.get(&edge.name), sample_size).await {
+            Ok(audit) => audit,
+            Err(e) => EdgeTypeAudit {
+                edge_type: edge.name.clone(),
+                from_label: edge.from.clone().unwrap_or_default(),
+                to_label: edge.to.clone().unwrap_or_default(),
+                error: Some(e),
+                ..Default::default()
+            },
+        };
+        edge_types.push(audit);
+    }
+    let problem_count = edge_types.iter().map(EdgeTypeAudit::problem_count).sum();
+    EdgeAuditReport { edge_types, problem_count, healthy: problem_count == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_and_reciprocals() {
+        let edge = |id: &str, from: &str, to: &str| SampledEdge { id: id.to_string(), from: from.to_string(), to: to.to_string() };
+        let set = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<HashSet<_>>();
+        let (users, posts) = (set(&["u1", "u2"]), set(&["p1"]));
+        assert_eq!(classify(&edge("e1", "u1", "p1"), &users, &posts), None);
+        assert_eq!(classify(&edge("e2", "p1", "u1"), &users, &posts), Some(EdgeProblem::Reversed));
+        assert_eq!(classify(&edge("e3", "x", "p1"), &users, &posts), Some(EdgeProblem::WrongFromLabel));
+        assert_eq!(classify(&edge("e4", "u1", "u2"), &users, &posts), Some(EdgeProblem::WrongToLabel));
+
+        let parsed = SampledEdge::from_value(&serde_json::json!({ "id": "e5", "from": "u1", "to_node": "p1" }));
+        assert_eq!(parsed, Some(edge("e5", "u1", "p1")));
+
+        let item = |name: &str, from: &str, to: &str| CookbookItem { name: name.to_string(), from: Some(from.to_string()), to: Some(to.to_string()), ..Default::default() };
+        let schema = CookbookSchema {
+            edges: vec![item("Wrote", "User", "Post"), item("WrittenBy", "Post", "User"), item("Follows", "User", "User")],
+            ..Default::default()
+        };
+        let pairs = reciprocal_pairs(&schema, None);
+        assert_eq!(pairs.get("Wrote").map(String::as_str), Some("WrittenBy"));
+        assert_eq!(pairs.get("WrittenBy").map(String::as_str), Some("Wrote"));
+        assert!(!pairs.contains_key("Follows"));
+        let explicit = [("Follows".to_string(), "Follows".to_string())];
+        assert_eq!(reciprocal_pairs(&schema, Some(&explicit)).len(), 1);
+
+        let wrote = [edge("e1", "u1", "p1"), edge("e6", "u2", "p1")];
+        let missing = missing_reciprocals(&wrote, &[edge("e7", "p1", "u1")]);
+        assert_eq!(missing, vec![&wrote[1]]);
+    }
+}
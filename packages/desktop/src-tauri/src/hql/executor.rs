@@ -1,33 +1,341 @@
 
-use crate::hql::tool_args::{ToolArgs, FilterProperties, FilterTraversal, Operator};
-use crate::hql::translator::{map_traversal_to_tools, FinalAction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use futures::{StreamExt, TryStreamExt};
+use crate::hql::guardrails::ResultLimits;
+use crate::hql::pool::{McpPool, PooledConnection};
+use crate::hql::profiles::TranslationProfile;
+use crate::hql::metrics::MetricsRecorder;
+use crate::hql::progress::{action_name, ProgressReporter};
+use crate::network_trace::SendTraced;
+use crate::hql::tool_args::{EdgeType, ToolArgs, FilterProperties, FilterTraversal, Operator};
+use crate::hql::translator::{map_traversal_to_tools, parse_date, vector_embedding_key, ClientFilter, EdgeDirection, EdgeEndpoint, EdgeHop, FinalAction, PipelinePlan, SearchCall, PREFILTER_MAX_OVERSAMPLE, PREFILTER_OVERSAMPLE};
 use helix_db::protocol::value::Value;
 
+/// Items a per-candidate COUNT filter may fan out over, and how many run at once.
+const MAX_COUNT_CANDIDATES: usize = 200;
+const COUNT_CONCURRENCY: usize = 8;
+
+/// A gateway reachable over MCP, together with how tools must be encoded for it.
+#[derive(Clone)]
+pub struct McpTarget {
+    pub client: reqwest::Client,
+    pub url: String,
+    pub auth: crate::gateway_auth::GatewayAuth,
+    pub profile: TranslationProfile,
+    /// Where scratch connections come from; without a pool each one is a fresh `init`.
+    pub pool: Option<Arc<McpPool>>,
+    /// Told about every finished step, for `query-progress` events.
+    pub progress: Option<ProgressReporter>,
+    /// Records call timings for the metrics returned with results.
+    pub metrics: Option<MetricsRecorder>,
+    /// Pipelines return their item count instead of their items.
+    pub count_only: bool,
+    /// Caps on the rows a final collect returns.
+    pub limits: ResultLimits,
+    /// Told when a pipeline was replayed because the server forgot its connection.
+    pub on_recovered: Option<RecoveryHook>,
+    /// Spaces tool calls out for connections with a `rateLimit`.
+    pub rate_limit: Option<Arc<crate::rate_limit::Limiter>>,
+    /// Connections are opened anew and closed after use, never taken from or given
+    /// back to the pool's idle ones.
+    pub fresh_connections: bool,
+    /// Where what is learned about the server (whether it filters by id) is kept.
+    pub capabilities: Option<crate::capabilities::CapabilityCache>,
+}
+
+/// Sent as `connection-recovered` once a variable has been replayed on a fresh
+/// connection after the server stopped recognizing the old one (usually a restart).
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionRecovered {
+    pub url: String,
+    pub variable: String,
+    /// Why the old connection was given up.
+    pub reason: String,
+}
+
+pub type RecoveryHook = Arc<dyn Fn(ConnectionRecovered) + Send + Sync>;
+
+impl McpTarget {
+    pub fn new(client: reqwest::Client, url: &str, auth: crate::gateway_auth::GatewayAuth, profile: TranslationProfile) -> Self {
+        Self { client, url: url.to_string(), auth, profile, pool: None, progress: None, metrics: None, count_only: false, limits: ResultLimits::default(), on_recovered: None, rate_limit: None, fresh_connections: false, capabilities: None }
+    }
+
+    pub fn with_pool(mut self, pool: Arc<McpPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressReporter) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: MetricsRecorder) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: crate::capabilities::CapabilityCache) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Whether the server filters by id, once an execution has found out.
+    fn filters_ids(&self) -> Option<bool> {
+        self.capabilities.as_ref()?.lock().unwrap().get(&self.url)?.filters_ids
+    }
+
+    fn remember_filters_ids(&self, filters: bool) {
+        if let Some(cache) = &self.capabilities {
+            cache.lock().unwrap().entry(self.url.clone()).or_default().filters_ids = Some(filters);
+        }
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: Option<Arc<crate::rate_limit::Limiter>>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    pub fn with_count_only(mut self, count_only: bool) -> Self {
+        self.count_only = count_only;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: ResultLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn on_recovered(mut self, hook: impl Fn(ConnectionRecovered) + Send + Sync + 'static) -> Self {
+        self.on_recovered = Some(Arc::new(hook));
+        self
+    }
+
+    /// Cuts a result the executor built itself to the limits, reporting the full size.
+    fn limit(&self, mut value: serde_json::Value) -> serde_json::Value {
+        let dropped = self.limits.apply(&mut value);
+        if dropped > 0 {
+            let total = value.as_array().map_or(0, |rows| rows.len()) + dropped;
+            if let Some(metrics) = &self.metrics {
+                metrics.truncated(Some(total));
+            }
+        }
+        value
+    }
+
+    /// A server call that started at `started` and has just finished.
+    fn record_call(&self, action: &str, started: std::time::Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics.tool_call(action, started.elapsed());
+        }
+    }
+
+    /// A finished pipeline step, for progress events and metrics.
+    fn report_step(&self, action: &str, started: std::time::Instant) {
+        self.record_call(action, started);
+        if let Some(progress) = &self.progress {
+            progress.step(action);
+        }
+    }
+
+    /// Reads a result body as JSON, counting its size. The client asks for gzip or
+    /// brotli and decompresses, so the size is what the server's rows amount to.
+    async fn read_results(&self, resp: reqwest::Response, what: &str) -> Result<serde_json::Value, String> {
+        let body = resp.bytes().await.map_err(|e| request_error(e, what))?;
+        if let Some(metrics) = &self.metrics {
+            metrics.response_bytes(body.len());
+        }
+        serde_json::from_slice(&body).map_err(|e| format!("{}: {}", what, e))
+    }
+
+    /// POST to `{url}/mcp/{endpoint}` with the profile's endpoint name, the API key and
+    /// the connection's headers.
+    pub fn post(&self, endpoint: &str) -> reqwest::RequestBuilder {
+        self.auth.apply(self.client.post(format!("{}/mcp/{}", self.url, self.profile.endpoint(endpoint))))
+    }
+}
+
+/// Attempts per variable when the transport drops mid-pipeline.
+const MAX_ATTEMPTS: u32 = 3;
+/// Marks errors from dropped connections, so callers can tell them from query errors.
+const INTERRUPTED: &str = "connection interrupted";
+
+/// `IncompleteMessage` and connection resets show up in the error's source chain,
+/// not its message.
+fn is_interrupted(e: &reqwest::Error) -> bool {
+    if e.is_timeout() {
+        return false;
+    }
+    let mut source: Option<&dyn std::error::Error> = Some(e);
+    while let Some(err) = source {
+        let text = format!("{:?}", err).to_lowercase();
+        if text.contains("incompletemessage") || text.contains("connection reset") || text.contains("connection closed before message completed") {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+fn request_error(e: reqwest::Error, what: &str) -> String {
+    if is_interrupted(&e) {
+        format!("{}: {} ({})", what, INTERRUPTED, e)
+    } else {
+        format!("{}: {}", what, e)
+    }
+}
+
+pub fn is_interrupted_error(message: &str) -> bool {
+    message.contains(INTERRUPTED)
+}
+
+/// Marks errors from a connection id the server no longer knows.
+const STALE: &str = "connection no longer known to the server";
+
+/// A 4xx whose body says the connection id is unknown, as after a server restart
+/// dropped its MCP connections.
+fn is_stale_response(status: reqwest::StatusCode, body: &str) -> bool {
+    let body = body.to_lowercase();
+    status.is_client_error()
+        && body.contains("connection")
+        && ["not found", "unknown", "invalid", "no such", "does not exist", "expired"].iter().any(|s| body.contains(s))
+}
+
+/// Reads a failed response into `"{what} ({status}): {body}"`, marked when the
+/// connection has gone stale.
+async fn response_error(resp: reqwest::Response, what: &str) -> String {
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    if is_stale_response(status, &body) {
+        format!("{} ({}): {} ({})", what, status, STALE, body)
+    } else {
+        format!("{} ({}): {}", what, status, body)
+    }
+}
+
+pub fn is_stale_error(message: &str) -> bool {
+    message.contains(STALE)
+}
+
+/// Runs one RETURN variable (a traversal, a search, or a traversal continuing from a
+/// search) on a pooled connection. If the connection drops mid-way, or the server no
+/// longer knows it, the pipeline is replayed from scratch on a fresh one: tool calls
+/// are read-only, so replaying is safe, but the old connection's half-built pipeline
+/// is never reused.
+pub async fn execute_variable(
+    target: &McpTarget,
+    variable: &str,
+    search: Option<&SearchCall>,
+    traversal: Option<&helix_db::helixc::parser::types::Traversal>,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let mut scoped = target.clone();
+    let mut attempt = 1;
+    let mut recovered_from = None;
+    loop {
+        // Steps are counted afresh on each attempt
+        scoped.progress = target.progress.as_ref().map(|p| p.for_variable(variable));
+        scoped.metrics = target.metrics.as_ref().map(|m| m.for_variable(variable));
+        let target = &scoped;
+        let conn = acquire_connection(target).await?;
+        // Probes and second passes opened meanwhile may borrow this connection's slot
+        let result = conn.lend(async {
+            match (traversal, search) {
+                (Some(t), _) => execute_pipeline(target, &conn, search, t, params).await,
+                (None, Some(s)) if target.count_only => execute_search_tool(target, &conn, s).await
+                    .map(|v| serde_json::json!(v.as_array().map_or(0, |a| a.len()))),
+                (None, Some(s)) => execute_search_tool(target, &conn, s).await,
+                (None, None) => Ok(serde_json::Value::Null),
+            }
+        }).await;
+        match result {
+            Ok(value) => {
+                // Search endpoints do not drop their state like `collect` does
+                if traversal.is_some() {
+                    conn.release();
+                }
+                if let Some(progress) = &target.progress {
+                    progress.done();
+                }
+                if let (Some(reason), Some(hook)) = (recovered_from, &target.on_recovered) {
+                    hook(ConnectionRecovered { url: target.url.clone(), variable: variable.to_string(), reason });
+                }
+                return Ok(value);
+            }
+            Err(e) if attempt < MAX_ATTEMPTS && is_stale_error(&e) => {
+                println!(">>> [Executor] Connection went stale on attempt {}/{}, re-initializing: {}", attempt, MAX_ATTEMPTS, e);
+                // Its idle siblings were most likely dropped by the same restart
+                if let Some(pool) = &target.pool {
+                    pool.forget_idle(target);
+                }
+                conn.discard();
+                recovered_from = Some(e);
+                attempt += 1;
+            }
+            Err(e) if attempt < MAX_ATTEMPTS && is_interrupted_error(&e) => {
+                println!(">>> [Executor] Attempt {}/{} interrupted, replaying on a new connection: {}", attempt, MAX_ATTEMPTS, e);
+                drop(conn);
+                tokio::time::sleep(std::time::Duration::from_millis(500 * attempt as u64)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Runs a traversal. `start` is the search a traversal starting from a search
+/// variable continues from; it is sent first so the steps apply to its results.
 pub async fn execute_pipeline(
-    client: &reqwest::Client,
-    url: &str,
+    target: &McpTarget,
     connection_id: &str,
+    start: Option<&SearchCall>,
     traversal: &helix_db::helixc::parser::types::Traversal,
     params: &serde_json::Value,
-    api_key: Option<String>
 ) -> Result<serde_json::Value, String> {
     
     // 1. Map to tools
-    let (tools, final_action, id_filters) = map_traversal_to_tools(traversal, params)?;
+    let mut plan = map_traversal_to_tools(traversal, params)?;
+    if target.count_only {
+        plan.count_only();
+    }
+    if let Some(search) = start {
+        if search.prefilter.is_some() {
+            return Err("PREFILTER searches cannot be followed by traversal steps yet; RETURN the search results directly".to_string());
+        }
+        plan.tools.insert(0, search.tool.clone());
+    }
+    if let Some(progress) = &target.progress {
+        progress.set_total(plan.tools.len() + 1);
+    }
+    if let Some(hop) = &plan.edge_hop {
+        return execute_edge_hop(target, connection_id, &plan, hop).await;
+    }
+    if !plan.client_filters.is_empty() {
+        return execute_with_client_filters(target, connection_id, &plan).await;
+    }
+    let PipelinePlan { tools, final_action, id_filters, .. } = plan;
+
+    // 2. Filter by ID on the server when it supports it
+    if !id_filters.is_empty() && server_filters_ids(target, &tools[0], &id_filters).await? {
+        let mut filtered_tools = tools.clone();
+        filtered_tools.insert(1, id_filter_tool(&id_filters));
+        for tool in &filtered_tools {
+            send_tool(target, connection_id, tool).await?;
+        }
+        return execute_final_action(target, connection_id, final_action).await;
+    }
 
-    // 2. Determine execution strategy based on whether we have ID filters
+    // 3. Otherwise fall back to fetching the label and filtering IDs locally
     let has_subsequent_steps = tools.len() > 1;
 
     if !id_filters.is_empty() && has_subsequent_steps {
         // ==================================================================================
-        // WORKAROUND: Two-Pass Execution for ID Filtering
+        // FALLBACK: Two-Pass Execution for ID Filtering
         // ==================================================================================
-        // CURRENT LIMITATION:
-        // The helix-db MCP protocol (`tools.rs` / `ToolArgs`) currently lacks a direct way
-        // to filter by ID (e.g., `NFromId` or `FilterById` tool does not exist).
-        // It only supports `NFromType`.
+        // Only used when the server ignores `id` in FilterItems (see `server_filters_ids`).
         //
-        // CURRENT IMPLEMENTATION:
         // 1. Pass 1: Fetch ALL nodes of the given type (`NFromType`).
         //    - RISK: This pulls the entire table into client memory. Dangerous for large datasets!
         // 2. Client-side Filter: Find the specific node by ID in the result set.
@@ -35,16 +343,12 @@ pub async fn execute_pipeline(
         //    - RISK: Correctness issue. If multiple nodes share the same non-ID properties
         //      but have different IDs, this second pass might match the wrong nodes.
         // 4. Pass 2: Re-run the query chain using `FilterItems` instead of ID.
-        //
-        // TODO(upstream): 
-        // Implement `NFromId` or `FilterById` in `helix-db`'s `mcp.rs` and `tools.rs`.
-        // Once available, replace this entire block with a single `FilterById` tool call.
         // ==================================================================================
         let start_tool = &tools[0];
         let remaining_tools = &tools[1..];
 
-        send_tool(client, url, connection_id, start_tool, api_key.clone()).await?;
-        let all_items = collect_results(client, url, connection_id, None, api_key.clone()).await?;
+        send_tool(target, connection_id, start_tool).await?;
+        let all_items = collect_all(target, connection_id, None).await?;
         let filtered = filter_by_ids(&all_items, &id_filters);
 
         let prop_filter = if let Some(item) = filtered.as_array().and_then(|a| a.first()) {
@@ -83,27 +387,27 @@ pub async fn execute_pipeline(
             return Ok(serde_json::Value::Array(vec![]));
         };
 
-        let init_resp = client.post(format!("{}/mcp/init", url)).send().await
+        let conn2 = acquire_connection(target).await
             .map_err(|e| format!("Init failed for pass 2: {}", e))?;
-        let init_body = init_resp.text().await.map_err(|e| format!("Failed to read init body: {}", e))?;
-        let conn2: String = serde_json::from_str(&init_body).map_err(|e| format!("Failed to parse connection_id: {}", e))?;
 
-        send_tool(client, url, &conn2, start_tool, api_key.clone()).await?;
+        send_tool(target, &conn2, start_tool).await?;
         if let Some(pf) = &prop_filter {
-            send_tool(client, url, &conn2, pf, api_key.clone()).await?;
+            send_tool(target, &conn2, pf).await?;
         }
         for tool in remaining_tools {
-            send_tool(client, url, &conn2, tool, api_key.clone()).await?;
+            send_tool(target, &conn2, tool).await?;
         }
 
-        execute_final_action(client, url, &conn2, final_action, api_key.clone()).await
+        let result = execute_final_action(target, &conn2, final_action).await?;
+        conn2.release();
+        Ok(result)
     } else {
         // STANDARD EXECUTION
         for tool in &tools {
-            send_tool(client, url, connection_id, tool, api_key.clone()).await?;
+            send_tool(target, connection_id, tool).await?;
         }
 
-        let result = execute_final_action(client, url, connection_id, final_action, api_key.clone()).await?;
+        let result = execute_final_action(target, connection_id, final_action).await?;
 
         if !id_filters.is_empty() {
             Ok(filter_by_ids(&result, &id_filters))
@@ -113,124 +417,314 @@ pub async fn execute_pipeline(
     }
 }
 
-pub async fn execute_search_tool(
-    client: &reqwest::Client,
-    url: &str,
-    connection_id: &str,
-    tool: &ToolArgs,
-    api_key: Option<String>
-) -> Result<serde_json::Value, String> {
-    let (endpoint, body) = match tool {
-        ToolArgs::SearchKeyword { query, limit, label } => ("search_keyword", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "limit": limit, "label": label } })),
-        ToolArgs::SearchVec { vector, k, min_score, cutoff, label } => ("search_vector", serde_json::json!({ "connection_id": connection_id, "data": { "vector": vector, "k": k, "min_score": min_score, "cutoff": cutoff, "label": label } })),
-        ToolArgs::SearchVecText { query, label, k } => ("search_vector_text", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "label": label, "k": k } })),
-        _ => return Err("Not a search tool".to_string()),
+/// A `FilterItems` keeping items whose `id` is any of `ids` (one OR group per id).
+fn id_filter_tool(ids: &[String]) -> ToolArgs {
+    ToolArgs::FilterItems {
+        filter: FilterTraversal {
+            properties: Some(ids.iter().map(|id| vec![FilterProperties {
+                key: "id".to_string(),
+                value: Value::String(id.clone()),
+                operator: Some(Operator::Eq),
+            }]).collect()),
+            filter_traversals: None,
+        }
+    }
+}
+
+/// Whether the server resolves `id` in FilterItems, checked once per target on scratch
+/// connections. A server that ignores the filter returns items that were not asked
+/// for, and one that does not know the key returns nothing; both use the fallback.
+/// When none of `ids` match, the check is repeated with an id the label does have, so
+/// a lookup of missing ids is not taken for a server that cannot filter.
+async fn server_filters_ids(target: &McpTarget, start: &ToolArgs, ids: &[String]) -> Result<bool, String> {
+    if let Some(filters) = target.filters_ids() {
+        return Ok(filters);
+    }
+    let items = probe_id_filter(target, start, ids).await?;
+    let filters = if items.as_array().is_some_and(|items| items.is_empty()) {
+        match first_id(target, start).await? {
+            Some(id) => {
+                let ids = [id];
+                only_requested_ids(&probe_id_filter(target, start, &ids).await?, &ids)
+            }
+            // An empty label: filtering on the server cannot miss anything, and nothing is learned
+            None => return Ok(true),
+        }
+    } else {
+        only_requested_ids(&items, ids)
     };
+    target.remember_filters_ids(filters);
+    Ok(filters)
+}
 
-    let mut req = client.post(format!("{}/mcp/{}", url, endpoint))
-        .json(&body);
+/// `start` filtered by `ids`, cut one row past them: enough to tell an ignored filter
+/// from a working one.
+async fn probe_id_filter(target: &McpTarget, start: &ToolArgs, ids: &[String]) -> Result<serde_json::Value, String> {
+    let conn = acquire_connection(target).await?;
+    send_tool(target, &conn, start).await?;
+    send_tool(target, &conn, &id_filter_tool(ids)).await?;
+    let items = collect_with(target, &conn, Some((0, Some(ids.len() + 1))), true).await?;
+    conn.release();
+    Ok(items)
+}
+
+/// The id of the first item of `start`, if it has any.
+async fn first_id(target: &McpTarget, start: &ToolArgs) -> Result<Option<String>, String> {
+    let conn = acquire_connection(target).await?;
+    send_tool(target, &conn, start).await?;
+    let items = collect_with(target, &conn, Some((0, Some(1))), true).await?;
+    conn.release();
+    Ok(items.get(0).and_then(|item| item.get("id")).and_then(|id| id.as_str()).map(String::from))
+}
 
-    if let Some(key) = &api_key {
-        req = req.header("x-api-key", key);
+/// Whether `items` is a non-empty list holding only items whose `id` is one of `ids`.
+fn only_requested_ids(items: &serde_json::Value, ids: &[String]) -> bool {
+    let Some(items) = items.as_array().filter(|items| !items.is_empty() && items.len() <= ids.len()) else {
+        return false;
+    };
+    items.iter().all(|item| item.get("id").and_then(|v| v.as_str()).is_some_and(|id| ids.iter().any(|wanted| wanted == id)))
+}
+
+/// Builds the dedicated MCP search endpoint call for a search tool.
+pub(crate) fn search_request(tool: &ToolArgs, connection_id: &str) -> Option<(&'static str, serde_json::Value)> {
+    match tool {
+        ToolArgs::SearchKeyword { query, limit, label } => Some(("search_keyword", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "limit": limit, "label": label } }))),
+        ToolArgs::SearchVec { vector, k, min_score, cutoff, label } => Some(("search_vector", serde_json::json!({ "connection_id": connection_id, "data": { "vector": vector, "k": k, "min_score": min_score, "cutoff": cutoff, "label": label } }))),
+        ToolArgs::SearchVecText { query, label, k } => Some(("search_vector_text", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "label": label, "k": k } }))),
+        _ => None,
     }
+}
 
-    let resp = req.send()
+pub async fn execute_search_tool(
+    target: &McpTarget,
+    connection_id: &str,
+    search: &SearchCall,
+) -> Result<serde_json::Value, String> {
+    if let Some(filter) = &search.prefilter {
+        // Filter the oversampled candidates on the connection, then cut back to k. Too few
+        // matches are searched again from a larger sample, and reported if still short.
+        let k = search.k().unwrap_or(0);
+        let wanted = target.limits.max_rows.map_or(k, |max| k.min(max));
+        let mut factor = PREFILTER_OVERSAMPLE;
+        loop {
+            send_tool(target, connection_id, &search.sampled_tool(factor)).await?;
+            send_tool(target, connection_id, &ToolArgs::FilterItems { filter: filter.clone() }).await?;
+            let results = collect_results(target, connection_id, Some((0, search.k()))).await?;
+            let found = results.as_array().map_or(0, Vec::len);
+            if found >= wanted || factor >= PREFILTER_MAX_OVERSAMPLE {
+                if found < wanted {
+                    if let Some(metrics) = &target.metrics {
+                        metrics.prefilter_shortfall(wanted, found, k.saturating_mul(factor));
+                    }
+                }
+                return Ok(results);
+            }
+            factor *= 10;
+        }
+    }
+
+    let (endpoint, body) = search_request(&search.tool, connection_id)
+        .ok_or_else(|| "Not a search tool".to_string())?;
+
+    let started = std::time::Instant::now();
+    let resp = target.post(endpoint)
+        .json(&body)
+        .send_traced()
         .await
-        .map_err(|e| format!("Search request failed: {}", e))?;
+        .map_err(|e| request_error(e, "Search request failed"))?;
 
     if resp.status().is_success() {
-        resp.json().await.map_err(|e| format!("Failed to parse search results: {}", e))
+        let results = target.read_results(resp, "Failed to read search results").await;
+        target.record_call(&action_name(&search.tool), started);
+        results
     } else {
         Err(format!("Search error ({}): {}", resp.status(), resp.text().await.unwrap_or_default()))
     }
 }
 
-async fn send_tool(client: &reqwest::Client, url: &str, connection_id: &str, tool: &ToolArgs, api_key: Option<String>) -> Result<(), String> {
-    let is_search = matches!(tool, ToolArgs::SearchKeyword { .. } | ToolArgs::SearchVec { .. } | ToolArgs::SearchVecText { .. });
-    
-    if is_search {
-        let (endpoint, body) = match tool {
-            ToolArgs::SearchKeyword { query, limit, label } => ("search_keyword", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "limit": limit, "label": label } })),
-            ToolArgs::SearchVec { vector, k, min_score, cutoff, label } => ("search_vector", serde_json::json!({ "connection_id": connection_id, "data": { "vector": vector, "k": k, "min_score": min_score, "cutoff": cutoff, "label": label } })),
-            ToolArgs::SearchVecText { query, label, k } => ("search_vector_text", serde_json::json!({ "connection_id": connection_id, "data": { "query": query, "label": label, "k": k } })),
-            _ => unreachable!(),
-        };
-
-        let mut req = client.post(format!("{}/mcp/{}", url, endpoint)).json(&body);
-        if let Some(key) = &api_key {
-            req = req.header("x-api-key", key);
+/// Sends a traversal's tools and leaves its pipeline open for `collect_window`, so it
+/// can be read page by page. Returns the traversal's own RANGE, if any. Plans that
+/// need client-side steps cannot be paged.
+pub async fn open_pipeline(
+    target: &McpTarget,
+    connection_id: &str,
+    traversal: &helix_db::helixc::parser::types::Traversal,
+    params: &serde_json::Value,
+) -> Result<Option<(usize, Option<usize>)>, String> {
+    let plan = map_traversal_to_tools(traversal, params)?;
+    if plan.edge_hop.is_some() || !plan.client_filters.is_empty() {
+        return Err("This traversal needs client-side steps (DEDUP, COUNT or edge hops) and cannot be paged; run it without paging".to_string());
+    }
+    let range = match plan.final_action {
+        FinalAction::Collect { range } => range,
+        _ => return Err("Only traversals that return rows can be paged".to_string()),
+    };
+    let mut tools = plan.tools;
+    if !plan.id_filters.is_empty() {
+        if !server_filters_ids(target, &tools[0], &plan.id_filters).await? {
+            return Err("Filtering by ID cannot be paged on this server".to_string());
         }
-        let tool_resp = req.send().await
-            .map_err(|e| format!("Search call failed: {}", e))?;
+        tools.insert(1, id_filter_tool(&plan.id_filters));
+    }
+    for tool in &tools {
+        send_tool(target, connection_id, tool).await?;
+    }
+    Ok(range)
+}
+
+/// Sends tools built outside a query and leaves the pipeline open for `collect_window`.
+pub async fn open_tools(target: &McpTarget, connection_id: &str, tools: &[ToolArgs]) -> Result<(), String> {
+    for tool in tools {
+        send_tool(target, connection_id, tool).await?;
+    }
+    Ok(())
+}
+
+/// The items of `start` whose id is one of `ids`, filtered by the server when it can
+/// and locally otherwise.
+pub async fn fetch_by_ids(target: &McpTarget, start: &ToolArgs, ids: &[String]) -> Result<serde_json::Value, String> {
+    if ids.is_empty() {
+        return Ok(serde_json::Value::Array(Vec::new()));
+    }
+    let server_filters = server_filters_ids(target, start, ids).await?;
+    let conn = acquire_connection(target).await?;
+    send_tool(target, &conn, start).await?;
+    if server_filters {
+        send_tool(target, &conn, &id_filter_tool(ids)).await?;
+    }
+    let items = collect_all(target, &conn, None).await?;
+    conn.release();
+    Ok(filter_by_ids(&items, ids))
+}
+
+async fn send_tool(target: &McpTarget, connection_id: &str, tool: &ToolArgs) -> Result<(), String> {
+    if let Some(limiter) = &target.rate_limit {
+        limiter.wait().await;
+    }
+    let started = std::time::Instant::now();
+    if let Some((endpoint, body)) = search_request(tool, connection_id) {
+        let tool_resp = target.post(endpoint).json(&body).send_traced().await
+            .map_err(|e| request_error(e, "Search call failed"))?;
         if !tool_resp.status().is_success() {
-            return Err(format!("Search error ({}): {}", tool_resp.status(), tool_resp.text().await.unwrap_or_default()));
+            return Err(response_error(tool_resp, "Search error").await);
         }
     } else {
-        let mut req = client.post(format!("{}/mcp/tool_call", url)).json(&serde_json::json!({ "connection_id": connection_id, "tool": tool }));
-        if let Some(key) = &api_key {
-            req = req.header("x-api-key", key);
-        }
-        let tool_resp = req.send().await
-            .map_err(|e| format!("Tool call failed: {}", e))?;
+        let encoded = target.profile.encode_tool(tool)?;
+        let tool_resp = target.post("tool_call")
+            .json(&serde_json::json!({ "connection_id": connection_id, "tool": encoded }))
+            .send_traced().await
+            .map_err(|e| request_error(e, "Tool call failed"))?;
         if !tool_resp.status().is_success() {
-            return Err(format!("Tool call error ({}): {}", tool_resp.status(), tool_resp.text().await.unwrap_or_default()));
+            return Err(response_error(tool_resp, "Tool call error").await);
         }
     }
+    target.report_step(&action_name(tool), started);
     Ok(())
 }
 
-async fn execute_final_action(client: &reqwest::Client, url: &str, conn: &str, action: FinalAction, api_key: Option<String>) -> Result<serde_json::Value, String> {
-    match action {
-        FinalAction::Collect { range } => collect_results(client, url, conn, range, api_key.clone()).await,
-        FinalAction::Count => {
-            let mut req = client.post(format!("{}/mcp/aggregate_by", url)).json(&serde_json::json!({ "connection_id": conn, "properties": Vec::<String>::new(), "drop": true }));
-            if let Some(key) = &api_key {
-                req = req.header("x-api-key", key);
-            }
-            let resp = req.send().await
-                .map_err(|e| format!("Count failed: {}", e))?;
-            
-            if resp.status().is_success() { 
-                let val: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
-                
-                // HQL "::COUNT" expects a scalar number, but aggregate_by returns a full report.
-                // We unwrap { "Count": { "": { "count": N, ... } } } -> N
-                if let Some(count_obj) = val.get("Count").and_then(|c| c.as_object()) {
-                    if let Some(empty_group) = count_obj.get("").and_then(|g| g.as_object()) {
-                        if let Some(count_val) = empty_group.get("count").filter(|c| c.is_number()) {
-                            return Ok(count_val.clone());
-                        }
-                    }
+async fn count_results(target: &McpTarget, conn: &str) -> Result<serde_json::Value, String> {
+    let started = std::time::Instant::now();
+    let resp = target.post("aggregate_by").json(&serde_json::json!({ "connection_id": conn, "properties": Vec::<String>::new(), "drop": true }))
+        .send_traced().await
+        .map_err(|e| request_error(e, "Count failed"))?;
+    
+    if resp.status().is_success() { 
+        let val: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+        target.report_step("count", started);
+        
+        // HQL "::COUNT" expects a scalar number, but aggregate_by returns a full report.
+        // We unwrap { "Count": { "": { "count": N, ... } } } -> N
+        if let Some(count_obj) = val.get("Count").and_then(|c| c.as_object()) {
+            if let Some(empty_group) = count_obj.get("").and_then(|g| g.as_object()) {
+                if let Some(count_val) = empty_group.get("count").filter(|c| c.is_number()) {
+                    return Ok(count_val.clone());
                 }
-                
-                // Fallback: return original if structure doesn't match
-                Ok(val)
-            } else { 
-                Err(format!("Count error: {}", resp.status())) 
             }
         }
+        
+        // Fallback: return original if structure doesn't match
+        Ok(val)
+    } else { 
+        Err(format!("Count error: {}", resp.status())) 
+    }
+}
+
+async fn execute_final_action(target: &McpTarget, conn: &str, action: FinalAction) -> Result<serde_json::Value, String> {
+    match action {
+        FinalAction::Collect { range } => collect_results(target, conn, range).await,
+        FinalAction::Count => count_results(target, conn).await,
+        FinalAction::CountCompare { operator, value } => {
+            let count = count_results(target, conn).await?;
+            let n = count.as_f64()
+                .ok_or_else(|| format!("Unexpected COUNT result: {}", count))?;
+            Ok(serde_json::Value::Bool(compare_f64(operator, n, value)))
+        }
         FinalAction::Aggregate { properties } => {
-            let mut req = client.post(format!("{}/mcp/aggregate_by", url)).json(&serde_json::json!({ "connection_id": conn, "properties": properties, "drop": true }));
-            if let Some(key) = &api_key {
-                req = req.header("x-api-key", key);
+            let started = std::time::Instant::now();
+            let resp = target.post("aggregate_by").json(&serde_json::json!({ "connection_id": conn, "properties": properties, "drop": true }))
+                .send_traced().await
+                .map_err(|e| request_error(e, "Aggregate failed"))?;
+            if !resp.status().is_success() {
+                return Err(format!("Aggregate error: {}", resp.status()));
             }
-            let resp = req.send().await
-                .map_err(|e| format!("Aggregate failed: {}", e))?;
-            if resp.status().is_success() { resp.json().await.map_err(|e| e.to_string()) } else { Err(format!("Aggregate error: {}", resp.status())) }
+            target.report_step("aggregate_by", started);
+            resp.json().await.map_err(|e| e.to_string())
         }
         FinalAction::GroupBy { properties } => {
-            let mut req = client.post(format!("{}/mcp/group_by", url)).json(&serde_json::json!({ "connection_id": conn, "properties": properties, "drop": true }));
-            if let Some(key) = &api_key {
-                req = req.header("x-api-key", key);
+            let started = std::time::Instant::now();
+            let resp = target.post("group_by").json(&serde_json::json!({ "connection_id": conn, "properties": properties, "drop": true }))
+                .send_traced().await
+                .map_err(|e| request_error(e, "GroupBy failed"))?;
+            if !resp.status().is_success() {
+                return Err(format!("GroupBy error: {}", resp.status()));
             }
-            let resp = req.send().await
-                .map_err(|e| format!("GroupBy failed: {}", e))?;
-            if resp.status().is_success() { resp.json().await.map_err(|e| e.to_string()) } else { Err(format!("GroupBy error: {}", resp.status())) }
+            target.report_step("group_by", started);
+            resp.json().await.map_err(|e| e.to_string())
         }
     }
 }
 
-async fn collect_results(client: &reqwest::Client, url: &str, connection_id: &str, range: Option<(usize, Option<usize>)>, api_key: Option<String>) -> Result<serde_json::Value, String> {
+/// Collects a final result within the target's limits. Past `max_rows` only the leading
+/// rows are read and the pipeline is counted for the metrics; rows past `max_bytes`
+/// are dropped once read.
+async fn collect_results(target: &McpTarget, connection_id: &str, range: Option<(usize, Option<usize>)>) -> Result<serde_json::Value, String> {
+    let (start, end) = range.unwrap_or((0, None));
+    let capped_end = target.limits.max_rows.map(|max| start.saturating_add(max)).filter(|capped| end.is_none_or(|end| end > *capped));
+    let Some(capped_end) = capped_end else {
+        let items = collect_with(target, connection_id, range, true).await?;
+        return Ok(target.limit(items));
+    };
+
+    let items = collect_with(target, connection_id, Some((start, Some(capped_end))), false).await?;
+    let total = count_results(target, connection_id).await?.as_u64()
+        .map(|n| (n as usize).min(end.unwrap_or(usize::MAX)).saturating_sub(start));
+    let returned = items.as_array().map_or(0, |rows| rows.len());
+    match total {
+        Some(total) if total > returned => {
+            if let Some(metrics) = &target.metrics {
+                metrics.truncated(Some(total));
+            }
+            // Rows past the byte limit are already counted in the total
+            let mut items = items;
+            target.limits.apply(&mut items);
+            Ok(items)
+        }
+        _ => Ok(target.limit(items)),
+    }
+}
+
+/// Collects everything, for rows the executor filters or joins before returning them.
+async fn collect_all(target: &McpTarget, connection_id: &str, range: Option<(usize, Option<usize>)>) -> Result<serde_json::Value, String> {
+    collect_with(target, connection_id, range, true).await
+}
+
+/// Reads rows `start..end` of a pipeline left open by `open_pipeline`. With `keep_open`
+/// the pipeline survives for the next window.
+pub async fn collect_window(target: &McpTarget, connection_id: &str, start: usize, end: usize, keep_open: bool) -> Result<serde_json::Value, String> {
+    collect_with(target, connection_id, Some((start, Some(end))), !keep_open).await
+}
+
+async fn collect_with(target: &McpTarget, connection_id: &str, range: Option<(usize, Option<usize>)>, drop: bool) -> Result<serde_json::Value, String> {
     let range_json = if let Some((start, end)) = range {
         let e = end.unwrap_or(1_000_000); // Backend requires 'end', fallback to a large limit if None
         serde_json::json!({ "start": start, "end": e })
@@ -238,15 +732,17 @@ async fn collect_results(client: &reqwest::Client, url: &str, connection_id: &st
         serde_json::json!(null)
     };
 
-    let mut req = client.post(format!("{}/mcp/collect", url)).json(&serde_json::json!({ "connection_id": connection_id, "range": range_json, "drop": true }));
-    if let Some(key) = &api_key {
-        req = req.header("x-api-key", key);
-    }
-    let resp = req.send().await
-        .map_err(|e| format!("Collect failed: {}", e))?;
+    let started = std::time::Instant::now();
+    let resp = target.post("collect").json(&serde_json::json!({ "connection_id": connection_id, "range": range_json, "drop": drop }))
+        .send_traced().await
+        .map_err(|e| request_error(e, "Collect failed"))?;
 
-    if resp.status().is_success() { resp.json().await.map_err(|e| format!("Failed to parse results: {}", e)) }
-    else { Err(format!("Query execution error ({}): {}", resp.status(), resp.text().await.unwrap_or_default())) }
+    if !resp.status().is_success() {
+        return Err(response_error(resp, "Query execution error").await);
+    }
+    let items = target.read_results(resp, "Failed to read results").await?;
+    target.report_step("collect", started);
+    Ok(items)
 }
 
 fn filter_by_ids(value: &serde_json::Value, ids: &[String]) -> serde_json::Value {
@@ -263,3 +759,423 @@ fn filter_by_ids(value: &serde_json::Value, ids: &[String]) -> serde_json::Value
     }
 }
 
+pub async fn init_connection(target: &McpTarget) -> Result<String, String> {
+    if let Some(metrics) = &target.metrics {
+        metrics.init_call();
+    }
+    let resp = target.post("init").send_traced().await
+        .map_err(|e| crate::commands::network::map_reqwest_error(e, "Init failed"))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        return Err(format!("Init request failed ({}): {}", status, resp.text().await.unwrap_or_default()));
+    }
+    let body = resp.text().await.map_err(|e| format!("Failed to read init body: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse connection_id from '{}': {}", body, e))
+}
+
+/// A connection from the target's pool, or a fresh unpooled one. Call `release`
+/// after a final action so it can be reused; otherwise it is closed when dropped.
+pub async fn acquire_connection(target: &McpTarget) -> Result<PooledConnection, String> {
+    match &target.pool {
+        Some(pool) => pool.acquire(target).await,
+        None => Ok(PooledConnection::unpooled(init_connection(target).await?, target)),
+    }
+}
+
+pub fn compare_f64(op: Operator, lhs: f64, rhs: f64) -> bool {
+    match op {
+        Operator::Eq => lhs == rhs,
+        Operator::Neq => lhs != rhs,
+        Operator::Gt => lhs > rhs,
+        Operator::Gte => lhs >= rhs,
+        Operator::Lt => lhs < rhs,
+        Operator::Lte => lhs <= rhs,
+    }
+}
+
+/// Runs a plan whose filters cannot be expressed over MCP: the server pipeline is
+/// collected in full, client filters are applied, then RANGE/COUNT are done locally.
+async fn execute_with_client_filters(
+    target: &McpTarget,
+    connection_id: &str,
+    plan: &PipelinePlan,
+) -> Result<serde_json::Value, String> {
+    let mut server_tools = plan.tools.clone();
+    if !plan.id_filters.is_empty() && plan.tools.len() > 1 {
+        if !server_filters_ids(target, &plan.tools[0], &plan.id_filters).await? {
+            return Err("Filtering by ID cannot be combined with client-side steps (DEDUP, COUNT or property-to-property WHERE) on this server".to_string());
+        }
+        // Client filters keep using `plan.tools`: degree counts over the unfiltered
+        // prefix are still looked up per candidate id.
+        server_tools.insert(1, id_filter_tool(&plan.id_filters));
+    }
+
+    for tool in &server_tools {
+        send_tool(target, connection_id, tool).await?;
+    }
+    let mut items = collect_all(target, connection_id, None).await?;
+    if !plan.id_filters.is_empty() {
+        items = filter_by_ids(&items, &plan.id_filters);
+    }
+
+    for cf in &plan.client_filters {
+        items = apply_client_filter(target, &plan.tools, cf, items).await?;
+    }
+
+    finish_locally(items, &plan.final_action).map(|v| target.limit(v))
+}
+
+/// Looks up the node (or vector) label on one side of an edge type in the server schema.
+async fn edge_endpoint_label(target: &McpTarget, conn: &str, edge_label: &str, endpoint: EdgeEndpoint) -> Result<String, String> {
+    let resp = target.post("schema_resource")
+        .json(&serde_json::json!({ "connection_id": conn }))
+        .send_traced().await
+        .map_err(|e| request_error(e, "Schema request failed"))?;
+    if !resp.status().is_success() {
+        return Err(format!("Schema request failed ({}): {}", resp.status(), resp.text().await.unwrap_or_default()));
+    }
+    let val: serde_json::Value = resp.json().await.map_err(|e| format!("Failed to parse schema response: {}", e))?;
+    let schema = match &val {
+        serde_json::Value::String(s) => serde_json::from_str(s).unwrap_or(serde_json::Value::Null),
+        _ => val,
+    };
+
+    let keys = match endpoint {
+        EdgeEndpoint::From => ["from", "from_node"],
+        EdgeEndpoint::To => ["to", "to_node"],
+    };
+    schema.get("edges")
+        .and_then(|e| e.as_array())
+        .and_then(|edges| edges.iter().find(|e| e.get("name").and_then(|n| n.as_str()) == Some(edge_label)))
+        .and_then(|edge| keys.iter().find_map(|k| edge.get(*k).and_then(|v| v.as_str())))
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("Could not find the endpoint label of edge type '{}' in the server schema", edge_label))
+}
+
+/// Runs `E<Type>::...::ToN` style plans: fetch the (filtered, ordered, ranged) edges,
+/// then fetch their endpoints by label and keep only the ones that were reached.
+async fn execute_edge_hop(
+    target: &McpTarget,
+    connection_id: &str,
+    plan: &PipelinePlan,
+    hop: &EdgeHop,
+) -> Result<serde_json::Value, String> {
+    for tool in &hop.edge_tools {
+        send_tool(target, connection_id, tool).await?;
+    }
+    // With ID filters the range has to be applied after filtering
+    let edges = if hop.edge_ids.is_empty() {
+        collect_all(target, connection_id, hop.edge_range).await?
+    } else {
+        let all = collect_all(target, connection_id, None).await?;
+        finish_locally(filter_by_ids(&all, &hop.edge_ids), &FinalAction::Collect { range: hop.edge_range })?
+    };
+
+    let key = match hop.endpoint {
+        EdgeEndpoint::From => "from_node",
+        EdgeEndpoint::To => "to_node",
+    };
+    let endpoint_ids: Vec<String> = edges.as_array().map(|a| a.as_slice()).unwrap_or(&[]).iter()
+        .filter_map(|e| e.get(key).or_else(|| e.get(key.trim_end_matches("_node"))).and_then(|v| v.as_str()))
+        .map(|s| s.to_string())
+        .collect();
+    if endpoint_ids.is_empty() {
+        return finish_locally(serde_json::Value::Array(vec![]), &plan.final_action);
+    }
+
+    let conn = acquire_connection(target).await?;
+    let label = edge_endpoint_label(target, &conn, &hop.edge_label, hop.endpoint).await?;
+    let start = match hop.target_type {
+        EdgeType::Node => ToolArgs::NFromType { node_type: label },
+        EdgeType::Vec => ToolArgs::VFromType { vector_type: label, filter: None },
+    };
+    send_tool(target, &conn, &start).await?;
+    for tool in &plan.tools {
+        send_tool(target, &conn, tool).await?;
+    }
+    let candidates = collect_all(target, &conn, None).await?;
+    conn.release();
+    let candidates = candidates.as_array().cloned().unwrap_or_default();
+
+    // Without an ORDER after the hop, results follow edge order (one per edge, as HelixDB does);
+    // with one, the server's node order wins.
+    let ordered = plan.tools.iter().any(|t| matches!(t, ToolArgs::OrderBy { .. }));
+    let items: Vec<serde_json::Value> = if ordered {
+        let wanted: std::collections::HashSet<&str> = endpoint_ids.iter().map(|s| s.as_str()).collect();
+        candidates.into_iter()
+            .filter(|c| c.get("id").and_then(|v| v.as_str()).map(|id| wanted.contains(id)).unwrap_or(false))
+            .collect()
+    } else {
+        let by_id: HashMap<&str, &serde_json::Value> = candidates.iter()
+            .filter_map(|c| c.get("id").and_then(|v| v.as_str()).map(|id| (id, c)))
+            .collect();
+        endpoint_ids.iter().filter_map(|id| by_id.get(id.as_str()).map(|c| (*c).clone())).collect()
+    };
+
+    let mut items = serde_json::Value::Array(items);
+    let mut full_tools = vec![start];
+    full_tools.extend(plan.tools.iter().cloned());
+    for cf in &plan.client_filters {
+        items = apply_client_filter(target, &full_tools, cf, items).await?;
+    }
+    finish_locally(items, &plan.final_action).map(|v| target.limit(v))
+}
+
+async fn apply_client_filter(
+    target: &McpTarget,
+    tools: &[ToolArgs],
+    filter: &ClientFilter,
+    items: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    match filter {
+        ClientFilter::EdgeCount { prefix_len, direction, edge_label, operator, value } => {
+            // Degrees are computed in one batch: fetch the edges leaving (or entering)
+            // the candidate set and group them by their endpoint on the candidate side.
+            let conn = acquire_connection(target).await?;
+            for tool in &tools[..*prefix_len] {
+                send_tool(target, &conn, tool).await?;
+            }
+            let (edge_step, endpoint_key) = match direction {
+                EdgeDirection::Out => (ToolArgs::OutEStep { edge_label: edge_label.clone(), filter: None }, "from_node"),
+                EdgeDirection::In => (ToolArgs::InEStep { edge_label: edge_label.clone(), filter: None }, "to_node"),
+            };
+            send_tool(target, &conn, &edge_step).await?;
+            let edges = collect_all(target, &conn, None).await?;
+            conn.release();
+
+            let mut degrees: HashMap<String, usize> = HashMap::new();
+            for edge in edges.as_array().map(|a| a.as_slice()).unwrap_or(&[]) {
+                let endpoint = edge.get(endpoint_key)
+                    .or_else(|| edge.get(endpoint_key.trim_end_matches("_node")))
+                    .and_then(|v| v.as_str());
+                if let Some(id) = endpoint {
+                    *degrees.entry(id.to_string()).or_insert(0) += 1;
+                }
+            }
+
+            Ok(retain_items(items, |item| {
+                let degree = item.get("id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|id| degrees.get(id).copied())
+                    .unwrap_or(0);
+                compare_f64(*operator, degree as f64, *value)
+            }))
+        }
+        ClientFilter::TraversalCount { tools: sub_tools, operator, value, .. } => {
+            let candidates = items.as_array().cloned().unwrap_or_default();
+            if candidates.len() > MAX_COUNT_CANDIDATES {
+                return Err(format!(
+                    "COUNT inside WHERE runs one sub-query per item; narrow the traversal to at most {} items first (got {})",
+                    MAX_COUNT_CANDIDATES, candidates.len()
+                ));
+            }
+            let starts = candidates.iter().map(candidate_start).collect::<Result<Vec<_>, _>>()?;
+            // Each sub-query starts from one item picked by id, so a server that ignores
+            // the id filter would count from the whole label
+            let mut probed: Vec<&ToolArgs> = Vec::new();
+            for (start, id) in &starts {
+                if probed.contains(&start) {
+                    continue;
+                }
+                if !server_filters_ids(target, start, std::slice::from_ref(id)).await? {
+                    return Err("COUNT inside WHERE needs a server that filters by id; this one does not".to_string());
+                }
+                probed.push(start);
+            }
+
+            // Per-candidate sub-queries would flood the progress events. Each takes a free
+            // slot, or waits its turn on the slot of the connection being filtered.
+            let quiet = McpTarget { progress: None, ..target.clone() };
+            let counts: Vec<f64> = futures::stream::iter(starts.iter().map(|(start, id)| {
+                let tools: Vec<ToolArgs> = [start.clone(), id_filter_tool(std::slice::from_ref(id))].into_iter()
+                    .chain(sub_tools.iter().cloned())
+                    .collect();
+                let quiet = &quiet;
+                async move {
+                    let conn = acquire_connection(quiet).await?;
+                    for tool in &tools {
+                        send_tool(quiet, &conn, tool).await?;
+                    }
+                    let count = count_results(quiet, &conn).await?;
+                    conn.release();
+                    count.as_f64().ok_or_else(|| format!("The server returned no count for item {}", id))
+                }
+            }))
+            .buffered(COUNT_CONCURRENCY)
+            .try_collect()
+            .await?;
+            Ok(serde_json::Value::Array(candidates.into_iter().zip(counts)
+                .filter(|(_, count)| compare_f64(*operator, *count, *value))
+                .map(|(item, _)| item)
+                .collect()))
+        }
+        ClientFilter::PropertyCompare { left, operator, right, .. } => {
+            Ok(retain_items(items, |item| {
+                match (item_property(item, left), item_property(item, right)) {
+                    (Some(l), Some(r)) => compare_json(*operator, l, r),
+                    _ => false,
+                }
+            }))
+        }
+        ClientFilter::Dedup { .. } => {
+            // Items without an id (e.g. scalar projections) are never considered duplicates.
+            let mut seen = std::collections::HashSet::new();
+            Ok(match items {
+                serde_json::Value::Array(arr) => serde_json::Value::Array(arr.into_iter()
+                    .filter(|item| match item.get("id").and_then(|v| v.as_str()) {
+                        Some(id) => seen.insert(id.to_string()),
+                        None => true,
+                    })
+                    .collect()),
+                other => other,
+            })
+        }
+    }
+}
+
+/// The start step of `item`'s label, as a node, edge or vector, and its id; the id
+/// filter narrows the start to the item.
+fn candidate_start(item: &serde_json::Value) -> Result<(ToolArgs, String), String> {
+    let map = item.as_object().ok_or_else(|| "COUNT inside WHERE needs items, not values".to_string())?;
+    let id = map.get("id").and_then(|v| v.as_str())
+        .ok_or_else(|| "COUNT inside WHERE needs items with an id".to_string())?;
+    let label = map.get("label").and_then(|v| v.as_str())
+        .ok_or_else(|| "COUNT inside WHERE needs items with a label".to_string())?
+        .to_string();
+    let start = if map.contains_key("from_node") || map.contains_key("to_node") {
+        ToolArgs::EFromType { edge_type: label }
+    } else if vector_embedding_key(map).is_some() {
+        ToolArgs::VFromType { vector_type: label, filter: None }
+    } else {
+        ToolArgs::NFromType { node_type: label }
+    };
+    Ok((start, id.to_string()))
+}
+
+/// Reads a property from a raw collected item, which nests user properties
+/// under `properties` (see `translator::normalize_value`).
+fn item_property<'a>(item: &'a serde_json::Value, key: &str) -> Option<&'a serde_json::Value> {
+    item.get("properties")
+        .and_then(|p| p.get(key))
+        .or_else(|| item.get(key))
+        .filter(|v| !v.is_null())
+}
+
+/// Numbers compare numerically, dates (RFC 3339 or `YYYY-MM-DD`) chronologically
+/// regardless of offset, other strings lexically; mixed types are only ever unequal.
+pub(crate) fn compare_json(op: Operator, lhs: &serde_json::Value, rhs: &serde_json::Value) -> bool {
+    if let (Some(l), Some(r)) = (lhs.as_f64(), rhs.as_f64()) {
+        return compare_f64(op, l, r);
+    }
+    let ordering = match (lhs, rhs) {
+        (serde_json::Value::String(l), serde_json::Value::String(r)) => match (parse_date(l), parse_date(r)) {
+            (Some(l), Some(r)) => Some(l.cmp(&r)),
+            _ => Some(l.cmp(r)),
+        },
+        (serde_json::Value::Bool(l), serde_json::Value::Bool(r)) => Some(l.cmp(r)),
+        _ => None,
+    };
+    match (op, ordering) {
+        (Operator::Eq, Some(o)) => o.is_eq(),
+        (Operator::Neq, Some(o)) => o.is_ne(),
+        (Operator::Gt, Some(o)) => o.is_gt(),
+        (Operator::Gte, Some(o)) => o.is_ge(),
+        (Operator::Lt, Some(o)) => o.is_lt(),
+        (Operator::Lte, Some(o)) => o.is_le(),
+        (Operator::Eq, None) => lhs == rhs,
+        (Operator::Neq, None) => lhs != rhs,
+        (_, None) => false,
+    }
+}
+
+fn retain_items<F>(items: serde_json::Value, keep: F) -> serde_json::Value
+where
+    F: Fn(&serde_json::Value) -> bool,
+{
+    match items {
+        serde_json::Value::Array(arr) => serde_json::Value::Array(arr.into_iter().filter(|item| keep(item)).collect()),
+        other => other,
+    }
+}
+
+fn finish_locally(items: serde_json::Value, action: &FinalAction) -> Result<serde_json::Value, String> {
+    let arr = match items {
+        serde_json::Value::Array(arr) => arr,
+        other => return Ok(other),
+    };
+    match action {
+        FinalAction::Collect { range: None } => Ok(serde_json::Value::Array(arr)),
+        FinalAction::Collect { range: Some((start, end)) } => {
+            let end = end.unwrap_or(arr.len()).min(arr.len());
+            let start = (*start).min(end);
+            Ok(serde_json::Value::Array(arr[start..end].to_vec()))
+        }
+        FinalAction::Count => Ok(serde_json::json!(arr.len())),
+        FinalAction::CountCompare { operator, value } => {
+            Ok(serde_json::Value::Bool(compare_f64(*operator, arr.len() as f64, *value)))
+        }
+        FinalAction::Aggregate { .. } | FinalAction::GroupBy { .. } => {
+            Err("AGGREGATE_BY/GROUP_BY cannot be combined with client-side steps (DEDUP, COUNT or property-to-property WHERE)".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_is_stale_response() {
+        assert!(is_stale_response(StatusCode::BAD_REQUEST, "Connection not found"));
+        assert!(is_stale_response(StatusCode::NOT_FOUND, "unknown connection_id 'abc'"));
+        assert!(!is_stale_response(StatusCode::BAD_REQUEST, "Invalid tool arguments"));
+        assert!(!is_stale_response(StatusCode::INTERNAL_SERVER_ERROR, "connection not found"));
+        assert!(is_stale_error(&format!("Tool call error (400 Bad Request): {} (Connection not found)", STALE)));
+    }
+
+    #[test]
+    fn test_candidate_start() {
+        let start = |item: serde_json::Value| candidate_start(&item).map(|(start, _)| start);
+        assert_eq!(start(serde_json::json!({ "id": "1", "label": "User", "properties": {} })),
+            Ok(ToolArgs::NFromType { node_type: "User".to_string() }));
+        assert_eq!(start(serde_json::json!({ "id": "2", "label": "Follows", "from_node": "1", "to_node": "3" })),
+            Ok(ToolArgs::EFromType { edge_type: "Follows".to_string() }));
+        assert_eq!(start(serde_json::json!({ "id": "4", "label": "Doc", "data": [0.1, 0.2] })),
+            Ok(ToolArgs::VFromType { vector_type: "Doc".to_string(), filter: None }));
+        assert!(start(serde_json::json!({ "id": "5" })).is_err());
+        assert!(start(serde_json::json!(3)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_id_filter_support_checked_once() {
+        let gateway = crate::test_support::FakeGateway::start(|path| match path {
+            "/mcp/init" => (200, "\"conn-1\"".to_string()),
+            "/mcp/collect" => (200, r#"[{ "id": "a", "label": "User" }]"#.to_string()),
+            _ => (200, "null".to_string()),
+        }).await;
+        let target = McpTarget::new(reqwest::Client::new(), &gateway.url, Default::default(), TranslationProfile::current())
+            .with_capabilities(Default::default());
+        let start = ToolArgs::NFromType { node_type: "User".to_string() };
+        // The server answers with an item that was not asked for: it ignores the filter
+        // Closes happen in the background, so only the probe's own calls are compared
+        let probes = || gateway.requests().into_iter().filter(|p| p != "/mcp/close").count();
+        assert!(!server_filters_ids(&target, &start, &["b".to_string()]).await.unwrap());
+        let probed = probes();
+        assert!(!server_filters_ids(&target, &start, &["c".to_string()]).await.unwrap());
+        assert_eq!(probes(), probed);
+        assert_eq!(target.filters_ids(), Some(false));
+    }
+
+    #[test]
+    fn test_only_requested_ids() {
+        let ids = vec!["a".to_string(), "b".to_string()];
+        assert!(only_requested_ids(&serde_json::json!([{ "id": "b" }]), &ids));
+        assert!(only_requested_ids(&serde_json::json!([{ "id": "a" }, { "id": "b" }]), &ids));
+        // As many rows as ids, but the server ignored the filter
+        assert!(!only_requested_ids(&serde_json::json!([{ "id": "a" }, { "id": "z" }]), &ids));
+        assert!(!only_requested_ids(&serde_json::json!([{ "id": "a" }, { "name": "no id" }]), &ids));
+        assert!(!only_requested_ids(&serde_json::json!([]), &ids));
+        assert!(!only_requested_ids(&serde_json::json!([{ "id": "a" }, { "id": "b" }, { "id": "a" }]), &ids));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::hql::tool_args::{FilterTraversal, ToolArgs};
+use crate::hql::translator::{map_traversal_to_tools, PipelinePlan, SearchCall};
+use helix_db::helixc::parser::types::Traversal;
+
+/// How one returned variable would be fetched.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VariablePlan {
+    pub name: String,
+    /// A standalone search, as sent (oversampled when it has a PREFILTER).
+    pub search: Option<ToolArgs>,
+    /// Applied on the connection after the search returns.
+    pub prefilter: Option<FilterTraversal>,
+    /// The pipeline, with any search it starts from as its first tool.
+    pub plan: Option<PipelinePlan>,
+    /// Why the variable cannot be translated; running the query fails with this.
+    pub error: Option<String>,
+}
+
+/// What `execute_dynamic_hql` would do with a query, worked out without a server.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExplanation {
+    pub query_name: String,
+    /// Whether the compiled endpoint `/<query_name>` is tried first. When it answers,
+    /// the plans below are not used.
+    pub compiled_fast_path: bool,
+    /// Parameters after literal assignments and environment references are applied.
+    pub params: serde_json::Value,
+    pub variables: Vec<VariablePlan>,
+    /// RETURN entries computed from the fetched variables.
+    pub computed_returns: Vec<String>,
+    /// RETURN aliases and the variable each one is read from.
+    pub aliases: Vec<(String, String)>,
+}
+
+/// Mirrors `executor::execute_variable`: a traversal runs as one pipeline after its
+/// search, a bare search runs on its own.
+pub fn explain_variable(name: &str, search: Option<&SearchCall>, traversal: Option<&Traversal>, params: &serde_json::Value) -> VariablePlan {
+    let mut explained = VariablePlan { name: name.to_string(), search: None, prefilter: None, plan: None, error: None };
+    match (traversal, search) {
+        (Some(t), _) => match map_traversal_to_tools(t, params) {
+            Ok(mut plan) => {
+                if let Some(search) = search {
+                    if search.prefilter.is_some() {
+                        explained.error = Some("PREFILTER searches cannot be followed by traversal steps yet; RETURN the search results directly".to_string());
+                    }
+                    plan.tools.insert(0, search.tool.clone());
+                }
+                explained.plan = Some(plan);
+            }
+            Err(e) => explained.error = Some(e),
+        },
+        (None, Some(s)) => {
+            explained.search = Some(s.oversampled_tool());
+            explained.prefilter = s.prefilter.clone();
+        }
+        (None, None) => {}
+    }
+    explained
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hql::translator::FinalAction;
+    use helix_db::helixc::parser::types::{ExpressionType, StatementType};
+    use helix_db::helixc::parser::{write_to_temp_file, HelixParser};
+
+    #[test]
+    fn test_explain_traversal() {
+        let content = write_to_temp_file(vec!["QUERY T() =>\n    users <- N<User>::RANGE(0, 5)\n    RETURN users"]);
+        let source = HelixParser::parse_source(&content).unwrap();
+        let StatementType::Assignment(assign) = &source.queries[0].statements[0].statement else {
+            panic!("expected an assignment");
+        };
+        let ExpressionType::Traversal(traversal) = &assign.value.expr else {
+            panic!("expected a traversal");
+        };
+        let explained = explain_variable("users", None, Some(traversal), &serde_json::json!({}));
+        assert!(explained.error.is_none());
+        let plan = explained.plan.unwrap();
+        assert!(!plan.tools.is_empty());
+        assert!(matches!(plan.final_action, FinalAction::Collect { range: Some(_) }));
+    }
+}
@@ -0,0 +1,86 @@
+/// Results larger than this are cut unless the connection sets `maxResultBytes`
+/// (0 turns the limit off). Everything returned is serialized through IPC at once.
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Caps on what one collect may return, from the connection's `maxResultRows` and
+/// `maxResultBytes`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResultLimits {
+    pub max_rows: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+impl Default for ResultLimits {
+    fn default() -> Self {
+        Self { max_rows: None, max_bytes: Some(DEFAULT_MAX_RESULT_BYTES) }
+    }
+}
+
+impl ResultLimits {
+    /// No limits, for reads the executor filters further before anything is returned.
+    pub const NONE: ResultLimits = ResultLimits { max_rows: None, max_bytes: None };
+
+    pub fn from_connection(connection: Option<&serde_json::Value>) -> Self {
+        let field = |k: &str| connection.and_then(|c| c.get(k)?.as_u64()).map(|n| n as usize);
+        Self {
+            max_rows: field("maxResultRows").filter(|n| *n > 0),
+            max_bytes: match field("maxResultBytes") {
+                Some(0) => None,
+                Some(n) => Some(n),
+                None => Some(DEFAULT_MAX_RESULT_BYTES),
+            },
+        }
+    }
+
+    pub fn for_connection(app: &tauri::AppHandle, url: &str) -> Self {
+        Self::from_connection(crate::policy::find_connection(app, Some(url)).as_ref())
+    }
+
+    /// Keeps the leading rows of an array result that fit both limits. Returns the
+    /// rows dropped, or 0 when the result fit (or is not an array).
+    pub fn apply(&self, value: &mut serde_json::Value) -> usize {
+        let serde_json::Value::Array(rows) = value else {
+            return 0;
+        };
+        let mut keep = self.max_rows.map_or(rows.len(), |max| rows.len().min(max));
+        if let Some(max_bytes) = self.max_bytes {
+            // Brackets plus a comma per row
+            let mut bytes = 2;
+            for (i, row) in rows.iter().take(keep).enumerate() {
+                bytes += serde_json::to_vec(row).map_or(0, |b| b.len()) + 1;
+                if bytes > max_bytes {
+                    keep = i;
+                    break;
+                }
+            }
+        }
+        let dropped = rows.len() - keep;
+        rows.truncate(keep);
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_limits() {
+        let rows = || serde_json::json!([{ "n": 1 }, { "n": 2 }, { "n": 3 }, { "n": 4 }]);
+        let mut value = rows();
+        assert_eq!(ResultLimits { max_rows: Some(3), max_bytes: None }.apply(&mut value), 1);
+        assert_eq!(value.as_array().unwrap().len(), 3);
+
+        // Each row is 7 bytes plus its comma
+        let mut value = rows();
+        assert_eq!(ResultLimits { max_rows: None, max_bytes: Some(20) }.apply(&mut value), 2);
+        assert_eq!(value, serde_json::json!([{ "n": 1 }, { "n": 2 }]));
+
+        let mut count = serde_json::json!(12);
+        assert_eq!(ResultLimits { max_rows: Some(1), max_bytes: Some(1) }.apply(&mut count), 0);
+
+        let connection = serde_json::json!({ "maxResultRows": 500, "maxResultBytes": 0 });
+        assert_eq!(ResultLimits::from_connection(Some(&connection)), ResultLimits { max_rows: Some(500), max_bytes: None });
+        assert_eq!(ResultLimits::from_connection(None), ResultLimits::default());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+use crate::hql::cookbook::CookbookSchema;
+use crate::hql::tool_args::{EdgeType, FilterTraversal, Operator, ToolArgs};
+use crate::hql::translator::PipelinePlan;
+
+/// How often one property of one label was filtered on.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Usage {
+    equality: usize,
+    range: usize,
+}
+
+/// Filters seen across recorded queries, keyed by (label, property).
+#[derive(Default)]
+pub struct FilterUsage {
+    usage: HashMap<(String, String), Usage>,
+}
+
+/// A suggested `INDEX` annotation and the evidence for it.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSuggestion {
+    pub label: String,
+    pub property: String,
+    /// The schema line to add, e.g. `INDEX email: String`.
+    pub annotation: String,
+    pub equality_filters: usize,
+    pub range_filters: usize,
+    pub label_size: Option<u64>,
+    /// Items scanned by the observed equality filters that an index lookup would skip.
+    pub estimated_rows_saved: Option<u64>,
+}
+
+impl FilterUsage {
+    fn add_filter(&mut self, label: Option<&str>, filter: &FilterTraversal, schema: Option<&CookbookSchema>) {
+        let Some(label) = label else {
+            return;
+        };
+        for property in filter.properties.iter().flatten().flatten() {
+            if property.key == "id" {
+                continue;
+            }
+            let usage = self.usage.entry((label.to_string(), property.key.clone())).or_default();
+            match property.operator {
+                None | Some(Operator::Eq) => usage.equality += 1,
+                Some(Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte) => usage.range += 1,
+                Some(Operator::Neq) => {}
+            }
+        }
+        if let Some(tools) = &filter.filter_traversals {
+            self.observe_tools(tools, Some(label), schema);
+        }
+    }
+
+    /// Walks `tools`, tracking which label the items have at each step. Without a schema
+    /// the label after an `Out`/`In` hop is unknown and its filters are not counted.
+    fn observe_tools(&mut self, tools: &[ToolArgs], start: Option<&str>, schema: Option<&CookbookSchema>) {
+        let endpoint = |edge_label: &str, outgoing: bool| -> Option<String> {
+            let edge = schema?.edges.iter().find(|e| e.name == edge_label)?;
+            if outgoing { edge.to.clone() } else { edge.from.clone() }
+        };
+        let mut label: Option<String> = start.map(String::from);
+        for tool in tools {
+            match tool {
+                ToolArgs::NFromType { node_type } => label = Some(node_type.clone()),
+                ToolArgs::EFromType { edge_type } => label = Some(edge_type.clone()),
+                ToolArgs::VFromType { vector_type, filter } => {
+                    label = Some(vector_type.clone());
+                    if let Some(f) = filter {
+                        self.add_filter(label.as_deref(), f, schema);
+                    }
+                }
+                ToolArgs::OutStep { edge_label, edge_type, filter } | ToolArgs::InStep { edge_label, edge_type, filter } => {
+                    let outgoing = matches!(tool, ToolArgs::OutStep { .. });
+                    label = match edge_type {
+                        EdgeType::Node => endpoint(edge_label, outgoing),
+                        EdgeType::Vec => None,
+                    };
+                    if let Some(f) = filter {
+                        self.add_filter(label.as_deref(), f, schema);
+                    }
+                }
+                ToolArgs::OutEStep { edge_label, filter } | ToolArgs::InEStep { edge_label, filter } => {
+                    label = Some(edge_label.clone());
+                    if let Some(f) = filter {
+                        self.add_filter(label.as_deref(), f, schema);
+                    }
+                }
+                ToolArgs::FilterItems { filter } => self.add_filter(label.as_deref(), filter, schema),
+                ToolArgs::SearchKeyword { label: l, .. } | ToolArgs::SearchVecText { label: l, .. } | ToolArgs::SearchVec { label: l, .. } => {
+                    label = Some(l.clone());
+                }
+                ToolArgs::OrderBy { .. } => {}
+            }
+        }
+    }
+
+    pub fn observe(&mut self, plan: &PipelinePlan, schema: Option<&CookbookSchema>) {
+        if let Some(hop) = &plan.edge_hop {
+            self.observe_tools(&hop.edge_tools, Some(&hop.edge_label), schema);
+        }
+        self.observe_tools(&plan.tools, None, schema);
+    }
+
+    /// Labels with at least one property filtered by equality.
+    pub fn candidate_labels(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.usage.iter()
+            .filter(|(_, u)| u.equality > 0)
+            .map(|((label, _), _)| label.clone())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        labels.sort();
+        labels
+    }
+
+    /// Properties filtered by equality that are not indexed yet, most beneficial first.
+    /// Range filters are reported but do not earn a suggestion on their own.
+    pub fn suggest(&self, label_sizes: &HashMap<String, u64>, indexed: &HashSet<(String, String)>, schema: Option<&CookbookSchema>) -> Vec<IndexSuggestion> {
+        let property_type = |label: &str, property: &str| -> Option<String> {
+            let schema = schema?;
+            let item = schema.nodes.iter().chain(&schema.edges).chain(&schema.vectors).find(|i| i.name == label)?;
+            item.properties.get(property)?.as_str().map(String::from)
+        };
+        let mut suggestions: Vec<IndexSuggestion> = self.usage.iter()
+            .filter(|((label, property), usage)| usage.equality > 0 && !indexed.contains(&(label.clone(), property.clone())))
+            .map(|((label, property), usage)| {
+                let label_size = label_sizes.get(label).copied();
+                IndexSuggestion {
+                    label: label.clone(),
+                    property: property.clone(),
+                    annotation: match property_type(label, property) {
+                        Some(ty) => format!("INDEX {}: {}", property, ty),
+                        None => format!("INDEX {}", property),
+                    },
+                    equality_filters: usage.equality,
+                    range_filters: usage.range,
+                    label_size,
+                    estimated_rows_saved: label_size.map(|n| n.saturating_sub(1) * usage.equality as u64),
+                }
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.estimated_rows_saved.unwrap_or(0).cmp(&a.estimated_rows_saved.unwrap_or(0))
+            .then(b.equality_filters.cmp(&a.equality_filters))
+            .then_with(|| (&a.label, &a.property).cmp(&(&b.label, &b.property))));
+        suggestions
+    }
+}
+
+/// (label, property) pairs already marked `INDEX` in a schema.hx.
+pub fn indexed_fields(schema_hx: &str) -> HashSet<(String, String)> {
+    let block = regex::Regex::new(r"(?s)\b[NEV]::(\w+)\s*\{(.*?)\}").unwrap();
+    let index = regex::Regex::new(r"\bINDEX\s+(\w+)").unwrap();
+    let mut out = HashSet::new();
+    for caps in block.captures_iter(schema_hx) {
+        for field in index.captures_iter(&caps[2]) {
+            out.insert((caps[1].to_string(), field[1].to_string()));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hql::cookbook::CookbookItem;
+    use crate::hql::tool_args::FilterProperties;
+
+    fn filter(key: &str, operator: Operator) -> FilterTraversal {
+        FilterTraversal {
+            properties: Some(vec![vec![FilterProperties { key: key.to_string(), value: helix_db::protocol::value::Value::I64(1), operator: Some(operator) }]]),
+            filter_traversals: None,
+        }
+    }
+
+    #[test]
+    fn test_suggest_from_equality_filters() {
+        let schema = CookbookSchema {
+            nodes: vec![CookbookItem { name: "User".to_string(), properties: serde_json::json!({ "email": "String" }).as_object().unwrap().clone(), ..Default::default() }],
+            edges: vec![CookbookItem { name: "Follows".to_string(), from: Some("User".to_string()), to: Some("User".to_string()), ..Default::default() }],
+            vectors: vec![],
+        };
+        let plan = PipelinePlan {
+            tools: vec![
+                ToolArgs::NFromType { node_type: "User".to_string() },
+                ToolArgs::FilterItems { filter: filter("email", Operator::Eq) },
+                ToolArgs::OutStep { edge_label: "Follows".to_string(), edge_type: EdgeType::Node, filter: Some(filter("age", Operator::Gt)) },
+                ToolArgs::FilterItems { filter: filter("handle", Operator::Eq) },
+            ],
+            final_action: crate::hql::translator::FinalAction::Count,
+            id_filters: vec![],
+            client_filters: vec![],
+            edge_hop: None,
+        };
+        let mut usage = FilterUsage::default();
+        usage.observe(&plan, Some(&schema));
+        usage.observe(&plan, Some(&schema));
+
+        let sizes = HashMap::from([("User".to_string(), 1000)]);
+        let indexed = indexed_fields("N::User {\n    INDEX handle: String,\n    email: String,\n}\n");
+        let suggestions = usage.suggest(&sizes, &indexed, Some(&schema));
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].annotation, "INDEX email: String");
+        assert_eq!((suggestions[0].equality_filters, suggestions[0].estimated_rows_saved), (2, Some(1998)));
+        assert_eq!(usage.candidate_labels(), vec!["User".to_string()]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Client-side evaluation of math function calls in RETURN (e.g. `MUL(count, 2)`,
+//! `AVG(ages)`) over variables that have already been fetched.
+
+use helix_db::helixc::parser::types::{Expression, ExpressionType, MathFunction};
+
+/// Variables referenced by an expression, so they can be fetched before evaluation.
+pub fn referenced_identifiers(expr: &Expression, out: &mut Vec<String>) {
+    match &expr.expr {
+        ExpressionType::Identifier(id) => {
+            if !out.contains(id) {
+                out.push(id.clone());
+            }
+        }
+        ExpressionType::MathFunctionCall(call) => {
+            for arg in &call.args {
+                referenced_identifiers(arg, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A readable result key for an un-aliased computed return, e.g. `MUL(count, 2)`.
+pub fn describe(expr: &Expression) -> String {
+    match &expr.expr {
+        ExpressionType::MathFunctionCall(call) => {
+            let args: Vec<String> = call.args.iter().map(describe).collect();
+            format!("{}({})", function_name(&call.function), args.join(", "))
+        }
+        ExpressionType::Identifier(id) => id.clone(),
+        ExpressionType::IntegerLiteral(i) => i.to_string(),
+        ExpressionType::FloatLiteral(f) => f.to_string(),
+        _ => "?".to_string(),
+    }
+}
+
+/// The name a function is written with in HQL, for result keys and messages.
+fn function_name(function: &MathFunction) -> String {
+    let name = match function {
+        MathFunction::Add => "ADD",
+        MathFunction::Sub => "SUB",
+        MathFunction::Mul => "MUL",
+        MathFunction::Div => "DIV",
+        MathFunction::Pow => "POW",
+        MathFunction::Mod => "MOD",
+        MathFunction::Atan2 => "ATAN2",
+        MathFunction::Log => "LOG",
+        MathFunction::Abs => "ABS",
+        MathFunction::Sqrt => "SQRT",
+        MathFunction::Ln => "LN",
+        MathFunction::Log10 => "LOG10",
+        MathFunction::Exp => "EXP",
+        MathFunction::Ceil => "CEIL",
+        MathFunction::Floor => "FLOOR",
+        MathFunction::Round => "ROUND",
+        MathFunction::Sin => "SIN",
+        MathFunction::Cos => "COS",
+        MathFunction::Tan => "TAN",
+        MathFunction::Asin => "ASIN",
+        MathFunction::Acos => "ACOS",
+        MathFunction::Atan => "ATAN",
+        MathFunction::Pi => "PI",
+        MathFunction::E => "E",
+        MathFunction::Sum => "SUM",
+        MathFunction::Count => "COUNT",
+        MathFunction::Avg => "AVG",
+        MathFunction::Min => "MIN",
+        MathFunction::Max => "MAX",
+        // Functions added to the parser after this list
+        #[allow(unreachable_patterns)]
+        other => return format!("{:?}", other).to_uppercase(),
+    };
+    name.to_string()
+}
+
+enum Operand {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+/// Reads a number out of a collected item: plain numbers, or objects projected
+/// down to a single numeric property (`users::{age}`).
+fn item_to_f64(item: &serde_json::Value) -> Option<f64> {
+    match item {
+        serde_json::Value::Object(map) => {
+            let mut numbers = map.iter()
+                .filter(|(k, _)| k.as_str() != "id")
+                .filter_map(|(_, v)| v.as_f64());
+            match (numbers.next(), numbers.next()) {
+                (Some(n), None) => Some(n),
+                _ => None,
+            }
+        }
+        other => other.as_f64(),
+    }
+}
+
+fn value_to_operand(name: &str, value: &serde_json::Value) -> Result<Operand, String> {
+    match value {
+        serde_json::Value::Array(items) => items.iter()
+            .map(|item| item_to_f64(item).ok_or_else(|| format!("'{}' contains non-numeric values", name)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Operand::List),
+        serde_json::Value::String(s) => s.parse::<f64>().map(Operand::Scalar)
+            .map_err(|_| format!("'{}' is not a number", name)),
+        other => item_to_f64(other).map(Operand::Scalar)
+            .ok_or_else(|| format!("'{}' is not a number", name)),
+    }
+}
+
+fn evaluate_operand(expr: &Expression, vars: &serde_json::Map<String, serde_json::Value>, params: &serde_json::Value) -> Result<Operand, String> {
+    match &expr.expr {
+        ExpressionType::IntegerLiteral(i) => Ok(Operand::Scalar(*i as f64)),
+        ExpressionType::FloatLiteral(f) => Ok(Operand::Scalar(*f)),
+        ExpressionType::Identifier(id) => {
+            let value = vars.get(id)
+                .or_else(|| params.get(id))
+                .ok_or_else(|| format!("Unknown variable '{}' in RETURN expression", id))?;
+            value_to_operand(id, value)
+        }
+        ExpressionType::MathFunctionCall(call) => {
+            let args = call.args.iter()
+                .map(|a| evaluate_operand(a, vars, params))
+                .collect::<Result<Vec<_>, _>>()?;
+            apply(&call.function, args)
+        }
+        _ => Err(format!("Unsupported expression in RETURN: {}", describe(expr))),
+    }
+}
+
+fn scalar(name: &str, op: &Operand) -> Result<f64, String> {
+    match op {
+        Operand::Scalar(n) => Ok(*n),
+        Operand::List(_) => Err(format!("{} expects numbers, got a list (use SUM/AVG/MIN/MAX/COUNT to reduce it)", name)),
+    }
+}
+
+/// Flattens aggregate arguments so `MAX(a, b)` and `MAX(list)` both work.
+fn flatten(args: Vec<Operand>) -> Vec<f64> {
+    args.into_iter()
+        .flat_map(|a| match a {
+            Operand::Scalar(n) => vec![n],
+            Operand::List(l) => l,
+        })
+        .collect()
+}
+
+fn apply(function: &MathFunction, args: Vec<Operand>) -> Result<Operand, String> {
+    let name = &function_name(function);
+    let unary = |f: fn(f64) -> f64| -> Result<Operand, String> {
+        match args.as_slice() {
+            [a] => Ok(Operand::Scalar(f(scalar(name, a)?))),
+            _ => Err(format!("{} expects 1 argument", name)),
+        }
+    };
+    let binary = |f: fn(f64, f64) -> f64| -> Result<Operand, String> {
+        match args.as_slice() {
+            [a, b] => Ok(Operand::Scalar(f(scalar(name, a)?, scalar(name, b)?))),
+            _ => Err(format!("{} expects 2 arguments", name)),
+        }
+    };
+
+    match function {
+        MathFunction::Add => binary(|a, b| a + b),
+        MathFunction::Sub => binary(|a, b| a - b),
+        MathFunction::Mul => binary(|a, b| a * b),
+        MathFunction::Div => match args.as_slice() {
+            [a, b] => {
+                let d = scalar(name, b)?;
+                if d == 0.0 {
+                    return Err("DIV by zero in RETURN expression".to_string());
+                }
+                Ok(Operand::Scalar(scalar(name, a)? / d))
+            }
+            _ => Err("DIV expects 2 arguments".to_string()),
+        },
+        MathFunction::Pow => binary(f64::powf),
+        MathFunction::Mod => binary(|a, b| a % b),
+        MathFunction::Atan2 => binary(f64::atan2),
+        MathFunction::Log => binary(f64::log),
+        MathFunction::Abs => unary(f64::abs),
+        MathFunction::Sqrt => unary(f64::sqrt),
+        MathFunction::Ln => unary(f64::ln),
+        MathFunction::Log10 => unary(f64::log10),
+        MathFunction::Exp => unary(f64::exp),
+        MathFunction::Ceil => unary(f64::ceil),
+        MathFunction::Floor => unary(f64::floor),
+        MathFunction::Round => unary(f64::round),
+        MathFunction::Sin => unary(f64::sin),
+        MathFunction::Cos => unary(f64::cos),
+        MathFunction::Tan => unary(f64::tan),
+        MathFunction::Asin => unary(f64::asin),
+        MathFunction::Acos => unary(f64::acos),
+        MathFunction::Atan => unary(f64::atan),
+        MathFunction::Pi => Ok(Operand::Scalar(std::f64::consts::PI)),
+        MathFunction::E => Ok(Operand::Scalar(std::f64::consts::E)),
+        MathFunction::Sum => Ok(Operand::Scalar(flatten(args).iter().sum())),
+        MathFunction::Count => Ok(Operand::Scalar(flatten(args).len() as f64)),
+        MathFunction::Avg => {
+            let values = flatten(args);
+            if values.is_empty() {
+                return Err("AVG over an empty list".to_string());
+            }
+            Ok(Operand::Scalar(values.iter().sum::<f64>() / values.len() as f64))
+        }
+        MathFunction::Min => flatten(args).into_iter().reduce(f64::min).map(Operand::Scalar)
+            .ok_or_else(|| "MIN over an empty list".to_string()),
+        MathFunction::Max => flatten(args).into_iter().reduce(f64::max).map(Operand::Scalar)
+            .ok_or_else(|| "MAX over an empty list".to_string()),
+        #[allow(unreachable_patterns)]
+        _ => Err(format!("Math function '{}' is not supported in Explorer mode", name)),
+    }
+}
+
+/// Evaluates a RETURN math expression. Whole results are returned as integers.
+pub fn evaluate(expr: &Expression, vars: &serde_json::Map<String, serde_json::Value>, params: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let to_json = |n: f64| {
+        if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+            serde_json::json!(n as i64)
+        } else {
+            serde_json::Number::from_f64(n).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null)
+        }
+    };
+    match evaluate_operand(expr, vars, params)? {
+        Operand::Scalar(n) => Ok(to_json(n)),
+        Operand::List(l) => Ok(serde_json::Value::Array(l.into_iter().map(to_json).collect())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+    use helix_db::helixc::parser::types::ReturnType;
+
+    fn returned(expr: &str) -> Expression {
+        let content = write_to_temp_file(vec![&format!("QUERY T() =>\n    users <- N<User>\n    RETURN {}", expr)]);
+        let source = HelixParser::parse_source(&content).expect("test query should parse");
+        match source.queries[0].return_values[0].clone() {
+            ReturnType::Expression(expr) => expr,
+            other => panic!("expected an expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_dispatches_on_function() {
+        let vars: serde_json::Map<String, serde_json::Value> = serde_json::from_value(serde_json::json!({
+            "count": 4,
+            "ages": [{ "id": "1", "age": 30 }, { "id": "2", "age": 40 }],
+        })).unwrap();
+        let params = serde_json::json!({});
+        let eval = |hql: &str| evaluate(&returned(hql), &vars, &params);
+
+        assert_eq!(eval("MUL(count, 2)").unwrap(), serde_json::json!(8));
+        assert_eq!(eval("AVG(ages)").unwrap(), serde_json::json!(35));
+        assert_eq!(eval("ADD(MAX(ages), 1)").unwrap(), serde_json::json!(41));
+        assert_eq!(eval("LOG10(100)").unwrap(), serde_json::json!(2));
+        assert_eq!(describe(&returned("ATAN2(count, 1)")), "ATAN2(count, 1)");
+
+        assert!(eval("DIV(count, 0)").unwrap_err().contains("by zero"));
+        assert!(eval("SQRT(ages)").unwrap_err().contains("SQRT expects numbers"));
+        assert!(eval("MUL(count)").unwrap_err().contains("MUL expects 2 arguments"));
+    }
+}
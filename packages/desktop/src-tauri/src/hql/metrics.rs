@@ -0,0 +1,210 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Tool calls kept per execution; per-item COUNT filters can issue thousands.
+const MAX_TIMINGS: usize = 1000;
+
+/// How a query was answered.
+#[derive(serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPath {
+    /// The compiled endpoint of a named query.
+    Compiled,
+    /// Translated to MCP tool calls.
+    #[default]
+    Mcp,
+    /// Served from the result cache.
+    Cache,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolTiming {
+    pub variable: String,
+    /// The tool or final action, e.g. `OutStep` or `collect`.
+    pub action: String,
+    pub ms: u64,
+}
+
+/// A RETURN variable whose value was not the kind its traversal produces.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShapeAnomaly {
+    pub variable: String,
+    pub expected: String,
+    pub found: String,
+    /// Running it again on a fresh connection gave the expected shape, and that value was returned.
+    pub recovered: bool,
+}
+
+/// A PREFILTER search that found fewer than `k` matches, even among the most candidates
+/// it fetched.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefilterShortfall {
+    pub variable: String,
+    pub wanted: usize,
+    pub found: usize,
+    /// Candidates searched, before the PREFILTER.
+    pub candidates: usize,
+}
+
+/// Returned with every `execute_dynamic_hql` result.
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionMetrics {
+    pub path: ExecutionPath,
+    pub total_ms: u64,
+    /// MCP connections opened; reused pooled connections are not counted.
+    pub init_calls: usize,
+    pub tool_call_count: usize,
+    /// The first calls, in the order they finished.
+    pub tool_calls: Vec<ToolTiming>,
+    pub rows: usize,
+    /// Size of the collect and search responses, decompressed.
+    pub response_bytes: usize,
+    /// Rows were left out to stay within the connection's result limits.
+    pub truncated: bool,
+    /// Rows the truncated results held in full, when the server could count them.
+    pub total_estimate: Option<usize>,
+    /// RETURN variables that failed while the others were returned.
+    pub failed_variables: Vec<String>,
+    pub shape_anomalies: Vec<ShapeAnomaly>,
+    pub prefilter_shortfalls: Vec<PrefilterShortfall>,
+}
+
+/// Collects metrics for one execution. Cheap to clone; clones share the totals.
+#[derive(Clone, Default)]
+pub struct MetricsRecorder {
+    metrics: Arc<Mutex<ExecutionMetrics>>,
+    variable: String,
+}
+
+impl MetricsRecorder {
+    /// A recorder that attributes calls to `variable`.
+    pub fn for_variable(&self, variable: &str) -> Self {
+        Self { metrics: self.metrics.clone(), variable: variable.to_string() }
+    }
+
+    pub fn init_call(&self) {
+        self.metrics.lock().unwrap().init_calls += 1;
+    }
+
+    pub fn tool_call(&self, action: &str, took: Duration) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.tool_call_count += 1;
+        if metrics.tool_calls.len() < MAX_TIMINGS {
+            metrics.tool_calls.push(ToolTiming { variable: self.variable.clone(), action: action.to_string(), ms: took.as_millis() as u64 });
+        }
+    }
+
+    pub fn response_bytes(&self, bytes: usize) {
+        self.metrics.lock().unwrap().response_bytes += bytes;
+    }
+
+    pub fn set_path(&self, path: ExecutionPath) {
+        self.metrics.lock().unwrap().path = path;
+    }
+
+    pub fn set_rows(&self, rows: usize) {
+        self.metrics.lock().unwrap().rows = rows;
+    }
+
+    /// A collect that held `total` rows (if known) returned only part of them.
+    pub fn truncated(&self, total: Option<usize>) {
+        let mut metrics = self.metrics.lock().unwrap();
+        // One unknown total makes the sum unknown
+        metrics.total_estimate = match (metrics.truncated, metrics.total_estimate, total) {
+            (false, _, total) => total,
+            (true, Some(sum), Some(total)) => Some(sum + total),
+            _ => None,
+        };
+        metrics.truncated = true;
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.metrics.lock().unwrap().truncated
+    }
+
+    /// This recorder's variable failed; its result holds the error instead.
+    pub fn variable_failed(&self) {
+        self.metrics.lock().unwrap().failed_variables.push(self.variable.clone());
+    }
+
+    /// This recorder's variable returned `found` where `expected` was due.
+    pub fn shape_anomaly(&self, expected: &str, found: String, recovered: bool) {
+        self.metrics.lock().unwrap().shape_anomalies.push(ShapeAnomaly {
+            variable: self.variable.clone(),
+            expected: expected.to_string(),
+            found,
+            recovered,
+        });
+    }
+
+    /// This recorder's PREFILTER search returned `found` of the `wanted` results.
+    pub fn prefilter_shortfall(&self, wanted: usize, found: usize, candidates: usize) {
+        self.metrics.lock().unwrap().prefilter_shortfalls.push(PrefilterShortfall {
+            variable: self.variable.clone(),
+            wanted,
+            found,
+            candidates,
+        });
+    }
+
+    pub fn is_partial(&self) -> bool {
+        !self.metrics.lock().unwrap().failed_variables.is_empty()
+    }
+
+    pub fn finish(&self, total: Duration) -> ExecutionMetrics {
+        let mut metrics = self.metrics.lock().unwrap().clone();
+        metrics.total_ms = total.as_millis() as u64;
+        metrics
+    }
+}
+
+/// Rows in a result: array lengths, summed over the fields of an object of results.
+pub fn count_rows(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Null => 0,
+        serde_json::Value::Array(rows) => rows.len(),
+        serde_json::Value::Object(fields) => fields.values()
+            .map(|v| match v {
+                serde_json::Value::Array(rows) => rows.len(),
+                serde_json::Value::Null => 0,
+                _ => 1,
+            })
+            .sum(),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_shares_totals() {
+        let recorder = MetricsRecorder::default();
+        let users = recorder.for_variable("users");
+        users.init_call();
+        users.tool_call("NFromType", Duration::from_millis(12));
+        users.response_bytes(300);
+        recorder.for_variable("posts").response_bytes(200);
+        recorder.for_variable("posts").tool_call("collect", Duration::from_millis(3));
+        recorder.set_rows(count_rows(&serde_json::json!({ "users": [1, 2], "posts": [3], "total": 3 })));
+        assert!(!recorder.is_partial());
+        recorder.for_variable("tags").variable_failed();
+        assert!(recorder.is_partial());
+        recorder.for_variable("posts").shape_anomaly("nodes", "a list holding an edge".to_string(), true);
+        recorder.for_variable("docs").prefilter_shortfall(10, 4, 10_000);
+
+        let metrics = recorder.finish(Duration::from_millis(40));
+        assert_eq!((metrics.init_calls, metrics.tool_call_count, metrics.rows, metrics.total_ms), (1, 2, 4, 40));
+        assert_eq!(metrics.tool_calls[0], ToolTiming { variable: "users".to_string(), action: "NFromType".to_string(), ms: 12 });
+        assert_eq!(metrics.path, ExecutionPath::Mcp);
+        assert_eq!(metrics.response_bytes, 500);
+        assert_eq!(metrics.failed_variables, vec!["tags".to_string()]);
+        assert_eq!((metrics.shape_anomalies[0].variable.as_str(), metrics.shape_anomalies[0].recovered), ("posts", true));
+        assert_eq!(metrics.prefilter_shortfalls[0], PrefilterShortfall { variable: "docs".to_string(), wanted: 10, found: 4, candidates: 10_000 });
+    }
+}
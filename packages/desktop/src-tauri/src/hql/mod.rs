@@ -1,5 +1,25 @@
 pub mod analyzer;
+pub mod assertions;
+pub mod cache;
+pub mod compat;
+pub mod cookbook;
+pub mod edge_audit;
 pub mod executor;
+pub mod explain;
+pub mod guardrails;
+pub mod index_advisor;
+pub mod math;
+pub mod metrics;
+pub mod paging;
+pub mod parameterize;
+pub mod pool;
 pub mod processor;
+pub mod profiles;
+pub mod progress;
+pub mod queue;
+pub mod sample;
+pub mod seed;
+pub mod shape_check;
+pub mod stream;
 pub mod translator;
 pub mod tool_args;
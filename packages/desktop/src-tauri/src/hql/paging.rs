@@ -0,0 +1,122 @@
+use crate::hql::executor::{self, McpTarget};
+use crate::hql::pool::PooledConnection;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Paged queries left unread this long give their connection back.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Open paged queries at once; the least recently used one is closed beyond this.
+const MAX_SESSIONS: usize = 16;
+pub const MAX_PAGE_SIZE: usize = 10_000;
+/// Rows per page when neither the request nor the connection's defaults set it.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// A traversal whose pipeline stays open on its own connection between pages.
+pub struct PageSession {
+    pub target: McpTarget,
+    pub conn: PooledConnection,
+    /// Next row to read.
+    pub position: usize,
+    /// Row after the last one to read (the query's RANGE or the policy's row limit).
+    pub end: Option<usize>,
+    last_used: Instant,
+}
+
+impl PageSession {
+    pub fn new(target: McpTarget, conn: PooledConnection, start: usize, end: Option<usize>) -> Self {
+        Self { target, conn, position: start, end, last_used: Instant::now() }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HqlPage {
+    pub rows: serde_json::Value,
+    /// Pass back for the next page; `None` after the last one.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Default)]
+pub struct PageSessions {
+    sessions: Mutex<HashMap<u64, PageSession>>,
+    next_id: AtomicU64,
+}
+
+/// Cursors carry the position they continue from, so a retried request cannot skip a page.
+fn parse_cursor(cursor: &str) -> Option<(u64, usize)> {
+    let (id, position) = cursor.split_once(':')?;
+    Some((id.parse().ok()?, position.parse().ok()?))
+}
+
+impl PageSessions {
+    fn take(&self, cursor: &str) -> Result<(u64, PageSession), String> {
+        let (id, position) = parse_cursor(cursor).ok_or_else(|| format!("Invalid page cursor '{}'", cursor))?;
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.last_used.elapsed() < SESSION_IDLE_TIMEOUT);
+        match sessions.remove(&id) {
+            Some(session) if session.position == position => Ok((id, session)),
+            Some(session) => {
+                sessions.insert(id, session);
+                Err("This page was already read; continue from the latest cursor".to_string())
+            }
+            None => Err("The paged query expired; run it again".to_string()),
+        }
+    }
+
+    fn put(&self, id: u64, mut session: PageSession) -> String {
+        session.last_used = Instant::now();
+        let cursor = format!("{}:{}", id, session.position);
+        let mut sessions = self.sessions.lock().unwrap();
+        while sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions.iter().min_by_key(|(_, s)| s.last_used).map(|(id, _)| *id);
+            match oldest {
+                Some(oldest) => sessions.remove(&oldest),
+                None => break,
+            };
+        }
+        sessions.insert(id, session);
+        cursor
+    }
+
+    /// Reads the first page of a freshly opened session.
+    pub async fn first_page(&self, session: PageSession, page_size: usize) -> Result<HqlPage, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.read(id, session, page_size).await
+    }
+
+    pub async fn next_page(&self, cursor: &str, page_size: usize) -> Result<HqlPage, String> {
+        let (id, session) = self.take(cursor)?;
+        self.read(id, session, page_size).await
+    }
+
+    async fn read(&self, id: u64, mut session: PageSession, page_size: usize) -> Result<HqlPage, String> {
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        let end = session.end.map_or(session.position + page_size, |end| end.min(session.position + page_size));
+        if end <= session.position {
+            return Ok(HqlPage { rows: serde_json::Value::Array(Vec::new()), next_cursor: None });
+        }
+        let last = session.end == Some(end);
+        // A failed read drops the session, and with it the connection
+        let rows = executor::collect_window(&session.target, &session.conn, session.position, end, !last).await?;
+        let read = rows.as_array().map(|r| r.len()).unwrap_or(0);
+        // A short page means the traversal ran out of rows
+        let exhausted = last || read < end - session.position;
+        session.position += read;
+        let next_cursor = (!exhausted).then(|| self.put(id, session));
+        Ok(HqlPage { rows, next_cursor })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cursor() {
+        assert_eq!(parse_cursor("3:2000"), Some((3, 2000)));
+        assert_eq!(parse_cursor("3"), None);
+        assert_eq!(parse_cursor("x:1"), None);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::hql::analyzer::{self, LitType};
+use crate::hql::processor::closing_paren;
+use helix_db::helixc::parser::types::{Source, StatementType};
+use helix_db::helixc::parser::{write_to_temp_file, HelixParser};
+use regex::Regex;
+use std::collections::HashSet;
+
+/// A literal turned into a query parameter.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposedParam {
+    pub name: String,
+    /// HQL type used in the signature.
+    #[serde(rename = "type")]
+    pub param_type: String,
+    /// The literal's value, for the parameter JSON.
+    pub value: serde_json::Value,
+    /// Byte ranges of the literal occurrences in the original code.
+    pub occurrences: Vec<(usize, usize)>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterizedQuery {
+    pub code: String,
+    pub params: serde_json::Value,
+    pub parameters: Vec<ProposedParam>,
+}
+
+fn parse(code: &str) -> Result<Source, String> {
+    let content = write_to_temp_file(vec![code]);
+    HelixParser::parse_source(&content).map_err(|e| format!("Failed to parse HQL: {:?}", e))
+}
+
+fn literal_value(text: &str, lit: LitType) -> (serde_json::Value, &'static str) {
+    match lit {
+        LitType::String => {
+            let inner = text.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+            let is_id = inner.len() == 36 && inner.chars().all(|c| c.is_ascii_hexdigit() || c == '-') && inner.matches('-').count() == 4;
+            (serde_json::Value::String(inner), if is_id { "ID" } else { "String" })
+        }
+        LitType::Number => match text.trim().parse::<i64>() {
+            Ok(i) => (serde_json::Value::from(i), "I64"),
+            Err(_) => (text.trim().parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null), "F64"),
+        },
+        LitType::Boolean => (serde_json::Value::Bool(text.trim() == "true"), "Boolean"),
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 && !out.ends_with('_') {
+            out.push('_');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// A name from what precedes the literal: the property it is compared with, the
+/// field it is assigned to, the label it looks up, or the variable it initializes.
+fn suggest_name(before: &str, lit: LitType) -> String {
+    let patterns = [
+        (r"\{\s*(\w+)\s*\}\s*::\s*\w+\s*\(\s*$", ""),
+        (r"<\s*(\w+)\s*>\s*\(\s*$", "_id"),
+        (r"(\w+)\s*<-\s*$", "_value"),
+        (r"(\w+)\s*:\s*$", ""),
+    ];
+    for (pattern, suffix) in patterns {
+        if let Some(cap) = Regex::new(pattern).unwrap().captures(before) {
+            return format!("{}{}", snake_case(&cap[1]), suffix);
+        }
+    }
+    match lit {
+        LitType::String => "text",
+        LitType::Number => "number",
+        LitType::Boolean => "flag",
+    }.to_string()
+}
+
+fn unique_name(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut name = base.to_string();
+    let mut n = 2;
+    while taken.contains(&name) {
+        name = format!("{}_{}", base, n);
+        n += 1;
+    }
+    taken.insert(name.clone());
+    name
+}
+
+/// Replaces the literals of a query with parameters and adds them to its signature.
+/// Bare worksheet statements are wrapped in `QUERY Query() =>` first. Repeated equal
+/// literals share one parameter.
+pub fn parameterize(code: &str) -> Result<ParameterizedQuery, String> {
+    let (code, source) = match parse(code) {
+        Ok(source) if !source.queries.is_empty() => (code.to_string(), source),
+        _ => {
+            let wrapped = format!("QUERY Query() =>\n{}", code);
+            let source = parse(&wrapped)?;
+            (wrapped, source)
+        }
+    };
+    if source.queries.len() != 1 {
+        return Err(crate::i18n::t("hql.multiple_queries", &[]));
+    }
+    let query = &source.queries[0];
+    let (used_ids, mut literals) = analyzer::collect_dwim_info(query);
+    literals.sort_by_key(|(range, _)| range.start);
+
+    let mut taken: HashSet<String> = used_ids.into_iter().collect();
+    taken.extend(query.parameters.iter().map(|p| p.name.1.clone()));
+    taken.extend(query.statements.iter().filter_map(|stmt| match &stmt.statement {
+        StatementType::Assignment(assign) => Some(assign.variable.clone()),
+        _ => None,
+    }));
+    let mut parameters: Vec<ProposedParam> = Vec::new();
+    for (range, lit) in literals {
+        let (value, param_type) = literal_value(&code[range.clone()], lit);
+        if let Some(existing) = parameters.iter_mut().find(|p| p.value == value && p.param_type == param_type) {
+            existing.occurrences.push((range.start, range.end));
+            continue;
+        }
+        let name = unique_name(&suggest_name(&code[..range.start], lit), &mut taken);
+        parameters.push(ProposedParam { name, param_type: param_type.to_string(), value, occurrences: vec![(range.start, range.end)] });
+    }
+    if parameters.is_empty() {
+        return Err("No literals to turn into parameters".to_string());
+    }
+
+    let query_range = query.loc.byte_range();
+    let open = code[query_range.clone()].find('(').map(|i| query_range.start + i)
+        .ok_or("Query signature has no parameter list")?;
+    let close = closing_paren(&code, open).ok_or("Unclosed query parameter list")? - 1;
+    let signature = parameters.iter().map(|p| format!("{}: {}", p.name, p.param_type)).collect::<Vec<_>>().join(", ");
+    let separator = if code[open + 1..close].trim().is_empty() { "" } else { ", " };
+
+    // Edits from the end, so earlier byte ranges stay valid
+    let mut edits: Vec<(usize, usize, String)> = parameters.iter()
+        .flat_map(|p| p.occurrences.iter().map(|&(start, end)| (start, end, p.name.clone())))
+        .collect();
+    edits.push((close, close, format!("{}{}", separator, signature)));
+    edits.sort_by(|a, b| b.0.cmp(&a.0));
+    let mut rewritten = code.clone();
+    for (start, end, text) in edits {
+        rewritten.replace_range(start..end, &text);
+    }
+    parse(&rewritten).map_err(|e| format!("Parameterized query does not parse: {}", e))?;
+
+    let params = serde_json::Value::Object(parameters.iter().map(|p| (p.name.clone(), p.value.clone())).collect());
+    Ok(ParameterizedQuery { code: rewritten, params, parameters })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameterize_literals() {
+        let result = parameterize("QUERY Find() =>\n    users <- N<User>::WHERE(_::{age}::GT(30))::WHERE(_::{firstName}::EQ(\"alice\"))\n    RETURN users").unwrap();
+        assert!(result.code.starts_with("QUERY Find(age: I64, first_name: String) =>"), "{}", result.code);
+        assert!(result.code.contains("_::{age}::GT(age)"));
+        assert!(result.code.contains("_::{firstName}::EQ(first_name)"));
+        assert_eq!(result.params, serde_json::json!({ "age": 30, "first_name": "alice" }));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::hql::executor::{init_connection, McpTarget};
+use crate::network_trace::SendTraced;
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Live connections to one target at once; idle connections are bounded separately.
+/// A connection opened inside `PooledConnection::lend` may borrow its lender's slot.
+pub const MAX_CONNECTIONS_PER_TARGET: usize = 32;
+/// Idle connections kept per target for reuse.
+const MAX_IDLE_PER_TARGET: usize = 8;
+/// Idle connections older than this may have expired on the server and are dropped.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Waiting longer than this for a free slot is reported instead of queueing forever.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sessions are only shared between callers that reach the gateway as the same
+/// identity: the same URL, credentials and headers, and translation profile.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PoolKey {
+    url: String,
+    auth: crate::gateway_auth::GatewayAuth,
+    profile: String,
+}
+
+impl PoolKey {
+    fn of(target: &McpTarget) -> Self {
+        Self { url: target.url.clone(), auth: target.auth.clone(), profile: target.profile.name.clone() }
+    }
+}
+
+/// The slot of a connection whose work is in progress, lent to the connections that
+/// work opens (a probe or a second pass) so they cannot wait on slots held by their
+/// own callers. It is lent to one of them at a time.
+#[derive(Clone)]
+struct Lease {
+    key: PoolKey,
+    permit: Arc<OwnedSemaphorePermit>,
+    lent: Arc<Semaphore>,
+}
+
+tokio::task_local! {
+    static LEASE: Lease;
+}
+
+/// What keeps a connection's place under `MAX_CONNECTIONS_PER_TARGET`.
+enum Slot {
+    Own(Arc<OwnedSemaphorePermit>),
+    Borrowed {
+        _lender: Arc<OwnedSemaphorePermit>,
+        _turn: OwnedSemaphorePermit,
+    },
+}
+
+#[derive(Default)]
+struct TargetPool {
+    idle: Vec<(String, Instant)>,
+    /// `Some(false)` once the server turned out not to serve the close endpoint.
+    close_supported: Option<bool>,
+}
+
+/// Reuses MCP connections per gateway identity (`PoolKey`). A connection goes back to
+/// the pool only after a final action that dropped its pipeline; anything else is closed.
+#[derive(Default)]
+pub struct McpPool {
+    targets: Mutex<HashMap<PoolKey, TargetPool>>,
+    slots: Mutex<HashMap<PoolKey, Arc<Semaphore>>>,
+}
+
+impl McpPool {
+    fn slots_for(&self, key: &PoolKey) -> Arc<Semaphore> {
+        self.slots.lock().unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(MAX_CONNECTIONS_PER_TARGET)))
+            .clone()
+    }
+
+    fn take_idle(&self, key: &PoolKey) -> Option<String> {
+        let mut targets = self.targets.lock().unwrap();
+        let pool = targets.get_mut(key)?;
+        pool.idle.retain(|(_, since)| since.elapsed() < IDLE_TIMEOUT);
+        pool.idle.pop().map(|(id, _)| id)
+    }
+
+    /// Returns true if the connection was kept for reuse.
+    fn put_idle(&self, key: &PoolKey, id: String) -> bool {
+        let mut targets = self.targets.lock().unwrap();
+        let pool = targets.entry(key.clone()).or_default();
+        if pool.idle.len() >= MAX_IDLE_PER_TARGET {
+            return false;
+        }
+        pool.idle.push((id, Instant::now()));
+        true
+    }
+
+    /// Drops the idle connections to `target` without closing them, once the server has
+    /// shown it no longer knows them.
+    pub fn forget_idle(&self, target: &McpTarget) {
+        if let Some(pool) = self.targets.lock().unwrap().get_mut(&PoolKey::of(target)) {
+            pool.idle.clear();
+        }
+    }
+
+    fn close_supported(&self, key: &PoolKey) -> bool {
+        self.targets.lock().unwrap().get(key).and_then(|p| p.close_supported) != Some(false)
+    }
+
+    fn set_close_supported(&self, key: &PoolKey, supported: bool) {
+        self.targets.lock().unwrap().entry(key.clone()).or_default().close_supported = Some(supported);
+    }
+
+    /// A slot of its own for the connection. Inside a lease for `key` a free slot is
+    /// taken if there is one, and the lender's slot is borrowed otherwise.
+    async fn slot(&self, key: &PoolKey) -> Result<Slot, String> {
+        let slots = self.slots_for(key);
+        let lease = LEASE.try_with(Lease::clone).ok().filter(|lease| lease.key == *key);
+        if let Some(lease) = lease {
+            if let Ok(permit) = slots.try_acquire_owned() {
+                return Ok(Slot::Own(Arc::new(permit)));
+            }
+            let turn = lease.lent.acquire_owned().await.map_err(|e| e.to_string())?;
+            return Ok(Slot::Borrowed { _lender: lease.permit, _turn: turn });
+        }
+        let permit = tokio::time::timeout(ACQUIRE_TIMEOUT, slots.acquire_owned())
+            .await
+            .map_err(|_| format!("Too many concurrent MCP connections to {}", key.url))?
+            .map_err(|e| e.to_string())?;
+        Ok(Slot::Own(Arc::new(permit)))
+    }
+
+    pub async fn acquire(self: &Arc<Self>, target: &McpTarget) -> Result<PooledConnection, String> {
+        let key = PoolKey::of(target);
+        let slot = self.slot(&key).await?;
+        let idle = if target.fresh_connections { None } else { self.take_idle(&key) };
+        let id = match idle {
+            Some(id) => id,
+            None => init_connection(target).await?,
+        };
+        Ok(PooledConnection { id, key, target: target.clone(), pool: Some(self.clone()), slot: Some(slot), reusable: false, close: true })
+    }
+}
+
+/// Best-effort close. Gateways without a close endpoint are remembered by the pool and
+/// skipped; their connections are simply not reused.
+async fn close_connection(pool: Option<&McpPool>, key: &PoolKey, target: &McpTarget, id: &str) {
+    if pool.is_some_and(|pool| !pool.close_supported(key)) {
+        return;
+    }
+    match target.post("close").json(&serde_json::json!({ "connection_id": id })).send_traced().await {
+        Ok(resp) => {
+            let missing = matches!(resp.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::METHOD_NOT_ALLOWED);
+            if let Some(pool) = pool {
+                pool.set_close_supported(key, !missing);
+            }
+        }
+        Err(e) => println!(">>> [McpPool] Failed to close connection {}: {}", id, e),
+    }
+}
+
+/// An MCP connection id. Dereferences to the id, so it can be passed wherever a
+/// `&str` connection id is expected.
+pub struct PooledConnection {
+    id: String,
+    key: PoolKey,
+    target: McpTarget,
+    pool: Option<Arc<McpPool>>,
+    slot: Option<Slot>,
+    reusable: bool,
+    /// False once the server no longer knows the connection.
+    close: bool,
+}
+
+impl PooledConnection {
+    /// A connection outside any pool; it is never reused and is closed when dropped.
+    pub fn unpooled(id: String, target: &McpTarget) -> Self {
+        Self { id, key: PoolKey::of(target), target: target.clone(), pool: None, slot: None, reusable: false, close: true }
+    }
+
+    /// Runs `work`, letting the connections it opens to the same target borrow this
+    /// connection's slot when the target has none free.
+    pub async fn lend<F: Future>(&self, work: F) -> F::Output {
+        let lender = match &self.slot {
+            Some(Slot::Own(permit)) => permit.clone(),
+            _ => return work.await,
+        };
+        let lease = Lease { key: self.key.clone(), permit: lender, lent: Arc::new(Semaphore::new(1)) };
+        LEASE.scope(lease, work).await
+    }
+
+    /// Marks the pipeline as finished (dropped by `collect`/`aggregate_by`), so the
+    /// connection can be reused once this handle goes away, unless its target asked
+    /// for fresh connections.
+    pub fn release(mut self) {
+        self.reusable = !self.target.fresh_connections;
+    }
+
+    /// Lets go of a connection the server no longer knows, without trying to close it.
+    pub fn discard(mut self) {
+        self.close = false;
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if !self.close {
+            return;
+        }
+        let pool = self.pool.take();
+        let id = std::mem::take(&mut self.id);
+        if let Some(pool) = pool.as_ref().filter(|_| self.reusable) {
+            if pool.put_idle(&self.key, id.clone()) {
+                return;
+            }
+        }
+        let (key, target) = (self.key.clone(), self.target.clone());
+        tauri::async_runtime::spawn(async move {
+            close_connection(pool.as_deref(), &key, &target, &id).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway_auth::GatewayAuth;
+    use crate::hql::profiles::TranslationProfile;
+    use crate::test_support::FakeGateway;
+
+    fn target(url: &str, api_key: &str) -> McpTarget {
+        let auth = GatewayAuth { api_key: Some(api_key.to_string()), headers: Vec::new() };
+        McpTarget::new(reqwest::Client::new(), url, auth, TranslationProfile::current())
+    }
+
+    #[test]
+    fn test_pool_reuse_per_identity_and_slot_cap() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let gateway = FakeGateway::start(|path| match path {
+                "/mcp/init" => (200, format!("\"conn-{}\"", rand::random::<u32>())),
+                _ => (200, "null".to_string()),
+            }).await;
+            let pool = Arc::new(McpPool::default());
+            let (alice, bob) = (target(&gateway.url, "alice"), target(&gateway.url, "bob"));
+
+            let first = pool.acquire(&alice).await.unwrap();
+            let id = first.to_string();
+            first.release();
+            assert_eq!(pool.acquire(&alice).await.unwrap().to_string(), id);
+            // Another API key on the same URL never gets alice's session
+            let other = pool.acquire(&bob).await.unwrap();
+            assert_ne!(other.to_string(), id);
+            other.release();
+
+            // Fresh connections neither come from the idle ones nor go back to them
+            let kept = pool.acquire(&alice).await.unwrap();
+            let kept_id = kept.to_string();
+            kept.release();
+            let fresh = pool.acquire(&McpTarget { fresh_connections: true, ..alice.clone() }).await.unwrap();
+            assert_ne!(fresh.to_string(), kept_id);
+            fresh.release();
+            assert_eq!(pool.acquire(&alice).await.unwrap().to_string(), kept_id);
+
+            // Concurrent acquisitions in one task each take a slot, up to the cap
+            let held: Vec<PooledConnection> = futures::future::try_join_all(
+                (0..MAX_CONNECTIONS_PER_TARGET).map(|_| pool.acquire(&alice))
+            ).await.unwrap();
+            let slots = pool.slots_for(&PoolKey::of(&alice));
+            assert_eq!(slots.available_permits(), 0);
+            let waiting = tokio::time::timeout(Duration::from_millis(200), pool.acquire(&alice)).await;
+            assert!(waiting.is_err(), "an acquisition past the cap should wait");
+
+            // Work on a full target borrows its connection's slot, one connection at a time
+            let lender = &held[0];
+            lender.lend(async {
+                let nested = pool.acquire(&alice).await.unwrap();
+                assert!(tokio::time::timeout(Duration::from_millis(200), pool.acquire(&alice)).await.is_err());
+                drop(nested);
+                pool.acquire(&alice).await.unwrap();
+            }).await;
+            assert_eq!(slots.available_permits(), 0);
+            drop(held);
+            assert_eq!(slots.available_permits(), MAX_CONNECTIONS_PER_TARGET);
+        });
+    }
+
+    #[test]
+    fn test_unpooled_connection_closed_on_drop() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let gateway = FakeGateway::start(|_| (200, "null".to_string())).await;
+            drop(PooledConnection::unpooled("conn-1".to_string(), &target(&gateway.url, "alice")));
+            for _ in 0..50 {
+                if gateway.requests().contains(&"/mcp/close".to_string()) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("the unpooled connection was not closed");
+        });
+    }
+}
@@ -80,6 +80,7 @@ pub fn preprocess_hql(code: &str) -> String {
          last_end = m.end();
     }
     result.push_str(&code[last_end..]);
+    let result = lift_chained_bm25(&result, &mut assignments);
     
     if assignments.is_empty() {
          return code.to_string();
@@ -101,6 +102,70 @@ pub fn preprocess_hql(code: &str) -> String {
     }
 }
 
+/// Index just past the `)` closing the `(` at `open`, skipping over string literals.
+pub(crate) fn closing_paren(code: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in code[open..].char_indices() {
+        if let Some(quote) = in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == quote {
+                in_string = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => in_string = Some(c),
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Lifts `SearchBM25<T>(...)` calls that are followed by traversal steps into a
+/// variable, the same way vector literals are lifted above.
+///
+/// The parser only accepts BM25 searches as standalone expressions, so
+/// `SearchBM25<Post>("rust", 10)::In<Authored>` becomes
+/// `tmpSearch_0 <- SearchBM25<Post>("rust", 10)` plus `tmpSearch_0::In<Authored>`.
+/// The executor then runs the search and the remaining steps on one MCP connection.
+fn lift_chained_bm25(code: &str, assignments: &mut Vec<String>) -> String {
+    let re = Regex::new(r"SearchBM25\s*<\s*\w+\s*>\s*\(").unwrap();
+    let mut result = String::new();
+    let mut last_end = 0;
+    let mut counter = 0;
+
+    for m in re.find_iter(code) {
+        if m.start() < last_end {
+            continue;
+        }
+        let Some(end) = closing_paren(code, m.end() - 1) else { continue };
+        if !code[end..].trim_start().starts_with("::") {
+            continue;
+        }
+        let var_name = format!("tmpSearch_{}", counter);
+        counter += 1;
+
+        assignments.push(format!("{} <- {}", var_name, &code[m.start()..end]));
+        result.push_str(&code[last_end..m.start()]);
+        result.push_str(&var_name);
+        last_end = end;
+    }
+    result.push_str(&code[last_end..]);
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,6 +267,21 @@ mod tests {
         assert!(processed.contains("tmpVec_0 <- [0.1]"));
     }
 
+    #[test]
+    fn test_chained_bm25_lifting() {
+        let code = r#"
+            QUERY Test() =>
+                authors <- SearchBM25<Post>("rust (lang)", 10)::In<Authored>
+                docs <- SearchBM25<Post>("rust", 5)
+                RETURN authors, docs
+        "#;
+        let processed = preprocess_hql(code);
+        assert!(processed.contains(r#"tmpSearch_0 <- SearchBM25<Post>("rust (lang)", 10)"#));
+        assert!(processed.contains("authors <- tmpSearch_0::In<Authored>"));
+        // Standalone searches are left alone
+        assert!(processed.contains(r#"docs <- SearchBM25<Post>("rust", 5)"#));
+    }
+
     #[test]
     fn test_no_strip_comment_in_string() {
         let code = r#"QUERY Test() => N<Doc>::WHERE(url == "http://example.com")"#;
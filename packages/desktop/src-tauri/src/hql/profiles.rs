@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+use serde::{Serialize, Deserialize};
+use tauri::Manager;
+use crate::hql::tool_args::ToolArgs;
+
+pub const DEFAULT_PROFILE: &str = "current";
+
+/// Describes how canonical `ToolArgs` (mirroring the latest `tools.rs`) are encoded
+/// for a given range of HelixDB releases. Only the differences are listed: an empty
+/// profile sends everything exactly as serialized.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranslationProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Oldest server version (inclusive) this profile applies to.
+    #[serde(default)]
+    pub min_version: Option<String>,
+    /// Newest server version (inclusive) this profile applies to.
+    #[serde(default)]
+    pub max_version: Option<String>,
+    /// Canonical `tool_name` -> name expected by the server.
+    #[serde(default)]
+    pub tool_names: HashMap<String, String>,
+    /// Canonical MCP endpoint (e.g. `search_keyword`) -> endpoint served by the gateway.
+    #[serde(default)]
+    pub endpoints: HashMap<String, String>,
+    /// Canonical `tool_name` -> (canonical arg -> server arg).
+    #[serde(default)]
+    pub arg_names: HashMap<String, HashMap<String, String>>,
+}
+
+impl TranslationProfile {
+    pub fn current() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            description: "Mirrors helix-db/src/helix_gateway/mcp/tools.rs".to_string(),
+            min_version: None,
+            max_version: None,
+            tool_names: HashMap::new(),
+            endpoints: HashMap::new(),
+            arg_names: HashMap::new(),
+        }
+    }
+
+    pub fn endpoint<'a>(&'a self, canonical: &'a str) -> &'a str {
+        self.endpoints.get(canonical).map(|s| s.as_str()).unwrap_or(canonical)
+    }
+
+    /// Serializes a tool the way this profile's servers expect it.
+    pub fn encode_tool(&self, tool: &ToolArgs) -> Result<serde_json::Value, String> {
+        let mut encoded = serde_json::to_value(tool).map_err(|e| format!("Failed to encode tool: {}", e))?;
+        let canonical = encoded.get("tool_name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+
+        if let Some(arg_map) = self.arg_names.get(&canonical) {
+            if let Some(serde_json::Value::Object(args)) = encoded.get_mut("args") {
+                for (from, to) in arg_map {
+                    if let Some(v) = args.remove(from) {
+                        args.insert(to.clone(), v);
+                    }
+                }
+            }
+        }
+        if let Some(renamed) = self.tool_names.get(&canonical) {
+            encoded["tool_name"] = serde_json::Value::String(renamed.clone());
+        }
+        Ok(encoded)
+    }
+
+    fn matches_version(&self, version: &str) -> bool {
+        let v = parse_version(version);
+        if v.is_empty() {
+            return false;
+        }
+        let above_min = self.min_version.as_deref().map(|m| v >= parse_version(m)).unwrap_or(true);
+        let below_max = self.max_version.as_deref().map(|m| v <= parse_version(m)).unwrap_or(true);
+        above_min && below_max
+    }
+}
+
+/// Extracts the numeric components of a version string ("v1.2.9-beta" -> [1, 2, 9]).
+fn parse_version(version: &str) -> Vec<u64> {
+    static VERSION: OnceLock<regex::Regex> = OnceLock::new();
+    let re = VERSION.get_or_init(|| regex::Regex::new(r"\d+(\.\d+)*").unwrap());
+    re.find(version)
+        .map(|m| m.as_str().split('.').filter_map(|p| p.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Built-in profiles plus user-defined ones from `translation_profiles.json` in the
+/// config directory. User profiles with a built-in name override it.
+pub fn load_profiles(app: &tauri::AppHandle) -> Vec<TranslationProfile> {
+    profiles_in(app.path().app_config_dir().ok().as_deref())
+}
+
+pub(crate) fn profiles_in(config_dir: Option<&std::path::Path>) -> Vec<TranslationProfile> {
+    let mut profiles = vec![TranslationProfile::current()];
+
+    let path = config_dir.map(|p| p.join("translation_profiles.json"));
+    if let Some(content) = path.and_then(|p| fs::read_to_string(p).ok()) {
+        match serde_json::from_str::<Vec<TranslationProfile>>(&content) {
+            Ok(custom) => {
+                for profile in custom {
+                    profiles.retain(|p| p.name != profile.name);
+                    profiles.push(profile);
+                }
+            }
+            Err(e) => println!(">>> [Profiles] Ignoring invalid translation_profiles.json: {}", e),
+        }
+    }
+    profiles
+}
+
+/// Picks `preferred` when given, otherwise the most specific profile whose version
+/// range contains the detected server version, falling back to `current`.
+pub fn select_profile(profiles: &[TranslationProfile], server_version: Option<&str>, preferred: Option<&str>) -> Result<TranslationProfile, String> {
+    if let Some(name) = preferred {
+        return profiles.iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown translation profile '{}'", name));
+    }
+
+    if let Some(version) = server_version {
+        let best = profiles.iter()
+            .filter(|p| p.min_version.is_some() || p.max_version.is_some())
+            .filter(|p| p.matches_version(version))
+            .max_by_key(|p| p.min_version.as_deref().map(parse_version).unwrap_or_default());
+        if let Some(profile) = best {
+            return Ok(profile.clone());
+        }
+    }
+
+    Ok(profiles.iter()
+        .find(|p| p.name == DEFAULT_PROFILE)
+        .cloned()
+        .unwrap_or_else(TranslationProfile::current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranged(name: &str, min: Option<&str>, max: Option<&str>) -> TranslationProfile {
+        TranslationProfile {
+            name: name.to_string(),
+            min_version: min.map(|s| s.to_string()),
+            max_version: max.map(|s| s.to_string()),
+            ..TranslationProfile::current()
+        }
+    }
+
+    #[test]
+    fn test_select_profile_by_version() {
+        let profiles = vec![
+            TranslationProfile::current(),
+            ranged("legacy", None, Some("1.0.99")),
+            ranged("v1.1", Some("1.1.0"), None),
+        ];
+        assert_eq!(select_profile(&profiles, Some("v0.9.3"), None).unwrap().name, "legacy");
+        assert_eq!(select_profile(&profiles, Some("1.2.9"), None).unwrap().name, "v1.1");
+        assert_eq!(select_profile(&profiles, Some("unknown"), None).unwrap().name, "current");
+        assert_eq!(select_profile(&profiles, None, Some("legacy")).unwrap().name, "legacy");
+        assert!(select_profile(&profiles, None, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_encode_tool_renames() {
+        let mut profile = TranslationProfile::current();
+        profile.tool_names.insert("n_from_type".to_string(), "n_from_label".to_string());
+        profile.arg_names.insert(
+            "n_from_type".to_string(),
+            HashMap::from([("node_type".to_string(), "label".to_string())]),
+        );
+        let encoded = profile.encode_tool(&ToolArgs::NFromType { node_type: "User".to_string() }).unwrap();
+        assert_eq!(encoded["tool_name"], "n_from_label");
+        assert_eq!(encoded["args"]["label"], "User");
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Sent as `query-progress` after each step of a pipeline.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryProgress {
+    pub variable: String,
+    /// 1-based index of the step that just finished.
+    pub step: usize,
+    /// Server steps plus the final action, when known up front.
+    pub total_steps: Option<usize>,
+    /// The tool or final action, e.g. `OutStep` or `collect`.
+    pub action: String,
+    /// Since the query started, not the variable.
+    pub elapsed_ms: u64,
+    pub done: bool,
+}
+
+/// Reports pipeline steps for one variable of a query. Cheap to clone; clones share
+/// the step counter.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    emit: Arc<dyn Fn(QueryProgress) + Send + Sync>,
+    variable: String,
+    started: Instant,
+    step: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl ProgressReporter {
+    pub fn new(emit: impl Fn(QueryProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            emit: Arc::new(emit),
+            variable: String::new(),
+            started: Instant::now(),
+            step: Arc::new(AtomicUsize::new(0)),
+            total: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// A reporter for `variable` with its own step count and the query's start time.
+    pub fn for_variable(&self, variable: &str) -> Self {
+        Self {
+            emit: self.emit.clone(),
+            variable: variable.to_string(),
+            started: self.started,
+            step: Arc::new(AtomicUsize::new(0)),
+            total: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn send(&self, step: usize, action: &str, done: bool) {
+        let total = self.total.load(Ordering::Relaxed);
+        (self.emit)(QueryProgress {
+            variable: self.variable.clone(),
+            step,
+            total_steps: (total > 0).then_some(total),
+            action: action.to_string(),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            done,
+        });
+    }
+
+    pub fn step(&self, action: &str) {
+        let step = self.step.fetch_add(1, Ordering::Relaxed) + 1;
+        self.send(step, action, false);
+    }
+
+    pub fn done(&self) {
+        self.send(self.step.load(Ordering::Relaxed), "done", true);
+    }
+}
+
+/// The variant name of a tool, e.g. `OutStep` for `OutStep { .. }`.
+pub fn action_name(tool: &impl std::fmt::Debug) -> String {
+    let text = format!("{:?}", tool);
+    text.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_reporter_counts_steps_per_variable() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let reporter = ProgressReporter::new(move |e| sink.lock().unwrap().push(e)).for_variable("users");
+        reporter.set_total(2);
+        reporter.step("NFromType");
+        reporter.step("collect");
+        reporter.done();
+        let events = events.lock().unwrap();
+        let steps: Vec<_> = events.iter().map(|e| (e.variable.as_str(), e.step, e.total_steps, e.done)).collect();
+        assert_eq!(steps, vec![("users", 1, Some(2), false), ("users", 2, Some(2), false), ("users", 2, Some(2), true)]);
+        assert_eq!(action_name(&crate::hql::tool_args::ToolArgs::NFromType { node_type: "User".to_string() }), "NFromType");
+    }
+}
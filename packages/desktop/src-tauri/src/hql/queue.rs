@@ -0,0 +1,169 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+/// Queries run at once per connection unless its defaults set `maxConcurrency`.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+/// A worksheet execution waiting for, or holding, a slot on its connection.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedQuery {
+    pub id: u64,
+    pub url: String,
+    pub code: String,
+    pub enqueued_at: String,
+    pub started_at: Option<String>,
+    pub running: bool,
+}
+
+struct Entry {
+    query: QueuedQuery,
+    cancel: watch::Sender<bool>,
+}
+
+/// Runs worksheet executions in the order they were fired, at most a connection's
+/// limit at a time. Entries leave the queue when their ticket is dropped.
+pub struct QueryQueue {
+    entries: Mutex<Vec<Entry>>,
+    next_id: AtomicU64,
+    /// Bumped whenever a slot frees up or an entry is cancelled, to wake waiting tickets.
+    changed: watch::Sender<u64>,
+}
+
+impl Default for QueryQueue {
+    fn default() -> Self {
+        Self { entries: Mutex::new(Vec::new()), next_id: AtomicU64::new(1), changed: watch::channel(0).0 }
+    }
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// `id` may start when its connection has fewer than `max` running and nothing queued ahead of it.
+fn can_start(queue: &[QueuedQuery], id: u64, max: usize) -> bool {
+    let Some(query) = queue.iter().find(|q| q.id == id) else {
+        return false;
+    };
+    let same: Vec<&QueuedQuery> = queue.iter().filter(|q| same_url(&q.url, &query.url)).collect();
+    let running = same.iter().filter(|q| q.running).count();
+    let first_waiting = same.iter().find(|q| !q.running).map(|q| q.id);
+    running < max.max(1) && first_waiting == Some(id)
+}
+
+/// The limit for `url`: the connection's default `maxConcurrency`, else `DEFAULT_MAX_CONCURRENCY`.
+pub fn max_concurrency(app: &tauri::AppHandle, url: &str) -> usize {
+    let connection = crate::policy::find_connection(app, Some(url));
+    crate::config::ConnectionDefaults::of_connection(connection.as_ref()).max_concurrency
+        .unwrap_or(DEFAULT_MAX_CONCURRENCY)
+}
+
+impl QueryQueue {
+    pub fn enqueue(&self, url: &str, code: &str) -> QueueTicket<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (cancel, cancelled) = watch::channel(false);
+        let query = QueuedQuery {
+            id,
+            url: url.to_string(),
+            code: code.to_string(),
+            enqueued_at: chrono::Utc::now().to_rfc3339(),
+            started_at: None,
+            running: false,
+        };
+        self.entries.lock().unwrap().push(Entry { query, cancel });
+        QueueTicket { queue: self, id, cancelled }
+    }
+
+    /// Queued and running executions in queue order, optionally only those against `url`.
+    pub fn list(&self, url: Option<&str>) -> Vec<QueuedQuery> {
+        self.entries.lock().unwrap().iter()
+            .filter(|e| url.is_none_or(|u| same_url(&e.query.url, u)))
+            .map(|e| e.query.clone())
+            .collect()
+    }
+
+    /// Cancels a queued or running execution. Returns false when it already finished.
+    pub fn cancel(&self, id: u64) -> bool {
+        let found = match self.entries.lock().unwrap().iter().find(|e| e.query.id == id) {
+            Some(entry) => {
+                entry.cancel.send_replace(true);
+                true
+            }
+            None => false,
+        };
+        self.bump();
+        found
+    }
+
+    fn bump(&self) {
+        self.changed.send_modify(|n| *n = n.wrapping_add(1));
+    }
+}
+
+pub struct QueueTicket<'a> {
+    queue: &'a QueryQueue,
+    pub id: u64,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl QueueTicket<'_> {
+    /// Waits until the execution may start and marks it running.
+    pub async fn wait_turn(&mut self, max: usize) -> Result<(), String> {
+        let mut changed = self.queue.changed.subscribe();
+        loop {
+            if *self.cancelled.borrow() {
+                return Err(crate::i18n::t("hql.cancelled", &[]));
+            }
+            {
+                let mut entries = self.queue.entries.lock().unwrap();
+                let queue: Vec<QueuedQuery> = entries.iter().map(|e| e.query.clone()).collect();
+                if can_start(&queue, self.id, max) {
+                    if let Some(entry) = entries.iter_mut().find(|e| e.query.id == self.id) {
+                        entry.query.running = true;
+                        entry.query.started_at = Some(chrono::Utc::now().to_rfc3339());
+                    }
+                    return Ok(());
+                }
+            }
+            // The queue owns the sender, so this only fails if the queue is gone
+            changed.changed().await.map_err(|e| e.to_string())?;
+        }
+    }
+
+    /// Resolves once the execution is cancelled.
+    pub async fn cancelled(&mut self) {
+        let _ = self.cancelled.wait_for(|c| *c).await;
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        self.queue.entries.lock().unwrap().retain(|e| e.query.id != self.id);
+        self.queue.bump();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: u64, url: &str, running: bool) -> QueuedQuery {
+        QueuedQuery { id, url: url.to_string(), code: String::new(), enqueued_at: String::new(), started_at: None, running }
+    }
+
+    #[test]
+    fn test_can_start_in_order() {
+        let queue = vec![
+            query(1, "http://a", true),
+            query(2, "http://a", false),
+            query(3, "http://a/", false),
+            query(4, "http://b", false),
+        ];
+        assert!(can_start(&queue, 2, 2));
+        assert!(!can_start(&queue, 3, 2));
+        assert!(!can_start(&queue, 2, 1));
+        assert!(can_start(&queue, 4, 1));
+        assert!(!can_start(&queue, 9, 4));
+    }
+}
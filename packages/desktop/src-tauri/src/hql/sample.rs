@@ -0,0 +1,122 @@
+use crate::hql::executor::{self, McpTarget};
+use rand::Rng;
+
+/// Rows read per RANGE window while sampling.
+const SAMPLE_WINDOW: usize = 1000;
+/// Rows scanned at most; past this the sample only covers the rows read so far.
+pub const MAX_SAMPLE_SCAN: usize = 200_000;
+pub const MAX_SAMPLE_SIZE: usize = 10_000;
+
+/// A uniform random sample of a stream of unknown length (Algorithm R).
+pub struct Reservoir<R> {
+    size: usize,
+    seen: usize,
+    /// Kept with their position in the stream, so the sample reads in result order.
+    rows: Vec<(usize, serde_json::Value)>,
+    rng: R,
+}
+
+impl<R: Rng> Reservoir<R> {
+    pub fn new(size: usize, rng: R) -> Self {
+        Self { size, seen: 0, rows: Vec::with_capacity(size), rng }
+    }
+
+    pub fn offer(&mut self, row: serde_json::Value) {
+        let index = self.seen;
+        self.seen += 1;
+        if self.rows.len() < self.size {
+            self.rows.push((index, row));
+        } else {
+            let slot = self.rng.gen_range(0..self.seen);
+            if slot < self.size {
+                self.rows[slot] = (index, row);
+            }
+        }
+    }
+
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    pub fn into_rows(mut self) -> Vec<serde_json::Value> {
+        self.rows.sort_by_key(|(index, _)| *index);
+        self.rows.into_iter().map(|(_, row)| row).collect()
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HqlSample {
+    pub rows: serde_json::Value,
+    /// Always set, so a sample is never mistaken for the full result.
+    pub sampled: bool,
+    pub sample_size: usize,
+    /// Rows read to draw the sample.
+    pub scanned: usize,
+    /// The scan stopped at `MAX_SAMPLE_SCAN`, so later rows could not be picked.
+    pub truncated: bool,
+}
+
+/// Reads rows `start..end` of a pipeline left open by `open_pipeline` in RANGE windows,
+/// keeping a uniform sample of `size` of them.
+pub async fn sample_pipeline(
+    target: &McpTarget,
+    connection_id: &str,
+    start: usize,
+    end: Option<usize>,
+    size: usize,
+) -> Result<HqlSample, String> {
+    let size = size.clamp(1, MAX_SAMPLE_SIZE);
+    let limit = end.unwrap_or(usize::MAX).min(start.saturating_add(MAX_SAMPLE_SCAN));
+    let mut reservoir = Reservoir::new(size, <rand::rngs::StdRng as rand::SeedableRng>::from_entropy());
+    let mut position = start;
+    let mut exhausted = false;
+    while position < limit {
+        let window_end = position.saturating_add(SAMPLE_WINDOW).min(limit);
+        let rows = executor::collect_window(target, connection_id, position, window_end, window_end < limit).await?;
+        let rows = match rows {
+            serde_json::Value::Array(rows) => rows,
+            _ => Vec::new(),
+        };
+        let read = rows.len();
+        rows.into_iter().for_each(|row| reservoir.offer(row));
+        // A short window means the traversal ran out of rows
+        if read < window_end - position {
+            exhausted = true;
+            break;
+        }
+        position = window_end;
+    }
+    Ok(HqlSample {
+        truncated: !exhausted && end.is_none_or(|end| limit < end),
+        scanned: reservoir.seen(),
+        sampled: true,
+        sample_size: size,
+        rows: serde_json::Value::Array(reservoir.into_rows()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_reservoir_is_uniform() {
+        let mut picks = vec![0usize; 100];
+        for seed in 0..2000 {
+            let mut reservoir = Reservoir::new(10, rand::rngs::StdRng::seed_from_u64(seed));
+            (0..100).for_each(|i| reservoir.offer(serde_json::json!(i)));
+            let rows = reservoir.into_rows();
+            assert_eq!(rows.len(), 10);
+            assert!(rows.windows(2).all(|w| w[0].as_u64() < w[1].as_u64()));
+            rows.iter().for_each(|r| picks[r.as_u64().unwrap() as usize] += 1);
+        }
+        // Each row is expected 200 times
+        assert!(picks.iter().all(|&n| (120..280).contains(&n)), "{:?}", picks);
+
+        let mut small = Reservoir::new(5, rand::rngs::StdRng::seed_from_u64(1));
+        (0..3).for_each(|i| small.offer(serde_json::json!(i)));
+        assert_eq!((small.seen(), small.into_rows().len()), (3, 3));
+    }
+}
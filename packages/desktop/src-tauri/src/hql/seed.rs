@@ -0,0 +1,225 @@
+use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+use crate::hql::cookbook::{CookbookItem, CookbookSchema};
+
+/// Seed queries run as one compiled query, so the graph they build is kept small.
+pub const MAX_SEED_NODES: usize = 500;
+pub const MAX_SEED_EDGES: usize = 2000;
+const DEFAULT_SIZE: usize = 50;
+
+/// Graph shapes for stress-testing rendering and traversals.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedScenario {
+    /// One node linked to `size` others.
+    DenseHub,
+    /// `size` nodes in a line; needs an edge whose ends have the same label.
+    LongChain,
+    /// Every one of `size` source nodes linked to every one of `size` targets.
+    Bipartite,
+    /// `size` separate three-node components.
+    IsolatedComponents,
+    /// Source `i` links to about `size / (i + 1)` targets (a Zipf-like degree spread).
+    SkewedDegree,
+}
+
+impl SeedScenario {
+    fn query_name(self) -> &'static str {
+        match self {
+            SeedScenario::DenseHub => "SeedDenseHub",
+            SeedScenario::LongChain => "SeedLongChain",
+            SeedScenario::Bipartite => "SeedBipartite",
+            SeedScenario::IsolatedComponents => "SeedIsolatedComponents",
+            SeedScenario::SkewedDegree => "SeedSkewedDegree",
+        }
+    }
+}
+
+/// A generated seed query, ready to sync into the project and call.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedQuery {
+    pub name: String,
+    pub code: String,
+    pub edge_label: String,
+    pub nodes: usize,
+    pub edges: usize,
+}
+
+/// A literal for a property of type `ty`, or `None` when the type has no obvious sample.
+fn sample_value(ty: &str, label: &str, prop: &str, i: usize) -> Option<String> {
+    match ty {
+        "String" => Some(format!("\"{} {} {}\"", label, prop, i)),
+        "Boolean" => Some(i.is_multiple_of(2).to_string()),
+        "F32" | "F64" => Some(format!("{}.5", i)),
+        "I8" | "U8" => Some((i % 100).to_string()),
+        "I16" | "I32" | "I64" | "U16" | "U32" | "U64" | "U128" => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+fn properties(item: &CookbookItem, i: usize) -> String {
+    let fields: Vec<String> = item.properties.iter()
+        .filter_map(|(prop, ty)| sample_value(ty.as_str()?, &item.name, prop, i).map(|v| format!("{}: {}", prop, v)))
+        .collect();
+    if fields.is_empty() {
+        String::new()
+    } else {
+        format!("({{{}}})", fields.join(", "))
+    }
+}
+
+struct SeedBuilder<'a> {
+    schema: &'a CookbookSchema,
+    edge: &'a CookbookItem,
+    lines: Vec<String>,
+    nodes: usize,
+    edges: usize,
+}
+
+impl SeedBuilder<'_> {
+    /// Adds a node with the given label and returns its variable.
+    fn node(&mut self, label: &str) -> Result<String, String> {
+        if self.nodes >= MAX_SEED_NODES {
+            return Err(format!("Seed scenarios are limited to {} nodes", MAX_SEED_NODES));
+        }
+        let item = self.schema.nodes.iter().find(|n| n.name == label)
+            .ok_or_else(|| format!("Node type '{}' is not in the schema", label))?;
+        let var = format!("n{}", self.nodes);
+        self.lines.push(format!("{} <- AddN<{}>{}", var, label, properties(item, self.nodes)));
+        self.nodes += 1;
+        Ok(var)
+    }
+
+    fn link(&mut self, from: &str, to: &str) -> Result<(), String> {
+        if self.edges >= MAX_SEED_EDGES {
+            return Err(format!("Seed scenarios are limited to {} edges", MAX_SEED_EDGES));
+        }
+        self.lines.push(format!("AddE<{}>{}::From({})::To({})", self.edge.name, properties(self.edge, self.edges), from, to));
+        self.edges += 1;
+        Ok(())
+    }
+}
+
+/// The edge to build with: `edge` if given, else the first one the scenario can use.
+fn pick_edge<'a>(schema: &'a CookbookSchema, scenario: SeedScenario, edge: Option<&str>) -> Result<&'a CookbookItem, String> {
+    let usable = |e: &&CookbookItem| match (&e.from, &e.to) {
+        (Some(from), Some(to)) => scenario != SeedScenario::LongChain || from == to,
+        _ => false,
+    };
+    match edge {
+        Some(name) => {
+            let found = schema.edges.iter().find(|e| e.name == name)
+                .ok_or_else(|| format!("Edge type '{}' is not in the schema", name))?;
+            if !usable(&found) {
+                return Err(format!("Edge type '{}' cannot build a {:?} scenario", name, scenario));
+            }
+            Ok(found)
+        }
+        None => schema.edges.iter().find(usable)
+            .ok_or_else(|| format!("The schema has no edge type that can build a {:?} scenario", scenario)),
+    }
+}
+
+/// Builds a seed query for `scenario` from the schema's labels and property types.
+/// The query is checked with the HQL parser before it is returned.
+pub fn generate_seed(schema: &CookbookSchema, scenario: SeedScenario, size: Option<usize>, edge: Option<&str>) -> Result<SeedQuery, String> {
+    let edge = pick_edge(schema, scenario, edge)?;
+    let (from, to) = (edge.from.clone().unwrap_or_default(), edge.to.clone().unwrap_or_default());
+    let size = size.unwrap_or(DEFAULT_SIZE).max(1);
+    let mut b = SeedBuilder { schema, edge, lines: Vec::new(), nodes: 0, edges: 0 };
+
+    match scenario {
+        SeedScenario::DenseHub => {
+            let hub = b.node(&from)?;
+            for _ in 0..size {
+                let spoke = b.node(&to)?;
+                b.link(&hub, &spoke)?;
+            }
+        }
+        SeedScenario::LongChain => {
+            let mut prev = b.node(&from)?;
+            for _ in 1..size {
+                let next = b.node(&to)?;
+                b.link(&prev, &next)?;
+                prev = next;
+            }
+        }
+        SeedScenario::Bipartite => {
+            let sources = (0..size).map(|_| b.node(&from)).collect::<Result<Vec<_>, _>>()?;
+            let targets = (0..size).map(|_| b.node(&to)).collect::<Result<Vec<_>, _>>()?;
+            for s in &sources {
+                for t in &targets {
+                    b.link(s, t)?;
+                }
+            }
+        }
+        SeedScenario::IsolatedComponents => {
+            for _ in 0..size {
+                let center = b.node(&from)?;
+                for _ in 0..2 {
+                    let leaf = b.node(&to)?;
+                    b.link(&center, &leaf)?;
+                }
+            }
+        }
+        SeedScenario::SkewedDegree => {
+            let sources = (0..size).map(|_| b.node(&from)).collect::<Result<Vec<_>, _>>()?;
+            let targets = (0..size).map(|_| b.node(&to)).collect::<Result<Vec<_>, _>>()?;
+            for (i, s) in sources.iter().enumerate() {
+                for t in targets.iter().take(size.div_ceil(i + 1)) {
+                    b.link(s, t)?;
+                }
+            }
+        }
+    }
+
+    let name = scenario.query_name();
+    let code = format!("QUERY {}() =>\n    {}\n    RETURN n0\n", name, b.lines.join("\n    "));
+    let content = write_to_temp_file(vec![&code]);
+    HelixParser::parse_source(&content).map_err(|e| format!("Generated seed query does not parse: {:?}", e))?;
+
+    Ok(SeedQuery { name: name.to_string(), code, edge_label: edge.name.clone(), nodes: b.nodes, edges: b.edges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, from: Option<&str>, to: Option<&str>, props: serde_json::Value) -> CookbookItem {
+        CookbookItem {
+            name: name.to_string(),
+            from: from.map(String::from),
+            to: to.map(String::from),
+            properties: props.as_object().cloned().unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_generate_seed_scenarios() {
+        let schema = CookbookSchema {
+            nodes: vec![
+                item("Person", None, None, serde_json::json!({ "name": "String", "age": "I32" })),
+                item("City", None, None, serde_json::json!({ "name": "String" })),
+            ],
+            edges: vec![
+                item("LivesIn", Some("Person"), Some("City"), serde_json::json!({})),
+                item("Knows", Some("Person"), Some("Person"), serde_json::json!({ "since": "I32" })),
+            ],
+            vectors: vec![],
+        };
+
+        let hub = generate_seed(&schema, SeedScenario::DenseHub, Some(3), None).unwrap();
+        assert_eq!((hub.edge_label.as_str(), hub.nodes, hub.edges), ("LivesIn", 4, 3));
+        assert!(hub.code.contains("n0 <- AddN<Person>({name: \"Person name 0\", age: 0})"), "{}", hub.code);
+
+        let chain = generate_seed(&schema, SeedScenario::LongChain, Some(4), None).unwrap();
+        assert_eq!((chain.edge_label.as_str(), chain.edges), ("Knows", 3));
+        assert!(chain.code.contains("AddE<Knows>({since: 0})::From(n0)::To(n1)"));
+
+        let skewed = generate_seed(&schema, SeedScenario::SkewedDegree, Some(4), None).unwrap();
+        assert_eq!(skewed.edges, 4 + 2 + 2 + 1);
+
+        assert!(generate_seed(&schema, SeedScenario::LongChain, None, Some("LivesIn")).is_err());
+        assert!(generate_seed(&schema, SeedScenario::Bipartite, Some(100), None).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Checks each RETURN variable of a multi-return query against the kind of value its
+//! traversal produces: edges where nodes were expected, or a list where a count was,
+//! means the variable read a pipeline that was not its own. Such a variable is run
+//! again on a fresh connection, and the anomaly is kept in the execution metrics.
+
+use helix_db::helixc::parser::types::Traversal;
+use crate::hql::executor::{self, McpTarget};
+use crate::hql::tool_args::{EdgeType, ToolArgs};
+use crate::hql::translator::{self, FinalAction, PipelinePlan, SearchCall};
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpectedShape {
+    Nodes,
+    Edges,
+    /// Told apart from nodes only by an embedding the server may leave out, so vector
+    /// items are only checked for not being edges.
+    Vectors,
+    Count,
+    Bool,
+    /// Aggregates, or nothing known to check against.
+    Any,
+}
+
+impl ExpectedShape {
+    pub fn describe(self) -> &'static str {
+        match self {
+            ExpectedShape::Nodes => "nodes",
+            ExpectedShape::Edges => "edges",
+            ExpectedShape::Vectors => "vectors",
+            ExpectedShape::Count => "a count",
+            ExpectedShape::Bool => "a boolean",
+            ExpectedShape::Any => "anything",
+        }
+    }
+}
+
+fn element(edge_type: &EdgeType) -> ExpectedShape {
+    match edge_type {
+        EdgeType::Node => ExpectedShape::Nodes,
+        EdgeType::Vec => ExpectedShape::Vectors,
+    }
+}
+
+/// The items a tool leaves in the pipeline; `None` for tools that keep the current ones.
+fn tool_shape(tool: &ToolArgs) -> Option<ExpectedShape> {
+    match tool {
+        ToolArgs::NFromType { .. } | ToolArgs::SearchKeyword { .. } => Some(ExpectedShape::Nodes),
+        ToolArgs::OutStep { edge_type, .. } | ToolArgs::InStep { edge_type, .. } => Some(element(edge_type)),
+        ToolArgs::OutEStep { .. } | ToolArgs::InEStep { .. } | ToolArgs::EFromType { .. } => Some(ExpectedShape::Edges),
+        ToolArgs::VFromType { .. } | ToolArgs::SearchVec { .. } | ToolArgs::SearchVecText { .. } => Some(ExpectedShape::Vectors),
+        ToolArgs::FilterItems { .. } | ToolArgs::OrderBy { .. } => None,
+    }
+}
+
+/// What a plan returns: its final action decides, and a collect returns the items of
+/// the last step that changed them (the search a traversal starts from, failing that).
+pub fn plan_shape(plan: &PipelinePlan, search: Option<&ToolArgs>, count_only: bool) -> ExpectedShape {
+    match &plan.final_action {
+        FinalAction::Count => ExpectedShape::Count,
+        FinalAction::CountCompare { .. } => ExpectedShape::Bool,
+        FinalAction::Aggregate { .. } | FinalAction::GroupBy { .. } => ExpectedShape::Any,
+        FinalAction::Collect { .. } if count_only => ExpectedShape::Count,
+        FinalAction::Collect { .. } => plan.tools.iter().rev().find_map(tool_shape)
+            .or_else(|| plan.edge_hop.as_ref().map(|hop| element(&hop.target_type)))
+            .or_else(|| search.and_then(tool_shape))
+            .unwrap_or(ExpectedShape::Any),
+    }
+}
+
+pub fn expected_shape(search: Option<&SearchCall>, traversal: Option<&Traversal>, params: &serde_json::Value, count_only: bool) -> ExpectedShape {
+    match (traversal, search) {
+        (Some(t), _) => translator::map_traversal_to_tools(t, params)
+            .map_or(ExpectedShape::Any, |plan| plan_shape(&plan, search.map(|s| &s.tool), count_only)),
+        (None, Some(_)) if count_only => ExpectedShape::Count,
+        (None, Some(s)) => tool_shape(&s.tool).unwrap_or(ExpectedShape::Any),
+        (None, None) => ExpectedShape::Any,
+    }
+}
+
+fn is_edge(item: &serde_json::Value) -> bool {
+    item.as_object().is_some_and(|map| map.contains_key("from_node") || map.contains_key("to_node"))
+}
+
+fn kind(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "a list",
+        serde_json::Value::Object(_) if is_edge(value) => "an edge",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+fn item_fits(expected: ExpectedShape, item: &serde_json::Value) -> bool {
+    item.is_object() && (expected == ExpectedShape::Edges) == is_edge(item)
+}
+
+/// What was found instead, when `value` does not have the `expected` shape. Null (a
+/// variable with nothing to run) fits anything.
+pub fn mismatch(expected: ExpectedShape, value: &serde_json::Value) -> Option<String> {
+    match (expected, value) {
+        (ExpectedShape::Any, _) | (_, serde_json::Value::Null) => None,
+        (ExpectedShape::Count, v) if v.is_number() => None,
+        (ExpectedShape::Bool, v) if v.is_boolean() => None,
+        (ExpectedShape::Count | ExpectedShape::Bool, v) => Some(kind(v).to_string()),
+        (_, serde_json::Value::Array(items)) => items.iter()
+            .find(|item| !item_fits(expected, item))
+            .map(|item| format!("a list holding {}", kind(item))),
+        (_, v) => Some(kind(v).to_string()),
+    }
+}
+
+/// Checks one variable's `value` and, when it has the wrong shape, runs the variable
+/// again on fresh connections, closed afterwards. The re-run's value replaces it only if it
+/// has the expected shape; either way the anomaly is logged and recorded.
+pub async fn verify(
+    target: &McpTarget,
+    variable: &str,
+    search: Option<&SearchCall>,
+    traversal: Option<&Traversal>,
+    params: &serde_json::Value,
+    value: &mut serde_json::Value,
+) {
+    let expected = expected_shape(search, traversal, params, target.count_only);
+    let Some(found) = mismatch(expected, value) else {
+        return;
+    };
+    println!(">>> [Executor] '{}' returned {} where {} were expected; running it again on a fresh connection", variable, found, expected.describe());
+
+    // Progress already counted this variable as done
+    let fresh = McpTarget { progress: None, fresh_connections: true, ..target.clone() };
+    let recovered = match executor::execute_variable(&fresh, variable, search, traversal, params).await {
+        Ok(retried) => match mismatch(expected, &retried) {
+            None => {
+                *value = retried;
+                true
+            }
+            Some(again) => {
+                println!(">>> [Executor] '{}' returned {} again; keeping the first result", variable, again);
+                false
+            }
+        },
+        Err(e) => {
+            println!(">>> [Executor] Re-running '{}' failed: {}", variable, e);
+            false
+        }
+    };
+    if let Some(metrics) = &target.metrics {
+        metrics.for_variable(variable).shape_anomaly(expected.describe(), found, recovered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(tools: Vec<ToolArgs>, final_action: FinalAction) -> PipelinePlan {
+        PipelinePlan { tools, final_action, id_filters: Vec::new(), client_filters: Vec::new(), edge_hop: None }
+    }
+
+    #[test]
+    fn test_expected_shapes_and_mismatches() {
+        let collect = FinalAction::Collect { range: None };
+        let out_e = ToolArgs::OutEStep { edge_label: "Follows".into(), filter: None };
+        let users = ToolArgs::NFromType { node_type: "User".into() };
+        let order = ToolArgs::OrderBy { properties: "name".into(), order: crate::hql::tool_args::Order::Asc };
+        assert_eq!(plan_shape(&plan(vec![users.clone(), order.clone()], collect.clone()), None, false), ExpectedShape::Nodes);
+        assert_eq!(plan_shape(&plan(vec![users.clone(), out_e], collect.clone()), None, false), ExpectedShape::Edges);
+        assert_eq!(plan_shape(&plan(vec![users.clone()], collect.clone()), None, true), ExpectedShape::Count);
+        assert_eq!(plan_shape(&plan(vec![users], FinalAction::Count), None, false), ExpectedShape::Count);
+        let search = ToolArgs::SearchVecText { query: "q".into(), label: "Doc".into(), k: 5 };
+        assert_eq!(plan_shape(&plan(vec![order], collect), Some(&search), false), ExpectedShape::Vectors);
+
+        let nodes = serde_json::json!([{ "id": "1", "label": "User" }]);
+        let edges = serde_json::json!([{ "id": "e", "from_node": "1", "to_node": "2" }]);
+        assert_eq!(mismatch(ExpectedShape::Nodes, &nodes), None);
+        assert_eq!(mismatch(ExpectedShape::Vectors, &nodes), None);
+        assert_eq!(mismatch(ExpectedShape::Edges, &edges), None);
+        assert_eq!(mismatch(ExpectedShape::Nodes, &edges).as_deref(), Some("a list holding an edge"));
+        assert_eq!(mismatch(ExpectedShape::Edges, &nodes).as_deref(), Some("a list holding an object"));
+        assert_eq!(mismatch(ExpectedShape::Count, &nodes).as_deref(), Some("a list"));
+        assert_eq!(mismatch(ExpectedShape::Nodes, &serde_json::json!(3)).as_deref(), Some("a number"));
+        assert_eq!(mismatch(ExpectedShape::Count, &serde_json::json!(3)), None);
+        assert_eq!(mismatch(ExpectedShape::Edges, &serde_json::Value::Null), None);
+        assert_eq!(mismatch(ExpectedShape::Any, &nodes), None);
+    }
+}
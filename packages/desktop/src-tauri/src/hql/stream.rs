@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::Emitter;
+
+/// Rows per `query-chunk` event; small enough that one chunk never stalls the webview.
+pub const CHUNK_ROWS: usize = 2000;
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// One slice of a result. `variable` names the RETURN entry the rows belong to, or
+/// is `None` when the result is a single list.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryChunk {
+    pub stream_id: String,
+    pub variable: Option<String>,
+    pub index: usize,
+    pub rows: Vec<serde_json::Value>,
+}
+
+/// Sent as `query-complete` after the last chunk and returned by the command. `result`
+/// is the full result with every streamed list left empty, for chunks to be appended to.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSummary {
+    pub stream_id: String,
+    pub streamed: bool,
+    pub total_rows: usize,
+    pub chunks: usize,
+    pub result: serde_json::Value,
+}
+
+/// Splits the lists in a result (a list, or an object of lists) into chunks and
+/// returns what is left once they are taken out.
+pub fn split_chunks(value: serde_json::Value, chunk_rows: usize) -> (serde_json::Value, Vec<(Option<String>, Vec<serde_json::Value>)>) {
+    fn chunked(rows: Vec<serde_json::Value>, variable: Option<String>, chunk_rows: usize, out: &mut Vec<(Option<String>, Vec<serde_json::Value>)>) {
+        let mut rows = rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            out.push((variable.clone(), rows.by_ref().take(chunk_rows).collect()));
+        }
+    }
+
+    let chunk_rows = chunk_rows.max(1);
+    let mut chunks = Vec::new();
+    let skeleton = match value {
+        serde_json::Value::Array(rows) => {
+            chunked(rows, None, chunk_rows, &mut chunks);
+            serde_json::Value::Array(Vec::new())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter()
+            .map(|(k, v)| match v {
+                serde_json::Value::Array(rows) => {
+                    chunked(rows, Some(k.clone()), chunk_rows, &mut chunks);
+                    (k, serde_json::Value::Array(Vec::new()))
+                }
+                other => (k, other),
+            })
+            .collect()),
+        other => other,
+    };
+    (skeleton, chunks)
+}
+
+/// Emits a finished result to `window` as `query-chunk` events followed by one
+/// `query-complete`, and returns the summary in place of the rows.
+pub fn emit_chunks(window: &tauri::Window, value: serde_json::Value) -> serde_json::Value {
+    let stream_id = format!("stream-{}", NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed));
+    let (skeleton, chunks) = split_chunks(value, CHUNK_ROWS);
+    let total_rows = chunks.iter().map(|(_, rows)| rows.len()).sum();
+    let count = chunks.len();
+
+    for (index, (variable, rows)) in chunks.into_iter().enumerate() {
+        let chunk = QueryChunk { stream_id: stream_id.clone(), variable, index, rows };
+        if let Err(e) = window.emit("query-chunk", chunk) {
+            println!(">>> [Stream] Failed to emit chunk {} of {}: {}", index, stream_id, e);
+        }
+    }
+
+    let summary = StreamSummary { stream_id, streamed: true, total_rows, chunks: count, result: skeleton };
+    if let Err(e) = window.emit("query-complete", summary.clone()) {
+        println!(">>> [Stream] Failed to emit summary of {}: {}", summary.stream_id, e);
+    }
+    serde_json::to_value(summary).unwrap_or(serde_json::Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks_keeps_scalars() {
+        let rows: Vec<_> = (0..5).map(|i| serde_json::json!({ "n": i })).collect();
+        let (skeleton, chunks) = split_chunks(serde_json::json!({ "users": rows, "total": 5 }), 2);
+        assert_eq!(skeleton, serde_json::json!({ "users": [], "total": 5 }));
+        let sizes: Vec<_> = chunks.iter().map(|(v, r)| (v.as_deref(), r.len())).collect();
+        assert_eq!(sizes, vec![(Some("users"), 2), (Some("users"), 2), (Some("users"), 1)]);
+        assert_eq!(chunks[2].1[0]["n"], 4);
+    }
+}
@@ -9,14 +9,159 @@ use crate::hql::tool_args::{ToolArgs, EdgeType, FilterProperties, FilterTraversa
 
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub enum FinalAction {
     Collect { range: Option<(usize, Option<usize>)> }, // (start, end)
     Count,
+    CountCompare { operator: Operator, value: f64 }, // ::COUNT::GT(3) -> bool
     Aggregate { properties: Vec<String> },
     GroupBy { properties: Vec<String> },
 } 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum EdgeDirection {
+    Out,
+    In,
+}
+
+/// Filters the MCP protocol cannot express, applied by the executor after collect.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub enum ClientFilter {
+    /// Keeps items whose number of `edge_label` edges in `direction` satisfies
+    /// `operator value`. Counts are computed from the pipeline up to `prefix_len` tools.
+    EdgeCount {
+        prefix_len: usize,
+        direction: EdgeDirection,
+        edge_label: String,
+        operator: Operator,
+        value: f64,
+    },
+    /// Like `EdgeCount` for sub-traversals a degree lookup cannot answer (several hops,
+    /// or a WHERE inside): `tools` run from each candidate and their results are counted.
+    TraversalCount {
+        prefix_len: usize,
+        tools: Vec<ToolArgs>,
+        operator: Operator,
+        value: f64,
+    },
+    /// Drops repeated items (by id) produced by fan-out traversals.
+    Dedup { prefix_len: usize },
+    /// Keeps items where property `left` compares to property `right` of the same item,
+    /// e.g. `_::{updated_at}::GT(_::{created_at})`.
+    PropertyCompare {
+        prefix_len: usize,
+        left: String,
+        operator: Operator,
+        right: String,
+    },
+}
+
+impl ClientFilter {
+    pub fn prefix_len(&self) -> usize {
+        match self {
+            ClientFilter::EdgeCount { prefix_len, .. } => *prefix_len,
+            ClientFilter::TraversalCount { prefix_len, .. } => *prefix_len,
+            ClientFilter::Dedup { prefix_len } => *prefix_len,
+            ClientFilter::PropertyCompare { prefix_len, .. } => *prefix_len,
+        }
+    }
+
+    fn step_name(&self) -> &'static str {
+        match self {
+            ClientFilter::EdgeCount { .. } | ClientFilter::TraversalCount { .. } => "WHERE filters on COUNT",
+            ClientFilter::Dedup { .. } => "DEDUP",
+            ClientFilter::PropertyCompare { .. } => "WHERE comparisons between two properties",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum EdgeEndpoint {
+    From,
+    To,
+}
+
+/// An edge-start traversal (`E<Type>::WHERE(..)::RANGE(..)::ToN`) that MCP cannot fuse
+/// into a node step. The edges are fetched first; `tools` of the plan then run from
+/// the endpoint label and are restricted to the endpoints that were reached.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct EdgeHop {
+    pub edge_label: String,
+    pub edge_tools: Vec<ToolArgs>,
+    pub edge_ids: Vec<String>,
+    pub edge_range: Option<(usize, Option<usize>)>,
+    pub endpoint: EdgeEndpoint,
+    pub target_type: EdgeType,
+}
+
+/// The MCP search call has no filter argument, so a PREFILTER search fetches this
+/// many times `k` candidates, filters them on the connection and keeps the first `k`.
+pub const PREFILTER_OVERSAMPLE: usize = 10;
+/// Too few matches among the candidates are searched again from ten times as many,
+/// up to this many times `k`.
+pub const PREFILTER_MAX_OVERSAMPLE: usize = 1000;
+
+/// A standalone search (`SearchBM25`/`SearchV`), with its PREFILTER if any.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SearchCall {
+    pub tool: ToolArgs,
+    pub prefilter: Option<FilterTraversal>,
+}
+
+impl From<ToolArgs> for SearchCall {
+    fn from(tool: ToolArgs) -> Self {
+        Self { tool, prefilter: None }
+    }
+}
+
+impl SearchCall {
+    /// The number of results the user asked for.
+    pub fn k(&self) -> Option<usize> {
+        match &self.tool {
+            ToolArgs::SearchVec { k, .. } | ToolArgs::SearchVecText { k, .. } => Some(*k),
+            ToolArgs::SearchKeyword { limit, .. } => Some(*limit),
+            _ => None,
+        }
+    }
+
+    /// The search as sent: oversampled when a PREFILTER will narrow it down.
+    pub fn oversampled_tool(&self) -> ToolArgs {
+        self.sampled_tool(PREFILTER_OVERSAMPLE)
+    }
+
+    /// The search fetching `factor` times `k` candidates for its PREFILTER.
+    pub fn sampled_tool(&self, factor: usize) -> ToolArgs {
+        let mut tool = self.tool.clone();
+        if self.prefilter.is_some() {
+            match &mut tool {
+                ToolArgs::SearchVec { k, .. } | ToolArgs::SearchVecText { k, .. } => *k = k.saturating_mul(factor),
+                _ => {}
+            }
+        }
+        tool
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct PipelinePlan {
+    pub tools: Vec<ToolArgs>,
+    pub final_action: FinalAction,
+    pub id_filters: Vec<String>,
+    pub client_filters: Vec<ClientFilter>,
+    pub edge_hop: Option<EdgeHop>,
+}
+
+impl PipelinePlan {
+    /// Count-only mode: a plan that would return rows returns how many there are instead.
+    /// Everything before the final action is kept; a RANGE on the final collect is not,
+    /// so the count covers every matching item. Aggregates are left as they are.
+    pub fn count_only(&mut self) {
+        if matches!(self.final_action, FinalAction::Collect { .. }) {
+            self.final_action = FinalAction::Count;
+        }
+    }
+}
+
 pub fn map_bm25_to_tool(bm25: &helix_db::helixc::parser::types::BM25Search) -> Result<ToolArgs, String> {
     let label = bm25.type_arg.clone().unwrap_or_default();
     let query = match &bm25.data {
@@ -39,10 +184,12 @@ pub fn map_bm25_to_tool(bm25: &helix_db::helixc::parser::types::BM25Search) -> R
     })
 }
 
-pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value) -> Result<(Vec<ToolArgs>, FinalAction, Vec<String>), String> {
+pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value) -> Result<PipelinePlan, String> {
     let mut tools = Vec::new();
     let mut final_action = FinalAction::Collect { range: None };
     let mut id_filters_out = Vec::new();
+    let mut client_filters = Vec::new();
+    let mut edge_hop: Option<EdgeHop> = None;
 
     // Map StartNode
     // IDs are handled separately (client-side filter or property-based re-filter).
@@ -94,6 +241,9 @@ pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value)
             }
         }
         StartNode::SearchVector(sv) => {
+            if sv.pre_filter.is_some() {
+                return Err("PREFILTER searches cannot be followed by traversal steps yet; RETURN the search results directly".to_string());
+            }
             if let Some(label) = &sv.vector_type {
                 tools.push(ToolArgs::VFromType { vector_type: label.clone(), filter: None });
             }
@@ -106,6 +256,24 @@ pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value)
 
     // Map Steps
     for (i, step) in traversal.steps.iter().enumerate() {
+        // COUNT is terminal, except for a trailing comparison (`::COUNT::GT(3)`)
+        match &final_action {
+            FinalAction::Count => {
+                if let StepType::BooleanOperation(op) = &step.step {
+                    let (operator, value) = map_boolean_op(&op.op, params)?;
+                    let value = value_to_f64(&value)
+                        .ok_or_else(|| "COUNT can only be compared against a number".to_string())?;
+                    final_action = FinalAction::CountCompare { operator, value };
+                    continue;
+                }
+                return Err("COUNT must be the last step of a traversal, optionally followed by a comparison such as ::GT(3)".to_string());
+            }
+            FinalAction::CountCompare { .. } => {
+                return Err("No further steps are allowed after a COUNT comparison".to_string());
+            }
+            _ => {}
+        }
+
         match &step.step {
             StepType::Node(gs) | StepType::Edge(gs) => {
                  match &gs.step {
@@ -171,15 +339,25 @@ pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value)
                              }
                          }
                          if !found {
-                             return Err("::ToN, ::FromN, ::ToV, or ::FromV must follow an edge traversal step (like ::OutE or ::InE)".to_string());
+                             let endpoint = match gs.step {
+                                 GraphStepType::FromN | GraphStepType::FromV => EdgeEndpoint::From,
+                                 _ => EdgeEndpoint::To,
+                             };
+                             edge_hop = Some(split_edge_start(&mut tools, &mut final_action, &mut id_filters_out, &client_filters, &edge_hop, endpoint, target_edge_type)?);
                          }
                      }
                      _ => {} 
                  }
             }
             StepType::Where(expr) => {
-                let filter = map_expression_to_filter(expr, params)?;
-                tools.push(ToolArgs::FilterItems { filter });
+                if let Some(cf) = map_count_filter(expr, params, tools.len(), false)? {
+                    client_filters.push(cf);
+                } else if let Some(cf) = map_property_compare_filter(expr, tools.len(), false)? {
+                    client_filters.push(cf);
+                } else {
+                    let filter = map_expression_to_filter(expr, params)?;
+                    tools.push(ToolArgs::FilterItems { filter });
+                }
             }
             StepType::OrderBy(order_by) => {
                 let property = match &order_by.expression.expr {
@@ -210,23 +388,19 @@ pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value)
                 };
             }
             StepType::Range((start_expr, end_expr)) => {
-                 let start = match extract_value(start_expr, params)? {
-                     Value::I32(val) => val as usize,
-                     Value::I64(val) => val as usize,
-                     Value::F64(val) => val as usize,
-                     _ => 0,
-                 };
-                 let end = match extract_value(end_expr, params)? {
-                     Value::I32(val) => Some(val as usize),
-                     Value::I64(val) => Some(val as usize),
-                     Value::F64(val) => Some(val as usize),
-                     _ => None,
-                 };
-                 final_action = FinalAction::Collect { range: Some((start, end)) };
+                 let start = extract_usize(start_expr, params)?;
+                 let end = extract_usize(end_expr, params)?;
+                 if end < start {
+                     return Err(format!("RANGE end ({}) must not be smaller than start ({})", end, start));
+                 }
+                 final_action = FinalAction::Collect { range: Some((start, Some(end))) };
             }
             StepType::First => {
                 final_action = FinalAction::Collect { range: Some((0, Some(1))) };
             }
+            StepType::Dedup => {
+                client_filters.push(ClientFilter::Dedup { prefix_len: tools.len() });
+            }
             StepType::Object(obj) => {
                 let filter = map_object_to_filter(obj, params)?;
                 tools.push(ToolArgs::FilterItems { filter });
@@ -241,13 +415,234 @@ pub fn map_traversal_to_tools(traversal: &Traversal, params: &serde_json::Value)
                     StepType::AddEdge(_) => "AddEdge",
                     _ => "Mutation",
                 };
-                return Err(format!("Explorer Mode is Read-Only: The step '{}' attempts to modify data, which is not supported by the MCP protocol. See https://docs.helix-db.com/guides/mcp-guide", step_name));
+                return Err(crate::i18n::t("hql.read_only_step", &[("step", step_name)]));
             }
             _ => return Err(format!("Unsupported step type at index {}: {:?}", i, step.step)),
         }
     }
 
-    Ok((tools, final_action, id_filters_out))
+    // Client-side filters run after collect, so only order-preserving or
+    // commutative tools may follow them in the server pipeline.
+    for cf in &client_filters {
+        let later_graph_step = tools[cf.prefix_len()..].iter()
+            .any(|t| !matches!(t, ToolArgs::FilterItems { .. } | ToolArgs::OrderBy { .. }));
+        if later_graph_step {
+            return Err(format!("{} must come after the last traversal step (only WHERE/ORDER may follow)", cf.step_name()));
+        }
+    }
+    if let Some(cf) = client_filters.first() {
+        if matches!(final_action, FinalAction::Aggregate { .. } | FinalAction::GroupBy { .. }) {
+            return Err(format!("AGGREGATE_BY/GROUP_BY cannot be combined with {}", cf.step_name()));
+        }
+    }
+    if edge_hop.is_some() {
+        if tools.iter().any(|t| !matches!(t, ToolArgs::FilterItems { .. } | ToolArgs::OrderBy { .. })) {
+            return Err("After E<Type>::ToN/FromN only WHERE, ORDER, RANGE, COUNT and DEDUP are supported in Explorer mode".to_string());
+        }
+        if client_filters.iter().any(|cf| matches!(cf, ClientFilter::EdgeCount { .. } | ClientFilter::TraversalCount { .. })) {
+            return Err("WHERE filters on COUNT cannot follow E<Type>::ToN/FromN yet".to_string());
+        }
+        if matches!(final_action, FinalAction::Aggregate { .. } | FinalAction::GroupBy { .. }) {
+            return Err("AGGREGATE_BY/GROUP_BY cannot follow E<Type>::ToN/FromN yet".to_string());
+        }
+    }
+
+    Ok(PipelinePlan {
+        tools,
+        final_action,
+        id_filters: id_filters_out,
+        client_filters,
+        edge_hop,
+    })
+}
+
+/// Turns the edge part of `E<Type>[::WHERE|ORDER|RANGE]*::ToN` into an `EdgeHop`,
+/// leaving `tools` empty for the steps that follow the hop.
+fn split_edge_start(
+    tools: &mut Vec<ToolArgs>,
+    final_action: &mut FinalAction,
+    id_filters: &mut Vec<String>,
+    client_filters: &[ClientFilter],
+    existing: &Option<EdgeHop>,
+    endpoint: EdgeEndpoint,
+    target_type: EdgeType,
+) -> Result<EdgeHop, String> {
+    let edge_label = match tools.first() {
+        Some(ToolArgs::EFromType { edge_type }) if existing.is_none() => edge_type.clone(),
+        _ => return Err("::ToN, ::FromN, ::ToV, or ::FromV must follow an edge traversal step (like ::OutE or ::InE) or an E<Type> start".to_string()),
+    };
+    if tools[1..].iter().any(|t| !matches!(t, ToolArgs::FilterItems { .. } | ToolArgs::OrderBy { .. })) {
+        return Err("Only WHERE, ORDER and RANGE may appear between E<Type> and ::ToN/::FromN".to_string());
+    }
+    if !client_filters.is_empty() {
+        return Err("Client-side steps (DEDUP, COUNT or property comparisons) are not supported before ::ToN/::FromN on an E<Type> start".to_string());
+    }
+    let edge_range = match final_action {
+        FinalAction::Collect { range } => range.take(),
+        _ => return Err("COUNT, AGGREGATE_BY and GROUP_BY must come after ::ToN/::FromN".to_string()),
+    };
+
+    Ok(EdgeHop {
+        edge_label,
+        edge_tools: std::mem::take(tools),
+        edge_ids: std::mem::take(id_filters),
+        edge_range,
+        endpoint,
+        target_type,
+    })
+}
+
+/// Maps `_::Out<Label>::COUNT::GT(n)` (and In/OutE/InE) to a client-side degree filter,
+/// and longer sub-traversals such as `_::Out<Follows>::WHERE(..)::COUNT::GT(n)` to a
+/// per-candidate count. Returns `Ok(None)` when the expression is not a COUNT comparison.
+fn map_count_filter(expr: &Expression, params: &serde_json::Value, prefix_len: usize, negated: bool) -> Result<Option<ClientFilter>, String> {
+    let traversal = match &expr.expr {
+        ExpressionType::Not(inner) => return map_count_filter(inner, params, prefix_len, !negated),
+        ExpressionType::Traversal(t) => &**t,
+        _ => return Ok(None),
+    };
+    if !matches!(traversal.start, StartNode::Anonymous) {
+        return Ok(None);
+    }
+    let count_pos = match traversal.steps.iter().position(|s| matches!(s.step, StepType::Count)) {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    if count_pos == 0 {
+        return Err("COUNT inside WHERE needs a traversal to count, e.g. _::Out<Follows>::COUNT::GT(3)".to_string());
+    }
+    let (operator, value) = match traversal.steps.get(count_pos + 1).map(|s| &s.step) {
+        Some(StepType::BooleanOperation(op)) if traversal.steps.len() == count_pos + 2 => map_boolean_op(&op.op, params)?,
+        _ => return Err("COUNT inside WHERE must be followed by a comparison, e.g. ::COUNT::GT(3)".to_string()),
+    };
+    let value = value_to_f64(&value)
+        .ok_or_else(|| "COUNT can only be compared against a number".to_string())?;
+    let operator = if negated { invert_operator(operator) } else { operator };
+
+    let single_hop = match &traversal.steps[0].step {
+        StepType::Node(gs) | StepType::Edge(gs) if count_pos == 1 => match &gs.step {
+            GraphStepType::Out(label) | GraphStepType::OutE(label) => Some((EdgeDirection::Out, label.clone())),
+            GraphStepType::In(label) | GraphStepType::InE(label) => Some((EdgeDirection::In, label.clone())),
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some((direction, edge_label)) = single_hop {
+        return Ok(Some(ClientFilter::EdgeCount {
+            prefix_len,
+            direction,
+            edge_label,
+            operator,
+            value,
+        }));
+    }
+
+    let mut sub = traversal.clone();
+    sub.steps.truncate(count_pos);
+    let plan = map_traversal_to_tools(&sub, params)?;
+    if !plan.client_filters.is_empty() || plan.edge_hop.is_some() || plan.final_action != (FinalAction::Collect { range: None }) {
+        return Err("COUNT inside WHERE supports traversal steps and WHERE filters only (no RANGE, DEDUP or nested COUNT)".to_string());
+    }
+    Ok(Some(ClientFilter::TraversalCount {
+        prefix_len,
+        tools: plan.tools,
+        operator,
+        value,
+    }))
+}
+
+/// Returns `Some(property)` when `expr` is exactly `_::{property}`.
+fn single_property_ref(expr: &Expression) -> Option<String> {
+    let traversal = match &expr.expr {
+        ExpressionType::Traversal(t) => &**t,
+        _ => return None,
+    };
+    if !matches!(traversal.start, StartNode::Anonymous) || traversal.steps.len() != 1 {
+        return None;
+    }
+    match &traversal.steps[0].step {
+        StepType::Object(obj) if obj.fields.len() == 1 => Some(obj.fields[0].key.clone()),
+        _ => None,
+    }
+}
+
+/// Maps `_::{a}::GT(_::{b})` (optionally under `!`) to a client-side comparison.
+/// Returns `Ok(None)` when the right-hand side is not a property of the same item.
+fn map_property_compare_filter(expr: &Expression, prefix_len: usize, negated: bool) -> Result<Option<ClientFilter>, String> {
+    let traversal = match &expr.expr {
+        ExpressionType::Not(inner) => return map_property_compare_filter(inner, prefix_len, !negated),
+        ExpressionType::Traversal(t) => &**t,
+        _ => return Ok(None),
+    };
+    if !matches!(traversal.start, StartNode::Anonymous) || traversal.steps.len() != 2 {
+        return Ok(None);
+    }
+    let left = match &traversal.steps[0].step {
+        StepType::Object(obj) if obj.fields.len() == 1 => obj.fields[0].key.clone(),
+        _ => return Ok(None),
+    };
+    let (operator, rhs) = match &traversal.steps[1].step {
+        StepType::BooleanOperation(op) => match boolean_op_parts(&op.op) {
+            Some(parts) => parts,
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let right = match single_property_ref(rhs) {
+        Some(r) => r,
+        None => return Ok(None),
+    };
+
+    Ok(Some(ClientFilter::PropertyCompare {
+        prefix_len,
+        left,
+        operator: if negated { invert_operator(operator) } else { operator },
+        right,
+    }))
+}
+
+fn boolean_op_parts(op: &BooleanOpType) -> Option<(Operator, &Expression)> {
+    match op {
+        BooleanOpType::Equal(e) => Some((Operator::Eq, &**e)),
+        BooleanOpType::NotEqual(e) => Some((Operator::Neq, &**e)),
+        BooleanOpType::GreaterThan(e) => Some((Operator::Gt, &**e)),
+        BooleanOpType::GreaterThanOrEqual(e) => Some((Operator::Gte, &**e)),
+        BooleanOpType::LessThan(e) => Some((Operator::Lt, &**e)),
+        BooleanOpType::LessThanOrEqual(e) => Some((Operator::Lte, &**e)),
+        _ => None,
+    }
+}
+
+fn map_boolean_op(op: &BooleanOpType, params: &serde_json::Value) -> Result<(Operator, Value), String> {
+    let (operator, expr) = boolean_op_parts(op)
+        .ok_or_else(|| "Unsupported boolean operator in dynamic HQL".to_string())?;
+    if single_property_ref(expr).is_some() {
+        return Err("Comparing two properties is only supported as a top-level WHERE condition, e.g. WHERE(_::{a}::GT(_::{b}))".to_string());
+    }
+    let value = extract_value(expr, params)?;
+    match operator {
+        Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => Ok((operator, range_value(value))),
+        Operator::Eq | Operator::Neq => Ok((operator, value)),
+    }
+}
+
+pub fn value_to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::I8(v) => Some(*v as f64),
+        Value::I16(v) => Some(*v as f64),
+        Value::I32(v) => Some(*v as f64),
+        Value::I64(v) => Some(*v as f64),
+        Value::U8(v) => Some(*v as f64),
+        Value::U16(v) => Some(*v as f64),
+        Value::U32(v) => Some(*v as f64),
+        Value::U64(v) => Some(*v as f64),
+        Value::U128(v) => Some(*v as f64),
+        Value::F32(v) => Some(*v as f64),
+        Value::F64(v) => Some(*v),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
 }
 
 fn map_expression_to_filter(expr: &Expression, params: &serde_json::Value) -> Result<FilterTraversal, String> {
@@ -313,15 +708,10 @@ fn map_expression_to_filter_internal(expr: &Expression, params: &serde_json::Val
                      if obj.fields.len() == 1 {
                          if let StepType::BooleanOperation(op) = &traversal.steps[1].step {
                              let prop_key = obj.fields[0].key.clone();
-                             let (operator, value) = match &op.op {
-                                 BooleanOpType::Equal(e) => (Operator::Eq, extract_value(e, params)?),
-                                 BooleanOpType::NotEqual(e) => (Operator::Neq, extract_value(e, params)?),
-                                 BooleanOpType::GreaterThan(e) => (Operator::Gt, extract_value(e, params)?),
-                                 BooleanOpType::GreaterThanOrEqual(e) => (Operator::Gte, extract_value(e, params)?),
-                                 BooleanOpType::LessThan(e) => (Operator::Lt, extract_value(e, params)?),
-                                 BooleanOpType::LessThanOrEqual(e) => (Operator::Lte, extract_value(e, params)?),
-                                 _ => return Err("Unsupported boolean operator in dynamic HQL".to_string()),
-                             };
+                             if let BooleanOpType::IsIn(list) = &op.op {
+                                 return is_in_filter(&prop_key, list, params, negated);
+                             }
+                             let (operator, value) = map_boolean_op(&op.op, params)?;
 
                              let final_operator = if negated {
                                  invert_operator(operator)
@@ -376,6 +766,7 @@ fn map_expression_to_filter_internal(expr: &Expression, params: &serde_json::Val
                              _ => return Err(format!("Unsupported graph step in filter: {:?}", gs.step)),
                          }
                      },
+                     StepType::Count => return Err("COUNT inside WHERE cannot be combined with AND/OR yet; use one WHERE per condition".to_string()),
                      _ => return Err(format!("Unsupported step type in filter chain: {:?}", step.step)),
                  };
 
@@ -463,6 +854,76 @@ fn invert_operator(op: Operator) -> Operator {
     }
 }
 
+/// Parses the date forms accepted in WHERE: RFC 3339, `YYYY-MM-DDTHH:MM:SS` and
+/// `YYYY-MM-DD` (both read as UTC).
+pub fn parse_date(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let s = s.trim();
+    if s.len() < 10 || !s.as_bytes()[0].is_ascii_digit() {
+        return None;
+    }
+    chrono::DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&chrono::Utc))
+        .or_else(|| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|d| d.and_utc()))
+        .or_else(|| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)).map(|d| d.and_utc()))
+}
+
+/// Dates are sent in one canonical UTC form (`2025-01-01T00:00:00Z`) so the
+/// server's string comparison orders them chronologically.
+fn canonical_date(date: chrono::DateTime<chrono::Utc>) -> Value {
+    Value::String(date.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+}
+
+/// The value compared against in a GT/GTE/LT/LTE: date strings in canonical form.
+/// EQ and NEQ keep the literal, which must match the stored string as written.
+fn range_value(value: Value) -> Value {
+    match value {
+        Value::String(s) => match parse_date(&s) {
+            Some(date) => canonical_date(date),
+            None => Value::String(s),
+        },
+        other => other,
+    }
+}
+
+/// Converts a JSON parameter into a comparison value.
+fn param_value(val: &serde_json::Value) -> Value {
+    match val {
+        serde_json::Value::String(vs) => Value::String(vs.clone()),
+        serde_json::Value::Number(vn) => {
+            if let Some(i) = vn.as_i64() {
+                Value::I64(i)
+            } else if let Some(u) = vn.as_u64() {
+                Value::U64(u)
+            } else if let Some(f) = vn.as_f64() {
+                Value::F64(f)
+            } else {
+                Value::String(vn.to_string())
+            }
+        }
+        serde_json::Value::Bool(vb) => Value::Boolean(*vb),
+        _ => Value::String(val.to_string()),
+    }
+}
+
+/// Items of a list parameter (`[ID]`, `[String]`, ...). The UI's parameter form sends
+/// lists either as JSON arrays or as their JSON text, so both are accepted.
+fn param_list(val: &serde_json::Value) -> Option<Vec<serde_json::Value>> {
+    match val {
+        serde_json::Value::Array(items) => Some(items.clone()),
+        serde_json::Value::String(s) if s.trim_start().starts_with('[') => {
+            match serde_json::from_str(s) {
+                Ok(serde_json::Value::Array(items)) => Some(items),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn id_string(val: &serde_json::Value) -> String {
+    val.as_str().map(String::from).unwrap_or_else(|| val.to_string())
+}
+
+/// `NOW` (unless shadowed by a parameter) expands to the current UTC time.
 fn extract_value(expr: &Expression, params: &serde_json::Value) -> Result<Value, String> {
     match &expr.expr {
         ExpressionType::StringLiteral(s) => Ok(Value::String(s.clone())),
@@ -471,22 +932,9 @@ fn extract_value(expr: &Expression, params: &serde_json::Value) -> Result<Value,
         ExpressionType::BooleanLiteral(b) => Ok(Value::Boolean(*b)),
         ExpressionType::Identifier(s) => {
             if let Some(val) = params.get(s) {
-                match val {
-                    serde_json::Value::String(vs) => Ok(Value::String(vs.clone())),
-                    serde_json::Value::Number(vn) => {
-                        if let Some(i) = vn.as_i64() {
-                            Ok(Value::I64(i))
-                        } else if let Some(u) = vn.as_u64() {
-                            Ok(Value::U64(u))
-                        } else if let Some(f) = vn.as_f64() {
-                            Ok(Value::F64(f))
-                        } else {
-                            Ok(Value::String(vn.to_string()))
-                        }
-                    }
-                    serde_json::Value::Bool(vb) => Ok(Value::Boolean(*vb)),
-                    _ => Ok(Value::String(val.to_string())),
-                }
+                Ok(param_value(val))
+            } else if s == "NOW" {
+                Ok(canonical_date(chrono::Utc::now()))
             } else {
                 Ok(Value::String(s.clone()))
             }
@@ -495,6 +943,56 @@ fn extract_value(expr: &Expression, params: &serde_json::Value) -> Result<Value,
     }
 }
 
+/// The candidate values of an IS_IN: every item of a list parameter, or a single value.
+fn extract_values(expr: &Expression, params: &serde_json::Value) -> Result<Vec<Value>, String> {
+    if let ExpressionType::Identifier(name) = &expr.expr {
+        if let Some(items) = params.get(name).and_then(param_list) {
+            return Ok(items.iter().map(param_value).collect());
+        }
+    }
+    Ok(vec![extract_value(expr, params)?])
+}
+
+/// `_::{key}::IS_IN(list)` becomes one EQ branch per value (OR); negated, a single
+/// branch of NEQs (AND).
+fn is_in_filter(key: &str, list: &Expression, params: &serde_json::Value, negated: bool) -> Result<FilterTraversal, String> {
+    let values = extract_values(list, params)?;
+    if values.is_empty() {
+        return Err(format!("IS_IN on '{}' needs at least one value", key));
+    }
+    let condition = |value: Value, operator| FilterProperties { key: key.to_string(), value, operator: Some(operator) };
+    let properties = if negated {
+        vec![values.into_iter().map(|v| condition(v, Operator::Neq)).collect()]
+    } else {
+        values.into_iter().map(|v| vec![condition(v, Operator::Eq)]).collect()
+    };
+    Ok(FilterTraversal { properties: Some(properties), filter_traversals: None })
+}
+
+/// Resolves a RANGE bound to a non-negative integer. Identifiers are looked up in
+/// `params`, and numeric strings (as sent by the UI's parameter form) are accepted.
+fn extract_usize(expr: &Expression, params: &serde_json::Value) -> Result<usize, String> {
+    match &expr.expr {
+        ExpressionType::IntegerLiteral(i) => usize::try_from(*i)
+            .map_err(|_| format!("RANGE bound must be a non-negative integer, got {}", i)),
+        ExpressionType::FloatLiteral(f) if f.fract() == 0.0 && *f >= 0.0 => Ok(*f as usize),
+        ExpressionType::Identifier(name) => {
+            let val = params.get(name)
+                .ok_or_else(|| format!("Parameter '{}' is required but missing from arguments.", name))?;
+            let parsed = match val {
+                serde_json::Value::Number(n) => n.as_u64()
+                    .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0 && *f >= 0.0).map(|f| f as u64)),
+                serde_json::Value::String(s) => s.trim().parse::<u64>().ok(),
+                _ => None,
+            };
+            parsed
+                .map(|n| n as usize)
+                .ok_or_else(|| format!("parameter '{}' must be an integer", name))
+        }
+        _ => Err(format!("Unsupported RANGE bound: {:?}", expr.expr)),
+    }
+}
+
 fn map_object_to_filter(obj: &Object, params: &serde_json::Value) -> Result<FilterTraversal, String> {
     let mut props = Vec::new();
     for field in &obj.fields {
@@ -573,7 +1071,10 @@ fn extract_ids_and_props(ids: &[IdType], params: &serde_json::Value) -> Result<(
             }
             IdType::Identifier { value, .. } => {
                 if let Some(val) = params.get(value) {
-                    id_strings.push(val.as_str().unwrap_or(&val.to_string()).to_string());
+                    match param_list(val) {
+                        Some(items) => id_strings.extend(items.iter().map(id_string)),
+                        None => id_strings.push(id_string(val)),
+                    }
                 } else {
                     return Err(format!("Parameter '{}' is required but missing from arguments.", value));
                 }
@@ -654,6 +1155,17 @@ pub fn map_search_vector_to_tool(sv: &helix_db::helixc::parser::types::SearchVec
     }
 }
 
+/// `SearchV<T>(vec, k)::PREFILTER(cond)`: the search plus `cond` as a filter over
+/// its candidates.
+pub fn map_search_vector_call(sv: &helix_db::helixc::parser::types::SearchVector, params: &serde_json::Value) -> Result<SearchCall, String> {
+    let tool = map_search_vector_to_tool(sv, params)?;
+    let prefilter = match &sv.pre_filter {
+        Some(expr) => Some(map_expression_to_filter(expr, params)?),
+        None => None,
+    };
+    Ok(SearchCall { tool, prefilter })
+}
+
 fn extract_property_from_traversal(traversal: &Traversal) -> Result<String, String> {
     if traversal.steps.len() == 1 {
         if let StepType::Object(obj) = &traversal.steps[0].step {
@@ -688,34 +1200,71 @@ fn resolve_traversal_recursive<'a>(
 
     let mut resolved = t.clone();
 
+    // A variable that is not a traversal (e.g. a search result) is left as the start;
+    // the caller decides whether it can be chained.
     if let StartNode::Identifier(id) = &resolved.start {
-        let parent_t = resolve_traversal_recursive(id, assignments, depth + 1)?
-            .ok_or_else(|| format!("Variable '{}' not found", id))?;
-        
-        let mut all_steps = parent_t.steps.clone();
-        all_steps.extend(resolved.steps);
-        resolved.start = parent_t.start;
-        resolved.steps = all_steps;
+        if let Some(parent_t) = resolve_traversal_recursive(id, assignments, depth + 1)? {
+            let mut all_steps = parent_t.steps.clone();
+            all_steps.extend(resolved.steps);
+            resolved.start = parent_t.start;
+            resolved.steps = all_steps;
+        }
     }
 
     Ok(Some(resolved))
 }
 
+/// Internal HNSW bookkeeping on vector items that is meaningless in the grid.
+const VECTOR_INTERNAL_KEYS: &[&str] = &["level", "deleted", "is_deleted"];
+
+/// Vectors carry their embedding in `data` (older gateways: `vector`) as a list of numbers.
+pub(crate) fn vector_embedding_key(map: &serde_json::Map<String, serde_json::Value>) -> Option<&'static str> {
+    if map.contains_key("from_node") || map.contains_key("to_node") {
+        return None;
+    }
+    ["data", "vector"].into_iter().find(|k| {
+        map.get(*k)
+            .and_then(|v| v.as_array())
+            .map(|a| !a.is_empty() && a.iter().all(|x| x.is_number()))
+            .unwrap_or(false)
+    })
+}
+
+/// Gives vector items a stable shape: embedding under `data`, its `dimensions`,
+/// and no index internals.
+fn normalize_vector(map: &mut serde_json::Map<String, serde_json::Value>) {
+    let key = match vector_embedding_key(map) {
+        Some(k) => k,
+        None => return,
+    };
+    for internal in VECTOR_INTERNAL_KEYS {
+        map.remove(*internal);
+    }
+    if let Some(embedding) = map.remove(key) {
+        let dims = embedding.as_array().map(|a| a.len()).unwrap_or(0);
+        map.insert("dimensions".to_string(), serde_json::json!(dims));
+        map.insert("data".to_string(), embedding);
+    }
+}
+
 pub fn normalize_value(v: serde_json::Value) -> serde_json::Value {
     match v {
         serde_json::Value::Array(arr) => {
             serde_json::Value::Array(arr.into_iter().map(normalize_value).collect())
         }
         serde_json::Value::Object(mut map) => {
-            if let Some(serde_json::Value::Object(props)) = map.remove("properties") {                
+            // Before properties are flattened, so user properties named like internals survive
+            normalize_vector(&mut map);
+            // `shift_remove` keeps the remaining keys in the order the server sent them
+            if let Some(serde_json::Value::Object(props)) = map.shift_remove("properties") {                
                 for (k, v) in props {
                     map.insert(k, v);
                 }
             }
-            map.remove("out_edges");
-            map.remove("in_edges");
-            map.remove("vectors");
-            map.remove("version");
+            map.shift_remove("out_edges");
+            map.shift_remove("in_edges");
+            map.shift_remove("vectors");
+            map.shift_remove("version");
             
             for (_, v) in map.iter_mut() {
                 *v = normalize_value(v.clone());
@@ -726,3 +1275,257 @@ pub fn normalize_value(v: serde_json::Value) -> serde_json::Value {
         _ => v,
     }
 }
+
+/// Puts the keys named in `order` first, in that order, like a compiled query's output.
+/// Keys not in `order` keep their relative order after them.
+pub fn order_by_return(mut map: serde_json::Map<String, serde_json::Value>, order: &[String]) -> serde_json::Map<String, serde_json::Value> {
+    let mut ordered = serde_json::Map::new();
+    for key in order {
+        if let Some(value) = map.shift_remove(key) {
+            ordered.insert(key.clone(), value);
+        }
+    }
+    ordered.extend(map);
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+    use helix_db::helixc::parser::types::StatementType;
+
+    fn plan_for(hql: &str) -> Result<PipelinePlan, String> {
+        plan_with_params(hql, &serde_json::json!({}))
+    }
+
+    fn plan_with_params(hql: &str, params: &serde_json::Value) -> Result<PipelinePlan, String> {
+        let content = write_to_temp_file(vec![&format!("QUERY T() =>\n    res <- {}\n    RETURN res", hql)]);
+        let source = HelixParser::parse_source(&content).expect("test query should parse");
+        let traversal = match &source.queries[0].statements[0].statement {
+            StatementType::Assignment(assign) => match &assign.value.expr {
+                ExpressionType::Traversal(t) => (**t).clone(),
+                other => panic!("expected a traversal, got {:?}", other),
+            },
+            other => panic!("expected an assignment, got {:?}", other),
+        };
+        map_traversal_to_tools(&traversal, params)
+    }
+
+    #[test]
+    fn test_edge_start_with_filters_before_to_n() {
+        let plan = plan_for("E<Follows>::WHERE(_::{followed_at}::GT(5))::ORDER<Desc>(_::{followed_at})::RANGE(0, 10)::ToN").unwrap();
+        let hop = plan.edge_hop.expect("edge-start traversal should produce an edge hop");
+        assert_eq!(hop.edge_label, "Follows");
+        assert_eq!(hop.endpoint, EdgeEndpoint::To);
+        assert_eq!(hop.edge_range, Some((0, Some(10))));
+        assert!(matches!(hop.edge_tools[0], ToolArgs::EFromType { .. }));
+        assert!(matches!(hop.edge_tools[1], ToolArgs::FilterItems { .. }));
+        assert!(matches!(hop.edge_tools[2], ToolArgs::OrderBy { .. }));
+        assert!(plan.tools.is_empty());
+        assert_eq!(plan.final_action, FinalAction::Collect { range: None });
+    }
+
+    #[test]
+    fn test_edge_start_steps_after_from_n() {
+        let plan = plan_for("E<Follows>::FromN::WHERE(_::{age}::GT(30))::COUNT").unwrap();
+        let hop = plan.edge_hop.unwrap();
+        assert_eq!(hop.endpoint, EdgeEndpoint::From);
+        assert_eq!(hop.edge_range, None);
+        assert!(matches!(plan.tools[..], [ToolArgs::FilterItems { .. }]));
+        assert_eq!(plan.final_action, FinalAction::Count);
+
+        assert!(plan_for("E<Follows>::ToN::Out<Likes>").is_err());
+        assert!(plan_for("E<Follows>::COUNT::ToN").is_err());
+    }
+
+    #[test]
+    fn test_out_e_to_n_still_fuses() {
+        let plan = plan_for("N<User>::OutE<Follows>::ToN").unwrap();
+        assert!(plan.edge_hop.is_none());
+        assert!(matches!(plan.tools[1], ToolArgs::OutStep { edge_type: EdgeType::Node, .. }));
+    }
+
+    #[test]
+    fn test_prefilter_search_call() {
+        let content = write_to_temp_file(vec![
+            "QUERY T(vec: [F64]) =>\n    res <- SearchV<Doc>(vec, 10)::PREFILTER(_::{lang}::EQ(\"en\"))\n    RETURN res",
+        ]);
+        let source = HelixParser::parse_source(&content).expect("test query should parse");
+        let sv = match &source.queries[0].statements[0].statement {
+            StatementType::Assignment(assign) => match &assign.value.expr {
+                ExpressionType::SearchVector(sv) => sv.clone(),
+                other => panic!("expected a vector search, got {:?}", other),
+            },
+            other => panic!("expected an assignment, got {:?}", other),
+        };
+        let search = map_search_vector_call(&sv, &serde_json::json!({ "vec": [0.1, 0.2] })).unwrap();
+        assert!(search.prefilter.is_some());
+        assert_eq!(search.k(), Some(10));
+        assert!(matches!(search.oversampled_tool(), ToolArgs::SearchVec { k: 100, .. }));
+        assert!(matches!(search.sampled_tool(PREFILTER_MAX_OVERSAMPLE), ToolArgs::SearchVec { k: 10_000, .. }));
+    }
+
+    #[test]
+    fn test_date_literals_are_canonicalized() {
+        let range_value = |s: &str| range_value(Value::String(s.to_string()));
+        assert_eq!(range_value("2025-01-01"), Value::String("2025-01-01T00:00:00Z".to_string()));
+        assert_eq!(range_value("2025-01-01T02:00:00+02:00"), Value::String("2025-01-01T00:00:00Z".to_string()));
+        assert_eq!(range_value("2025-01-01T00:00:00.250"), Value::String("2025-01-01T00:00:00.250Z".to_string()));
+        assert_eq!(range_value("Berlin"), Value::String("Berlin".to_string()));
+        assert_eq!(range_value("12345678901"), Value::String("12345678901".to_string()));
+    }
+
+    #[test]
+    fn test_eq_keeps_date_literal() {
+        let compared = |hql: &str| match &plan_for(hql).unwrap().tools[1] {
+            ToolArgs::FilterItems { filter } => filter.properties.clone().unwrap()[0][0].value.clone(),
+            other => panic!("expected a filter, got {:?}", other),
+        };
+        assert_eq!(compared("N<User>::WHERE(_::{joined}::EQ(\"2024-01-01\"))"), Value::String("2024-01-01".to_string()));
+        assert_eq!(compared("N<User>::WHERE(_::{joined}::NEQ(\"2024-01-01\"))"), Value::String("2024-01-01".to_string()));
+        assert_eq!(compared("N<User>::WHERE(_::{joined}::GTE(\"2024-01-01\"))"), Value::String("2024-01-01T00:00:00Z".to_string()));
+    }
+
+    #[test]
+    fn test_array_param_expands_to_id_list() {
+        let plan = plan_with_params("N<User>(user_ids)", &serde_json::json!({ "user_ids": ["u1", "u2"] })).unwrap();
+        assert_eq!(plan.id_filters, vec!["u1", "u2"]);
+        let plan = plan_with_params("N<User>(user_ids)", &serde_json::json!({ "user_ids": "[\"u3\", 4]" })).unwrap();
+        assert_eq!(plan.id_filters, vec!["u3", "4"]);
+    }
+
+    #[test]
+    fn test_is_in_expands_array_param() {
+        let params = serde_json::json!({ "names": ["Alice", "Bob"] });
+        let branches = |hql: &str| match &plan_with_params(hql, &params).unwrap().tools[1] {
+            ToolArgs::FilterItems { filter } => filter.properties.clone().unwrap(),
+            other => panic!("expected a filter, got {:?}", other),
+        };
+        assert_eq!(branches("N<User>::WHERE(_::{name}::IS_IN(names))").len(), 2);
+        assert_eq!(branches("N<User>::WHERE(!_::{name}::IS_IN(names))")[0].len(), 2);
+    }
+
+    #[test]
+    fn test_count_filters_on_sub_traversals() {
+        let plan = plan_for("N<User>::WHERE(_::Out<Follows>::WHERE(_::{age}::GT(18))::Out<Likes>::COUNT::GT(10))").unwrap();
+        match &plan.client_filters[..] {
+            [ClientFilter::TraversalCount { prefix_len: 1, tools, operator: Operator::Gt, value }] => {
+                assert_eq!(tools.len(), 3);
+                assert_eq!(*value, 10.0);
+            }
+            other => panic!("expected a traversal count, got {:?}", other),
+        }
+
+        let plan = plan_for("N<User>::WHERE(!_::Out<Follows>::COUNT::GT(3))").unwrap();
+        assert!(matches!(plan.client_filters[..], [ClientFilter::EdgeCount { operator: Operator::Lte, .. }]));
+    }
+
+    #[test]
+    fn test_range_and_first_resolve_parameters() {
+        let params = serde_json::json!({ "offset": "5", "limit": 15 });
+        let plan = plan_with_params("N<User>::RANGE(offset, limit)", &params).unwrap();
+        assert_eq!(plan.final_action, FinalAction::Collect { range: Some((5, Some(15))) });
+        let plan = plan_for("N<User>::WHERE(_::{age}::GT(30))::FIRST").unwrap();
+        assert_eq!(plan.final_action, FinalAction::Collect { range: Some((0, Some(1))) });
+
+        let err = plan_with_params("N<User>::RANGE(0, limit)", &serde_json::json!({ "limit": "ten" })).unwrap_err();
+        assert_eq!(err, "parameter 'limit' must be an integer");
+        assert!(plan_for("N<User>::RANGE(0, limit)").unwrap_err().contains("'limit' is required"));
+        assert!(plan_with_params("N<User>::RANGE(offset, limit)", &serde_json::json!({ "offset": 20, "limit": 10 })).is_err());
+    }
+
+    #[test]
+    fn test_count_compared_after_traversal() {
+        let plan = plan_for("N<User>::Out<Follows>::COUNT::GT(3)").unwrap();
+        assert_eq!(plan.tools.len(), 2);
+        assert_eq!(plan.final_action, FinalAction::CountCompare { operator: Operator::Gt, value: 3.0 });
+
+        let plan = plan_for("N<User>::WHERE(_::In<Follows>::COUNT::GTE(100))").unwrap();
+        match &plan.client_filters[..] {
+            [ClientFilter::EdgeCount { prefix_len: 1, direction: EdgeDirection::In, edge_label, operator: Operator::Gte, value }] => {
+                assert_eq!(edge_label, "Follows");
+                assert_eq!(*value, 100.0);
+            }
+            other => panic!("expected an edge count, got {:?}", other),
+        }
+
+        assert!(plan_for("N<User>::COUNT::Out<Follows>").is_err());
+        assert_eq!(plan_for("N<User>::COUNT::GT(\"many\")").unwrap_err(), "COUNT can only be compared against a number");
+        assert!(plan_for("N<User>::WHERE(_::Out<Follows>::COUNT)").unwrap_err().contains("followed by a comparison"));
+    }
+
+    #[test]
+    fn test_dedup_after_fan_out() {
+        let plan = plan_for("N<User>::Out<Likes>::In<Likes>::DEDUP").unwrap();
+        assert_eq!(plan.tools.len(), 3);
+        assert_eq!(plan.client_filters, vec![ClientFilter::Dedup { prefix_len: 3 }]);
+        assert_eq!(plan.final_action, FinalAction::Collect { range: None });
+        let plan = plan_for("N<User>::Out<Likes>::DEDUP::ORDER<Asc>(_::{name})::RANGE(0, 5)").unwrap();
+        assert_eq!(plan.client_filters, vec![ClientFilter::Dedup { prefix_len: 2 }]);
+
+        let err = plan_for("N<User>::DEDUP::Out<Likes>").unwrap_err();
+        assert!(err.starts_with("DEDUP must come after the last traversal step"), "{}", err);
+    }
+
+    #[test]
+    fn test_property_to_property_where() {
+        let plan = plan_for("N<User>::WHERE(_::{updated_at}::GT(_::{created_at}))").unwrap();
+        assert_eq!(plan.tools.len(), 1);
+        assert_eq!(plan.client_filters, vec![ClientFilter::PropertyCompare {
+            prefix_len: 1,
+            left: "updated_at".to_string(),
+            operator: Operator::Gt,
+            right: "created_at".to_string(),
+        }]);
+        let plan = plan_for("N<User>::WHERE(!_::{spent}::LT(_::{budget}))").unwrap();
+        assert!(matches!(plan.client_filters[..], [ClientFilter::PropertyCompare { operator: Operator::Gte, .. }]));
+
+        let err = plan_for("N<User>::WHERE(_::{a}::GT(_::{b}))::Out<Follows>").unwrap_err();
+        assert!(err.starts_with("WHERE comparisons between two properties must come after"), "{}", err);
+        let err = plan_for("N<User>::WHERE(AND(_::{a}::GT(_::{b}), _::{c}::EQ(1)))").unwrap_err();
+        assert!(err.contains("only supported as a top-level WHERE condition"), "{}", err);
+    }
+
+    #[test]
+    fn test_vector_start_and_payload() {
+        let plan = plan_for("V<PostEmbedding>::WHERE(_::{lang}::EQ(\"en\"))").unwrap();
+        assert_eq!(plan.tools[0], ToolArgs::VFromType { vector_type: "PostEmbedding".to_string(), filter: None });
+        assert!(matches!(plan.tools[1], ToolArgs::FilterItems { .. }));
+        let plan = plan_with_params("V<PostEmbedding>(vec_id)", &serde_json::json!({ "vec_id": "v1" })).unwrap();
+        assert_eq!(plan.id_filters, vec!["v1"]);
+        assert!(plan_for("V<PostEmbedding>(vec_id)").unwrap_err().contains("'vec_id' is required"));
+
+        let vector = normalize_value(serde_json::json!({
+            "id": "v1", "label": "PostEmbedding", "vector": [0.5, 0.25, 1.0], "level": 0, "deleted": false,
+            "properties": { "lang": "en" },
+        }));
+        assert_eq!(vector, serde_json::json!({ "id": "v1", "label": "PostEmbedding", "dimensions": 3, "data": [0.5, 0.25, 1.0], "lang": "en" }));
+        // An edge or a non-numeric list is not a vector payload
+        let edge = serde_json::json!({ "id": "e1", "from_node": "a", "to_node": "b", "level": 2 });
+        assert_eq!(normalize_value(edge.clone()), edge);
+        let tagged = serde_json::json!({ "id": "n1", "data": ["a", "b"], "level": 2 });
+        assert_eq!(normalize_value(tagged.clone()), tagged);
+    }
+
+    #[test]
+    fn test_order_by_return() {
+        let map = serde_json::json!({ "posts": 1, "_implicit_": 2, "total": 3, "users": 4 });
+        let ordered = order_by_return(map.as_object().unwrap().clone(), &["users".to_string(), "total".to_string(), "posts".to_string()]);
+        assert_eq!(ordered.keys().collect::<Vec<_>>(), vec!["users", "total", "posts", "_implicit_"]);
+    }
+
+    #[test]
+    fn test_count_only_keeps_steps() {
+        let mut plan = plan_for("N<User>::WHERE(_::{age}::GT(30))::Out<Follows>::RANGE(0, 10)").unwrap();
+        let tools = plan.tools.clone();
+        plan.count_only();
+        assert_eq!(plan.final_action, FinalAction::Count);
+        assert_eq!(plan.tools, tools);
+
+        let mut compared = plan_for("N<User>::COUNT::GT(3)").unwrap();
+        let action = compared.final_action.clone();
+        compared.count_only();
+        assert_eq!(compared.final_action, action);
+    }
+}
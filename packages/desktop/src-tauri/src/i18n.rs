@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use tauri::Manager;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Catalogs shipped with the app. User files in `<config>/locales/<locale>.json`
+/// override individual keys and can add new locales.
+const BUNDLED_CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("ja", include_str!("../locales/ja.json")),
+    ("zh", include_str!("../locales/zh.json")),
+];
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct LocaleMessages {
+    pub locale: String,
+    pub available: Vec<String>,
+    /// Always complete: keys missing from the locale fall back to English.
+    pub messages: HashMap<String, String>,
+}
+
+static ACTIVE: OnceLock<RwLock<LocaleMessages>> = OnceLock::new();
+
+fn active() -> &'static RwLock<LocaleMessages> {
+    ACTIVE.get_or_init(|| RwLock::new(build_catalog(None, DEFAULT_LOCALE)))
+}
+
+fn user_locales_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|p| p.join("locales"))
+}
+
+fn parse_catalog(content: &str) -> HashMap<String, String> {
+    serde_json::from_str(content).unwrap_or_default()
+}
+
+/// All locales with a bundled or user catalog.
+pub fn available_locales(app: Option<&tauri::AppHandle>) -> Vec<String> {
+    let mut locales: Vec<String> = BUNDLED_CATALOGS.iter().map(|(l, _)| l.to_string()).collect();
+    if let Some(entries) = app.and_then(user_locales_dir).and_then(|d| fs::read_dir(d).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                if let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().to_string()) {
+                    if !locales.contains(&stem) {
+                        locales.push(stem);
+                    }
+                }
+            }
+        }
+    }
+    locales.sort();
+    locales
+}
+
+/// Picks the best available catalog for a tag: `de-AT` falls back to `de`, then English.
+fn resolve_locale(requested: &str, available: &[String]) -> String {
+    let tag = requested.replace('_', "-");
+    let language = tag.split('-').next().unwrap_or_default().to_lowercase();
+    available.iter()
+        .find(|l| l.eq_ignore_ascii_case(&tag))
+        .or_else(|| available.iter().find(|l| l.eq_ignore_ascii_case(&language)))
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string())
+}
+
+/// English, then the bundled catalog, then the user's override file.
+fn build_catalog(app: Option<&tauri::AppHandle>, requested: &str) -> LocaleMessages {
+    let available = available_locales(app);
+    let locale = resolve_locale(requested, &available);
+
+    let bundled = |l: &str| BUNDLED_CATALOGS.iter().find(|(id, _)| *id == l).map(|(_, c)| parse_catalog(c));
+    let mut messages = bundled(DEFAULT_LOCALE).unwrap_or_default();
+    if locale != DEFAULT_LOCALE {
+        messages.extend(bundled(&locale).unwrap_or_default());
+    }
+    if let Some(path) = app.and_then(user_locales_dir).map(|d| d.join(format!("{}.json", locale))) {
+        match fs::read_to_string(&path).map(|c| serde_json::from_str::<HashMap<String, String>>(&c)) {
+            Ok(Ok(overrides)) => messages.extend(overrides),
+            Ok(Err(e)) => println!(">>> [i18n] Ignoring invalid catalog {:?}: {}", path, e),
+            Err(_) => {}
+        }
+    }
+
+    LocaleMessages { locale, available, messages }
+}
+
+fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join("locale.json"))
+}
+
+fn saved_locale(app: &tauri::AppHandle) -> Option<String> {
+    let content = fs::read_to_string(settings_path(app).ok()?).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&content).ok()?;
+    value.get("locale").and_then(|l| l.as_str()).map(String::from)
+}
+
+/// Activates the saved locale, or the system locale on first run.
+pub fn init(app: &tauri::AppHandle) {
+    let requested = saved_locale(app)
+        .or_else(crate::format::system_locale)
+        .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    *active().write().unwrap() = build_catalog(Some(app), &requested);
+}
+
+/// Catalog for `locale` without changing the active one; `None` returns the active catalog.
+pub fn get_messages(app: &tauri::AppHandle, locale: Option<&str>) -> LocaleMessages {
+    match locale {
+        Some(l) => build_catalog(Some(app), l),
+        None => active().read().unwrap().clone(),
+    }
+}
+
+pub fn set_locale(app: &tauri::AppHandle, locale: &str) -> Result<LocaleMessages, String> {
+    let catalog = build_catalog(Some(app), locale);
+    let content = serde_json::to_string_pretty(&serde_json::json!({ "locale": catalog.locale }))
+        .map_err(|e| e.to_string())?;
+    fs::write(settings_path(app)?, content).map_err(|e| e.to_string())?;
+    *active().write().unwrap() = catalog.clone();
+    Ok(catalog)
+}
+
+/// Looks up `key` in the active catalog and fills `{name}` placeholders.
+/// Unknown keys are returned as-is so a missing translation is visible, not fatal.
+pub fn t(key: &str, args: &[(&str, &str)]) -> String {
+    let catalog = active().read().unwrap();
+    let mut text = catalog.messages.get(key).cloned().unwrap_or_else(|| key.to_string());
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalogs_are_complete() {
+        let english = parse_catalog(BUNDLED_CATALOGS[0].1);
+        for (locale, content) in BUNDLED_CATALOGS {
+            let catalog = parse_catalog(content);
+            for key in english.keys() {
+                assert!(catalog.contains_key(key), "'{}' is missing {}", locale, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_locale() {
+        let available: Vec<String> = ["de", "en", "ja", "zh"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(resolve_locale("de-AT", &available), "de");
+        assert_eq!(resolve_locale("zh_CN", &available), "zh");
+        assert_eq!(resolve_locale("fr-FR", &available), "en");
+    }
+}
@@ -0,0 +1,53 @@
+//! One Explorer at a time: launching it again (a double-clicked .hx file, a query
+//! link) hands the arguments to the running instance and focuses its window instead
+//! of starting a second process with its own state and config watchers.
+
+use std::path::{Path, PathBuf};
+use tauri::{Emitter, Manager};
+
+/// Carries a `helixdb-explorer://` link forwarded from a second launch.
+pub const LINK_EVENT: &str = "query-link-opened";
+
+/// Query links among launch arguments.
+pub fn query_links(args: &[String]) -> Vec<String> {
+    let prefix = format!("{}://", crate::share::LINK_SCHEME);
+    args.iter().filter(|arg| arg.starts_with(&prefix)).cloned().collect()
+}
+
+/// Relative paths are relative to where the second launch ran, not to this process.
+fn resolve(paths: Vec<PathBuf>, cwd: &Path) -> Vec<PathBuf> {
+    paths.into_iter().map(|path| if path.is_relative() { cwd.join(path) } else { path }).collect()
+}
+
+pub fn focus_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Handles the arguments of a second launch, `argv[0]` being its executable.
+pub fn forward(app: &tauri::AppHandle, argv: Vec<String>, cwd: String) {
+    println!(">>> [Instance] Forwarded launch: {:?}", argv);
+    focus_main_window(app);
+    for link in query_links(argv.get(1..).unwrap_or_default()) {
+        if let Err(e) = app.emit_to("main", LINK_EVENT, link) {
+            println!(">>> [Instance] Failed to forward query link: {}", e);
+        }
+    }
+    crate::file_open::open_paths(app, resolve(crate::file_open::hx_paths(argv), Path::new(&cwd)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_args() {
+        let argv: Vec<String> = ["explorer", "helixdb-explorer://query?q=abc", "queries.hx", "/tmp/other.hx"].map(String::from).to_vec();
+        assert_eq!(query_links(&argv[1..]), vec!["helixdb-explorer://query?q=abc".to_string()]);
+        let paths = resolve(crate::file_open::hx_paths(argv), Path::new("/home/me/project"));
+        assert_eq!(paths, vec![PathBuf::from("/home/me/project/queries.hx"), PathBuf::from("/tmp/other.hx")]);
+    }
+}
@@ -0,0 +1,319 @@
+//! Live instance events: a subscription reads the gateway's event stream (`events`, or
+//! the connection's `eventsPath`) and forwards each event to the window as
+//! `instance-event`, so query logs can be watched while exploring. A `text/event-stream`
+//! response is read as server-sent events; any other response is treated as a long
+//! poll, its lines forwarded and the request sent again. Events already forwarded (by
+//! id, or by content for long-poll lines without one) are not sent twice. Dropped
+//! streams reconnect with backoff until unsubscribed; a 4xx answer ends the subscription.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use crate::gateway_auth::GatewayAuth;
+use crate::network_trace::SendTraced;
+
+pub const DEFAULT_EVENTS_PATH: &str = "events";
+pub const INSTANCE_EVENT: &str = "instance-event";
+pub const INSTANCE_EVENTS_STATUS: &str = "instance-events-status";
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// A long-poll body, or one server-sent line, may not grow past this.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Event keys remembered for de-duplication.
+const SEEN_CAPACITY: usize = 1024;
+
+/// One server-sent event; `event` is `message` unless the server names it.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+/// Sent as `instance-event`.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceEvent {
+    pub subscription: u64,
+    pub url: String,
+    #[serde(flatten)]
+    pub event: SseEvent,
+}
+
+/// Sent as `instance-events-status` when a subscription connects or loses its stream.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStatus {
+    pub subscription: u64,
+    pub url: String,
+    pub connected: bool,
+    pub error: Option<String>,
+}
+
+/// Splits a byte stream into server-sent events. Chunks may end mid-line or mid-character.
+#[derive(Default)]
+pub struct SseParser {
+    pending: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+    /// The last event id seen, sent as `Last-Event-ID` when reconnecting.
+    pub last_id: Option<String>,
+}
+
+impl SseParser {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                events.extend(self.dispatch());
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                "id" => self.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let id = self.id.take();
+        if id.is_some() {
+            self.last_id = id.clone();
+        }
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data).join("\n");
+        Some(SseEvent { event: event.unwrap_or_else(|| "message".to_string()), data, id })
+    }
+}
+
+/// A long-poll line as an event; a JSON line's `id` becomes the event id.
+fn poll_event(line: &str) -> SseEvent {
+    let id = serde_json::from_str::<serde_json::Value>(line).ok()
+        .and_then(|value| match value.get("id")? {
+            serde_json::Value::String(id) => Some(id.clone()),
+            serde_json::Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        });
+    SseEvent { event: "message".to_string(), data: line.to_string(), id }
+}
+
+/// The most recent events forwarded, so a long poll answering with the same lines again
+/// (or a stream replaying after `Last-Event-ID`) does not repeat them.
+#[derive(Default)]
+struct Seen {
+    order: VecDeque<String>,
+    keys: HashSet<String>,
+}
+
+impl Seen {
+    /// Whether `event` is new; events without an id are only compared when `by_content`.
+    fn first_time(&mut self, event: &SseEvent, by_content: bool) -> bool {
+        let key = match (&event.id, by_content) {
+            (Some(id), _) => format!("id:{}", id),
+            (None, true) => format!("data:{}", event.data),
+            (None, false) => return true,
+        };
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Running subscriptions.
+#[derive(Default)]
+pub struct InstanceEvents {
+    next_id: AtomicU64,
+    subscriptions: Mutex<HashMap<u64, tauri::async_runtime::JoinHandle<()>>>,
+}
+
+fn events_url(connection: Option<&serde_json::Value>, url: &str) -> String {
+    let path = connection.and_then(|c| c.get("eventsPath")?.as_str())
+        .filter(|p| !p.is_empty())
+        .unwrap_or(DEFAULT_EVENTS_PATH);
+    format!("{}/{}", url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+/// Reads one response to its end, forwarding its events. Returns why a stream stopped;
+/// a long poll that answered returns `None`.
+async fn forward(app: &tauri::AppHandle, subscription: u64, url: &str, parser: &mut SseParser, seen: &mut Seen, mut resp: reqwest::Response) -> Option<String> {
+    let sse = resp.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    let mut emit = |event: SseEvent| {
+        if seen.first_time(&event, !sse) {
+            let _ = app.emit(INSTANCE_EVENT, InstanceEvent { subscription, url: url.to_string(), event });
+        }
+    };
+    loop {
+        match resp.chunk().await {
+            Ok(Some(chunk)) if sse => parser.push(&chunk).into_iter().for_each(&mut emit),
+            // Long poll: every line is an event of its own
+            Ok(Some(chunk)) => parser.pending.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(e) => return Some(format!("Event stream failed: {}", e)),
+        }
+        if parser.pending.len() > MAX_BODY_BYTES {
+            parser.pending.clear();
+            return Some(format!("Event stream sent more than {} bytes without a line break", MAX_BODY_BYTES));
+        }
+    }
+    if sse {
+        return Some("Event stream ended".to_string());
+    }
+    let body = std::mem::take(&mut parser.pending);
+    for event in String::from_utf8_lossy(&body).lines().filter(|line| !line.trim().is_empty()).map(poll_event) {
+        if event.id.is_some() {
+            parser.last_id = event.id.clone();
+        }
+        emit(event);
+    }
+    None
+}
+
+async fn run(app: tauri::AppHandle, subscription: u64, client: reqwest::Client, url: String, auth: GatewayAuth) {
+    let events_url = events_url(crate::policy::find_connection(&app, Some(&url)).as_ref(), &url);
+    let mut parser = SseParser::default();
+    let mut seen = Seen::default();
+    let mut backoff = MIN_BACKOFF;
+    let mut connected = false;
+    let status = |connected: bool, error: Option<String>| {
+        let _ = app.emit(INSTANCE_EVENTS_STATUS, StreamStatus { subscription, url: url.clone(), connected, error });
+    };
+    loop {
+        let mut req = client.get(&events_url).header(reqwest::header::ACCEPT, "text/event-stream");
+        if let Some(id) = &parser.last_id {
+            req = req.header("Last-Event-ID", id);
+        }
+        let stopped = match auth.apply(req).send_traced().await {
+            Ok(resp) if resp.status().is_success() => {
+                if !connected {
+                    connected = true;
+                    status(true, None);
+                }
+                backoff = MIN_BACKOFF;
+                match forward(&app, subscription, &url, &mut parser, &mut seen, resp).await {
+                    Some(stopped) => stopped,
+                    None => {
+                        // Polled again at most once a second, should the server not hold requests
+                        tokio::time::sleep(MIN_BACKOFF).await;
+                        continue;
+                    }
+                }
+            }
+            Ok(resp) if resp.status() == reqwest::StatusCode::NOT_FOUND => {
+                status(false, Some(format!("The gateway has no event stream at {}", events_url)));
+                return;
+            }
+            // Asking again will not help, bar timeouts and rate limits
+            Ok(resp) if resp.status().is_client_error()
+                && !matches!(resp.status(), reqwest::StatusCode::REQUEST_TIMEOUT | reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+                status(false, Some(format!("Event stream answered {}; not reconnecting", resp.status())));
+                return;
+            }
+            Ok(resp) => format!("Event stream answered {}", resp.status()),
+            Err(e) => crate::commands::network::map_reqwest_error(e, "Event stream failed"),
+        };
+        println!(">>> [Events] {} ({}); reconnecting in {:?}", stopped, events_url, backoff);
+        connected = false;
+        status(false, Some(stopped));
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Starts forwarding `url`'s events; the returned id unsubscribes.
+pub fn subscribe(app: &tauri::AppHandle, client: reqwest::Client, url: &str, auth: GatewayAuth) -> u64 {
+    let state = app.state::<InstanceEvents>();
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let (task_app, url) = (app.clone(), url.trim_end_matches('/').to_string());
+    // Held until the task is recorded, so one that ends at once still finds itself to remove
+    let mut subscriptions = state.subscriptions.lock().unwrap();
+    let task = tauri::async_runtime::spawn(async move {
+        run(task_app.clone(), id, client, url, auth).await;
+        task_app.state::<InstanceEvents>().subscriptions.lock().unwrap().remove(&id);
+    });
+    subscriptions.insert(id, task);
+    id
+}
+
+/// Returns whether the subscription was running.
+pub fn unsubscribe(app: &tauri::AppHandle, id: u64) -> bool {
+    match app.state::<InstanceEvents>().subscriptions.lock().unwrap().remove(&id) {
+        Some(task) => {
+            task.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b": keep-alive\nevent: query\ndata: {\"ms\":").is_empty());
+        let events = parser.push(" 12}\r\nid: 7\n\ndata: a\ndata: b\n\n".as_bytes());
+        assert_eq!(events, vec![
+            SseEvent { event: "query".to_string(), data: "{\"ms\": 12}".to_string(), id: Some("7".to_string()) },
+            SseEvent { event: "message".to_string(), data: "a\nb".to_string(), id: None },
+        ]);
+        assert_eq!(parser.last_id.as_deref(), Some("7"));
+
+        // A character split across chunks
+        let bytes = "data: é\n\n".as_bytes();
+        assert!(parser.push(&bytes[..7]).is_empty());
+        assert_eq!(parser.push(&bytes[7..])[0].data, "é");
+
+        assert_eq!(events_url(None, "http://h:6969/"), "http://h:6969/events");
+        assert_eq!(events_url(Some(&serde_json::json!({ "eventsPath": "/logs/stream" })), "http://h"), "http://h/logs/stream");
+    }
+
+    #[test]
+    fn test_seen_skips_repeated_events() {
+        let mut seen = Seen::default();
+        let first = poll_event(r#"{"id": 4, "query": "a"}"#);
+        assert_eq!(first.id.as_deref(), Some("4"));
+        assert!(seen.first_time(&first, true));
+        assert!(!seen.first_time(&poll_event(r#"{"id": 4, "query": "b"}"#), true));
+        assert!(seen.first_time(&poll_event("plain line"), true));
+        assert!(!seen.first_time(&poll_event("plain line"), true));
+        // Stream events without an id are never dropped
+        let message = SseEvent { event: "message".to_string(), data: "tick".to_string(), id: None };
+        assert!(seen.first_time(&message, false) && seen.first_time(&message, false));
+
+        for i in 0..SEEN_CAPACITY {
+            seen.first_time(&poll_event(&format!("line {}", i)), true);
+        }
+        assert_eq!(seen.order.len(), SEEN_CAPACITY);
+        assert!(seen.first_time(&first, true));
+    }
+}
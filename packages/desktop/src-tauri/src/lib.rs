@@ -1,7 +1,55 @@
 pub mod hql;
 pub mod stats;
+pub mod db_verify;
 pub mod commands;
 pub mod config;
+pub mod connection_groups;
+pub mod config_profiles;
+pub mod workspace;
+pub mod helix_cli;
+pub mod capabilities;
+pub mod compiled_queries;
+pub mod onboarding;
+pub mod tutorials;
+pub mod library;
+pub mod library_sync;
+pub mod summary;
+pub mod usage;
+pub mod format;
+pub mod gateway_auth;
+pub mod client_identity;
+pub mod proxy;
+pub mod connection_clients;
+pub mod ssh_tunnel;
+pub mod health;
+pub mod instance_events;
+pub mod network_trace;
+pub mod curl;
+pub mod rate_limit;
+pub mod i18n;
+pub mod appearance;
+pub mod window_state;
+pub mod grid_prefs;
+pub mod pinned;
+pub mod print;
+pub mod find_replace;
+pub mod versions;
+pub mod diff;
+pub mod history;
+pub mod slow_queries;
+pub mod share;
+pub mod policy;
+pub mod redaction;
+pub mod schema_cache;
+pub mod demo;
+pub mod environment;
+pub mod project_queries;
+pub mod local_api;
+pub mod cli;
+pub mod file_open;
+pub mod instance;
+#[cfg(test)]
+pub mod test_support;
 
 use tauri::menu::{Menu, MenuItem, Submenu, PredefinedMenuItem};
 use tauri::{Emitter, Manager};
@@ -13,7 +61,27 @@ use reqwest::Client;
 
 pub struct NetworkState {
     pub client: Client,
-    pub mcp_connections: Mutex<HashMap<String, String>>, // URL -> connection_id
+    pub mcp_pool: std::sync::Arc<hql::pool::McpPool>,
+    pub server_capabilities: capabilities::CapabilityCache, // URL -> probed capabilities
+    pub page_sessions: hql::paging::PageSessions,
+    pub query_cache: hql::cache::QueryCache,
+    pub compiled_queries: Mutex<HashMap<String, Vec<compiled_queries::CompiledQuery>>>, // URL -> deployed queries
+    pub query_queue: hql::queue::QueryQueue,
+    pub schema_cache: schema_cache::SchemaCache,
+    pub connection_clients: connection_clients::ConnectionClients,
+    pub rate_limits: rate_limit::RateLimits,
+}
+
+impl NetworkState {
+    /// The HTTP client for requests to `url`, with its connection's client certificate and proxy.
+    pub fn client_for(&self, app: &tauri::AppHandle, url: &str) -> Result<Client, String> {
+        self.connection_clients.client_for(&self.client, crate::policy::find_connection(app, Some(url)).as_ref())
+    }
+
+    /// The limiter for `url`'s connection, when it sets a `rateLimit`.
+    pub fn rate_limit_for(&self, app: &tauri::AppHandle, url: &str) -> Option<std::sync::Arc<rate_limit::Limiter>> {
+        self.rate_limits.for_connection(url, crate::policy::find_connection(app, Some(url)).as_ref())
+    }
 }
 
 pub struct PendingCopyData {
@@ -27,36 +95,55 @@ pub struct AppState(pub Mutex<PendingCopyData>);
 pub fn run() {
     println!(">>> [HelixDB Explorer] Backend starting up on port 1420...");
 
-    let client = Client::builder()
-        .no_proxy()
-        .pool_max_idle_per_host(10)
-        .tcp_keepalive(Some(std::time::Duration::from_secs(60)))
-        .build()
-        .expect("Failed to build reqwest client");
+    let client = commands::network::build_client().expect("Failed to build reqwest client");
 
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // Registered first so a second launch exits before setting anything up
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+        instance::forward(app, argv, cwd);
+    }));
+
+    builder
         .manage(AppState(Mutex::new(PendingCopyData { tsv: String::new(), json: String::new() })))
         .manage(NetworkState { 
             client, 
-            mcp_connections: Mutex::new(HashMap::new()) 
+            mcp_pool: Default::default(),
+            server_capabilities: Default::default(),
+            page_sessions: Default::default(),
+            query_cache: Default::default(),
+            compiled_queries: Mutex::new(HashMap::new()),
+            query_queue: Default::default(),
+            schema_cache: Default::default(),
+            connection_clients: Default::default(),
+            rate_limits: Default::default(),
         })
+        .manage(config::ConfigWatchState::default())
+        .manage(local_api::LocalApiState::default())
+        .manage(instance_events::InstanceEvents::default())
+        .manage(file_open::OpenedFiles::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_http::init())
         .setup(|app| {
-            #[cfg(target_os = "macos")]
             if let Some(window) = app.get_webview_window("main") {
-                use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
-                let _ = apply_vibrancy(&window, NSVisualEffectMaterial::Sidebar, None, None);
+                if let Err(e) = window_state::restore_window_state(&window) {
+                    println!(">>> [WindowState] Failed to restore window state: {}", e);
+                }
+                if let Err(e) = appearance::apply_appearance(&window, &appearance::WindowAppearance::default()) {
+                    println!(">>> [Appearance] Failed to apply window appearance: {}", e);
+                }
             }
 
-            let settings_item = MenuItem::with_id(app, "settings", "Settings...", true, Some("CmdOrCtrl+,"))?;
-            let about_item = PredefinedMenuItem::about(app, Some("About HelixDB Explorer"), None)?;
-            let quit_item = PredefinedMenuItem::quit(app, Some("Quit HelixDB Explorer"))?;
+            i18n::init(app.handle());
+
+            let settings_item = MenuItem::with_id(app, "settings", &i18n::t("menu.settings", &[]), true, Some("CmdOrCtrl+,"))?;
+            let about_item = PredefinedMenuItem::about(app, Some(i18n::t("menu.about", &[]).as_str()), None)?;
+            let quit_item = PredefinedMenuItem::quit(app, Some(i18n::t("menu.quit", &[]).as_str()))?;
             let separator = PredefinedMenuItem::separator(app)?;
-            let hide_item = PredefinedMenuItem::hide(app, Some("Hide HelixDB Explorer"))?;
-            let hide_others_item = PredefinedMenuItem::hide_others(app, Some("Hide Others"))?;
-            let show_all_item = PredefinedMenuItem::show_all(app, Some("Show All"))?;
+            let hide_item = PredefinedMenuItem::hide(app, Some(i18n::t("menu.hide", &[]).as_str()))?;
+            let hide_others_item = PredefinedMenuItem::hide_others(app, Some(i18n::t("menu.hide_others", &[]).as_str()))?;
+            let show_all_item = PredefinedMenuItem::show_all(app, Some(i18n::t("menu.show_all", &[]).as_str()))?;
 
             let app_menu = Submenu::with_items(
                 app,
@@ -77,7 +164,7 @@ pub fn run() {
 
             let edit_menu = Submenu::with_items(
                 app,
-                "Edit",
+                i18n::t("menu.edit", &[]),
                 true,
                 &[
                     &PredefinedMenuItem::undo(app, None)?,
@@ -92,7 +179,7 @@ pub fn run() {
 
             let window_menu = Submenu::with_items(
                 app,
-                "Window",
+                i18n::t("menu.window", &[]),
                 true,
                 &[
                     &PredefinedMenuItem::minimize(app, None)?,
@@ -105,6 +192,20 @@ pub fn run() {
             let menu = Menu::with_items(app, &[&app_menu, &edit_menu, &window_menu])?;
             app.set_menu(menu)?;
 
+            config::watch_connection_config(app.handle().clone());
+            file_open::open_paths(app.handle(), file_open::hx_paths(std::env::args()));
+
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let started = match local_api::load_settings(&handle) {
+                    Ok(settings) => local_api::apply(&handle, &settings).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = started {
+                    println!(">>> [LocalApi] Failed to start: {}", e);
+                }
+            });
+
             Ok(())
         })
         .on_menu_event(|app, event| {
@@ -127,25 +228,128 @@ pub fn run() {
             terminate_app,
             helix_request,
             fetch_mcp_schema,
+            refresh_schema,
+            get_server_capabilities,
+            check_connection,
+            subscribe_instance_events,
+            unsubscribe_instance_events,
+            set_network_trace,
+            get_network_trace,
+            clear_network_trace,
+            copy_last_request_as_curl,
+            get_ssh_tunnel_status,
+            trust_ssh_host_key,
+            list_compiled_queries,
+            describe_query,
             execute_query,
             execute_dynamic_hql,
+            execute_dynamic_hql_page,
+            execute_dynamic_hql_sample,
+            execute_hql_batch,
+            list_query_queue,
+            cancel_queued_query,
+            explain_hql,
+            replay_load,
+            find_entity_occurrences,
+            get_slow_queries,
+            clear_slow_queries,
+            get_usage_stats,
+            reset_usage_stats,
+            suggest_indexes,
+            invalidate_query_cache,
+            list_translation_profiles,
+            check_protocol_compat,
+            audit_edge_directions,
+            pin_result,
+            list_pinned_results,
+            get_pinned_result,
+            relabel_pinned_result,
+            unpin_result,
+            compare_pinned,
+            summarize_result,
+            check_hql_expectations,
+            parameterize_query,
+            generate_seed_query,
             load_connection_config,
             save_connection_config,
+            list_config_profiles,
+            switch_config_profile,
+            create_connection_group,
+            rename_connection_group,
+            set_connection_group_collapsed,
+            delete_connection_group,
+            move_connection_group,
+            move_connection,
+            import_from_helix_cli,
+            get_workspace_policy,
+            get_local_api_settings,
+            set_local_api_settings,
+            regenerate_local_api_token,
+            detect_onboarding_environment,
+            run_onboarding,
+            list_tutorials,
+            get_tutorial,
+            check_tutorial_attempt,
+            reset_tutorial_progress,
+            list_saved_queries,
+            save_query,
+            delete_query,
+            generate_cookbook,
+            encode_query_link,
+            decode_query_link,
+            configure_library_repo,
+            library_repo_status,
+            pull_library_repo,
+            push_library_repo,
             sync_hql_to_project,
+            compute_sync_diff,
+            sync_library_to_project,
+            clean_project_queries,
             detect_workspace_path,
+            revalidate_workspace,
             show_grid_context_menu,
+            get_grid_prefs,
+            set_grid_prefs,
+            take_opened_files,
+            format_values,
+            set_window_appearance,
+            set_webview_zoom,
+            print_view,
+            set_demo_mode,
+            get_demo_mode,
+            find_replace_in_tabs,
+            get_locale_messages,
+            set_locale,
+            snapshot_worksheet,
+            list_worksheet_versions,
+            restore_worksheet_version,
+            diff_worksheet_versions,
             validate_hql,
             get_hql_completion,
             format_hql,
             get_vector_projections,
             get_local_db_stats,
+            verify_local_db,
+            compact_local_db,
+            swap_compacted_db,
             validate_helix_workspace
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { .. } = event {
+                if let Err(e) = window_state::save_window_state(window) {
+                    println!(">>> [WindowState] Failed to save window state: {}", e);
+                }
                 let _ = window.emit("cleanup-on-exit", ());
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            // macOS delivers file association opens as an event rather than as arguments
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                let paths = urls.iter().filter_map(|url| url.to_file_path().ok()).collect();
+                file_open::open_paths(_app, paths);
+            }
+        });
 }
@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+/// A query saved in the library. Field names match the frontend (camelCase).
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub folder: Option<String>,
+    pub code: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct QueryLibrary {
+    #[serde(default)]
+    pub queries: Vec<SavedQuery>,
+}
+
+/// Order of queries in a bulk sync; an explicit list of ids overrides it.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionOrder {
+    /// As stored in the library.
+    #[default]
+    Library,
+    Name,
+    /// Least recently updated first, so newer edits land last.
+    Updated,
+}
+
+impl QueryLibrary {
+    /// Queries in `folder` and its subfolders (`a` matches `a/b`), or every query
+    /// without a folder filter. With `ids`, only those are returned, in that order.
+    pub fn select(&self, folder: Option<&str>, ids: Option<&[String]>, order: SelectionOrder) -> Vec<&SavedQuery> {
+        let in_folder = |q: &&SavedQuery| match folder {
+            None => true,
+            Some(folder) => q.folder.as_deref()
+                .map(|f| f == folder || f.starts_with(&format!("{}/", folder)))
+                .unwrap_or(false),
+        };
+        if let Some(ids) = ids {
+            return ids.iter()
+                .filter_map(|id| self.queries.iter().find(|q| &q.id == id))
+                .filter(in_folder)
+                .collect();
+        }
+        let mut selected: Vec<&SavedQuery> = self.queries.iter().filter(in_folder).collect();
+        match order {
+            SelectionOrder::Library => {}
+            SelectionOrder::Name => selected.sort_by_key(|q| q.name.to_lowercase()),
+            SelectionOrder::Updated => selected.sort_by(|a, b| a.updated_at.cmp(&b.updated_at)),
+        }
+        selected
+    }
+
+    /// Inserts or replaces by id, keeping the original creation time.
+    pub fn upsert(&mut self, mut query: SavedQuery) {
+        let now = chrono::Utc::now().to_rfc3339();
+        query.updated_at = now.clone();
+        match self.queries.iter_mut().find(|q| q.id == query.id) {
+            Some(existing) => {
+                query.created_at = existing.created_at.clone();
+                *existing = query;
+            }
+            None => {
+                if query.created_at.is_empty() {
+                    query.created_at = now;
+                }
+                self.queries.push(query);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.queries.len();
+        self.queries.retain(|q| q.id != id);
+        self.queries.len() != before
+    }
+}
+
+pub const LIBRARY_FILE: &str = "query_library.json";
+
+pub fn get_library_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir()
+        .map_err(|e| format!("Could not find config directory: {}", e))?;
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    }
+    Ok(dir.join(LIBRARY_FILE))
+}
+
+pub fn load_library(app: &tauri::AppHandle) -> Result<QueryLibrary, String> {
+    load_library_at(&get_library_path(app)?)
+}
+
+pub fn load_library_at(path: &Path) -> Result<QueryLibrary, String> {
+    if !path.exists() {
+        return Ok(QueryLibrary::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid query library: {}", e))
+}
+
+pub fn save_library(app: &tauri::AppHandle, library: &QueryLibrary) -> Result<(), String> {
+    save_library_at(&get_library_path(app)?, library)
+}
+
+pub fn save_library_at(path: &Path, library: &QueryLibrary) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(library).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: &str, name: &str, folder: Option<&str>) -> SavedQuery {
+        SavedQuery {
+            id: id.to_string(),
+            name: name.to_string(),
+            folder: folder.map(String::from),
+            code: String::new(),
+            params: None,
+            description: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_select_by_folder_and_order() {
+        let library = QueryLibrary { queries: vec![
+            query("1", "users", Some("reports")),
+            query("2", "Active", Some("reports/daily")),
+            query("3", "posts", Some("reporting")),
+        ] };
+        let names = |qs: Vec<&SavedQuery>| qs.iter().map(|q| q.id.clone()).collect::<Vec<_>>();
+        assert_eq!(names(library.select(Some("reports"), None, SelectionOrder::Library)), vec!["1", "2"]);
+        assert_eq!(names(library.select(Some("reports"), None, SelectionOrder::Name)), vec!["2", "1"]);
+        let ids = vec!["3".to_string(), "1".to_string()];
+        assert_eq!(names(library.select(None, Some(&ids), SelectionOrder::Name)), vec!["3", "1"]);
+    }
+}
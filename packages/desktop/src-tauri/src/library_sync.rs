@@ -0,0 +1,423 @@
+use crate::hql::cookbook;
+use crate::library::{self, SavedQuery};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Manager;
+
+/// Queries live one file each under this directory of the repository, so every
+/// change is its own commit and diffs stay readable.
+const QUERIES_DIR: &str = "queries";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRepoSettings {
+    pub remote: String,
+    /// Defaults to the remote's default branch.
+    #[serde(default)]
+    pub branch: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRepoStatus {
+    pub settings: Option<LibraryRepoSettings>,
+    /// Ids of queries changed locally since the last sync.
+    pub local_changes: Vec<String>,
+}
+
+/// A query edited on both sides since the last sync. `None` means deleted on that side.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryConflict {
+    pub id: String,
+    pub local: Option<SavedQuery>,
+    pub remote: Option<SavedQuery>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySyncResult {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Unresolved conflicts; when non-empty, nothing was applied.
+    pub conflicts: Vec<QueryConflict>,
+    pub commits: usize,
+}
+
+/// How to settle a conflict on pull: keep the "local" or take the "remote" version.
+pub type ConflictResolutions = HashMap<String, String>;
+
+type QuerySet = BTreeMap<String, SavedQuery>;
+
+/// The app config directory, which holds the library and its repository clone.
+pub fn config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn repo_dir(config: &Path) -> PathBuf {
+    config.join("library_repo")
+}
+
+impl LibraryRepoSettings {
+    /// Remotes and branches reach git as arguments, so one starting with `-` would be
+    /// read as an option.
+    fn check(&self) -> Result<(), String> {
+        let remote = self.remote.trim();
+        if remote.is_empty() || remote.starts_with('-') {
+            return Err(format!("Invalid library repository remote '{}'", self.remote));
+        }
+        if let Some(branch) = &self.branch {
+            if branch.trim().is_empty() || branch.starts_with('-') {
+                return Err(format!("Invalid library repository branch '{}'", branch));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn load_settings(config: &Path) -> Result<Option<LibraryRepoSettings>, String> {
+    let path = config.join("library_repo.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let settings: LibraryRepoSettings = serde_json::from_str(&content).map_err(|e| format!("Invalid library repository settings: {}", e))?;
+    settings.check()?;
+    Ok(Some(settings))
+}
+
+fn save_settings(config: &Path, settings: &LibraryRepoSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(config.join("library_repo.json"), content).map_err(|e| e.to_string())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git (is it installed?): {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+    } else {
+        Err(format!("git {} failed: {}", args.first().unwrap_or(&""), String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+fn query_file(id: &str) -> String {
+    let stem: String = id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}/{}.json", QUERIES_DIR, stem)
+}
+
+/// Timestamps are bumped on every save, so only the content decides whether a query changed.
+fn same_content(a: &SavedQuery, b: &SavedQuery) -> bool {
+    a.name == b.name && a.folder == b.folder && a.code == b.code && a.params == b.params && a.description == b.description
+}
+
+fn changed(a: Option<&SavedQuery>, b: Option<&SavedQuery>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => !same_content(a, b),
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// Cookbook examples are regenerated from each user's schema and never shared.
+fn local_queries(config: &Path) -> Result<QuerySet, String> {
+    Ok(library::load_library_at(&config.join(library::LIBRARY_FILE))?.queries.into_iter()
+        .filter(|q| !cookbook::is_example(q))
+        .map(|q| (q.id.clone(), q))
+        .collect())
+}
+
+/// The last synced state: the clone's working tree, which is clean between operations.
+fn base_queries(dir: &Path) -> QuerySet {
+    let entries = match fs::read_dir(dir.join(QUERIES_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return QuerySet::new(),
+    };
+    entries.flatten()
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|c| serde_json::from_str::<SavedQuery>(&c).ok())
+        .map(|q| (q.id.clone(), q))
+        .collect()
+}
+
+fn remote_ref(branch: &str) -> String {
+    format!("origin/{}", branch)
+}
+
+/// Queries on the fetched remote branch; empty when the remote has no commits yet.
+fn remote_queries(dir: &Path, branch: &str) -> Result<QuerySet, String> {
+    let rev = remote_ref(branch);
+    if git(dir, &["rev-parse", "--verify", "--quiet", &rev]).is_err() {
+        return Ok(QuerySet::new());
+    }
+    let files = git(dir, &["ls-tree", "--name-only", &rev, &format!("{}/", QUERIES_DIR)])?;
+    let mut queries = QuerySet::new();
+    for file in files.lines().filter(|f| f.ends_with(".json")) {
+        let content = git(dir, &["show", &format!("{}:{}", rev, file)])?;
+        match serde_json::from_str::<SavedQuery>(&content) {
+            Ok(q) => {
+                queries.insert(q.id.clone(), q);
+            }
+            Err(e) => println!(">>> [LibrarySync] Skipping invalid {}: {}", file, e),
+        }
+    }
+    Ok(queries)
+}
+
+fn current_branch(dir: &Path, settings: &LibraryRepoSettings) -> String {
+    settings.branch.clone()
+        .or_else(|| git(dir, &["symbolic-ref", "--short", "HEAD"]).ok())
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Clones the repository (replacing any previous clone) and merges its queries into
+/// the local library. Local queries with the same id are kept; they win on next push.
+pub fn configure(config: &Path, settings: LibraryRepoSettings) -> Result<LibrarySyncResult, String> {
+    settings.check()?;
+    let dir = repo_dir(config);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove previous clone: {}", e))?;
+    }
+    let target = dir.to_string_lossy().to_string();
+    let mut args = vec!["clone"];
+    if let Some(branch) = &settings.branch {
+        args.extend(["--branch", branch.as_str()]);
+    }
+    args.extend(["--", settings.remote.as_str(), target.as_str()]);
+    git(config, &args)?;
+    if git(&dir, &["config", "user.email"]).is_err() {
+        git(&dir, &["config", "user.name", "HelixDB Explorer"])?;
+        git(&dir, &["config", "user.email", "explorer@localhost"])?;
+    }
+    save_settings(config, &settings)?;
+
+    let library_path = config.join(library::LIBRARY_FILE);
+    let mut lib = library::load_library_at(&library_path)?;
+    let mut result = LibrarySyncResult::default();
+    for (id, query) in base_queries(&dir) {
+        if !lib.queries.iter().any(|q| q.id == id) {
+            lib.queries.push(query);
+            result.added.push(id);
+        }
+    }
+    library::save_library_at(&library_path, &lib)?;
+    Ok(result)
+}
+
+pub fn status(config: &Path) -> Result<LibraryRepoStatus, String> {
+    let settings = load_settings(config)?;
+    let local_changes = match &settings {
+        Some(_) => {
+            let base = base_queries(&repo_dir(config));
+            let local = local_queries(config)?;
+            let ids: BTreeSet<&String> = base.keys().chain(local.keys()).collect();
+            ids.into_iter().filter(|id| changed(base.get(*id), local.get(*id))).cloned().collect()
+        }
+        None => Vec::new(),
+    };
+    Ok(LibraryRepoStatus { settings, local_changes })
+}
+
+fn require_repo(config: &Path) -> Result<(LibraryRepoSettings, PathBuf), String> {
+    let settings = load_settings(config)?.ok_or_else(|| "No library repository is configured".to_string())?;
+    let dir = repo_dir(config);
+    if !dir.join(".git").exists() {
+        return Err("The library repository clone is missing; configure it again".to_string());
+    }
+    Ok((settings, dir))
+}
+
+/// Three-way merge of local and remote against the last synced state. Returns the
+/// queries to write into the local library (`None` = delete) and the conflicts left.
+fn merge(base: &QuerySet, local: &QuerySet, remote: &QuerySet, resolutions: &ConflictResolutions) -> (Vec<(String, Option<SavedQuery>)>, Vec<QueryConflict>) {
+    let ids: BTreeSet<&String> = base.keys().chain(local.keys()).chain(remote.keys()).collect();
+    let mut apply = Vec::new();
+    let mut conflicts = Vec::new();
+    for id in ids {
+        let (b, l, r) = (base.get(id), local.get(id), remote.get(id));
+        let local_changed = changed(b, l);
+        let remote_changed = changed(b, r);
+        if !remote_changed || !changed(l, r) {
+            continue;
+        }
+        if !local_changed {
+            apply.push((id.clone(), r.cloned()));
+            continue;
+        }
+        match resolutions.get(id).map(String::as_str) {
+            Some("remote") => apply.push((id.clone(), r.cloned())),
+            Some("local") => {}
+            _ => conflicts.push(QueryConflict { id: id.clone(), local: l.cloned(), remote: r.cloned() }),
+        }
+    }
+    (apply, conflicts)
+}
+
+/// Fetches the remote and applies its changes to the local library. If any query was
+/// edited on both sides and has no entry in `resolutions`, nothing is changed and the
+/// conflicts are returned for the user to settle.
+pub fn pull(config: &Path, resolutions: &ConflictResolutions) -> Result<LibrarySyncResult, String> {
+    let (settings, dir) = require_repo(config)?;
+    let branch = current_branch(&dir, &settings);
+    git(&dir, &["fetch", "origin"])?;
+
+    let base = base_queries(&dir);
+    let local = local_queries(config)?;
+    let remote = remote_queries(&dir, &branch)?;
+    let (apply, conflicts) = merge(&base, &local, &remote, resolutions);
+    if !conflicts.is_empty() {
+        return Ok(LibrarySyncResult { conflicts, ..Default::default() });
+    }
+
+    // Local edits live in the library file and are committed on push, so the clone
+    // can simply move to the remote state.
+    if git(&dir, &["rev-parse", "--verify", "--quiet", &remote_ref(&branch)]).is_ok() {
+        git(&dir, &["reset", "--hard", &remote_ref(&branch)])?;
+    }
+
+    let library_path = config.join(library::LIBRARY_FILE);
+    let mut lib = library::load_library_at(&library_path)?;
+    let mut result = LibrarySyncResult::default();
+    for (id, query) in apply {
+        let existing = lib.queries.iter().position(|q| q.id == id);
+        match (existing, query) {
+            (Some(i), Some(q)) => {
+                lib.queries[i] = q;
+                result.updated.push(id);
+            }
+            (None, Some(q)) => {
+                lib.queries.push(q);
+                result.added.push(id);
+            }
+            (Some(i), None) => {
+                lib.queries.remove(i);
+                result.removed.push(id);
+            }
+            (None, None) => {}
+        }
+    }
+    library::save_library_at(&library_path, &lib)?;
+    Ok(result)
+}
+
+/// Commits each locally changed query separately and pushes. A rejected push (the
+/// remote moved on) asks for a pull first rather than forcing.
+pub fn push(config: &Path, message: Option<&str>) -> Result<LibrarySyncResult, String> {
+    let (settings, dir) = require_repo(config)?;
+    let branch = current_branch(&dir, &settings);
+    let base = base_queries(&dir);
+    let local = local_queries(config)?;
+    fs::create_dir_all(dir.join(QUERIES_DIR)).map_err(|e| e.to_string())?;
+
+    let mut result = LibrarySyncResult::default();
+    let ids: BTreeSet<&String> = base.keys().chain(local.keys()).collect();
+    for id in ids {
+        let (b, l) = (base.get(id), local.get(id));
+        if !changed(b, l) {
+            continue;
+        }
+        let file = query_file(id);
+        let summary = match (b, l) {
+            (_, Some(q)) => {
+                let content = serde_json::to_string_pretty(q).map_err(|e| e.to_string())?;
+                fs::write(dir.join(&file), content + "\n").map_err(|e| e.to_string())?;
+                git(&dir, &["add", &file])?;
+                if b.is_some() {
+                    result.updated.push(id.clone());
+                    format!("Update query '{}'", q.name)
+                } else {
+                    result.added.push(id.clone());
+                    format!("Add query '{}'", q.name)
+                }
+            }
+            (Some(q), None) => {
+                git(&dir, &["rm", "--quiet", "--ignore-unmatch", &file])?;
+                let _ = fs::remove_file(dir.join(&file));
+                result.removed.push(id.clone());
+                format!("Delete query '{}'", q.name)
+            }
+            (None, None) => continue,
+        };
+        let commit_message = match message {
+            Some(m) if !m.trim().is_empty() => format!("{}\n\n{}", summary, m.trim()),
+            _ => summary,
+        };
+        git(&dir, &["commit", "--quiet", "-m", &commit_message])?;
+        result.commits += 1;
+    }
+
+    if result.commits > 0 {
+        git(&dir, &["push", "origin", &format!("HEAD:{}", branch)]).map_err(|e| {
+            if e.contains("rejected") || e.contains("non-fast-forward") {
+                "The shared library has new changes. Pull before pushing.".to_string()
+            } else {
+                e
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: &str, code: &str) -> SavedQuery {
+        SavedQuery {
+            id: id.to_string(),
+            name: id.to_string(),
+            folder: None,
+            code: code.to_string(),
+            params: None,
+            description: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn set(queries: &[SavedQuery]) -> QuerySet {
+        queries.iter().map(|q| (q.id.clone(), q.clone())).collect()
+    }
+
+    #[test]
+    fn test_merge_detects_conflicts() {
+        let base = set(&[query("a", "1"), query("b", "1"), query("c", "1")]);
+        let local = set(&[query("a", "2"), query("b", "1"), query("c", "local")]);
+        let remote = set(&[query("a", "1"), query("b", "2"), query("c", "remote"), query("d", "1")]);
+
+        let (apply, conflicts) = merge(&base, &local, &remote, &ConflictResolutions::new());
+        let applied: Vec<&str> = apply.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(applied, vec!["b", "d"]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, "c");
+
+        let resolutions = ConflictResolutions::from([("c".to_string(), "remote".to_string())]);
+        let (apply, conflicts) = merge(&base, &local, &remote, &resolutions);
+        assert!(conflicts.is_empty());
+        assert_eq!(apply.len(), 3);
+    }
+
+    #[test]
+    fn test_settings_refuse_option_like_values() {
+        let settings = |remote: &str, branch: Option<&str>| LibraryRepoSettings { remote: remote.to_string(), branch: branch.map(String::from) };
+        assert!(settings("git@example.com:team/queries.git", Some("main")).check().is_ok());
+        assert!(settings("--upload-pack=touch /tmp/x", None).check().is_err());
+        assert!(settings(" ", None).check().is_err());
+        assert!(settings("https://example.com/q.git", Some("-b")).check().is_err());
+
+        let config = tempfile::tempdir().unwrap();
+        fs::write(config.path().join("library_repo.json"), r#"{ "remote": "-oProxyCommand=x" }"#).unwrap();
+        assert!(load_settings(config.path()).is_err());
+        assert!(configure(config.path(), settings("--template=/tmp", None)).is_err());
+    }
+}
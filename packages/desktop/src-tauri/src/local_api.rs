@@ -0,0 +1,422 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const SETTINGS_FILE: &str = "local_api.json";
+pub const DEFAULT_PORT: u16 = 6970;
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The localhost API lets scripts on this machine run saved queries, export results
+/// and read schemas. It is off until enabled, and every request needs the token.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+/// The running server, if any.
+#[derive(Default)]
+pub struct LocalApiState {
+    server: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+pub fn new_token() -> String {
+    let bytes: [u8; 24] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Saved settings; a token is generated and saved the first time.
+pub fn load_settings(app: &tauri::AppHandle) -> Result<LocalApiSettings, String> {
+    let mut settings = fs::read_to_string(settings_path(app)?).ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or(LocalApiSettings { enabled: false, port: DEFAULT_PORT, token: String::new() });
+    if settings.token.is_empty() {
+        settings.token = new_token();
+        save_settings(app, &settings)?;
+    }
+    Ok(settings)
+}
+
+pub fn save_settings(app: &tauri::AppHandle, settings: &LocalApiSettings) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(settings_path(app)?, content).map_err(|e| e.to_string())
+}
+
+/// Stops the server, then starts it again on the settings' port if they enable it.
+pub async fn apply(app: &tauri::AppHandle, settings: &LocalApiSettings) -> Result<(), String> {
+    let state = app.state::<LocalApiState>();
+    if let Some(server) = state.server.lock().unwrap().take() {
+        server.abort();
+    }
+    if !settings.enabled {
+        return Ok(());
+    }
+    let listener = TcpListener::bind(("127.0.0.1", settings.port)).await
+        .map_err(|e| format!("Could not listen on 127.0.0.1:{}: {}", settings.port, e))?;
+    let (app_handle, token) = (app.clone(), settings.token.clone());
+    let server = tauri::async_runtime::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let (app, token) = (app_handle.clone(), token.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = serve(&app, &token, stream).await {
+                    println!(">>> [LocalApi] Connection failed: {}", e);
+                }
+            });
+        }
+    });
+    *state.server.lock().unwrap() = Some(server);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses the request line and headers, everything before the blank line.
+fn parse_head(head: &str) -> Result<Request, String> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(path), Some(_version)) = (request_line.next(), request_line.next(), request_line.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let headers = lines
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let (name, value) = l.split_once(':').ok_or_else(|| format!("Malformed header: {}", l))?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Request { method: method.to_string(), path: path.to_string(), headers, body: Vec::new() })
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return Err("Request headers too large".to_string());
+        }
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("Connection closed before the request was complete".to_string());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = std::str::from_utf8(&buf[..head_end]).map_err(|_| "Request headers are not UTF-8".to_string())?;
+    let mut request = parse_head(head)?;
+    request.body = buf[head_end + 4..].to_vec();
+    let length: usize = request.header("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    while request.body.len() < length {
+        let n = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        request.body.extend_from_slice(&chunk[..n]);
+    }
+    request.body.truncate(length);
+    Ok(request)
+}
+
+/// Reads a request, giving up with a 408 on a client that stops sending before it
+/// is complete, so a stalled socket cannot hold its task open.
+async fn receive(stream: &mut TcpStream, limit: Duration) -> Result<Request, Response> {
+    match tokio::time::timeout(limit, read_request(stream)).await {
+        Err(_) => Err(Response::error(408, "Timed out waiting for the request")),
+        Ok(request) => request.map_err(|e| Response::error(400, &e)),
+    }
+}
+
+/// Compares the bearer token without stopping at the first differing byte.
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(given) = request.header("authorization").and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    !token.is_empty()
+        && given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    ListQueries,
+    RunQuery(String),
+    Export,
+    Schema,
+}
+
+fn route(method: &str, path: &str) -> Option<Route> {
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "queries"]) => Some(Route::ListQueries),
+        ("POST", ["v1", "queries", id, "run"]) if !id.is_empty() => Some(Route::RunQuery(id.to_string())),
+        ("POST", ["v1", "export"]) => Some(Route::Export),
+        ("POST", ["v1", "schema"]) => Some(Route::Schema),
+        _ => None,
+    }
+}
+
+/// Body of the POST routes. `url` picks the connection, as in the Explorer.
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ApiBody {
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    api_key: Option<String>,
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+    /// Export only: HQL to run, or a saved query by id.
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    query_id: Option<String>,
+    /// Export only: `json` (default) or `csv`.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// A result's rows: the array itself, or the only array among its return values.
+pub(crate) fn result_rows(result: &serde_json::Value) -> Vec<serde_json::Value> {
+    match result {
+        serde_json::Value::Array(rows) => rows.clone(),
+        serde_json::Value::Object(map) => {
+            let arrays: Vec<&Vec<serde_json::Value>> = map.values().filter_map(|v| v.as_array()).collect();
+            match arrays.as_slice() {
+                [rows] => (*rows).clone(),
+                _ => vec![result.clone()],
+            }
+        }
+        other => vec![other.clone()],
+    }
+}
+
+/// CSV with a column per key, in order of first appearance. Nested values are written as JSON.
+pub(crate) fn rows_to_csv(rows: &[serde_json::Value]) -> String {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        for key in row.as_object().into_iter().flat_map(|m| m.keys()) {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let escape = |cell: String| {
+        if cell.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell
+        }
+    };
+    let mut out = columns.iter().map(|c| escape(c.clone())).collect::<Vec<_>>().join(",");
+    out.push('\n');
+    for row in rows {
+        let cells: Vec<String> = columns.iter()
+            .map(|c| match row.get(c) {
+                None | Some(serde_json::Value::Null) => String::new(),
+                Some(serde_json::Value::String(s)) => escape(s.clone()),
+                Some(v) => escape(v.to_string()),
+            })
+            .collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn saved_query(app: &tauri::AppHandle, id: &str) -> Result<crate::library::SavedQuery, String> {
+    crate::library::load_library(app)?.queries.into_iter()
+        .find(|q| q.id == id)
+        .ok_or_else(|| format!("No saved query with id '{}'", id))
+}
+
+/// Request params override the saved query's params key by key.
+fn merge_params(saved: Option<serde_json::Value>, given: Option<serde_json::Value>) -> Option<serde_json::Value> {
+    match (saved, given) {
+        (Some(serde_json::Value::Object(mut saved)), Some(serde_json::Value::Object(given))) => {
+            saved.extend(given);
+            Some(serde_json::Value::Object(saved))
+        }
+        (saved, given) => given.or(saved),
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self { status, content_type: "application/json", body: value.to_string() }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+}
+
+async fn handle(app: &tauri::AppHandle, route: Route, body: ApiBody) -> Result<Response, String> {
+    if !matches!(route, Route::ListQueries) && body.url.is_empty() {
+        return Ok(Response::error(400, "Missing \"url\""));
+    }
+    match route {
+        Route::ListQueries => {
+            let queries = crate::library::load_library(app)?.queries;
+            Ok(Response::json(200, &serde_json::json!({ "queries": queries })))
+        }
+        Route::RunQuery(id) => {
+            let query = saved_query(app, &id)?;
+            let params = merge_params(query.params, body.params);
+            let response = crate::commands::hql::run_headless(app, body.url, query.code, params, body.api_key).await?;
+            Ok(Response::json(200, &serde_json::to_value(response).map_err(|e| e.to_string())?))
+        }
+        Route::Export => {
+            crate::policy::effective_policy(app, Some(&body.url))?.check_export()?;
+            let (code, params) = match (body.code, body.query_id) {
+                (Some(code), _) => (code, body.params),
+                (None, Some(id)) => {
+                    let query = saved_query(app, &id)?;
+                    (query.code, merge_params(query.params, body.params))
+                }
+                (None, None) => return Ok(Response::error(400, "Give \"code\" or \"queryId\" to export")),
+            };
+            let response = crate::commands::hql::run_headless(app, body.url, code, params, body.api_key).await?;
+            match body.format.as_deref().unwrap_or("json") {
+                "json" => Ok(Response::json(200, &response.result)),
+                "csv" => Ok(Response { status: 200, content_type: "text/csv", body: rows_to_csv(&result_rows(&response.result)) }),
+                other => Ok(Response::error(400, &format!("Unknown export format '{}'", other))),
+            }
+        }
+        Route::Schema => {
+            let schema = crate::commands::network::cached_schema(app, &body.url, body.api_key.as_deref()).await?;
+            Ok(Response::json(200, &schema))
+        }
+    }
+}
+
+async fn serve(app: &tauri::AppHandle, token: &str, mut stream: TcpStream) -> Result<(), String> {
+    let response = match receive(&mut stream, READ_TIMEOUT).await {
+        Err(response) => response,
+        Ok(request) if !authorized(&request, token) => Response::error(401, "Missing or wrong bearer token"),
+        Ok(request) => match route(&request.method, &request.path) {
+            None => Response::error(404, "No such endpoint"),
+            Some(route) => {
+                let body = if request.body.is_empty() {
+                    Ok(ApiBody::default())
+                } else {
+                    serde_json::from_slice::<ApiBody>(&request.body).map_err(|e| format!("Invalid JSON body: {}", e))
+                };
+                match body {
+                    Err(e) => Response::error(400, &e),
+                    Ok(body) => handle(app, route, body).await.unwrap_or_else(|e| Response::error(500, &e)),
+                }
+            }
+        },
+    };
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        _ => "Internal Server Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status, reason, response.content_type, response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.write_all(response.body.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_routing_and_export() {
+        let request = parse_head("POST /v1/queries/abc/run HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer s3cret").unwrap();
+        assert_eq!(route(&request.method, &request.path), Some(Route::RunQuery("abc".to_string())));
+        assert!(authorized(&request, "s3cret"));
+        assert!(!authorized(&request, "s3cre"));
+        assert!(!authorized(&request, ""));
+
+        assert_eq!(route("GET", "/v1/queries/?all=1"), Some(Route::ListQueries));
+        assert_eq!(route("GET", "/v1/export"), None);
+        assert!(parse_head("GET").is_err());
+
+        let result = serde_json::json!({ "users": [{ "name": "Ann, B", "age": 3 }, { "name": "say \"hi\"", "tags": ["a"] }] });
+        assert_eq!(
+            rows_to_csv(&result_rows(&result)),
+            "name,age,tags\n\"Ann, B\",3,\n\"say \"\"hi\"\"\",,\"[\"\"a\"\"]\"\n"
+        );
+        assert_eq!(
+            merge_params(Some(serde_json::json!({ "a": 1, "b": 2 })), Some(serde_json::json!({ "b": 3 }))),
+            Some(serde_json::json!({ "a": 1, "b": 3 }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stalled_request_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        client.write_all(b"POST /v1/queries HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}").await.unwrap();
+        match receive(&mut stream, Duration::from_millis(50)).await {
+            Err(response) => assert_eq!(response.status, 408),
+            Ok(_) => panic!("a truncated body must not be accepted"),
+        }
+
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+        client.write_all(b"GET /v1/queries HTTP/1.1\r\n\r\n").await.unwrap();
+        let Ok(request) = receive(&mut stream, Duration::from_secs(5)).await else {
+            panic!("a complete request must be read");
+        };
+        assert_eq!(request.path, "/v1/queries");
+    }
+}
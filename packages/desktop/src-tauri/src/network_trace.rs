@@ -0,0 +1,323 @@
+//! Opt-in network trace: while enabled, every request to a gateway is kept (method,
+//! URL, headers with secrets redacted, body) with its response status, size and body, in
+//! a ring buffer read by `get_network_trace`, which applies the connection's redaction
+//! rules to the bodies. Meant for telling why a query behaves
+//! differently in the app than from curl. Separately, the requests of the last
+//! execution are always kept (in memory only) for `copy_last_request_as_curl`.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use futures::StreamExt;
+use reqwest::ResponseBuilderExt;
+use crate::redaction::{redact, RedactionRule};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Requests kept; older ones are dropped.
+const MAX_ENTRIES: usize = 500;
+/// Request and response bodies are cut to this many bytes.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+pub const REDACTED: &str = "[redacted]";
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceEntry {
+    pub id: u64,
+    pub at: String,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub body_truncated: bool,
+    pub status: Option<u16>,
+    /// Bytes of the response body read so far, decompressed.
+    pub response_bytes: Option<u64>,
+    pub response_body: Option<String>,
+    pub response_truncated: bool,
+    pub duration_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+pub struct Trace {
+    enabled: bool,
+    next_id: u64,
+    entries: VecDeque<TraceEntry>,
+}
+
+impl Trace {
+    const fn new() -> Self {
+        Self { enabled: false, next_id: 0, entries: VecDeque::new() }
+    }
+
+    fn push(&mut self, mut entry: TraceEntry) -> u64 {
+        self.next_id += 1;
+        entry.id = self.next_id;
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        self.next_id
+    }
+
+    fn finish(&mut self, id: u64, update: impl FnOnce(&mut TraceEntry)) {
+        if let Some(entry) = self.entries.iter_mut().rev().find(|e| e.id == id) {
+            update(entry);
+        }
+    }
+
+    /// The last `limit` entries, oldest first.
+    fn recent(&self, limit: Option<usize>) -> Vec<TraceEntry> {
+        let skip = limit.map_or(0, |l| self.entries.len().saturating_sub(l));
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+static TRACE: Mutex<Trace> = Mutex::new(Trace::new());
+
+/// A request as it was sent, secrets included; only rendered with them replaced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl RecordedRequest {
+    fn of(request: &reqwest::Request) -> Self {
+        Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: request.headers().iter()
+                .map(|(name, value)| (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+                .collect(),
+            body: request.body().and_then(|b| b.as_bytes()).map(|b| String::from_utf8_lossy(b).into_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LastExecution {
+    pub url: String,
+    pub at: String,
+    pub requests: Vec<RecordedRequest>,
+}
+
+tokio::task_local! {
+    static RECORDING: Arc<Mutex<Vec<RecordedRequest>>>;
+}
+
+static LAST_EXECUTION: Mutex<Option<LastExecution>> = Mutex::new(None);
+
+/// Runs `execution`, keeping the requests it sends as the last execution against `url`.
+/// An execution that sent nothing (a cache hit) leaves the previous one in place.
+pub async fn record<F: Future>(url: &str, execution: F) -> F::Output {
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let output = RECORDING.scope(recorded.clone(), execution).await;
+    let requests = std::mem::take(&mut *recorded.lock().unwrap());
+    if !requests.is_empty() {
+        *LAST_EXECUTION.lock().unwrap() = Some(LastExecution { url: url.to_string(), at: chrono::Utc::now().to_rfc3339(), requests });
+    }
+    output
+}
+
+pub fn last_execution() -> Option<LastExecution> {
+    LAST_EXECUTION.lock().unwrap().clone()
+}
+
+/// Whether a header carries a credential: the API key, authorization, cookies, or a
+/// connection header named like a key, token or secret.
+pub fn is_secret_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    matches!(name.as_str(), "authorization" | "proxy-authorization" | "cookie" | "set-cookie")
+        || ["key", "token", "secret", "password", "auth"].iter().any(|s| name.contains(s))
+}
+
+pub fn redact_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(name, value)| {
+            let value = if is_secret_header(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+fn entry_for(request: &reqwest::Request) -> TraceEntry {
+    let body = request.body().and_then(|b| b.as_bytes());
+    TraceEntry {
+        id: 0,
+        at: chrono::Utc::now().to_rfc3339(),
+        method: request.method().to_string(),
+        url: request.url().to_string(),
+        headers: redact_headers(request.headers()),
+        body: body.map(|b| String::from_utf8_lossy(&b[..b.len().min(MAX_BODY_BYTES)]).into_owned()),
+        body_truncated: body.is_some_and(|b| b.len() > MAX_BODY_BYTES),
+        status: None,
+        response_bytes: None,
+        response_body: None,
+        response_truncated: false,
+        duration_ms: None,
+        error: None,
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    TRACE.lock().unwrap().enabled = enabled;
+}
+
+pub fn is_enabled() -> bool {
+    TRACE.lock().unwrap().enabled
+}
+
+pub fn entries(limit: Option<usize>) -> Vec<TraceEntry> {
+    TRACE.lock().unwrap().recent(limit)
+}
+
+pub fn clear() {
+    TRACE.lock().unwrap().entries.clear();
+}
+
+/// Applies a connection's redaction rules to an entry's bodies. A body that cannot be
+/// read as JSON (one cut short, say) is hidden, since its properties cannot be told apart.
+pub fn redact_entry(entry: &mut TraceEntry, rules: &[RedactionRule]) {
+    if rules.is_empty() {
+        return;
+    }
+    for body in [&mut entry.body, &mut entry.response_body].into_iter().flatten() {
+        *body = match serde_json::from_str(body) {
+            Ok(json) => serde_json::to_string(&redact(json, rules)).unwrap_or_else(|_| REDACTED.to_string()),
+            Err(_) => REDACTED.to_string(),
+        };
+    }
+}
+
+/// Adds a chunk of response body to the entry's count and kept body.
+fn read_chunk(entry: &mut TraceEntry, chunk: &[u8]) {
+    entry.response_bytes = Some(entry.response_bytes.unwrap_or(0) + chunk.len() as u64);
+    let body = entry.response_body.get_or_insert_with(String::new);
+    let room = MAX_BODY_BYTES.saturating_sub(body.len());
+    if chunk.len() > room {
+        entry.response_truncated = true;
+    }
+    body.push_str(&String::from_utf8_lossy(&chunk[..chunk.len().min(room)]));
+}
+
+/// `resp` with its body counted and kept in entry `id` as the caller reads it, so
+/// streamed and compressed bodies are measured by what actually arrived.
+fn traced_response(id: u64, resp: reqwest::Response) -> reqwest::Response {
+    let mut builder = tauri::http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = resp.headers().clone();
+    }
+    let body = resp.bytes_stream().map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            TRACE.lock().unwrap().finish(id, |entry| read_chunk(entry, chunk));
+        }
+        chunk
+    });
+    reqwest::Response::from(builder.body(reqwest::Body::wrap_stream(body)).expect("a response rebuilt from valid parts"))
+}
+
+/// `send`, recorded in the trace while it is enabled.
+pub trait SendTraced {
+    fn send_traced(self) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send;
+}
+
+impl SendTraced for reqwest::RequestBuilder {
+    fn send_traced(self) -> impl Future<Output = reqwest::Result<reqwest::Response>> + Send {
+        async move {
+            let recording = RECORDING.try_with(|r| r.clone()).ok();
+            if !is_enabled() && recording.is_none() {
+                return self.send().await;
+            }
+            let (client, request) = self.build_split();
+            let request = request?;
+            if let Some(recording) = recording {
+                recording.lock().unwrap().push(RecordedRequest::of(&request));
+            }
+            if !is_enabled() {
+                return client.execute(request).await;
+            }
+            let id = TRACE.lock().unwrap().push(entry_for(&request));
+            let started = Instant::now();
+            let result = client.execute(request).await;
+            let took = started.elapsed().as_millis() as u64;
+            TRACE.lock().unwrap().finish(id, |entry| {
+                entry.duration_ms = Some(took);
+                match &result {
+                    Ok(resp) => entry.status = Some(resp.status().as_u16()),
+                    Err(e) => entry.error = Some(e.to_string()),
+                }
+            });
+            result.map(|resp| traced_response(id, resp))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_ring_and_redaction() {
+        let request = reqwest::Client::new().post("http://h:6969/mcp/tool_call")
+            .header("x-api-key", "secret")
+            .header("X-Tenant-Token", "t")
+            .header("content-type", "application/json")
+            .body("{\"connection_id\":\"c\"}")
+            .build()
+            .unwrap();
+        let entry = entry_for(&request);
+        assert_eq!(entry.headers, vec![
+            ("x-api-key".to_string(), REDACTED.to_string()),
+            ("x-tenant-token".to_string(), REDACTED.to_string()),
+            ("content-type".to_string(), "application/json".to_string()),
+        ]);
+        assert_eq!(entry.body.as_deref(), Some("{\"connection_id\":\"c\"}"));
+
+        let mut trace = Trace::new();
+        for _ in 0..MAX_ENTRIES + 2 {
+            trace.push(entry.clone());
+        }
+        trace.finish(MAX_ENTRIES as u64 + 2, |e| e.status = Some(200));
+        let recent = trace.recent(Some(2));
+        assert_eq!(recent.iter().map(|e| (e.id, e.status)).collect::<Vec<_>>(),
+            vec![(MAX_ENTRIES as u64 + 1, None), (MAX_ENTRIES as u64 + 2, Some(200))]);
+        assert_eq!(trace.recent(None).len(), MAX_ENTRIES);
+        assert_eq!(trace.recent(None)[0].id, 3);
+    }
+
+    #[test]
+    fn test_entry_bodies_counted_and_redacted() {
+        let request = reqwest::Client::new().post("http://h:6969/mcp/collect")
+            .body(r#"{"connection_id":"c","email":"a@b.c"}"#)
+            .build()
+            .unwrap();
+        let mut entry = entry_for(&request);
+        read_chunk(&mut entry, br#"[{"name":"Ann","#);
+        read_chunk(&mut entry, br#""email":"ann@example.com"}]"#);
+        assert_eq!(entry.response_bytes, Some(42));
+        assert!(!entry.response_truncated);
+
+        let rules = vec![RedactionRule { pattern: "email".to_string(), action: crate::redaction::RedactionAction::Hide }];
+        let mut redacted = entry.clone();
+        redact_entry(&mut redacted, &rules);
+        assert_eq!(redacted.body.as_deref(), Some(r#"{"connection_id":"c"}"#));
+        assert_eq!(redacted.response_body.as_deref(), Some(r#"[{"name":"Ann"}]"#));
+
+        // A body cut short cannot be redacted property by property
+        read_chunk(&mut entry, &vec![b' '; MAX_BODY_BYTES]);
+        assert!(entry.response_truncated);
+        assert_eq!(entry.response_bytes, Some(42 + MAX_BODY_BYTES as u64));
+        entry.response_body = Some(r#"[{"email":"ann@exa"#.to_string());
+        redact_entry(&mut entry, &rules);
+        assert_eq!(entry.response_body.as_deref(), Some(REDACTED));
+    }
+}
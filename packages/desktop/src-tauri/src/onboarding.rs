@@ -0,0 +1,366 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::process::Command;
+use crate::{config, helix_cli};
+use crate::network_trace::SendTraced;
+
+pub const DEMO_CONNECTION_ID: &str = "onboarding-demo";
+const DEMO_INSTANCE: &str = "dev";
+const READY_TIMEOUT: Duration = Duration::from_secs(90);
+
+const DEMO_SCHEMA: &str = r#"N::Person {
+    name: String,
+    age: I32,
+}
+
+N::City {
+    name: String,
+}
+
+E::Knows {
+    From: Person,
+    To: Person,
+    Properties: {
+        since: I32,
+    }
+}
+
+E::LivesIn {
+    From: Person,
+    To: City,
+}
+"#;
+
+const DEMO_QUERIES: &str = r#"QUERY SeedDemo() =>
+    alice <- AddN<Person>({name: "Alice", age: 31})
+    bob <- AddN<Person>({name: "Bob", age: 27})
+    carol <- AddN<Person>({name: "Carol", age: 35})
+    berlin <- AddN<City>({name: "Berlin"})
+    lisbon <- AddN<City>({name: "Lisbon"})
+    AddE<Knows>({since: 2019})::From(alice)::To(bob)
+    AddE<Knows>({since: 2021})::From(bob)::To(carol)
+    AddE<Knows>({since: 2020})::From(alice)::To(carol)
+    AddE<LivesIn>::From(alice)::To(berlin)
+    AddE<LivesIn>::From(bob)::To(lisbon)
+    AddE<LivesIn>::From(carol)::To(berlin)
+    RETURN alice
+
+QUERY Showcase() =>
+    people <- N<Person>
+    friends <- N<Person>::Out<Knows>
+    RETURN people, friends
+"#;
+
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct OnboardingEnvironment {
+    pub docker_available: bool,
+    pub docker_version: Option<String>,
+    pub helix_cli_available: bool,
+    pub helix_cli_version: Option<String>,
+    /// Workspace of an instance that is already running, if any.
+    pub running_workspace: Option<String>,
+    pub demo_connection_exists: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingOptions {
+    /// Reuse an existing demo project instead of re-initializing it.
+    #[serde(default)]
+    pub reuse_existing: bool,
+    #[serde(default = "default_true")]
+    pub seed: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for OnboardingOptions {
+    fn default() -> Self {
+        Self { reuse_existing: false, seed: true }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    Detect,
+    CreateProject,
+    StartInstance,
+    WaitReady,
+    Seed,
+    CreateConnection,
+    Showcase,
+    Done,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OnboardingProgress {
+    pub step: OnboardingStep,
+    pub status: String, // "running", "done" or "failed"
+    pub message: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OnboardingResult {
+    pub connection: serde_json::Value,
+    pub showcase: serde_json::Value,
+}
+
+fn emit(app: &tauri::AppHandle, step: OnboardingStep, status: &str, message: impl Into<String>) {
+    let _ = app.emit("onboarding-progress", OnboardingProgress {
+        step,
+        status: status.to_string(),
+        message: message.into(),
+    });
+}
+
+async fn command_version(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub async fn detect_environment(app: &tauri::AppHandle) -> OnboardingEnvironment {
+    let docker_version = command_version(&config::get_docker_path()).await;
+    let helix_cli_version = command_version("helix").await;
+    let demo_connection_exists = config::load_connection_config(app.clone())
+        .is_ok_and(|c| c.connection(DEMO_CONNECTION_ID).is_some());
+
+    OnboardingEnvironment {
+        docker_available: docker_version.is_some(),
+        docker_version,
+        helix_cli_available: helix_cli_version.is_some(),
+        helix_cli_version,
+        running_workspace: config::detect_workspace_path(app, None).ok(),
+        demo_connection_exists,
+    }
+}
+
+fn demo_workspace(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir()
+        .map(|p| p.join("onboarding-demo"))
+        .map_err(|e| format!("Could not find app data directory: {}", e))
+}
+
+async fn run_helix(workspace: &Path, args: &[&str]) -> Result<(), String> {
+    let output = Command::new("helix")
+        .args(args)
+        .current_dir(workspace)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run helix {}: {}", args.join(" "), e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "helix {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Port of the demo instance as written by `helix init`.
+fn demo_port(workspace: &Path) -> String {
+    std::fs::read_to_string(workspace.join("helix.toml"))
+        .ok()
+        .map(|c| helix_cli::parse_sections(&c))
+        .and_then(|s| s.get(&format!("local.{}", DEMO_INSTANCE)).and_then(|l| l.get("port").cloned()))
+        .unwrap_or_else(|| "6969".to_string())
+}
+
+async fn create_project(workspace: &Path, reuse: bool) -> Result<(), String> {
+    if reuse && workspace.join("helix.toml").exists() {
+        return Ok(());
+    }
+    if workspace.exists() {
+        std::fs::remove_dir_all(workspace).map_err(|e| format!("Failed to reset demo project: {}", e))?;
+    }
+    std::fs::create_dir_all(workspace).map_err(|e| format!("Failed to create demo project: {}", e))?;
+    run_helix(workspace, &["init"]).await?;
+
+    let db = workspace.join("db");
+    std::fs::create_dir_all(&db).map_err(|e| e.to_string())?;
+    std::fs::write(db.join("schema.hx"), DEMO_SCHEMA).map_err(|e| format!("Failed to write demo schema: {}", e))?;
+    std::fs::write(db.join("queries.hx"), DEMO_QUERIES).map_err(|e| format!("Failed to write demo queries: {}", e))?;
+    Ok(())
+}
+
+async fn call_query(client: &reqwest::Client, url: &str, name: &str) -> Result<serde_json::Value, String> {
+    let resp = client.post(format!("{}/{}", url, name))
+        .json(&serde_json::json!({}))
+        .send_traced()
+        .await
+        .map_err(|e| crate::commands::network::map_reqwest_error(e, &format!("{} failed", name)))?;
+    if !resp.status().is_success() {
+        return Err(format!("{} failed ({}): {}", name, resp.status(), resp.text().await.unwrap_or_default()));
+    }
+    resp.json().await.map_err(|e| format!("Failed to parse {} response: {}", name, e))
+}
+
+async fn wait_until_ready(client: &reqwest::Client, url: &str) -> Result<(), String> {
+    let deadline = tokio::time::Instant::now() + READY_TIMEOUT;
+    loop {
+        if call_query(client, url, "Showcase").await.is_ok() {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(format!("Demo instance did not become ready at {} within {}s", url, READY_TIMEOUT.as_secs()));
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+fn save_demo_connection(app: &tauri::AppHandle, port: &str, workspace: &Path) -> Result<serde_json::Value, String> {
+    let connection = serde_json::json!({
+        "id": DEMO_CONNECTION_ID,
+        "name": "Helix Demo",
+        "type": "local",
+        "host": "127.0.0.1",
+        "port": port,
+        "apiKey": "",
+        "localPath": workspace.to_string_lossy(),
+    });
+
+    let mut cfg = config::load_connection_config(app.clone())?;
+    add_demo_connection(&mut cfg, connection.clone());
+    config::save_connection_config(app.clone(), cfg)?;
+    Ok(connection)
+}
+
+/// Replaces any earlier demo connection and makes the new one current.
+fn add_demo_connection(cfg: &mut config::ConnectionsConfig, connection: serde_json::Value) {
+    cfg.connections.retain(|c| c.get("id").and_then(|v| v.as_str()) != Some(DEMO_CONNECTION_ID));
+    cfg.connections.push(connection);
+    cfg.current = Some(DEMO_CONNECTION_ID.to_string());
+}
+
+/// The step that follows a successful `step`.
+fn next_step(step: OnboardingStep, options: &OnboardingOptions) -> OnboardingStep {
+    match step {
+        OnboardingStep::Detect => OnboardingStep::CreateProject,
+        OnboardingStep::CreateProject => OnboardingStep::StartInstance,
+        OnboardingStep::StartInstance => OnboardingStep::WaitReady,
+        OnboardingStep::WaitReady if options.seed => OnboardingStep::Seed,
+        OnboardingStep::WaitReady | OnboardingStep::Seed => OnboardingStep::CreateConnection,
+        OnboardingStep::CreateConnection => OnboardingStep::Showcase,
+        OnboardingStep::Showcase | OnboardingStep::Done => OnboardingStep::Done,
+    }
+}
+
+/// Runs the onboarding state machine, emitting `onboarding-progress` for each step.
+pub async fn run_onboarding(app: &tauri::AppHandle, client: &reqwest::Client, options: OnboardingOptions) -> Result<OnboardingResult, String> {
+    let workspace = demo_workspace(app)?;
+    let mut url = String::new();
+    let mut connection = serde_json::Value::Null;
+    let mut showcase = serde_json::Value::Null;
+    let mut step = OnboardingStep::Detect;
+
+    loop {
+        emit(app, step, "running", "");
+        let outcome: Result<(), String> = async {
+            match step {
+                OnboardingStep::Detect => {
+                    let env = detect_environment(app).await;
+                    if !env.docker_available {
+                        return Err("Docker was not found. Install Docker Desktop to run a local demo instance.".to_string());
+                    }
+                    if !env.helix_cli_available {
+                        return Err("The helix CLI was not found. Install it with `curl -sSL https://install.helix-db.com | bash`.".to_string());
+                    }
+                    Ok(())
+                }
+                OnboardingStep::CreateProject => {
+                    create_project(&workspace, options.reuse_existing).await?;
+                    url = format!("http://127.0.0.1:{}", demo_port(&workspace));
+                    Ok(())
+                }
+                OnboardingStep::StartInstance => {
+                    run_helix(&workspace, &["push", DEMO_INSTANCE]).await
+                }
+                OnboardingStep::WaitReady => {
+                    wait_until_ready(client, &url).await
+                }
+                OnboardingStep::Seed => {
+                    call_query(client, &url, "SeedDemo").await.map(|_| ())
+                }
+                OnboardingStep::CreateConnection => {
+                    connection = save_demo_connection(app, &demo_port(&workspace), &workspace)?;
+                    Ok(())
+                }
+                OnboardingStep::Showcase => {
+                    showcase = crate::hql::translator::normalize_value(call_query(client, &url, "Showcase").await?);
+                    Ok(())
+                }
+                OnboardingStep::Done => Ok(()),
+            }
+        }.await.map(|()| next_step(step, &options));
+
+        match outcome {
+            Ok(OnboardingStep::Done) => {
+                emit(app, step, "done", "");
+                if step != OnboardingStep::Done {
+                    emit(app, OnboardingStep::Done, "done", "Demo environment is ready");
+                }
+                return Ok(OnboardingResult { connection, showcase });
+            }
+            Ok(next) => {
+                emit(app, step, "done", "");
+                step = next;
+            }
+            Err(e) => {
+                emit(app, step, "failed", e.clone());
+                return Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_transitions() {
+        let walk = |options: OnboardingOptions| {
+            let mut steps = vec![OnboardingStep::Detect];
+            while let Some(&step) = steps.last().filter(|&&s| s != OnboardingStep::Done) {
+                steps.push(next_step(step, &options));
+            }
+            steps
+        };
+        use OnboardingStep::*;
+        assert_eq!(
+            walk(OnboardingOptions::default()),
+            vec![Detect, CreateProject, StartInstance, WaitReady, Seed, CreateConnection, Showcase, Done]
+        );
+        assert_eq!(
+            walk(OnboardingOptions { reuse_existing: true, seed: false }),
+            vec![Detect, CreateProject, StartInstance, WaitReady, CreateConnection, Showcase, Done]
+        );
+        assert_eq!(next_step(Done, &OnboardingOptions::default()), Done);
+
+        let options: OnboardingOptions = serde_json::from_str(r#"{ "reuseExisting": true }"#).unwrap();
+        assert!(options.reuse_existing && options.seed);
+    }
+
+    #[test]
+    fn test_demo_connection_round_trip() {
+        let stored = r#"{ "connections": [{ "id": "prod", "port": "7000" }, { "id": "onboarding-demo", "port": "6000" }], "current": "prod", "theme": "dark" }"#;
+        let mut cfg = config::ConnectionsConfig::parse(stored).unwrap();
+        add_demo_connection(&mut cfg, serde_json::json!({ "id": DEMO_CONNECTION_ID, "port": "6969" }));
+        add_demo_connection(&mut cfg, serde_json::json!({ "id": DEMO_CONNECTION_ID, "port": "6970" }));
+
+        let saved = config::ConnectionsConfig::parse(&serde_json::to_string(&cfg).unwrap()).unwrap();
+        assert_eq!(saved, cfg);
+        assert_eq!(saved.current.as_deref(), Some(DEMO_CONNECTION_ID));
+        assert_eq!(saved.connections.len(), 2);
+        assert_eq!(saved.connection("prod").unwrap()["port"], "7000");
+        assert_eq!(saved.connection(DEMO_CONNECTION_ID).unwrap()["port"], "6970");
+        assert_eq!(saved.other.get("theme").unwrap(), "dark");
+    }
+}
@@ -0,0 +1,296 @@
+//! Results pinned to keep across sessions, and the row-by-row comparison of two of
+//! them behind the split compare view.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+const PINNED_FILE: &str = "pinned-results.json";
+/// Larger results are refused; every pin is loaded to list them.
+const MAX_PIN_BYTES: usize = 8 * 1024 * 1024;
+/// Rows are matched on this property unless the comparison names another.
+pub const DEFAULT_KEY: &str = "id";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedResult {
+    pub id: String,
+    pub label: String,
+    pub url: String,
+    /// The query that produced the result.
+    #[serde(default)]
+    pub code: Option<String>,
+    pub pinned_at: String,
+    pub result: serde_json::Value,
+}
+
+/// A pin without its result, for listing.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedSummary {
+    pub id: String,
+    pub label: String,
+    pub url: String,
+    pub code: Option<String>,
+    pub pinned_at: String,
+    pub rows: usize,
+}
+
+impl From<&PinnedResult> for PinnedSummary {
+    fn from(pin: &PinnedResult) -> Self {
+        Self {
+            id: pin.id.clone(),
+            label: pin.label.clone(),
+            url: pin.url.clone(),
+            code: pin.code.clone(),
+            pinned_at: pin.pinned_at.clone(),
+            rows: crate::hql::metrics::count_rows(&pin.result),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RowStatus {
+    Same,
+    Changed,
+    OnlyLeft,
+    OnlyRight,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedRow {
+    /// The row's key value, or `#<position>` for a row without one.
+    pub key: String,
+    pub status: RowStatus,
+    pub left: Option<serde_json::Value>,
+    pub right: Option<serde_json::Value>,
+    /// Columns whose values differ.
+    pub changed: Vec<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonCounts {
+    pub same: usize,
+    pub changed: usize,
+    pub only_left: usize,
+    pub only_right: usize,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    pub left: PinnedSummary,
+    pub right: PinnedSummary,
+    pub key: String,
+    /// Every column of either side, the left side's first.
+    pub columns: Vec<String>,
+    /// Left rows in order, then rows only on the right.
+    pub rows: Vec<ComparedRow>,
+    pub counts: ComparisonCounts,
+}
+
+fn pinned_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(PINNED_FILE))
+}
+
+fn load_all(app: &tauri::AppHandle) -> Result<Vec<PinnedResult>, String> {
+    let path = pinned_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to read pinned results: {}", e))
+}
+
+fn save_all(app: &tauri::AppHandle, pins: &[PinnedResult]) -> Result<(), String> {
+    let content = serde_json::to_string(pins).map_err(|e| e.to_string())?;
+    fs::write(pinned_path(app)?, content).map_err(|e| e.to_string())
+}
+
+pub fn list(app: &tauri::AppHandle) -> Result<Vec<PinnedSummary>, String> {
+    Ok(load_all(app)?.iter().map(PinnedSummary::from).collect())
+}
+
+pub fn get(app: &tauri::AppHandle, id: &str) -> Result<PinnedResult, String> {
+    load_all(app)?.into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("No pinned result '{}'", id))
+}
+
+pub fn pin(app: &tauri::AppHandle, label: String, url: String, code: Option<String>, result: serde_json::Value) -> Result<PinnedSummary, String> {
+    let size = serde_json::to_vec(&result).map_or(0, |b| b.len());
+    if size > MAX_PIN_BYTES {
+        return Err(format!("The result is {} bytes; results up to {} bytes can be pinned", size, MAX_PIN_BYTES));
+    }
+    let mut pins = load_all(app)?;
+    let now = chrono::Utc::now();
+    let mut millis = now.timestamp_millis();
+    while pins.iter().any(|p| p.id == format!("pin-{}", millis)) {
+        millis += 1;
+    }
+    let pin = PinnedResult { id: format!("pin-{}", millis), label, url, code, pinned_at: now.to_rfc3339(), result };
+    let summary = PinnedSummary::from(&pin);
+    pins.push(pin);
+    save_all(app, &pins)?;
+    Ok(summary)
+}
+
+pub fn relabel(app: &tauri::AppHandle, id: &str, label: String) -> Result<(), String> {
+    let mut pins = load_all(app)?;
+    let pin = pins.iter_mut().find(|p| p.id == id).ok_or_else(|| format!("No pinned result '{}'", id))?;
+    pin.label = label;
+    save_all(app, &pins)
+}
+
+/// Returns whether the pin existed.
+pub fn unpin(app: &tauri::AppHandle, id: &str) -> Result<bool, String> {
+    let mut pins = load_all(app)?;
+    let before = pins.len();
+    pins.retain(|p| p.id != id);
+    if pins.len() == before {
+        return Ok(false);
+    }
+    save_all(app, &pins).map(|_| true)
+}
+
+/// The rows of a result: an array as is, or one RETURN variable of a multi-return
+/// object (`variable`, or the only array among its values).
+fn result_rows<'a>(result: &'a serde_json::Value, variable: Option<&str>) -> Result<&'a [serde_json::Value], String> {
+    match (result, variable) {
+        (serde_json::Value::Array(rows), _) => Ok(rows),
+        (serde_json::Value::Object(vars), Some(name)) => vars.get(name)
+            .and_then(|v| v.as_array())
+            .map(|rows| rows.as_slice())
+            .ok_or_else(|| format!("The result has no list named '{}'", name)),
+        (serde_json::Value::Object(vars), None) => {
+            let mut lists = vars.values().filter_map(|v| v.as_array());
+            match (lists.next(), lists.next()) {
+                (Some(rows), None) => Ok(rows),
+                (None, _) => Err("The result has no rows to compare".to_string()),
+                _ => Err("The result returns several lists; choose the variable to compare".to_string()),
+            }
+        }
+        _ => Err("The result has no rows to compare".to_string()),
+    }
+}
+
+fn row_key(row: &serde_json::Value, key: &str, position: usize) -> String {
+    match row.get(key) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(v) if !v.is_null() => v.to_string(),
+        _ => format!("#{}", position),
+    }
+}
+
+fn push_columns(columns: &mut Vec<String>, row: &serde_json::Value) {
+    if let Some(object) = row.as_object() {
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+}
+
+/// Aligns two row lists on `key`. Rows missing the key only match the row at the same
+/// position on the other side.
+pub fn compare_rows(left: &[serde_json::Value], right: &[serde_json::Value], key: &str) -> (Vec<String>, Vec<ComparedRow>, ComparisonCounts) {
+    let mut columns = Vec::new();
+    left.iter().chain(right).for_each(|row| push_columns(&mut columns, row));
+
+    let keyed: Vec<(String, &serde_json::Value)> = right.iter().enumerate()
+        .map(|(i, row)| (row_key(row, key, i), row))
+        .collect();
+    // Repeated keys match in order
+    let mut by_key: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, (k, _)) in keyed.iter().enumerate() {
+        by_key.entry(k.as_str()).or_default().push_back(i);
+    }
+    let mut matched = vec![false; keyed.len()];
+    let mut rows = Vec::new();
+    let mut counts = ComparisonCounts::default();
+    for (i, row) in left.iter().enumerate() {
+        let row_key = row_key(row, key, i);
+        match by_key.get_mut(row_key.as_str()).and_then(|queue| queue.pop_front()) {
+            Some(j) => {
+                matched[j] = true;
+                let other = keyed[j].1;
+                let changed: Vec<String> = columns.iter()
+                    .filter(|c| row.get(c.as_str()) != other.get(c.as_str()))
+                    .cloned()
+                    .collect();
+                let status = if changed.is_empty() && row == other { RowStatus::Same } else { RowStatus::Changed };
+                match status {
+                    RowStatus::Same => counts.same += 1,
+                    _ => counts.changed += 1,
+                }
+                rows.push(ComparedRow { key: row_key, status, left: Some(row.clone()), right: Some(other.clone()), changed });
+            }
+            None => {
+                counts.only_left += 1;
+                rows.push(ComparedRow { key: row_key, status: RowStatus::OnlyLeft, left: Some(row.clone()), right: None, changed: Vec::new() });
+            }
+        }
+    }
+    for ((row_key, row), _) in keyed.iter().zip(&matched).filter(|(_, matched)| !**matched) {
+        counts.only_right += 1;
+        rows.push(ComparedRow { key: row_key.clone(), status: RowStatus::OnlyRight, left: None, right: Some((*row).clone()), changed: Vec::new() });
+    }
+    (columns, rows, counts)
+}
+
+/// Compares two pins row by row, matched on `key` (`id` by default).
+pub fn compare(app: &tauri::AppHandle, left_id: &str, right_id: &str, key: Option<&str>, variable: Option<&str>) -> Result<Comparison, String> {
+    let (left, right) = (get(app, left_id)?, get(app, right_id)?);
+    let key = key.unwrap_or(DEFAULT_KEY);
+    let (columns, rows, counts) = compare_rows(result_rows(&left.result, variable)?, result_rows(&right.result, variable)?, key);
+    Ok(Comparison {
+        left: PinnedSummary::from(&left),
+        right: PinnedSummary::from(&right),
+        key: key.to_string(),
+        columns,
+        rows,
+        counts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_rows() {
+        let left = serde_json::json!([
+            { "id": "a", "name": "Ann", "age": 30 },
+            { "id": "b", "name": "Bob" },
+            { "id": "c", "name": "Cy" },
+            { "name": "no key" }
+        ]);
+        let right = serde_json::json!([
+            { "id": "b", "name": "Bob" },
+            { "id": "a", "name": "Ann", "age": 31 },
+            { "id": "d", "name": "Di", "city": "Oslo" },
+            { "name": "no key" }
+        ]);
+        let (columns, rows, counts) = compare_rows(left.as_array().unwrap(), right.as_array().unwrap(), "id");
+        assert_eq!(columns, vec!["id", "name", "age", "city"]);
+        assert_eq!(counts, ComparisonCounts { same: 2, changed: 1, only_left: 1, only_right: 1 });
+        let statuses: Vec<(&str, RowStatus)> = rows.iter().map(|r| (r.key.as_str(), r.status)).collect();
+        assert_eq!(statuses, vec![
+            ("a", RowStatus::Changed), ("b", RowStatus::Same), ("c", RowStatus::OnlyLeft),
+            ("#3", RowStatus::Same), ("d", RowStatus::OnlyRight),
+        ]);
+        assert_eq!(rows[0].changed, vec!["age"]);
+
+        let multi = serde_json::json!({ "users": [{ "id": 1 }], "total": 1 });
+        assert_eq!(result_rows(&multi, None).unwrap().len(), 1);
+        assert!(result_rows(&multi, Some("missing")).is_err());
+        assert!(result_rows(&serde_json::json!({ "a": [], "b": [] }), None).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use crate::hql::tool_args::{FilterTraversal, ToolArgs};
+use crate::hql::translator::{self, PipelinePlan};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::Manager;
+
+/// Admin-managed policy in the app config directory.
+const CONFIG_POLICY_FILE: &str = "policy.json";
+/// Policy checked into a helix project, applied to connections using that project.
+const PROJECT_POLICY_FILE: &str = "explorer-policy.json";
+
+/// Restrictions for one connection. Unset fields impose no restriction.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionPolicy {
+    /// `false` blocks calls to compiled queries (which may write) and project syncs.
+    #[serde(default)]
+    pub allow_writes: Option<bool>,
+    /// Compiled queries known to be read-only, still callable when writes are off.
+    #[serde(default)]
+    pub read_only_queries: Option<Vec<String>>,
+    /// `false` blocks printing and shareable links.
+    #[serde(default)]
+    pub allow_exports: Option<bool>,
+    /// Results are cut to this many rows.
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+    /// Node, edge and vector labels queries may touch.
+    #[serde(default)]
+    pub allowed_labels: Option<Vec<String>>,
+}
+
+/// A policy file: `default` applies everywhere, `connections` entries (keyed by
+/// connection id, name or URL) override it field by field.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyFile {
+    #[serde(default)]
+    pub default: ConnectionPolicy,
+    #[serde(default)]
+    pub connections: HashMap<String, ConnectionPolicy>,
+}
+
+impl ConnectionPolicy {
+    fn overridden_by(self, other: &ConnectionPolicy) -> ConnectionPolicy {
+        ConnectionPolicy {
+            allow_writes: other.allow_writes.or(self.allow_writes),
+            read_only_queries: other.read_only_queries.clone().or(self.read_only_queries),
+            allow_exports: other.allow_exports.or(self.allow_exports),
+            max_rows: other.max_rows.or(self.max_rows),
+            allowed_labels: other.allowed_labels.clone().or(self.allowed_labels),
+        }
+    }
+
+    /// Combines two independent policies so that neither can loosen the other.
+    fn restricted_by(self, other: ConnectionPolicy) -> ConnectionPolicy {
+        fn both<T: PartialEq + Clone>(a: Option<Vec<T>>, b: Option<Vec<T>>) -> Option<Vec<T>> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.into_iter().filter(|x| b.contains(x)).collect()),
+                (a, b) => a.or(b),
+            }
+        }
+        ConnectionPolicy {
+            allow_writes: match (self.allow_writes, other.allow_writes) {
+                (Some(a), Some(b)) => Some(a && b),
+                (a, b) => a.or(b),
+            },
+            read_only_queries: both(self.read_only_queries, other.read_only_queries),
+            allow_exports: match (self.allow_exports, other.allow_exports) {
+                (Some(a), Some(b)) => Some(a && b),
+                (a, b) => a.or(b),
+            },
+            max_rows: match (self.max_rows, other.max_rows) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+            allowed_labels: both(self.allowed_labels, other.allowed_labels),
+        }
+    }
+
+    pub fn check_export(&self) -> Result<(), String> {
+        if self.allow_exports == Some(false) {
+            return Err("Exporting results is disabled by the workspace policy".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn check_writes(&self) -> Result<(), String> {
+        if self.allow_writes == Some(false) {
+            return Err("Writes are disabled by the workspace policy".to_string());
+        }
+        Ok(())
+    }
+
+    /// Compiled queries may write, so with writes off only listed ones can be called.
+    pub fn check_compiled_query(&self, name: &str) -> Result<(), String> {
+        let listed = self.read_only_queries.as_ref().map(|q| q.iter().any(|q| q == name)).unwrap_or(false);
+        if self.allow_writes == Some(false) && !listed {
+            return Err(format!("Query '{}' is not in the policy's read-only list and writes are disabled", name));
+        }
+        Ok(())
+    }
+
+    /// Compiled queries do not say which labels they read, so their results are checked:
+    /// every node, edge or vector (an object with an `id` and a `label`) must be allowed.
+    pub fn check_result_labels(&self, value: &serde_json::Value) -> Result<(), String> {
+        if self.allowed_labels.is_none() {
+            return Ok(());
+        }
+        match value {
+            serde_json::Value::Array(items) => items.iter().try_for_each(|v| self.check_result_labels(v)),
+            serde_json::Value::Object(map) => {
+                if let (Some(_), Some(label)) = (map.get("id"), map.get("label").and_then(|l| l.as_str())) {
+                    self.check_labels([label])?;
+                }
+                map.values().try_for_each(|v| self.check_result_labels(v))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_labels<'a, I: IntoIterator<Item = &'a str>>(&self, labels: I) -> Result<(), String> {
+        if let Some(allowed) = &self.allowed_labels {
+            for label in labels {
+                if !label.is_empty() && !allowed.iter().any(|a| a == label) {
+                    return Err(format!("Label '{}' is not allowed by the workspace policy", label));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Truncates a result (an array, or an object of arrays) to `max_rows`.
+    pub fn limit_rows(&self, value: serde_json::Value) -> serde_json::Value {
+        let Some(max) = self.max_rows else {
+            return value;
+        };
+        match value {
+            serde_json::Value::Array(mut rows) => {
+                rows.truncate(max);
+                serde_json::Value::Array(rows)
+            }
+            serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter()
+                .map(|(k, v)| match v {
+                    serde_json::Value::Array(mut rows) => {
+                        rows.truncate(max);
+                        (k, serde_json::Value::Array(rows))
+                    }
+                    other => (k, other),
+                })
+                .collect()),
+            other => other,
+        }
+    }
+}
+
+fn filter_labels<'a>(filter: &'a FilterTraversal, out: &mut Vec<&'a str>) {
+    for tool in filter.filter_traversals.iter().flatten() {
+        tool_labels(tool, out);
+    }
+}
+
+/// Every label a tool (and its nested filters) reads.
+pub fn tool_labels<'a>(tool: &'a ToolArgs, out: &mut Vec<&'a str>) {
+    match tool {
+        ToolArgs::OutStep { edge_label, filter, .. }
+        | ToolArgs::InStep { edge_label, filter, .. }
+        | ToolArgs::OutEStep { edge_label, filter }
+        | ToolArgs::InEStep { edge_label, filter } => {
+            out.push(edge_label);
+            if let Some(f) = filter {
+                filter_labels(f, out);
+            }
+        }
+        ToolArgs::NFromType { node_type } => out.push(node_type),
+        ToolArgs::EFromType { edge_type } => out.push(edge_type),
+        ToolArgs::VFromType { vector_type, filter } => {
+            out.push(vector_type);
+            if let Some(f) = filter {
+                filter_labels(f, out);
+            }
+        }
+        ToolArgs::FilterItems { filter } => filter_labels(filter, out),
+        ToolArgs::OrderBy { .. } => {}
+        ToolArgs::SearchKeyword { label, .. } | ToolArgs::SearchVecText { label, .. } | ToolArgs::SearchVec { label, .. } => out.push(label),
+    }
+}
+
+pub fn plan_labels(plan: &PipelinePlan) -> Vec<&str> {
+    let mut out = Vec::new();
+    let hop_tools = plan.edge_hop.iter().flat_map(|h| h.edge_tools.iter());
+    for tool in plan.tools.iter().chain(hop_tools) {
+        tool_labels(tool, &mut out);
+    }
+    for cf in &plan.client_filters {
+        match cf {
+            translator::ClientFilter::EdgeCount { edge_label, .. } => out.push(edge_label),
+            translator::ClientFilter::TraversalCount { tools, .. } => tools.iter().for_each(|t| tool_labels(t, &mut out)),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn read_policy(path: &Path) -> Result<Option<PolicyFile>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map(Some).map_err(|e| format!("Invalid policy file {:?}: {}", path, e))
+}
+
+/// `url` with the scheme and host lowercased, `localhost` as 127.0.0.1, the scheme's
+/// default port and any trailing slash dropped, so equal addresses compare equal.
+pub(crate) fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let scheme = scheme.to_lowercase();
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let mut authority = authority.to_lowercase();
+    if authority == "localhost" || authority.starts_with("localhost:") {
+        authority.replace_range(.."localhost".len(), "127.0.0.1");
+    }
+    let default_port = if scheme == "https" { ":443" } else { ":80" };
+    if let Some(host) = authority.strip_suffix(default_port) {
+        authority = host.to_string();
+    }
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+/// Whether `url` is the connection's address or an endpoint under it.
+fn connection_matches(conn: &serde_json::Value, url: &str) -> bool {
+    let base = normalize_url(&connection_url(conn));
+    let url = normalize_url(url);
+    url == base || url.strip_prefix(&base).is_some_and(|rest| rest.starts_with(['/', '?', '#']))
+}
+
+/// The connection `url` points at, or the current connection when there is no URL.
+pub(crate) fn find_connection(app: &tauri::AppHandle, url: Option<&str>) -> Option<serde_json::Value> {
+    let config = crate::config::load_connection_config(app.clone()).ok()?;
+    connection_in(&config, url)
+}
+
+/// Like `find_connection`, over an already loaded connections.json.
+pub(crate) fn connection_in(config: &crate::config::ConnectionsConfig, url: Option<&str>) -> Option<serde_json::Value> {
+    match url {
+        Some(url) => config.connections.iter().find(|c| connection_matches(c, url)).cloned(),
+        None => config.connection(config.current.as_deref()?).cloned(),
+    }
+}
+
+/// The base URL the frontend uses for a connection (`getConnectionUrl`).
+pub(crate) fn connection_url(conn: &serde_json::Value) -> String {
+    let field = |k: &str| conn.get(k).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    if field("type") == Some("cloud") {
+        let host = field("cloudHost").unwrap_or("https://cloud.helix-db.com");
+        let host = if host.starts_with("http") { host.to_string() } else { format!("https://{}", host) };
+        return host.trim_end_matches('/').to_string();
+    }
+    let host = match field("host") {
+        Some(h) if !h.eq_ignore_ascii_case("localhost") => h,
+        _ => "127.0.0.1",
+    };
+    format!("http://{}:{}", host, field("port").unwrap_or("6969"))
+}
+
+fn resolve(file: &PolicyFile, keys: &[&str]) -> ConnectionPolicy {
+    keys.iter()
+        .filter_map(|k| file.connections.get(*k))
+        .fold(file.default.clone(), |policy, entry| policy.overridden_by(entry))
+}
+
+/// The policy in force for `url` (or the current connection): the config policy, the
+/// policy of the connection's project and its `readOnly` default combined, most
+/// restrictive first.
+/// An unreadable policy file fails closed.
+pub fn effective_policy(app: &tauri::AppHandle, url: Option<&str>) -> Result<ConnectionPolicy, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    policy_in(&config_dir, find_connection(app, url).as_ref(), url)
+}
+
+/// `effective_policy` for `connection`, reading the config policy from `config_dir`.
+pub(crate) fn policy_in(config_dir: &Path, connection: Option<&serde_json::Value>, url: Option<&str>) -> Result<ConnectionPolicy, String> {
+    let field = |k: &str| connection.and_then(|c| c.get(k)).and_then(|v| v.as_str()).map(String::from);
+    let url_key = url.map(|u| u.trim_end_matches('/').to_string());
+    let (id, name) = (field("id"), field("name"));
+    let keys: Vec<&str> = [&id, &name, &url_key].into_iter().flatten().map(|s| s.as_str()).collect();
+
+    let mut policy = ConnectionPolicy::default();
+    if let Some(file) = read_policy(&config_dir.join(CONFIG_POLICY_FILE))? {
+        policy = resolve(&file, &keys);
+    }
+    if let Some(project) = field("localPath") {
+        if let Some(file) = read_policy(&Path::new(&project).join(PROJECT_POLICY_FILE))? {
+            policy = policy.restricted_by(resolve(&file, &keys));
+        }
+    }
+    // Bad defaults fail every query, rather than the read-only flag among them being ignored
+    let defaults = crate::config::ConnectionDefaults::parse(connection)
+        .map_err(|e| format!("Connection '{}' has invalid defaults: {}", name.as_deref().or(id.as_deref()).unwrap_or("?"), e))?;
+    if defaults.read_only {
+        policy = policy.restricted_by(ConnectionPolicy { allow_writes: Some(false), ..Default::default() });
+    }
+    Ok(policy)
+}
+
+/// The policy of a project on disk, for commands that act on a project path directly.
+pub fn project_policy(app: &tauri::AppHandle, project: &Path) -> Result<ConnectionPolicy, String> {
+    let mut policy = effective_policy(app, None)?;
+    if let Some(file) = read_policy(&project.join(PROJECT_POLICY_FILE))? {
+        policy = policy.restricted_by(file.default);
+    }
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_and_restrict() {
+        let file: PolicyFile = serde_json::from_value(serde_json::json!({
+            "default": { "allowWrites": false, "maxRows": 500 },
+            "connections": { "prod": { "maxRows": 100, "allowedLabels": ["User", "Post"] } }
+        })).unwrap();
+        let prod = resolve(&file, &["prod"]);
+        assert_eq!(prod.max_rows, Some(100));
+        assert_eq!(prod.allow_writes, Some(false));
+
+        let project = ConnectionPolicy { allow_writes: Some(true), max_rows: Some(50), allowed_labels: Some(vec!["User".to_string()]), ..Default::default() };
+        let combined = prod.restricted_by(project);
+        assert_eq!(combined.allow_writes, Some(false));
+        assert_eq!(combined.max_rows, Some(50));
+        assert!(combined.check_labels(["User"]).is_ok());
+        assert!(combined.check_labels(["Post"]).is_err());
+        assert_eq!(combined.limit_rows(serde_json::json!({ "users": [1, 2, 3] }))["users"].as_array().unwrap().len(), 3);
+
+        assert!(combined.check_result_labels(&serde_json::json!({ "users": [{ "id": "1", "label": "User" }], "label": "Post" })).is_ok());
+        assert!(combined.check_result_labels(&serde_json::json!({ "posts": [{ "id": "2", "label": "Post" }] })).is_err());
+    }
+
+    #[test]
+    fn test_connection_matches_whole_address() {
+        let local = serde_json::json!({ "host": "localhost", "port": "6969" });
+        assert!(connection_matches(&local, "http://127.0.0.1:6969"));
+        assert!(connection_matches(&local, "HTTP://LOCALHOST:6969/"));
+        assert!(connection_matches(&local, "http://127.0.0.1:6969/getUser"));
+        assert!(!connection_matches(&local, "http://127.0.0.1:69690"));
+        assert!(!connection_matches(&local, "http://evil.example/?to=127.0.0.1:6969"));
+
+        let cloud = serde_json::json!({ "type": "cloud", "cloudHost": "prod.helix-db.com/" });
+        assert!(connection_matches(&cloud, "https://prod.helix-db.com:443"));
+        assert!(!connection_matches(&cloud, "https://prod.helix-db.com.evil.example"));
+        assert!(!connection_matches(&cloud, "http://prod.helix-db.com"));
+    }
+}
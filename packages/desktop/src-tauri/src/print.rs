@@ -0,0 +1,182 @@
+use tauri::webview::PageLoadEvent;
+use tauri::{WebviewUrl, WebviewWindowBuilder};
+
+const PRINT_WINDOW_LABEL: &str = "print-view";
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintRequest {
+    /// Query or report name shown in the page header.
+    pub title: Option<String>,
+    /// A result table (array of rows), a multi-variable result (object of arrays) or a scalar.
+    pub result: serde_json::Value,
+    /// Column order for tables; defaults to the order keys first appear in.
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
+    /// Optional text printed above the tables (e.g. a report summary).
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(s)) => escape(s),
+        Some(other) => escape(&other.to_string()),
+    }
+}
+
+fn columns_of(rows: &[serde_json::Value]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows {
+        match row {
+            serde_json::Value::Object(map) => {
+                for key in map.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            _ if !columns.iter().any(|c| c == "value") => columns.push("value".to_string()),
+            _ => {}
+        }
+    }
+    columns
+}
+
+fn render_table(rows: &[serde_json::Value], columns: Option<&[String]>) -> String {
+    let columns = columns.map(|c| c.to_vec()).unwrap_or_else(|| columns_of(rows));
+    let mut html = String::from("<table><thead><tr>");
+    for col in &columns {
+        html.push_str(&format!("<th>{}</th>", escape(col)));
+    }
+    html.push_str("</tr></thead><tbody>");
+    for row in rows {
+        html.push_str("<tr>");
+        for col in &columns {
+            let value = match row {
+                serde_json::Value::Object(map) => map.get(col),
+                scalar if col == "value" => Some(scalar),
+                _ => None,
+            };
+            html.push_str(&format!("<td>{}</td>", cell(value)));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    html.push_str(&format!("<p class=\"count\">{} rows</p>", rows.len()));
+    html
+}
+
+/// Renders a result as a standalone, print-friendly HTML page. The header repeats on
+/// every printed page and table headers repeat across page breaks.
+pub fn render_print_html(request: &PrintRequest) -> String {
+    let title = request.title.clone().unwrap_or_else(|| "Query result".to_string());
+    let printed_at = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+
+    let mut body = String::new();
+    if let Some(notes) = &request.notes {
+        body.push_str(&format!("<p class=\"notes\">{}</p>", escape(notes)));
+    }
+    match &request.result {
+        serde_json::Value::Array(rows) => body.push_str(&render_table(rows, request.columns.as_deref())),
+        serde_json::Value::Object(map) if map.values().any(|v| v.is_array()) => {
+            for (name, value) in map {
+                body.push_str(&format!("<h2>{}</h2>", escape(name)));
+                match value {
+                    serde_json::Value::Array(rows) => body.push_str(&render_table(rows, None)),
+                    single => body.push_str(&render_table(std::slice::from_ref(single), None)),
+                }
+            }
+        }
+        serde_json::Value::Object(_) => body.push_str(&render_table(std::slice::from_ref(&request.result), request.columns.as_deref())),
+        scalar => body.push_str(&format!("<p class=\"scalar\">{}</p>", cell(Some(scalar)))),
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+  @page {{ margin: 18mm 12mm 14mm; }}
+  body {{ font: 10pt -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #000; margin: 0; padding-top: 10mm; }}
+  header {{ position: fixed; top: 0; left: 0; right: 0; display: flex; justify-content: space-between; border-bottom: 1px solid #999; padding-bottom: 2mm; font-size: 9pt; }}
+  header .title {{ font-weight: 600; }}
+  h2 {{ font-size: 11pt; margin: 6mm 0 2mm; }}
+  table {{ width: 100%; border-collapse: collapse; }}
+  thead {{ display: table-header-group; }}
+  tr {{ page-break-inside: avoid; }}
+  th, td {{ border: 1px solid #bbb; padding: 1mm 1.5mm; text-align: left; vertical-align: top; word-break: break-word; }}
+  th {{ background: #eee; }}
+  .count, .notes {{ color: #444; font-size: 9pt; }}
+  .scalar {{ font-size: 14pt; }}
+</style>
+</head>
+<body>
+<header><span class="title">{title}</span><span>{printed_at}</span></header>
+{body}
+</body>
+</html>"#,
+        title = escape(&title),
+        printed_at = printed_at,
+        body = body,
+    )
+}
+
+/// Shows the page in a preview window and opens the OS print dialog once it has
+/// rendered (the dialog also offers "Save as PDF"). The dialog is attached to the
+/// preview, so the window stays open for reprinting until the user closes it.
+pub fn open_print_dialog(app: &tauri::AppHandle, html: &str) -> Result<(), String> {
+    let path = std::env::temp_dir().join("helixdb-explorer-print.html");
+    std::fs::write(&path, html).map_err(|e| format!("Failed to write print view: {}", e))?;
+    let url = tauri::Url::from_file_path(&path).map_err(|_| "Invalid print view path".to_string())?;
+
+    if let Some(existing) = tauri::Manager::get_webview_window(app, PRINT_WINDOW_LABEL) {
+        let _ = existing.destroy();
+    }
+
+    WebviewWindowBuilder::new(app, PRINT_WINDOW_LABEL, WebviewUrl::External(url))
+        .title("Print Preview")
+        .inner_size(800.0, 900.0)
+        .on_page_load(|window, payload| {
+            if payload.event() == PageLoadEvent::Finished {
+                if let Err(e) = window.print() {
+                    println!(">>> [Print] Failed to open print dialog: {}", e);
+                }
+            }
+        })
+        .build()
+        .map_err(|e| format!("Failed to open print view: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_escapes_and_orders_columns() {
+        let request = PrintRequest {
+            title: Some("Users <admin>".to_string()),
+            result: serde_json::json!([
+                { "name": "<b>Alice</b>", "age": 31 },
+                { "name": "Bob", "city": "Berlin" },
+            ]),
+            ..Default::default()
+        };
+        let html = render_print_html(&request);
+        assert!(html.contains("Users &lt;admin&gt;"));
+        assert!(html.contains("<td>&lt;b&gt;Alice&lt;/b&gt;</td>"));
+        assert!(html.contains("<th>name</th><th>age</th><th>city</th>"));
+        assert!(html.contains("2 rows"));
+    }
+}
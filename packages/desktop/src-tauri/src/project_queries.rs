@@ -0,0 +1,94 @@
+use helix_db::helixc::parser::{write_to_temp_file, HelixParser};
+
+/// Written above each query synced into a project's queries.hx.
+pub const SYNC_MARKER: &str = "// Synced from Helix Explorer";
+
+/// Collapses runs of blank lines to one and trims trailing whitespace per line.
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() && matches!(out.last(), None | Some(&"")) {
+            continue;
+        }
+        out.push(line);
+    }
+    while out.last() == Some(&"") {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// Splits the text between two queries into its own lines and the newest sync marker.
+fn split_gap(gap: &str) -> (Vec<&str>, Option<&str>) {
+    let mut kept = Vec::new();
+    let mut newest: Option<&str> = None;
+    for line in gap.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with(SYNC_MARKER) {
+            // Timestamps are `%Y-%m-%d %H:%M:%S`, so they sort as text
+            match newest {
+                Some(n) if n >= trimmed => {}
+                _ => newest = Some(trimmed),
+            }
+        } else if trimmed != "8" && trimmed != ";" {
+            kept.push(line);
+        }
+    }
+    (kept, newest)
+}
+
+/// Tidies a queries.hx: one sync marker per synced query (the newest), no stray
+/// markers, at most one blank line anywhere, and optionally queries sorted by name.
+/// Comments other than markers stay with the query below them.
+pub fn clean_queries_file(content: &str, sort_by_name: bool) -> Result<String, String> {
+    if content.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let source = HelixParser::parse_source(&write_to_temp_file(vec![content]))
+        .map_err(|e| format!("Failed to parse queries.hx: {}", e))?;
+    let mut queries: Vec<_> = source.queries.iter().map(|q| (q.name.clone(), q.loc.byte_range())).collect();
+    queries.sort_by_key(|(_, range)| range.start);
+
+    let mut blocks: Vec<(String, String)> = Vec::new();
+    let mut cursor = 0;
+    for (name, range) in &queries {
+        let (kept, marker) = split_gap(&content[cursor..range.start]);
+        let mut block = collapse_blank_lines(&kept.join("\n"));
+        if let Some(marker) = marker {
+            if !block.is_empty() {
+                block.push('\n');
+            }
+            block.push_str(marker);
+        }
+        if !block.is_empty() {
+            block.push('\n');
+        }
+        block.push_str(&collapse_blank_lines(content[range.clone()].trim()));
+        blocks.push((name.clone(), block));
+        cursor = range.end;
+    }
+    let (trailing, _) = split_gap(&content[cursor..]);
+    let trailing = collapse_blank_lines(&trailing.join("\n"));
+
+    if sort_by_name {
+        blocks.sort_by_key(|(name, _)| name.to_lowercase());
+    }
+    let mut parts: Vec<String> = blocks.into_iter().map(|(_, block)| block).collect();
+    if !trailing.trim().is_empty() {
+        parts.push(trailing.trim().to_string());
+    }
+    Ok(format!("{}\n", parts.join("\n\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_queries_file() {
+        let content = "// Synced from Helix Explorer at 2024-01-01 10:00:00\n// Synced from Helix Explorer at 2024-03-01 10:00:00\n// users by age\nQUERY Users() =>\n    users <- N<User>\n\n\n\n    RETURN users\n\n\n\n// Synced from Helix Explorer at 2024-02-01 10:00:00\n\n\nQUERY Active() =>\n    users <- N<User>\n    RETURN users\n// Synced from Helix Explorer at 2024-04-01 10:00:00\n\n";
+        let cleaned = clean_queries_file(content, true).unwrap();
+        assert_eq!(cleaned, "// Synced from Helix Explorer at 2024-02-01 10:00:00\nQUERY Active() =>\n    users <- N<User>\n    RETURN users\n\n// users by age\n// Synced from Helix Explorer at 2024-03-01 10:00:00\nQUERY Users() =>\n    users <- N<User>\n\n    RETURN users\n");
+        assert_eq!(clean_queries_file(&cleaned, true).unwrap(), cleaned);
+    }
+}
@@ -0,0 +1,82 @@
+//! Proxies for gateways only reachable through one, set per connection as `proxy` in
+//! connections.json: `"system"` to use the system's proxy settings, a proxy URL
+//! (`http://`, `https://`, `socks5://` or `socks5h://`), or
+//! `{ "url": "...", "username": "...", "password": "..." }`. Without one, requests go
+//! direct.
+
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProxySetting {
+    /// `HTTP_PROXY`/`HTTPS_PROXY`/`ALL_PROXY` and the system settings, as reqwest reads them.
+    System,
+    Url { url: String, auth: Option<(String, String)> },
+}
+
+impl ProxySetting {
+    /// The connection's `proxy`, if it sets one.
+    pub fn of_connection(connection: Option<&serde_json::Value>) -> Result<Option<Self>, String> {
+        let Some(config) = connection.and_then(|c| c.get("proxy")).filter(|p| !p.is_null()) else {
+            return Ok(None);
+        };
+        let (url, auth) = match config {
+            serde_json::Value::String(s) if s.trim().is_empty() => return Ok(None),
+            serde_json::Value::String(s) if s.trim().eq_ignore_ascii_case("system") => return Ok(Some(Self::System)),
+            serde_json::Value::String(s) => (s.trim(), None),
+            serde_json::Value::Object(map) => {
+                let field = |name: &str| map.get(name).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+                let url = field("url").ok_or("proxy needs a `url`")?;
+                let auth = match (field("username"), field("password")) {
+                    (Some(user), password) => Some((user.to_string(), password.unwrap_or_default().to_string())),
+                    (None, Some(_)) => return Err("proxy sets a `password` without a `username`".to_string()),
+                    (None, None) => None,
+                };
+                (url, auth)
+            }
+            _ => return Err("proxy must be \"system\", a proxy URL or an object with a `url`".to_string()),
+        };
+        let scheme = url.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase());
+        if !scheme.as_deref().is_some_and(|s| SCHEMES.contains(&s)) {
+            return Err(format!("Unsupported proxy URL '{}'; use http://, https://, socks5:// or socks5h://", url));
+        }
+        Ok(Some(Self::Url { url: url.to_string(), auth }))
+    }
+
+    pub fn apply(&self, builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder, String> {
+        match self {
+            // The builder reads the system proxies unless told otherwise
+            Self::System => Ok(builder),
+            Self::Url { url, auth } => {
+                let proxy = reqwest::Proxy::all(url.as_str()).map_err(|e| format!("Invalid proxy URL '{}': {}", url, e))?;
+                let proxy = match auth {
+                    Some((user, password)) => proxy.basic_auth(user, password),
+                    None => proxy,
+                };
+                Ok(builder.proxy(proxy))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_config() {
+        let of = |proxy: serde_json::Value| ProxySetting::of_connection(Some(&serde_json::json!({ "proxy": proxy })));
+        assert_eq!(of(serde_json::json!("System")).unwrap(), Some(ProxySetting::System));
+        assert_eq!(of(serde_json::json!("")).unwrap(), None);
+        assert_eq!(of(serde_json::json!("socks5h://proxy:1080")).unwrap(),
+            Some(ProxySetting::Url { url: "socks5h://proxy:1080".to_string(), auth: None }));
+        assert_eq!(of(serde_json::json!({ "url": "http://proxy:3128", "username": "me", "password": "pw" })).unwrap(),
+            Some(ProxySetting::Url { url: "http://proxy:3128".to_string(), auth: Some(("me".to_string(), "pw".to_string())) }));
+        assert!(of(serde_json::json!("proxy:3128")).is_err());
+        assert!(of(serde_json::json!("ftp://proxy")).is_err());
+        assert!(of(serde_json::json!({ "url": "http://proxy", "password": "pw" })).is_err());
+        assert_eq!(ProxySetting::of_connection(None).unwrap(), None);
+
+        let setting = of(serde_json::json!("http://proxy:3128")).unwrap().unwrap();
+        assert!(setting.apply(reqwest::Client::builder()).unwrap().build().is_ok());
+    }
+}
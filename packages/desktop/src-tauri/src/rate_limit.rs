@@ -0,0 +1,128 @@
+//! Per-connection request rate limits. Cloud instances throttle clients that send too
+//! fast, and one multi-variable query can fire dozens of tool calls a second. A
+//! connection's `rateLimit` (`{ "requestsPerSecond": 10, "burst": 20 }`) spaces its tool
+//! calls and compiled query calls out with a token bucket.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    /// Requests that may go out at once after a quiet spell; one second's worth by default.
+    #[serde(default)]
+    pub burst: Option<f64>,
+}
+
+impl RateLimit {
+    pub fn from_connection(connection: Option<&serde_json::Value>) -> Option<Self> {
+        connection.and_then(|c| c.get("rateLimit"))
+            .and_then(|limit| serde_json::from_value::<RateLimit>(limit.clone()).ok())
+            .filter(|limit| limit.requests_per_second > 0.0)
+    }
+
+    fn burst(&self) -> f64 {
+        self.burst.filter(|b| *b >= 1.0).unwrap_or(self.requests_per_second.max(1.0))
+    }
+}
+
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        Self { limit, tokens: limit.burst(), last: now }
+    }
+
+    /// Takes a token and returns how long to wait before sending. Tokens go negative
+    /// while requests wait, so later ones queue behind those already waiting.
+    pub fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = self.last.max(now);
+        self.tokens = (self.tokens + elapsed * self.limit.requests_per_second).min(self.limit.burst());
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.limit.requests_per_second)
+        }
+    }
+}
+
+/// One connection's bucket, shared by everything that sends to it.
+pub struct Limiter(Mutex<TokenBucket>);
+
+impl Limiter {
+    /// Waits for this connection's turn to send.
+    pub async fn wait(&self) {
+        let wait = self.0.lock().unwrap().reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Connection URL -> limiter, for connections that set a `rateLimit`.
+#[derive(Default)]
+pub struct RateLimits(Mutex<HashMap<String, Arc<Limiter>>>);
+
+impl RateLimits {
+    /// The limiter for `url`, kept across queries; `None` when `connection` sets no limit.
+    /// A changed limit applies from the next request.
+    pub fn for_connection(&self, url: &str, connection: Option<&serde_json::Value>) -> Option<Arc<Limiter>> {
+        let key = url.trim_end_matches('/');
+        let mut limiters = self.0.lock().unwrap();
+        let Some(limit) = RateLimit::from_connection(connection) else {
+            limiters.remove(key);
+            return None;
+        };
+        let limiter = limiters.entry(key.to_string())
+            .or_insert_with(|| Arc::new(Limiter(Mutex::new(TokenBucket::new(limit, Instant::now())))));
+        {
+            let mut bucket = limiter.0.lock().unwrap();
+            if bucket.limit != limit {
+                bucket.tokens = bucket.tokens.min(limit.burst());
+                bucket.limit = limit;
+            }
+        }
+        Some(limiter.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limit = RateLimit { requests_per_second: 10.0, burst: Some(2.0) };
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(limit, start);
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        // Out of tokens: each request waits a tenth of a second behind the last
+        assert_eq!(bucket.reserve(start).as_millis(), 100);
+        assert_eq!(bucket.reserve(start).as_millis(), 200);
+        // Refilled over time, but never past the burst
+        let later = start + Duration::from_secs(5);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+        assert_eq!(bucket.reserve(later).as_millis(), 100);
+
+        let connection = serde_json::json!({ "rateLimit": { "requestsPerSecond": 5 } });
+        assert_eq!(RateLimit::from_connection(Some(&connection)).map(|l| l.burst()), Some(5.0));
+        assert_eq!(RateLimit::from_connection(Some(&serde_json::json!({ "rateLimit": { "requestsPerSecond": 0 } }))), None);
+
+        let limits = RateLimits::default();
+        let first = limits.for_connection("http://h/", Some(&connection)).unwrap();
+        let again = limits.for_connection("http://h", Some(&connection)).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(limits.for_connection("http://h", None).is_none());
+        assert!(limits.0.lock().unwrap().is_empty());
+    }
+}
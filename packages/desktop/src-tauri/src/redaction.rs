@@ -0,0 +1,124 @@
+//! Masks sensitive properties before results leave the backend. Rules live on each
+//! connection in connections.json:
+//! `"redaction": [{ "pattern": "*email*", "action": "mask" }]`.
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionAction {
+    /// Keeps the first character: `a••••••`.
+    Mask,
+    /// Replaces the value with a stable digest, so equal values still group and join.
+    Hash,
+    /// Removes the property.
+    Hide,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RedactionRule {
+    /// Property name, case-insensitive; `*` matches any run of characters.
+    pub pattern: String,
+    pub action: RedactionAction,
+}
+
+/// Rules for the connection `url` points at. Redaction can be paused per connection
+/// with `"redactionEnabled": false` without losing the rules.
+pub fn rules_for(app: &tauri::AppHandle, url: &str) -> Vec<RedactionRule> {
+    let Some(connection) = crate::policy::find_connection(app, Some(url)) else {
+        return Vec::new();
+    };
+    rules_of(&connection)
+}
+
+pub(crate) fn rules_of(connection: &serde_json::Value) -> Vec<RedactionRule> {
+    if connection.get("redactionEnabled").and_then(|v| v.as_bool()) == Some(false) {
+        return Vec::new();
+    }
+    connection.get("redaction")
+        .and_then(|r| serde_json::from_value(r.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || !name[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// FNV-1a, so digests are identical across runs and machines.
+fn digest(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let hash = text.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("#{:016x}", hash)
+}
+
+fn mask(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Null => serde_json::Value::Null,
+        serde_json::Value::String(s) => {
+            let first: String = s.chars().take(1).collect();
+            serde_json::Value::String(format!("{}••••••", first))
+        }
+        _ => serde_json::Value::String("••••••".to_string()),
+    }
+}
+
+/// Applies `rules` to every object key at any depth of a (normalized) result.
+pub fn redact(value: serde_json::Value, rules: &[RedactionRule]) -> serde_json::Value {
+    if rules.is_empty() {
+        return value;
+    }
+    match value {
+        serde_json::Value::Array(items) => serde_json::Value::Array(items.into_iter().map(|v| redact(v, rules)).collect()),
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter()
+            .filter_map(|(key, v)| match rules.iter().find(|r| matches(&r.pattern, &key)).map(|r| r.action) {
+                Some(RedactionAction::Hide) => None,
+                Some(RedactionAction::Mask) => Some((key, mask(&v))),
+                Some(RedactionAction::Hash) => Some((key, serde_json::Value::String(digest(&v)))),
+                None => Some((key, redact(v, rules))),
+            })
+            .collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_nested_properties() {
+        let rules = vec![
+            RedactionRule { pattern: "*email*".to_string(), action: RedactionAction::Mask },
+            RedactionRule { pattern: "phone".to_string(), action: RedactionAction::Hide },
+            RedactionRule { pattern: "ssn".to_string(), action: RedactionAction::Hash },
+        ];
+        let result = redact(serde_json::json!({
+            "users": [{ "name": "Alice", "workEmail": "alice@example.com", "Phone": "555", "ssn": "123" }]
+        }), &rules);
+        let user = &result["users"][0];
+        assert_eq!(user["name"], "Alice");
+        assert_eq!(user["workEmail"], "a••••••");
+        assert!(user.get("Phone").is_none());
+        assert_eq!(user["ssn"], serde_json::Value::String(digest(&serde_json::json!("123"))));
+        assert!(matches("user_*_id", "user_account_id"));
+        assert!(!matches("user_*_id", "user_id"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a fetched schema is served before it is fetched again, unless the
+/// connection sets `schemaCacheTtlMs` (0 turns caching off).
+pub const DEFAULT_SCHEMA_TTL: Duration = Duration::from_secs(300);
+
+/// Parsed MCP schemas per connection URL, so autocomplete and validation do not
+/// re-init a connection for every lookup.
+#[derive(Default)]
+pub struct SchemaCache {
+    entries: Mutex<HashMap<String, (Instant, serde_json::Value)>>,
+}
+
+fn key(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+impl SchemaCache {
+    pub fn get(&self, url: &str, ttl: Duration) -> Option<serde_json::Value> {
+        let entries = self.entries.lock().unwrap();
+        let (fetched, schema) = entries.get(&key(url))?;
+        (fetched.elapsed() < ttl).then(|| schema.clone())
+    }
+
+    pub fn put(&self, url: &str, schema: serde_json::Value) {
+        self.entries.lock().unwrap().insert(key(url), (Instant::now(), schema));
+    }
+
+    /// Forgets `url`'s schema, or every schema without a URL.
+    pub fn invalidate(&self, url: Option<&str>) {
+        let mut entries = self.entries.lock().unwrap();
+        match url {
+            Some(url) => {
+                entries.remove(&key(url));
+            }
+            None => entries.clear(),
+        }
+    }
+}
+
+/// The connection's `schemaCacheTtlMs`, else the default.
+pub fn ttl_for(app: &tauri::AppHandle, url: &str) -> Duration {
+    crate::policy::find_connection(app, Some(url))
+        .and_then(|c| c.get("schemaCacheTtlMs")?.as_u64())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_SCHEMA_TTL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_cache() {
+        let cache = SchemaCache::default();
+        let schema = serde_json::json!({ "nodes": [{ "name": "User" }] });
+        cache.put("http://localhost:6969/", schema.clone());
+        assert_eq!(cache.get("http://localhost:6969", DEFAULT_SCHEMA_TTL), Some(schema));
+        assert_eq!(cache.get("http://localhost:6969", Duration::ZERO), None);
+
+        cache.invalidate(Some("http://localhost:6969"));
+        assert_eq!(cache.get("http://localhost:6969", DEFAULT_SCHEMA_TTL), None);
+    }
+}
@@ -0,0 +1,112 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+pub const LINK_SCHEME: &str = "helixdb-explorer";
+/// Current payload format; older versions must keep decoding.
+const PAYLOAD_VERSION: u32 = 1;
+/// Decompressed payloads above this are rejected rather than inflated without bound.
+const MAX_PAYLOAD_BYTES: u64 = 1024 * 1024;
+
+/// Where the query was written; only a hint, the receiver picks the actual connection.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHint {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedQuery {
+    #[serde(default = "payload_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub code: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+    #[serde(default)]
+    pub connection: Option<ConnectionHint>,
+}
+
+fn payload_version() -> u32 {
+    PAYLOAD_VERSION
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct QueryLink {
+    /// `helixdb-explorer://query?q=<payload>`
+    pub link: String,
+    pub payload: String,
+}
+
+pub fn encode_payload(query: &SharedQuery) -> Result<String, String> {
+    let json = serde_json::to_vec(&SharedQuery { version: PAYLOAD_VERSION, ..query.clone() })
+        .map_err(|e| e.to_string())?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&json).map_err(|e| e.to_string())?;
+    let compressed = encoder.finish().map_err(|e| e.to_string())?;
+    Ok(URL_SAFE_NO_PAD.encode(compressed))
+}
+
+pub fn encode_link(query: &SharedQuery) -> Result<QueryLink, String> {
+    let payload = encode_payload(query)?;
+    Ok(QueryLink { link: format!("{}://query?q={}", LINK_SCHEME, payload), payload })
+}
+
+/// Accepts a full link, a bare payload, or the contents of a `.helixq` file (a link
+/// on its own line).
+pub fn decode_link(input: &str) -> Result<SharedQuery, String> {
+    let input = input.trim();
+    let payload = match input.split_once("?q=").or_else(|| input.split_once("&q=")) {
+        Some((_, rest)) => rest.split('&').next().unwrap_or_default(),
+        None => input,
+    };
+
+    let compressed = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('='))
+        .map_err(|_| "Not a valid query link".to_string())?;
+    let mut json = Vec::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_PAYLOAD_BYTES + 1)
+        .read_to_end(&mut json)
+        .map_err(|_| "Not a valid query link".to_string())?;
+    if json.len() as u64 > MAX_PAYLOAD_BYTES {
+        return Err("Query link payload is too large".to_string());
+    }
+
+    let query: SharedQuery = serde_json::from_slice(&json)
+        .map_err(|e| format!("Invalid query link payload: {}", e))?;
+    if query.version > PAYLOAD_VERSION {
+        return Err(format!("This link was created by a newer version of the Explorer (format {})", query.version));
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_round_trip() {
+        let query = SharedQuery {
+            version: 0,
+            name: Some("Active users".to_string()),
+            code: "QUERY Q(min: I64) =>\n    users <- N<User>::WHERE(_::{age}::GT(min))\n    RETURN users".to_string(),
+            params: Some(serde_json::json!({ "min": 21 })),
+            connection: Some(ConnectionHint { name: Some("staging".to_string()), url: None }),
+        };
+        let link = encode_link(&query).unwrap();
+        assert!(link.link.starts_with("helixdb-explorer://query?q="));
+
+        let decoded = decode_link(&format!("{}\n", link.link)).unwrap();
+        assert_eq!(decoded, SharedQuery { version: PAYLOAD_VERSION, ..query });
+        assert_eq!(decode_link(&link.payload).unwrap().code, decoded.code);
+        assert!(decode_link("helixdb-explorer://query?q=not-a-payload").is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::Manager;
+
+const SLOW_QUERY_FILE: &str = "slow-queries.jsonl";
+/// Executions at least this slow are logged unless the connection sets `slowQueryThresholdMs`.
+pub const DEFAULT_THRESHOLD_MS: u64 = 1000;
+/// Once the log grows past this, the older half is dropped.
+const MAX_LOG_BYTES: u64 = 4 * 1024 * 1024;
+
+/// One execution that crossed the threshold.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQuery {
+    pub started_at: String,
+    pub url: String,
+    pub code: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub rows: usize,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The `explain_hql` plan at the time, when the query could be translated.
+    #[serde(default)]
+    pub plan: Option<serde_json::Value>,
+    /// Per-call timings, as returned with the result.
+    #[serde(default)]
+    pub metrics: Option<serde_json::Value>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowQueryOrder {
+    #[default]
+    Total,
+    Average,
+}
+
+/// All logged runs of one query against one connection.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQueryStats {
+    pub url: String,
+    pub code: String,
+    pub count: usize,
+    pub total_ms: u64,
+    pub average_ms: u64,
+    pub max_ms: u64,
+    /// The most recent run, with its plan and timings.
+    pub latest: SlowQuery,
+}
+
+/// The threshold for `url`: the connection's `slowQueryThresholdMs`, else the default.
+pub fn threshold_ms(app: &tauri::AppHandle, url: &str) -> u64 {
+    crate::policy::find_connection(app, Some(url))
+        .and_then(|c| c.get("slowQueryThresholdMs")?.as_u64())
+        .unwrap_or(DEFAULT_THRESHOLD_MS)
+}
+
+fn log_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(SLOW_QUERY_FILE))
+}
+
+pub fn record(app: &tauri::AppHandle, entry: &SlowQuery) -> Result<(), String> {
+    let path = log_path(app)?;
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+
+    if file.metadata().map(|m| m.len() > MAX_LOG_BYTES).unwrap_or(false) {
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let lines: Vec<&str> = content.lines().collect();
+        fs::write(&path, format!("{}\n", lines[lines.len() / 2..].join("\n"))).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn load(app: &tauri::AppHandle) -> Result<Vec<SlowQuery>, String> {
+    let path = log_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+pub fn clear(app: &tauri::AppHandle) -> Result<(), String> {
+    let path = log_path(app)?;
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Groups runs by connection and query text (ignoring layout), slowest first.
+pub fn rank(entries: Vec<SlowQuery>, order: SlowQueryOrder) -> Vec<SlowQueryStats> {
+    let mut groups: HashMap<(String, String), SlowQueryStats> = HashMap::new();
+    for entry in entries {
+        let key = (entry.url.trim_end_matches('/').to_string(), entry.code.split_whitespace().collect::<Vec<_>>().join(" "));
+        let stats = groups.entry(key).or_insert_with(|| SlowQueryStats {
+            url: entry.url.clone(),
+            code: entry.code.clone(),
+            count: 0,
+            total_ms: 0,
+            average_ms: 0,
+            max_ms: 0,
+            latest: entry.clone(),
+        });
+        stats.count += 1;
+        stats.total_ms += entry.duration_ms;
+        stats.max_ms = stats.max_ms.max(entry.duration_ms);
+        if entry.started_at >= stats.latest.started_at {
+            stats.latest = entry;
+        }
+    }
+
+    let mut ranked: Vec<SlowQueryStats> = groups.into_values()
+        .map(|mut s| {
+            s.average_ms = s.total_ms / s.count as u64;
+            s
+        })
+        .collect();
+    ranked.sort_by(|a, b| match order {
+        SlowQueryOrder::Total => b.total_ms.cmp(&a.total_ms),
+        SlowQueryOrder::Average => b.average_ms.cmp(&a.average_ms),
+    }.then_with(|| a.code.cmp(&b.code)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slow(code: &str, duration_ms: u64, started_at: &str) -> SlowQuery {
+        SlowQuery {
+            started_at: started_at.to_string(),
+            url: "http://localhost:6969".to_string(),
+            code: code.to_string(),
+            params: None,
+            duration_ms,
+            rows: 0,
+            error: None,
+            plan: None,
+            metrics: None,
+        }
+    }
+
+    #[test]
+    fn test_rank_by_total_and_average() {
+        let entries = vec![
+            slow("N<User>", 1200, "2024-05-01T10:00:00Z"),
+            slow("N<User>\n", 1400, "2024-05-01T11:00:00Z"),
+            slow("N<User>", 1300, "2024-05-01T12:00:00Z"),
+            slow("N<Post>::Out<Tagged>", 5000, "2024-05-01T10:30:00Z"),
+        ];
+
+        let by_total = rank(entries.clone(), SlowQueryOrder::Total);
+        assert_eq!(by_total[0].code, "N<Post>::Out<Tagged>");
+        assert_eq!((by_total[1].count, by_total[1].total_ms, by_total[1].average_ms), (3, 3900, 1300));
+        assert_eq!(by_total[1].latest.started_at, "2024-05-01T12:00:00Z");
+
+        let entries = vec![slow("A", 1100, "t1"), slow("A", 1100, "t2"), slow("A", 1100, "t3"), slow("B", 2000, "t1")];
+        let by_average = rank(entries, SlowQueryOrder::Average);
+        assert_eq!(by_average[0].code, "B");
+        assert_eq!(rank(vec![slow("A", 1100, "t1"), slow("A", 1100, "t2"), slow("B", 2000, "t1")], SlowQueryOrder::Total)[0].code, "A");
+    }
+}
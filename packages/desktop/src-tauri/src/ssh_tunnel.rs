@@ -0,0 +1,463 @@
+//! SSH tunnels for gateways on machines only reachable over SSH, set per connection as
+//! `sshTunnel` in connections.json:
+//! `{ "host": "vm.example.com", "user": "ubuntu", "keyPath": "~/.ssh/id_ed25519" }`,
+//! plus optionally `port` (22), `keyPassphrase`, and `remoteHost`/`remotePort`, where the
+//! gateway listens as seen from that machine (`127.0.0.1` and the connection's port by
+//! default).
+//!
+//! The connection's client sends its requests through a local SOCKS port, which forwards
+//! each stream to the gateway over one SSH session, opened on first use and reopened when
+//! it drops. The port asks for a username and password made up per tunnel, so other
+//! local processes cannot borrow the session. Host keys are checked against
+//! `~/.ssh/known_hosts`: a changed key is refused, and an unknown one is only added once
+//! the user confirmed its fingerprint (`trust_host_key`). A tunnel is closed when no
+//! connection uses its settings any more.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use russh::client;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCALHOST: &str = "127.0.0.1";
+const DEFAULT_SSH_PORT: u16 = 22;
+const DEFAULT_REMOTE_PORT: u16 = 6969;
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Length of the generated SOCKS username and password.
+const CREDENTIAL_LEN: usize = 24;
+
+/// SOCKS5 username/password authentication (RFC 1929).
+const SOCKS_METHOD_PASSWORD: u8 = 2;
+const SOCKS_NO_ACCEPTABLE_METHOD: u8 = 0xff;
+
+/// SOCKS5 reply codes.
+const SOCKS_SUCCEEDED: u8 = 0;
+const SOCKS_HOST_UNREACHABLE: u8 = 4;
+const SOCKS_COMMAND_NOT_SUPPORTED: u8 = 7;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TunnelSettings {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub key_path: PathBuf,
+    pub passphrase: Option<String>,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+fn port(value: Option<&serde_json::Value>, name: &str) -> Result<Option<u16>, String> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(v) => v.as_u64().or_else(|| v.as_str()?.trim().parse().ok())
+            .filter(|p| (1..=u16::MAX as u64).contains(p))
+            .map(|p| Some(p as u16))
+            .ok_or_else(|| format!("sshTunnel `{}` must be a port number", name)),
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+impl TunnelSettings {
+    /// The connection's `sshTunnel`, if it sets one.
+    pub fn of_connection(connection: Option<&serde_json::Value>) -> Result<Option<Self>, String> {
+        let Some(config) = connection.and_then(|c| c.get("sshTunnel")).filter(|t| !t.is_null()) else {
+            return Ok(None);
+        };
+        let config = config.as_object().ok_or("sshTunnel must be an object")?;
+        let field = |name: &str| config.get(name).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+        let required = |name: &str| field(name).map(String::from).ok_or_else(|| format!("sshTunnel needs a `{}`", name));
+        let connection_port = connection.and_then(|c| c.get("port")).and_then(|p| port(Some(p), "port").ok().flatten());
+        Ok(Some(Self {
+            host: required("host")?,
+            port: port(config.get("port"), "port")?.unwrap_or(DEFAULT_SSH_PORT),
+            user: required("user")?,
+            key_path: expand_home(&required("keyPath")?),
+            passphrase: field("keyPassphrase").map(String::from),
+            remote_host: field("remoteHost").unwrap_or(LOCALHOST).to_string(),
+            remote_port: port(config.get("remotePort"), "remotePort")?.or(connection_port).unwrap_or(DEFAULT_REMOTE_PORT),
+        }))
+    }
+}
+
+/// A host key not yet in known_hosts, kept until the user trusts or ignores it.
+type UnknownKey = Arc<Mutex<Option<russh_keys::key::PublicKey>>>;
+
+fn fingerprint(key: &russh_keys::key::PublicKey) -> String {
+    format!("SHA256:{}", key.fingerprint())
+}
+
+struct KnownHosts {
+    host: String,
+    port: u16,
+    unknown_key: UnknownKey,
+}
+
+#[async_trait::async_trait]
+impl client::Handler for KnownHosts {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &russh_keys::key::PublicKey) -> Result<bool, Self::Error> {
+        match russh_keys::check_known_hosts(&self.host, self.port, server_public_key) {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                println!(">>> [SSH] Unknown host key for {}: {}", self.host, fingerprint(server_public_key));
+                *self.unknown_key.lock().unwrap() = Some(server_public_key.clone());
+                Ok(false)
+            }
+            Err(e) => {
+                println!(">>> [SSH] Refusing {}: {}", self.host, e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// The username and password the tunnel's SOCKS port asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SocksCredentials {
+    user: String,
+    password: String,
+}
+
+impl SocksCredentials {
+    fn generate() -> Self {
+        use rand::{distributions::Alphanumeric, Rng};
+        let token = || rand::thread_rng().sample_iter(&Alphanumeric).take(CREDENTIAL_LEN).map(char::from).collect();
+        Self { user: token(), password: token() }
+    }
+}
+
+struct Tunnel {
+    settings: TunnelSettings,
+    local_port: u16,
+    credentials: SocksCredentials,
+    session: tokio::sync::Mutex<Option<client::Handle<KnownHosts>>>,
+    last_error: Mutex<Option<String>>,
+    unknown_key: UnknownKey,
+    accept_task: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+impl Tunnel {
+    async fn connect(&self) -> Result<client::Handle<KnownHosts>, String> {
+        let s = &self.settings;
+        let config = Arc::new(client::Config { keepalive_interval: Some(KEEPALIVE_INTERVAL), ..Default::default() });
+        let handler = KnownHosts { host: s.host.clone(), port: s.port, unknown_key: self.unknown_key.clone() };
+        let mut session = match client::connect(config, (s.host.as_str(), s.port), handler).await {
+            Ok(session) => session,
+            Err(e) => return Err(match self.unknown_key.lock().unwrap().as_ref() {
+                Some(key) => format!(
+                    "The host key of {}:{} is not known yet. Its fingerprint is {}; trust it once you have checked it to connect",
+                    s.host, s.port, fingerprint(key),
+                ),
+                None => format!("SSH connection to {}:{} failed: {}", s.host, s.port, e),
+            }),
+        };
+        let key = russh_keys::load_secret_key(&s.key_path, s.passphrase.as_deref())
+            .map_err(|e| format!("Could not load SSH key {}: {}", s.key_path.display(), e))?;
+        let accepted = session.authenticate_publickey(s.user.clone(), Arc::new(key)).await
+            .map_err(|e| format!("SSH authentication to {} failed: {}", s.host, e))?;
+        if !accepted {
+            return Err(format!("{}@{} did not accept the key {}", s.user, s.host, s.key_path.display()));
+        }
+        Ok(session)
+    }
+
+    /// A channel to the gateway, over the open session or a new one.
+    async fn open_channel(&self) -> Result<russh::Channel<client::Msg>, String> {
+        let mut session = self.session.lock().await;
+        if session.as_ref().is_none_or(|s| s.is_closed()) {
+            *session = Some(self.connect().await?);
+        }
+        let s = &self.settings;
+        let channel = session.as_ref().unwrap()
+            .channel_open_direct_tcpip(s.remote_host.clone(), s.remote_port as u32, LOCALHOST, self.local_port as u32)
+            .await
+            .map_err(|e| format!("Could not reach {}:{} through {}: {}", s.remote_host, s.remote_port, s.host, e));
+        if channel.is_err() {
+            // Reconnect on the next stream rather than reuse a session that may be dead
+            *session = None;
+        }
+        channel
+    }
+
+    /// Stops accepting local connections and ends the SSH session.
+    fn close(self: Arc<Self>) {
+        if let Some(task) = self.accept_task.lock().unwrap().take() {
+            task.abort();
+        }
+        tauri::async_runtime::spawn(async move {
+            if let Some(session) = self.session.lock().await.take() {
+                let _ = session.disconnect(russh::Disconnect::ByApplication, "", "en").await;
+            }
+        });
+    }
+}
+
+fn socks_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+async fn socks_reply<S: AsyncWrite + Unpin>(stream: &mut S, code: u8) -> std::io::Result<()> {
+    stream.write_all(&[5, code, 0, 1, 0, 0, 0, 0, 0, 0]).await
+}
+
+async fn read_field<S: AsyncRead + Unpin>(stream: &mut S) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 1];
+    stream.read_exact(&mut len).await?;
+    let mut field = vec![0u8; len[0] as usize];
+    stream.read_exact(&mut field).await?;
+    Ok(field)
+}
+
+/// Reads a SOCKS5 greeting, the tunnel's username and password, and a CONNECT request,
+/// leaving the reply to the caller. The target it names is ignored: a tunnel only
+/// reaches its own gateway.
+async fn accept_socks<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, credentials: &SocksCredentials) -> std::io::Result<()> {
+    let mut greeting = [0u8; 2];
+    stream.read_exact(&mut greeting).await?;
+    if greeting[0] != 5 {
+        return Err(socks_error("Not a SOCKS5 client"));
+    }
+    let mut methods = vec![0u8; greeting[1] as usize];
+    stream.read_exact(&mut methods).await?;
+    if !methods.contains(&SOCKS_METHOD_PASSWORD) {
+        stream.write_all(&[5, SOCKS_NO_ACCEPTABLE_METHOD]).await?;
+        return Err(socks_error("The client did not offer a SOCKS username and password"));
+    }
+    stream.write_all(&[5, SOCKS_METHOD_PASSWORD]).await?;
+
+    let mut version = [0u8; 1];
+    stream.read_exact(&mut version).await?;
+    if version[0] != 1 {
+        return Err(socks_error("Unknown SOCKS authentication version"));
+    }
+    let user = read_field(stream).await?;
+    let password = read_field(stream).await?;
+    if user != credentials.user.as_bytes() || password != credentials.password.as_bytes() {
+        stream.write_all(&[1, 1]).await?;
+        return Err(socks_error("Wrong SOCKS username or password"));
+    }
+    stream.write_all(&[1, 0]).await?;
+
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await?;
+    if request[1] != 1 {
+        socks_reply(stream, SOCKS_COMMAND_NOT_SUPPORTED).await?;
+        return Err(socks_error("Only CONNECT is supported"));
+    }
+    let address_len = match request[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        _ => return Err(socks_error("Unknown SOCKS address type")),
+    };
+    // The address and port
+    let mut target = vec![0u8; address_len + 2];
+    stream.read_exact(&mut target).await?;
+    Ok(())
+}
+
+async fn serve(tunnel: Arc<Tunnel>, mut local: tokio::net::TcpStream) {
+    if let Err(e) = accept_socks(&mut local, &tunnel.credentials).await {
+        println!(">>> [SSH] Dropped a local connection: {}", e);
+        return;
+    }
+    match tunnel.open_channel().await {
+        Ok(channel) => {
+            *tunnel.last_error.lock().unwrap() = None;
+            if socks_reply(&mut local, SOCKS_SUCCEEDED).await.is_ok() {
+                let mut remote = channel.into_stream();
+                let _ = tokio::io::copy_bidirectional(&mut local, &mut remote).await;
+            }
+        }
+        Err(e) => {
+            println!(">>> [SSH] {}", e);
+            *tunnel.last_error.lock().unwrap() = Some(e);
+            let _ = socks_reply(&mut local, SOCKS_HOST_UNREACHABLE).await;
+        }
+    }
+}
+
+async fn accept_loop(tunnel: Arc<Tunnel>, listener: std::net::TcpListener) {
+    let listener = match tokio::net::TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            *tunnel.last_error.lock().unwrap() = Some(format!("Could not listen for the tunnel: {}", e));
+            return;
+        }
+    };
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tauri::async_runtime::spawn(serve(tunnel.clone(), stream));
+            }
+            Err(e) => {
+                println!(">>> [SSH] Accept failed: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+/// Open tunnels, per settings, until `close_unused` drops them.
+fn tunnels() -> &'static Mutex<HashMap<TunnelSettings, Arc<Tunnel>>> {
+    static TUNNELS: OnceLock<Mutex<HashMap<TunnelSettings, Arc<Tunnel>>>> = OnceLock::new();
+    TUNNELS.get_or_init(Default::default)
+}
+
+/// The proxy URL of the tunnel for `settings`, credentials included, starting it if
+/// needed. The SSH session itself is opened by the first request.
+pub fn proxy_url(settings: &TunnelSettings) -> Result<String, String> {
+    let mut tunnels = tunnels().lock().unwrap();
+    let tunnel = match tunnels.get(settings) {
+        Some(tunnel) => tunnel.clone(),
+        None => {
+            let listener = std::net::TcpListener::bind((LOCALHOST, 0))
+                .and_then(|l| l.set_nonblocking(true).map(|_| l))
+                .map_err(|e| format!("Could not open a local port for the SSH tunnel: {}", e))?;
+            let local_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+            let tunnel = Arc::new(Tunnel {
+                settings: settings.clone(),
+                local_port,
+                credentials: SocksCredentials::generate(),
+                session: tokio::sync::Mutex::new(None),
+                last_error: Mutex::new(None),
+                unknown_key: Default::default(),
+                accept_task: Mutex::new(None),
+            });
+            *tunnel.accept_task.lock().unwrap() = Some(tauri::async_runtime::spawn(accept_loop(tunnel.clone(), listener)));
+            tunnels.insert(settings.clone(), tunnel.clone());
+            tunnel
+        }
+    };
+    let SocksCredentials { user, password } = &tunnel.credentials;
+    Ok(format!("socks5h://{}:{}@{}:{}", user, password, LOCALHOST, tunnel.local_port))
+}
+
+/// Closes the tunnels no connection in `connections` uses any more, as after one is
+/// removed or its `sshTunnel` changed.
+pub fn close_unused(connections: &[serde_json::Value]) {
+    let used: HashSet<TunnelSettings> = connections.iter()
+        .filter_map(|c| TunnelSettings::of_connection(Some(c)).ok().flatten())
+        .collect();
+    let mut closed = Vec::new();
+    tunnels().lock().unwrap().retain(|settings, tunnel| {
+        let keep = used.contains(settings);
+        if !keep {
+            closed.push(tunnel.clone());
+        }
+        keep
+    });
+    for tunnel in closed {
+        println!(">>> [SSH] Closing the tunnel to {}", tunnel.settings.host);
+        tunnel.close();
+    }
+}
+
+/// Adds the host key the tunnel for `settings` was refused for to known_hosts, once the
+/// user confirmed `fingerprint` is the one they expect.
+pub fn trust_host_key(settings: &TunnelSettings, fingerprint_shown: &str) -> Result<(), String> {
+    let tunnel = tunnels().lock().unwrap().get(settings).cloned()
+        .ok_or_else(|| format!("No SSH tunnel to {} is open", settings.host))?;
+    let mut unknown_key = tunnel.unknown_key.lock().unwrap();
+    let key = unknown_key.as_ref().ok_or_else(|| format!("No unknown host key is waiting for {}", settings.host))?;
+    if fingerprint(key) != fingerprint_shown.trim() {
+        return Err(format!("The host key of {} is {}, not the fingerprint confirmed", settings.host, fingerprint(key)));
+    }
+    russh_keys::learn_known_hosts(&settings.host, settings.port, key)
+        .map_err(|e| format!("Could not add {} to known_hosts: {}", settings.host, e))?;
+    *unknown_key = None;
+    *tunnel.last_error.lock().unwrap() = None;
+    Ok(())
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelStatus {
+    pub local_port: u16,
+    pub connected: bool,
+    /// Why the last stream could not be forwarded, until one is.
+    pub last_error: Option<String>,
+    /// Fingerprint of a host key not in known_hosts, waiting for `trust_host_key`.
+    pub unknown_host_key: Option<String>,
+}
+
+/// The tunnel for `settings`, or None before the connection's first request.
+pub async fn status(settings: &TunnelSettings) -> Option<TunnelStatus> {
+    let tunnel = tunnels().lock().unwrap().get(settings).cloned()?;
+    let connected = tunnel.session.lock().await.as_ref().is_some_and(|s| !s.is_closed());
+    let last_error = tunnel.last_error.lock().unwrap().clone();
+    let unknown_host_key = tunnel.unknown_key.lock().unwrap().as_ref().map(fingerprint);
+    Some(TunnelStatus { local_port: tunnel.local_port, connected, last_error, unknown_host_key })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tunnel_settings_and_socks() {
+        let connection = serde_json::json!({
+            "port": "7000",
+            "sshTunnel": { "host": "vm.example.com", "user": "ubuntu", "keyPath": "/keys/id_ed25519", "port": 2222 }
+        });
+        let settings = TunnelSettings::of_connection(Some(&connection)).unwrap().unwrap();
+        assert_eq!((settings.host.as_str(), settings.port, settings.user.as_str()), ("vm.example.com", 2222, "ubuntu"));
+        assert_eq!((settings.remote_host.as_str(), settings.remote_port), ("127.0.0.1", 7000));
+        assert_eq!(TunnelSettings::of_connection(Some(&serde_json::json!({ "port": "7000" }))).unwrap(), None);
+        assert!(TunnelSettings::of_connection(Some(&serde_json::json!({ "sshTunnel": { "host": "vm" } }))).is_err());
+        assert!(TunnelSettings::of_connection(Some(&serde_json::json!({
+            "sshTunnel": { "host": "vm", "user": "u", "keyPath": "k", "remotePort": 70000 }
+        }))).is_err());
+
+        let credentials = SocksCredentials::generate();
+        assert_eq!(credentials.user.len(), CREDENTIAL_LEN);
+        assert_ne!(credentials, SocksCredentials::generate());
+        let handshake = |user: &'static str, password: &'static str, credentials: SocksCredentials| async move {
+            let (mut client, mut server) = tokio::io::duplex(64);
+            let accepted = tokio::spawn(async move { accept_socks(&mut server, &credentials).await });
+            client.write_all(&[5, 1, SOCKS_METHOD_PASSWORD]).await.unwrap();
+            let mut choice = [0u8; 2];
+            client.read_exact(&mut choice).await.unwrap();
+            assert_eq!(choice, [5, SOCKS_METHOD_PASSWORD]);
+            client.write_all(&[1, user.len() as u8]).await.unwrap();
+            client.write_all(user.as_bytes()).await.unwrap();
+            client.write_all(&[password.len() as u8]).await.unwrap();
+            client.write_all(password.as_bytes()).await.unwrap();
+            let mut status = [0u8; 2];
+            client.read_exact(&mut status).await.unwrap();
+            if status == [1, 0] {
+                client.write_all(&[5, 1, 0, 3, 9]).await.unwrap();
+                client.write_all(b"localhost").await.unwrap();
+                client.write_all(&[0x1b, 0x39]).await.unwrap();
+            }
+            accepted.await.unwrap().is_ok()
+        };
+        let known = SocksCredentials { user: "user".to_string(), password: "secret".to_string() };
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            assert!(handshake("user", "secret", known.clone()).await);
+            assert!(!handshake("user", "guess", known.clone()).await);
+
+            // A client without credentials is turned away
+            let (mut client, mut server) = tokio::io::duplex(64);
+            let accepted = tokio::spawn(async move { accept_socks(&mut server, &known).await });
+            client.write_all(&[5, 1, 0]).await.unwrap();
+            let mut choice = [0u8; 2];
+            client.read_exact(&mut choice).await.unwrap();
+            assert_eq!(choice, [5, SOCKS_NO_ACCEPTABLE_METHOD]);
+            assert!(accepted.await.unwrap().is_err());
+        });
+    }
+}
@@ -1,6 +1,6 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
-use heed3::{EnvOpenOptions, Database, types::Bytes};
+use heed3::{CompactionOption, EnvOpenOptions, Database, types::Bytes};
 use serde::Serialize;
 use std::collections::HashMap;
 
@@ -48,7 +48,8 @@ pub struct LocalStorageStats {
     pub hnsw_stats: Option<HnswStat>,
 }
 
-pub fn get_local_db_stats(path: &str, instance_name: Option<&str>) -> Result<LocalStorageStats, String> {
+/// The directory holding the workspace's data.mdb, falling back to `path` itself.
+pub(crate) fn resolve_db_path(path: &str, instance_name: Option<&str>) -> PathBuf {
     let base_path = Path::new(path).to_path_buf();
     let instance = instance_name.unwrap_or("dev");
     
@@ -61,16 +62,19 @@ pub fn get_local_db_stats(path: &str, instance_name: Option<&str>) -> Result<Loc
     let native_user_path = base_path.join(".helix").join("user");
     let native_base_path = base_path.join(".helix");
     
-    let db_path = if docker_volume_path.join("data.mdb").exists() {
+    if docker_volume_path.join("data.mdb").exists() {
         docker_volume_path
     } else if native_user_path.join("data.mdb").exists() {
         native_user_path
     } else if native_base_path.join("data.mdb").exists() {
         native_base_path
     } else {
-        base_path.clone()
-    };
+        base_path
+    }
+}
 
+pub fn get_local_db_stats(path: &str, instance_name: Option<&str>) -> Result<LocalStorageStats, String> {
+    let db_path = resolve_db_path(path, instance_name);
     if !db_path.exists() {
         return Err(format!("Database path does not exist: {}", path));
     }
@@ -190,6 +194,169 @@ pub fn get_local_db_stats(path: &str, instance_name: Option<&str>) -> Result<Loc
     })
 }
 
+/// Where `compact_local_db` writes the compacted copy, inside the database directory.
+const COMPACT_DIR: &str = ".compact";
+/// What the replaced data.mdb is renamed to by `swap_compacted_db`.
+const PRE_COMPACT_BACKUP: &str = "data.mdb.pre-compact";
+/// The state of data.mdb the compacted copy was taken from, kept next to the copy.
+const COMPACT_SOURCE: &str = "source.json";
+
+/// Identifies the data.mdb a compacted copy was taken from; the swap is refused once
+/// any of it changes, as the copy would then drop the writes made since.
+#[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SourceState {
+    size: u64,
+    modified_ms: Option<u128>,
+    last_txn_id: usize,
+}
+
+impl SourceState {
+    fn of(data_file: &Path, env: &heed3::Env) -> Self {
+        let metadata = fs::metadata(data_file).ok();
+        SourceState {
+            size: metadata.as_ref().map_or(0, |m| m.len()),
+            modified_ms: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis()),
+            last_txn_id: env.info().last_txn_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CompactionReport {
+    pub db_path: String,
+    pub compacted_path: String,
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    /// Entries per named database in the compacted copy.
+    pub entries: HashMap<String, usize>,
+}
+
+pub(crate) fn open_env(db_path: &Path) -> Result<heed3::Env, String> {
+    unsafe {
+        EnvOpenOptions::new()
+            .max_dbs(200)
+            .max_readers(200)
+            .open(db_path)
+            .map_err(|e| format!("Failed to open database environment {}: {}", db_path.display(), e))
+    }
+}
+
+/// Entries in each named database, read through the main database like the stats are.
+pub(crate) fn entry_counts(env: &heed3::Env) -> Result<HashMap<String, usize>, String> {
+    let txn = env.read_txn().map_err(|e| format!("Failed to start read transaction: {}", e))?;
+    let mut counts = HashMap::new();
+    let Some(main_db) = env.open_database::<Bytes, Bytes>(&txn, None).map_err(|e| e.to_string())? else {
+        return Ok(counts);
+    };
+    for result in main_db.iter(&txn).map_err(|e| e.to_string())? {
+        let (key_bytes, _) = result.map_err(|e| e.to_string())?;
+        let Ok(raw_name) = std::str::from_utf8(key_bytes) else {
+            continue;
+        };
+        let db_name = raw_name.trim_matches('\0');
+        if let Ok(Some(db)) = env.open_database::<Bytes, Bytes>(&txn, Some(db_name)) {
+            let stat = db.stat(&txn).map_err(|e| format!("Failed to read {}: {}", db_name, e))?;
+            counts.insert(db_name.to_string(), stat.entries);
+        }
+    }
+    Ok(counts)
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Writes a compacted copy of the workspace's data.mdb (free pages dropped) next to it
+/// and checks that the copy opens with the same entries. The copy is taken in a read
+/// transaction, so a running instance keeps working; `swap_compacted_db` puts it in place
+/// once the instance is stopped, provided nothing was written in between.
+pub fn compact_local_db(path: &str, instance_name: Option<&str>) -> Result<CompactionReport, String> {
+    let db_path = resolve_db_path(path, instance_name);
+    let data_file = db_path.join("data.mdb");
+    if !data_file.exists() {
+        return Err(format!("No data.mdb found for {}", path));
+    }
+
+    let compact_dir = db_path.join(COMPACT_DIR);
+    if compact_dir.exists() {
+        fs::remove_dir_all(&compact_dir).map_err(|e| format!("Failed to clear {}: {}", compact_dir.display(), e))?;
+    }
+    fs::create_dir_all(&compact_dir).map_err(|e| e.to_string())?;
+
+    let before_bytes = file_size(&data_file);
+    let (entries, source) = {
+        let env = open_env(&db_path)?;
+        // Taken before the copy, so a write racing it makes the swap refuse rather than lose it
+        let source = SourceState::of(&data_file, &env);
+        let entries = entry_counts(&env)?;
+        env.copy_to_path(compact_dir.join("data.mdb"), CompactionOption::Enabled)
+            .map_err(|e| format!("Failed to write the compacted copy: {}", e))?;
+        (entries, source)
+    };
+    let source = serde_json::to_string_pretty(&source).map_err(|e| e.to_string())?;
+    fs::write(compact_dir.join(COMPACT_SOURCE), source).map_err(|e| format!("Failed to record the compacted source: {}", e))?;
+
+    let copied = entry_counts(&open_env(&compact_dir)?)
+        .map_err(|e| format!("The compacted copy does not open cleanly: {}", e))?;
+    // Counts may differ when a running instance wrote in between, but no database may go missing
+    if !entries.keys().all(|name| copied.contains_key(name)) {
+        return Err("The compacted copy is missing databases; data.mdb was left untouched".to_string());
+    }
+
+    Ok(CompactionReport {
+        db_path: db_path.to_string_lossy().into_owned(),
+        compacted_path: compact_dir.join("data.mdb").to_string_lossy().into_owned(),
+        before_bytes,
+        after_bytes: file_size(&compact_dir.join("data.mdb")),
+        entries: copied,
+    })
+}
+
+/// Replaces data.mdb with the copy from `compact_local_db`, keeping the original as
+/// `data.mdb.pre-compact`. The instance must be stopped: a running one would keep
+/// writing to the replaced file. Refused when data.mdb changed since it was compacted.
+pub fn swap_compacted_db(path: &str, instance_name: Option<&str>) -> Result<CompactionReport, String> {
+    let db_path = resolve_db_path(path, instance_name);
+    let compact_dir = db_path.join(COMPACT_DIR);
+    let compacted = compact_dir.join("data.mdb");
+    if !compacted.exists() {
+        return Err("Compact the database before swapping it in".to_string());
+    }
+    let entries = entry_counts(&open_env(&compact_dir)?)
+        .map_err(|e| format!("The compacted copy does not open cleanly: {}", e))?;
+
+    let data_file = db_path.join("data.mdb");
+    let source: SourceState = fs::read_to_string(compact_dir.join(COMPACT_SOURCE)).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .ok_or("The compacted copy does not record what it was taken from; compact the database again")?;
+    if SourceState::of(&data_file, &open_env(&db_path)?) != source {
+        return Err("data.mdb changed since it was compacted; compact it again before swapping".to_string());
+    }
+
+    let before_bytes = file_size(&data_file);
+    let backup = db_path.join(PRE_COMPACT_BACKUP);
+    fs::rename(&data_file, &backup).map_err(|e| format!("Failed to back up data.mdb: {}", e))?;
+    if let Err(e) = fs::rename(&compacted, &data_file) {
+        let _ = fs::rename(&backup, &data_file);
+        return Err(format!("Failed to move the compacted copy into place: {}", e));
+    }
+    let _ = fs::remove_dir_all(&compact_dir);
+    // The lock file describes the old file's readers; LMDB recreates it on open
+    let _ = fs::remove_file(db_path.join("lock.mdb"));
+
+    Ok(CompactionReport {
+        db_path: db_path.to_string_lossy().into_owned(),
+        compacted_path: data_file.to_string_lossy().into_owned(),
+        before_bytes,
+        after_bytes: file_size(&data_file),
+        entries,
+    })
+}
+
 pub fn validate_helix_workspace(path: &str) -> Result<bool, String> {
     let base_path = Path::new(path);
     if !base_path.exists() {
@@ -202,3 +369,58 @@ pub fn validate_helix_workspace(path: &str) -> Result<bool, String> {
         Err("Not a valid Helix workspace: helix.toml not found.".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_and_swap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        {
+            let env = open_env(dir.path()).unwrap();
+            let mut wtxn = env.write_txn().unwrap();
+            let db: Database<Bytes, Bytes> = env.create_database(&mut wtxn, Some("nodes")).unwrap();
+            for i in 0..2000u32 {
+                db.put(&mut wtxn, &i.to_be_bytes(), &[7u8; 256]).unwrap();
+            }
+            wtxn.commit().unwrap();
+            // Freed pages stay in the file until it is compacted
+            let mut wtxn = env.write_txn().unwrap();
+            db.delete_range(&mut wtxn, &(100u32.to_be_bytes().as_slice()..)).unwrap();
+            wtxn.commit().unwrap();
+        }
+
+        let report = compact_local_db(path, None).unwrap();
+        assert_eq!(report.entries.get("nodes"), Some(&100));
+        assert!(report.after_bytes < report.before_bytes);
+
+        let swapped = swap_compacted_db(path, None).unwrap();
+        assert_eq!(swapped.after_bytes, report.after_bytes);
+        assert!(dir.path().join(PRE_COMPACT_BACKUP).exists());
+        assert!(!dir.path().join(COMPACT_DIR).exists());
+        assert_eq!(entry_counts(&open_env(dir.path()).unwrap()).unwrap().get("nodes"), Some(&100));
+    }
+
+    #[test]
+    fn test_swap_refused_after_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let write = |key: u32| {
+            let env = open_env(dir.path()).unwrap();
+            let mut wtxn = env.write_txn().unwrap();
+            let db: Database<Bytes, Bytes> = env.create_database(&mut wtxn, Some("nodes")).unwrap();
+            db.put(&mut wtxn, &key.to_be_bytes(), b"value").unwrap();
+            wtxn.commit().unwrap();
+        };
+        write(1);
+        compact_local_db(path, None).unwrap();
+        write(2);
+
+        let err = swap_compacted_db(path, None).err().unwrap();
+        assert!(err.contains("changed since it was compacted"));
+        assert!(!dir.path().join(PRE_COMPACT_BACKUP).exists());
+        assert_eq!(entry_counts(&open_env(dir.path()).unwrap()).unwrap().get("nodes"), Some(&2));
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// How many notable values to mention per text column.
+const TOP_VALUES: usize = 3;
+/// Columns beyond this are listed by name only.
+const MAX_DESCRIBED_COLUMNS: usize = 12;
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct ColumnSummary {
+    pub name: String,
+    pub kind: String, // "number", "text", "boolean", "mixed" or "object"
+    pub filled: usize,
+    pub distinct: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Most frequent values, most common first.
+    pub top_values: Vec<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct SectionSummary {
+    pub name: Option<String>,
+    pub row_count: usize,
+    pub columns: Vec<ColumnSummary>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct ResultSummary {
+    /// Plain sentences meant to be read aloud by a screen reader.
+    pub text: String,
+    pub sections: Vec<SectionSummary>,
+}
+
+fn kind_of(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "text",
+        serde_json::Value::Bool(_) => "boolean",
+        _ => "object",
+    }
+}
+
+fn display(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn summarize_rows(name: Option<String>, rows: &[serde_json::Value]) -> SectionSummary {
+    // Column order follows first appearance across rows
+    let mut order: Vec<String> = Vec::new();
+    let mut values: HashMap<String, Vec<&serde_json::Value>> = HashMap::new();
+    for row in rows {
+        match row {
+            serde_json::Value::Object(map) => {
+                for (k, v) in map {
+                    if !values.contains_key(k) {
+                        order.push(k.clone());
+                    }
+                    if !v.is_null() {
+                        values.entry(k.clone()).or_default().push(v);
+                    } else {
+                        values.entry(k.clone()).or_default();
+                    }
+                }
+            }
+            scalar => {
+                if !values.contains_key("value") {
+                    order.push("value".to_string());
+                }
+                values.entry("value".to_string()).or_default().push(scalar);
+            }
+        }
+    }
+
+    let columns = order.into_iter().map(|col| {
+        let vals = values.remove(&col).unwrap_or_default();
+        let kinds: BTreeSet<&str> = vals.iter().map(|v| kind_of(v)).collect();
+        let kind = match kinds.len() {
+            0 => "object".to_string(),
+            1 => kinds.iter().next().unwrap().to_string(),
+            _ => "mixed".to_string(),
+        };
+
+        let numbers: Vec<f64> = vals.iter().filter_map(|v| v.as_f64()).collect();
+        let min = numbers.iter().cloned().reduce(f64::min);
+        let max = numbers.iter().cloned().reduce(f64::max);
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for v in &vals {
+            *counts.entry(display(v)).or_insert(0) += 1;
+        }
+        let distinct = counts.len();
+        let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let top_values = if kind == "text" || kind == "boolean" {
+            ranked.into_iter().take(TOP_VALUES).map(|(v, _)| v).collect()
+        } else {
+            Vec::new()
+        };
+
+        ColumnSummary { name: col, kind, filled: vals.len(), distinct, min, max, top_values }
+    }).collect();
+
+    SectionSummary { name, row_count: rows.len(), columns }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 { format!("1 {}", word) } else { format!("{} {}s", n, word) }
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 { format!("{}", n as i64) } else { format!("{:.2}", n) }
+}
+
+fn describe_section(section: &SectionSummary) -> String {
+    let mut sentences = Vec::new();
+    let subject = match &section.name {
+        Some(name) => format!("{} has", name),
+        None => "The result has".to_string(),
+    };
+    sentences.push(format!("{} {} and {}.", subject, plural(section.row_count, "row"), plural(section.columns.len(), "column")));
+
+    if section.columns.is_empty() {
+        return sentences.join(" ");
+    }
+
+    let names: Vec<&str> = section.columns.iter().map(|c| c.name.as_str()).collect();
+    sentences.push(format!("Columns: {}.", names.join(", ")));
+
+    for col in section.columns.iter().take(MAX_DESCRIBED_COLUMNS) {
+        let empty = section.row_count.saturating_sub(col.filled);
+        let mut parts = Vec::new();
+        match (col.min, col.max) {
+            (Some(min), Some(max)) if col.kind == "number" => {
+                parts.push(format!("ranges from {} to {}", format_number(min), format_number(max)));
+            }
+            _ => {}
+        }
+        if !col.top_values.is_empty() && col.distinct < col.filled {
+            parts.push(format!("most common {}", col.top_values.join(", ")));
+        } else if col.distinct == col.filled && col.filled > 1 {
+            parts.push("all values unique".to_string());
+        }
+        if empty > 0 {
+            parts.push(format!("{} empty", empty));
+        }
+        if !parts.is_empty() {
+            sentences.push(format!("{}: {}.", col.name, parts.join("; ")));
+        }
+    }
+    sentences.join(" ")
+}
+
+/// Summarizes a query result: a list of rows, a map of named lists (multi-variable
+/// RETURN), or a single value.
+pub fn summarize_result(result: &serde_json::Value) -> ResultSummary {
+    let sections = match result {
+        serde_json::Value::Array(rows) => vec![summarize_rows(None, rows)],
+        serde_json::Value::Object(map) if map.values().any(|v| v.is_array()) => map.iter()
+            .map(|(name, v)| match v {
+                serde_json::Value::Array(rows) => summarize_rows(Some(name.clone()), rows),
+                single => summarize_rows(Some(name.clone()), std::slice::from_ref(single)),
+            })
+            .collect(),
+        serde_json::Value::Null => Vec::new(),
+        single => vec![summarize_rows(None, std::slice::from_ref(single))],
+    };
+
+    let text = if sections.is_empty() {
+        "The query returned no result.".to_string()
+    } else if matches!(result, serde_json::Value::Number(_) | serde_json::Value::String(_) | serde_json::Value::Bool(_)) {
+        format!("The query returned a single value: {}.", display(result))
+    } else {
+        sections.iter().map(describe_section).collect::<Vec<_>>().join("\n")
+    };
+
+    ResultSummary { text, sections }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_rows() {
+        let result = serde_json::json!([
+            { "name": "Alice", "age": 31, "city": "Berlin" },
+            { "name": "Bob", "age": 27, "city": "Berlin" },
+            { "name": "Carol", "age": 35 },
+        ]);
+        let summary = summarize_result(&result);
+        let section = &summary.sections[0];
+        assert_eq!(section.row_count, 3);
+        let age = section.columns.iter().find(|c| c.name == "age").unwrap();
+        assert_eq!((age.min, age.max), (Some(27.0), Some(35.0)));
+        assert!(summary.text.contains("3 rows and 3 columns"));
+        assert!(summary.text.contains("age: ranges from 27 to 35"));
+        assert!(summary.text.contains("city: most common Berlin; 1 empty"));
+    }
+
+    #[test]
+    fn test_summarize_scalar() {
+        let summary = summarize_result(&serde_json::json!(42));
+        assert_eq!(summary.text, "The query returned a single value: 42.");
+    }
+}
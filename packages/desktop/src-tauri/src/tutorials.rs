@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use helix_db::helixc::parser::{HelixParser, write_to_temp_file};
+use helix_db::helixc::parser::types::{Query, ReturnType};
+use tauri::Manager;
+use crate::hql::processor;
+
+/// Lessons shipped with the app. User lessons in `<config>/tutorials/*.md` are added
+/// after these and replace a bundled lesson with the same id.
+const BUNDLED_LESSONS: &[&str] = &[
+    include_str!("../tutorials/01-first-traversal.md"),
+    include_str!("../tutorials/02-following-edges.md"),
+    include_str!("../tutorials/03-filtering.md"),
+];
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct LessonStep {
+    /// Markdown shown before the exercise.
+    pub instructions: String,
+    pub solution: String,
+    pub expected_result: Option<serde_json::Value>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Lesson {
+    pub id: String,
+    pub title: String,
+    pub order: u32,
+    pub steps: Vec<LessonStep>,
+    /// Markdown after the last exercise, if any.
+    pub outro: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct LessonSummary {
+    pub id: String,
+    pub title: String,
+    pub order: u32,
+    pub step_count: usize,
+    pub completed_steps: Vec<usize>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct AttemptResult {
+    pub passed: bool,
+    pub feedback: String,
+    pub lesson_completed: bool,
+}
+
+/// Completed step indices per lesson id.
+type TutorialProgress = HashMap<String, Vec<usize>>;
+
+/// Parses a lesson: an optional `---` front matter block (`id`, `title`, `order`),
+/// then markdown where each ```` ```hql expected ```` fence is an exercise and an
+/// optional ```` ```json result ```` fence right after it is the expected output.
+pub fn parse_lesson(content: &str, fallback_id: &str) -> Result<Lesson, String> {
+    let mut meta = HashMap::new();
+    let mut body = content;
+    if let Some(rest) = content.trim_start().strip_prefix("---") {
+        let end = rest.find("\n---").ok_or_else(|| format!("Lesson '{}' has unterminated front matter", fallback_id))?;
+        for line in rest[..end].lines() {
+            if let Some((k, v)) = line.split_once(':') {
+                meta.insert(k.trim().to_string(), v.trim().to_string());
+            }
+        }
+        body = rest[end + 4..].trim_start_matches(['-', '\r', '\n']);
+    }
+
+    let mut steps: Vec<LessonStep> = Vec::new();
+    let mut text = String::new();
+    let mut fence: Option<(String, String)> = None; // (info string, content)
+
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if let Some((info, mut block)) = fence.take() {
+            if trimmed == "```" {
+                block = block.trim().to_string();
+                match info.as_str() {
+                    "hql expected" => steps.push(LessonStep {
+                        instructions: std::mem::take(&mut text).trim().to_string(),
+                        solution: block,
+                        expected_result: None,
+                    }),
+                    "json result" => {
+                        let step = steps.last_mut()
+                            .ok_or_else(|| format!("Lesson '{}': a result block must follow an expected query", fallback_id))?;
+                        step.expected_result = Some(serde_json::from_str(&block)
+                            .map_err(|e| format!("Lesson '{}': invalid expected result: {}", fallback_id, e))?);
+                    }
+                    _ => text.push_str(&format!("```{}\n{}\n```\n", info, block)),
+                }
+            } else {
+                block.push_str(line);
+                block.push('\n');
+                fence = Some((info, block));
+            }
+        } else if let Some(info) = trimmed.strip_prefix("```") {
+            fence = Some((info.trim().to_string(), String::new()));
+        } else {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    if steps.is_empty() {
+        return Err(format!("Lesson '{}' has no ```hql expected block", fallback_id));
+    }
+
+    Ok(Lesson {
+        id: meta.get("id").cloned().unwrap_or_else(|| fallback_id.to_string()),
+        title: meta.get("title").cloned().unwrap_or_else(|| fallback_id.to_string()),
+        order: meta.get("order").and_then(|o| o.parse().ok()).unwrap_or(u32::MAX),
+        steps,
+        outro: text.trim().to_string(),
+    })
+}
+
+fn user_lessons_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|p| p.join("tutorials"))
+}
+
+pub fn load_lessons(app: &tauri::AppHandle) -> Vec<Lesson> {
+    let mut lessons: Vec<Lesson> = BUNDLED_LESSONS.iter()
+        .enumerate()
+        .filter_map(|(i, content)| parse_lesson(content, &format!("lesson-{}", i + 1)).ok())
+        .collect();
+
+    if let Some(entries) = user_lessons_dir(app).and_then(|d| fs::read_dir(d).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+            let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|c| parse_lesson(&c, &stem)) {
+                Ok(lesson) => {
+                    lessons.retain(|l| l.id != lesson.id);
+                    lessons.push(lesson);
+                }
+                Err(e) => println!(">>> [Tutorials] Skipping {:?}: {}", path, e),
+            }
+        }
+    }
+
+    lessons.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.id.cmp(&b.id)));
+    lessons
+}
+
+fn progress_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join("tutorial_progress.json"))
+}
+
+pub fn load_progress(app: &tauri::AppHandle) -> TutorialProgress {
+    progress_path(app).ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_progress(app: &tauri::AppHandle, progress: &TutorialProgress) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(progress).map_err(|e| e.to_string())?;
+    fs::write(progress_path(app)?, content).map_err(|e| e.to_string())
+}
+
+pub fn summarize(lessons: &[Lesson], progress: &TutorialProgress) -> Vec<LessonSummary> {
+    lessons.iter()
+        .map(|l| LessonSummary {
+            id: l.id.clone(),
+            title: l.title.clone(),
+            order: l.order,
+            step_count: l.steps.len(),
+            completed_steps: progress.get(&l.id).cloned().unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Parses an attempt or a solution, wrapping bare statements in a `QUERY`.
+fn parse_query(code: &str) -> Result<Query, String> {
+    let code = processor::preprocess_hql(code);
+    let wrapped = if code.trim_start().starts_with("QUERY") {
+        code
+    } else {
+        format!("QUERY TutorialAttempt() => {}", code)
+    };
+    let content = write_to_temp_file(vec![&wrapped]);
+    let mut source = HelixParser::parse_source(&content).map_err(|e| format!("{}", e))?;
+    if source.queries.is_empty() {
+        return Err("no query found".to_string());
+    }
+    Ok(source.queries.swap_remove(0))
+}
+
+/// What a query does, leaving out its name, formatting, the names of its
+/// parameters and the key order of an object RETURN.
+fn query_shape(query: &Query) -> Vec<String> {
+    let params: Vec<String> = query.parameters.iter().map(|p| format!("{:?}", p.name.1)).collect();
+    let normalize = |debug: String| {
+        params
+            .iter()
+            .enumerate()
+            .fold(strip_locations(&debug), |shape, (i, name)| shape.replace(name.as_str(), &format!("\"${}\"", i)))
+    };
+    query
+        .statements
+        .iter()
+        .map(|stmt| normalize(format!("{:?}", stmt.statement)))
+        .chain(query.return_values.iter().map(|ret| normalize(return_shape(ret))))
+        .collect()
+}
+
+fn return_shape(ret: &ReturnType) -> String {
+    match ret {
+        ReturnType::Object(fields) => {
+            let mut fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}: {}", key, return_shape(value))).collect();
+            fields.sort();
+            format!("{{{}}}", fields.join(", "))
+        }
+        ReturnType::Array(rets) => format!("[{}]", rets.iter().map(return_shape).collect::<Vec<_>>().join(", ")),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Removes every `loc: Loc { .. }` from a debug rendering of the AST, so two
+/// queries written on different lines compare equal.
+fn strip_locations(debug: &str) -> String {
+    let mut out = String::with_capacity(debug.len());
+    let mut rest = debug;
+    while let Some(pos) = rest.find(" loc: ") {
+        out.push_str(&rest[..=pos]);
+        rest = &rest[pos + " loc: ".len()..];
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut end = rest.len();
+        for (i, c) in rest.char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' | '(' | '[' => depth += 1,
+                '}' | ')' | ']' if depth == 0 => {
+                    end = i;
+                    break;
+                }
+                '}' | ')' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = i + 1;
+                        break;
+                    }
+                }
+                ',' if depth == 0 => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        rest = &rest[end..];
+        match rest.strip_prefix(", ") {
+            Some(after) => rest = after,
+            None if out.ends_with(", ") => out.truncate(out.len() - 2),
+            None => {}
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn same_query(attempt: &str, solution: &str) -> bool {
+    match (parse_query(attempt), parse_query(solution)) {
+        (Ok(attempt), Ok(solution)) => query_shape(&attempt) == query_shape(&solution),
+        _ => false,
+    }
+}
+
+/// Checks an attempt: it must parse, and either match the expected query statement
+/// for statement or, when the frontend ran it, produce the lesson's expected result.
+pub fn check_attempt(step: &LessonStep, code: &str, result: Option<&serde_json::Value>) -> AttemptResult {
+    if let Err(e) = parse_query(code) {
+        return AttemptResult { passed: false, feedback: format!("This doesn't parse yet: {}", e), lesson_completed: false };
+    }
+
+    let same_query = same_query(code, &step.solution);
+    let same_result = match (&step.expected_result, result) {
+        (Some(expected), Some(actual)) => Some(expected == actual),
+        _ => None,
+    };
+
+    let (passed, feedback) = match (same_query, same_result) {
+        (_, Some(true)) => (true, "Correct! Your query returns the expected result.".to_string()),
+        (true, _) => (true, "Correct!".to_string()),
+        (false, Some(false)) => (false, "Your query runs, but returns a different result than expected.".to_string()),
+        (false, None) => (false, "Your query is valid HQL, but doesn't do what this step asks yet.".to_string()),
+    };
+    AttemptResult { passed, feedback, lesson_completed: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bundled_lessons() {
+        for (i, content) in BUNDLED_LESSONS.iter().enumerate() {
+            let lesson = parse_lesson(content, &format!("lesson-{}", i)).unwrap();
+            assert!(!lesson.steps.is_empty());
+            assert!(!lesson.steps[0].instructions.contains("```hql expected"));
+        }
+        let filtering = parse_lesson(BUNDLED_LESSONS[2], "x").unwrap();
+        assert_eq!(filtering.id, "filtering");
+        assert_eq!(filtering.steps.len(), 2);
+        assert_eq!(filtering.steps[1].expected_result, Some(serde_json::json!(3)));
+    }
+
+    #[test]
+    fn test_same_query_ignores_formatting() {
+        assert!(same_query(
+            "friends <- N<Person>  ::Out<Knows> // friends\nRETURN friends",
+            "QUERY Q() =>\n    friends <- N<Person>::Out<Knows>\n    RETURN friends",
+        ));
+        assert!(!same_query("friends <- N<Person>::Out<Knows>\nRETURN friends", "friends <- N<Person>::In<Knows>\nRETURN friends"));
+    }
+
+    #[test]
+    fn test_same_query_ignores_parameter_names_and_return_key_order() {
+        assert!(same_query(
+            "QUERY Mine(user: ID) =>\n    u <- N<User>(user)\n    RETURN { posts: u::Out<Authored>, author: u }",
+            "QUERY Find(id: ID) =>\n    u <- N<User>(id)\n    RETURN { author: u, posts: u::Out<Authored> }",
+        ));
+        assert!(!same_query(
+            "QUERY Mine(user: ID) =>\n    u <- N<User>(user)\n    RETURN { author: u }",
+            "QUERY Find(id: ID) =>\n    u <- N<User>(id)\n    RETURN { author: u, posts: u::Out<Authored> }",
+        ));
+    }
+
+    #[test]
+    fn test_strip_locations() {
+        assert_eq!(
+            strip_locations(r#"Expression { loc: Loc { span: "a, }", start: Span { line: 1 } }, expr: Identifier("u") }"#),
+            r#"Expression { expr: Identifier("u") }"#,
+        );
+        assert_eq!(strip_locations("Step { step: Out(\"Knows\"), loc: Loc { line: 2 } }"), "Step { step: Out(\"Knows\") }");
+    }
+}
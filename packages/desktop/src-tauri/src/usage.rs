@@ -0,0 +1,339 @@
+//! A local ledger of what each connection has been asked for, per day: queries run,
+//! rows returned and bytes received. Connections may set soft `usageLimits`
+//! (`queriesPerDay`, `rowsPerDay`, `bytesPerDay`); crossing one emits a warning once
+//! per day, since metered cloud instances bill by what exploration pulls.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const LEDGER_FILE: &str = "usage-ledger.json";
+/// Executions since the ledger was last written, one JSON line each.
+const JOURNAL_FILE: &str = "usage-journal.jsonl";
+/// The journal is folded into the ledger once it has this many lines.
+const COMPACT_AFTER: usize = 500;
+/// Days kept per connection; older ones are dropped when a day is added.
+const MAX_DAYS: usize = 90;
+pub const USAGE_WARNING_EVENT: &str = "usage-limit-warning";
+
+/// The ledger of the open config directory, kept in memory once read. Executions
+/// finish concurrently; each appends a journal line rather than rewriting the ledger.
+static STORE: Mutex<Option<Store>> = Mutex::new(None);
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageDay {
+    pub queries: u64,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+impl UsageDay {
+    fn add(&mut self, other: &UsageDay) {
+        self.queries += other.queries;
+        self.rows += other.rows;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Connection URL -> day (`YYYY-MM-DD`, local time) -> usage.
+pub type Ledger = BTreeMap<String, BTreeMap<String, UsageDay>>;
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageLimits {
+    pub queries_per_day: Option<u64>,
+    pub rows_per_day: Option<u64>,
+    pub bytes_per_day: Option<u64>,
+}
+
+impl UsageLimits {
+    pub fn from_connection(connection: Option<&serde_json::Value>) -> Self {
+        connection.and_then(|c| c.get("usageLimits"))
+            .and_then(|limits| serde_json::from_value(limits.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn checks(&self, day: &UsageDay) -> [(&'static str, Option<u64>, u64); 3] {
+        [
+            ("queries", self.queries_per_day, day.queries),
+            ("rows", self.rows_per_day, day.rows),
+            ("bytes", self.bytes_per_day, day.bytes),
+        ]
+    }
+}
+
+/// Sent as `usage-limit-warning` when a day's usage first reaches a soft limit.
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageWarning {
+    pub url: String,
+    /// `queries`, `rows` or `bytes`.
+    pub metric: String,
+    pub limit: u64,
+    pub used: u64,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    pub date: String,
+    #[serde(flatten)]
+    pub usage: UsageDay,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionUsage {
+    pub url: String,
+    pub today: UsageDay,
+    /// Oldest first.
+    pub days: Vec<DailyUsage>,
+    pub total: UsageDay,
+}
+
+fn url_key(url: &str) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Adds `usage` to `url`'s `day`, returning the limits the addition reached.
+pub fn add(ledger: &mut Ledger, url: &str, day: &str, usage: UsageDay, limits: &UsageLimits) -> Vec<UsageWarning> {
+    let url = url_key(url);
+    let days = ledger.entry(url.clone()).or_default();
+    let entry = days.entry(day.to_string()).or_default();
+    let before = *entry;
+    entry.add(&usage);
+    let after = *entry;
+    while days.len() > MAX_DAYS {
+        days.pop_first();
+    }
+
+    limits.checks(&before).into_iter().zip(limits.checks(&after))
+        .filter_map(|((metric, limit, was), (_, _, used))| {
+            let limit = limit?;
+            (was < limit && used >= limit).then(|| UsageWarning { url: url.clone(), metric: metric.to_string(), limit, used })
+        })
+        .collect()
+}
+
+/// `url`'s usage (or every connection's), keeping the last `days` days.
+pub fn summarize(ledger: &Ledger, url: Option<&str>, days: Option<usize>, today: &str) -> Vec<ConnectionUsage> {
+    let wanted = url.map(url_key);
+    ledger.iter()
+        .filter(|(u, _)| wanted.as_ref().is_none_or(|w| w == *u))
+        .map(|(u, by_day)| {
+            let skip = days.map_or(0, |d| by_day.len().saturating_sub(d));
+            let days: Vec<DailyUsage> = by_day.iter().skip(skip)
+                .map(|(date, usage)| DailyUsage { date: date.clone(), usage: *usage })
+                .collect();
+            let mut total = UsageDay::default();
+            days.iter().for_each(|d| total.add(&d.usage));
+            ConnectionUsage { url: u.clone(), today: by_day.get(today).copied().unwrap_or_default(), days, total }
+        })
+        .collect()
+}
+
+/// One execution, as a journal line.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JournalEntry {
+    url: String,
+    day: String,
+    #[serde(flatten)]
+    usage: UsageDay,
+}
+
+/// The ledger file plus the journal written since.
+struct Store {
+    dir: PathBuf,
+    ledger: Ledger,
+    journal_lines: usize,
+}
+
+/// Moves an unreadable file aside, so the next write does not replace what it held.
+fn back_up(path: &Path, error: &str) -> Result<(), String> {
+    let backup = path.with_extension(format!("corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S")));
+    println!(">>> [Usage] {:?} is unreadable ({}); moved to {:?}", path, error, backup);
+    fs::rename(path, &backup).map_err(|e| format!("Could not back up {:?}: {}", path, e))
+}
+
+impl Store {
+    fn open(dir: &Path) -> Result<Self, String> {
+        let mut store = Store { dir: dir.to_path_buf(), ledger: Ledger::new(), journal_lines: 0 };
+        let ledger_path = dir.join(LEDGER_FILE);
+        if ledger_path.exists() {
+            let content = fs::read_to_string(&ledger_path).map_err(|e| e.to_string())?;
+            match serde_json::from_str(&content) {
+                Ok(ledger) => store.ledger = ledger,
+                Err(e) => back_up(&ledger_path, &e.to_string())?,
+            }
+        }
+        if let Ok(content) = fs::read_to_string(dir.join(JOURNAL_FILE)) {
+            for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                // A line cut short by a crash is dropped; the others still count
+                if let Ok(entry) = serde_json::from_str::<JournalEntry>(line) {
+                    add(&mut store.ledger, &entry.url, &entry.day, entry.usage, &UsageLimits::default());
+                }
+                store.journal_lines += 1;
+            }
+            // New lines appended to a cut one would be lost with it
+            if !content.is_empty() && !content.ends_with('\n') {
+                store.compact()?;
+            }
+        }
+        Ok(store)
+    }
+
+    fn record(&mut self, url: &str, day: &str, usage: UsageDay, limits: &UsageLimits) -> Result<Vec<UsageWarning>, String> {
+        let warnings = add(&mut self.ledger, url, day, usage, limits);
+        if self.journal_lines + 1 >= COMPACT_AFTER {
+            self.compact()?;
+            return Ok(warnings);
+        }
+        let line = serde_json::to_string(&JournalEntry { url: url_key(url), day: day.to_string(), usage }).map_err(|e| e.to_string())?;
+        let mut journal = fs::OpenOptions::new().create(true).append(true).open(self.dir.join(JOURNAL_FILE)).map_err(|e| e.to_string())?;
+        writeln!(journal, "{}", line).map_err(|e| e.to_string())?;
+        self.journal_lines += 1;
+        Ok(warnings)
+    }
+
+    /// Writes the whole ledger and starts an empty journal.
+    fn compact(&mut self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.ledger).map_err(|e| e.to_string())?;
+        let temp = self.dir.join(format!("{}.tmp", LEDGER_FILE));
+        fs::write(&temp, content).map_err(|e| e.to_string())?;
+        fs::rename(&temp, self.dir.join(LEDGER_FILE)).map_err(|e| e.to_string())?;
+        let journal = self.dir.join(JOURNAL_FILE);
+        if journal.exists() {
+            fs::remove_file(journal).map_err(|e| e.to_string())?;
+        }
+        self.journal_lines = 0;
+        Ok(())
+    }
+}
+
+fn config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Runs `f` on the store for `app`'s config directory, opening it first if needed.
+fn with_store<T>(app: &tauri::AppHandle, f: impl FnOnce(&mut Store) -> Result<T, String>) -> Result<T, String> {
+    let dir = config_dir(app)?;
+    let mut store = STORE.lock().unwrap();
+    if store.as_ref().is_none_or(|s| s.dir != dir) {
+        *store = Some(Store::open(&dir)?);
+    }
+    f(store.as_mut().unwrap())
+}
+
+pub fn load(app: &tauri::AppHandle) -> Result<Ledger, String> {
+    with_store(app, |store| Ok(store.ledger.clone()))
+}
+
+/// Records one execution against `url` and emits a warning for each soft limit it reached.
+pub fn record(app: &tauri::AppHandle, url: &str, rows: usize, bytes: usize) -> Result<(), String> {
+    let limits = UsageLimits::from_connection(crate::policy::find_connection(app, Some(url)).as_ref());
+    let usage = UsageDay { queries: 1, rows: rows as u64, bytes: bytes as u64 };
+    let warnings = with_store(app, |store| store.record(url, &today(), usage, &limits))?;
+    for warning in warnings {
+        let _ = app.emit(USAGE_WARNING_EVENT, warning);
+    }
+    Ok(())
+}
+
+/// Records a finished result: its rows, when it is an array, and its size as JSON.
+pub fn record_result(app: &tauri::AppHandle, url: &str, result: Option<&serde_json::Value>) {
+    let rows = result.and_then(|v| v.as_array()).map_or(0, |rows| rows.len());
+    let bytes = result.and_then(|v| serde_json::to_vec(v).ok()).map_or(0, |b| b.len());
+    if let Err(e) = record(app, url, rows, bytes) {
+        println!(">>> [Usage] Failed to record usage: {}", e);
+    }
+}
+
+pub fn usage_stats(app: &tauri::AppHandle, url: Option<&str>, days: Option<usize>) -> Result<Vec<ConnectionUsage>, String> {
+    with_store(app, |store| Ok(summarize(&store.ledger, url, days, &today())))
+}
+
+/// Forgets `url`'s usage, or everyone's.
+pub fn reset(app: &tauri::AppHandle, url: Option<&str>) -> Result<(), String> {
+    with_store(app, |store| {
+        match url {
+            Some(url) => {
+                store.ledger.remove(&url_key(url));
+            }
+            None => store.ledger.clear(),
+        }
+        store.compact()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_limits_and_summary() {
+        let limits = UsageLimits { queries_per_day: Some(2), rows_per_day: None, bytes_per_day: Some(1000) };
+        let mut ledger = Ledger::new();
+        let usage = |rows, bytes| UsageDay { queries: 1, rows, bytes };
+        assert!(add(&mut ledger, "http://h:6969/", "2026-01-01", usage(10, 400), &limits).is_empty());
+
+        let warnings = add(&mut ledger, "http://h:6969", "2026-01-01", usage(5, 700), &limits);
+        let metrics: Vec<&str> = warnings.iter().map(|w| w.metric.as_str()).collect();
+        assert_eq!(metrics, vec!["queries", "bytes"]);
+        assert_eq!(warnings[1].used, 1100);
+        // Already past the limits: no repeat warning the same day, but a new day starts over
+        assert!(add(&mut ledger, "http://h:6969", "2026-01-01", usage(1, 1), &limits).is_empty());
+        assert!(add(&mut ledger, "http://h:6969", "2026-01-02", usage(1, 1), &limits).is_empty());
+
+        let summary = summarize(&ledger, Some("http://h:6969/"), Some(1), "2026-01-01");
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].today, UsageDay { queries: 3, rows: 16, bytes: 1101 });
+        assert_eq!(summary[0].days.len(), 1);
+        assert_eq!(summary[0].total, UsageDay { queries: 1, rows: 1, bytes: 1 });
+
+        for day in 0..MAX_DAYS + 5 {
+            add(&mut ledger, "http://other", &format!("d{:03}", day), usage(0, 0), &limits);
+        }
+        assert_eq!(ledger["http://other"].len(), MAX_DAYS);
+        assert!(ledger["http://other"].contains_key(&format!("d{:03}", MAX_DAYS + 4)));
+    }
+
+    #[test]
+    fn test_store_journal_and_corrupt_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(LEDGER_FILE), "{ not json").unwrap();
+        let mut store = Store::open(dir.path()).unwrap();
+        // The unreadable ledger is kept aside, not overwritten
+        let backups = fs::read_dir(dir.path()).unwrap()
+            .filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().contains("corrupt-"))
+            .count();
+        assert_eq!(backups, 1);
+        assert!(store.ledger.is_empty());
+
+        let usage = UsageDay { queries: 1, rows: 2, bytes: 3 };
+        store.record("http://h:6969/", "2026-01-01", usage, &UsageLimits::default()).unwrap();
+        store.record("http://h:6969", "2026-01-01", usage, &UsageLimits::default()).unwrap();
+        assert!(!dir.path().join(LEDGER_FILE).exists());
+        fs::OpenOptions::new().append(true).open(dir.path().join(JOURNAL_FILE)).unwrap()
+            .write_all(b"{\"url\":\"http://h:69").unwrap();
+
+        let mut reopened = Store::open(dir.path()).unwrap();
+        assert_eq!(reopened.ledger["http://h:6969"]["2026-01-01"], UsageDay { queries: 2, rows: 4, bytes: 6 });
+        for _ in 0..COMPACT_AFTER {
+            reopened.record("http://h:6969", "2026-01-02", usage, &UsageLimits::default()).unwrap();
+        }
+        assert!(dir.path().join(LEDGER_FILE).exists());
+        let compacted = Store::open(dir.path()).unwrap();
+        assert_eq!(compacted.ledger["http://h:6969"]["2026-01-02"].queries, COMPACT_AFTER as u64);
+        assert_eq!(compacted.ledger["http://h:6969"]["2026-01-01"].queries, 2);
+    }
+}
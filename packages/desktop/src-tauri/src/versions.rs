@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::PathBuf;
+use tauri::Manager;
+
+/// Versions kept per worksheet; the oldest are dropped first.
+const MAX_VERSIONS: usize = 50;
+
+/// One snapshot of a worksheet. `id` increases per worksheet and is never reused.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorksheetVersion {
+    pub id: u64,
+    pub created_at: String,
+    /// "execute" or "save".
+    pub reason: String,
+    pub code: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+}
+
+/// Listing entry without the code, so the timeline stays cheap to load.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionSummary {
+    pub id: u64,
+    pub created_at: String,
+    pub reason: String,
+    pub lines: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct VersionHistory {
+    #[serde(default)]
+    pub versions: Vec<WorksheetVersion>,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+impl VersionHistory {
+    /// Appends a snapshot unless the code and params are unchanged since the last one.
+    /// Returns the new version, or `None` when nothing was recorded.
+    pub fn record(&mut self, reason: &str, code: &str, params: Option<serde_json::Value>) -> Option<WorksheetVersion> {
+        if let Some(last) = self.versions.last() {
+            if last.code == code && last.params == params {
+                return None;
+            }
+        }
+        let version = WorksheetVersion {
+            id: self.versions.last().map(|v| v.id + 1).unwrap_or(1),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            reason: reason.to_string(),
+            code: code.to_string(),
+            params,
+        };
+        self.versions.push(version.clone());
+        if self.versions.len() > MAX_VERSIONS {
+            let excess = self.versions.len() - MAX_VERSIONS;
+            self.versions.drain(..excess);
+        }
+        Some(version)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&WorksheetVersion> {
+        self.versions.iter().find(|v| v.id == id)
+    }
+
+    /// Newest first.
+    pub fn summaries(&self) -> Vec<VersionSummary> {
+        self.versions.iter().rev()
+            .map(|v| VersionSummary {
+                id: v.id,
+                created_at: v.created_at.clone(),
+                reason: v.reason.clone(),
+                lines: v.code.lines().count(),
+            })
+            .collect()
+    }
+}
+
+/// Line diff from `old` to `new` (longest common subsequence).
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    diff_tokens(&a, &b).into_iter()
+        .map(|(kind, text)| DiffLine { kind, text: text.to_string() })
+        .collect()
+}
+
+/// Diff of two token sequences (longest common subsequence), removals before additions.
+pub(crate) fn diff_tokens<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(DiffKind, &'a str)> {
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            diff.push((DiffKind::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push((DiffKind::Removed, a[i]));
+            i += 1;
+        } else {
+            diff.push((DiffKind::Added, b[j]));
+            j += 1;
+        }
+    }
+    diff.extend(a[i..].iter().map(|t| (DiffKind::Removed, *t)));
+    diff.extend(b[j..].iter().map(|t| (DiffKind::Added, *t)));
+    diff
+}
+
+/// Worksheet ids come from the frontend, so only filename-safe characters are kept.
+fn history_path(app: &tauri::AppHandle, worksheet_id: &str) -> Result<PathBuf, String> {
+    let safe: String = worksheet_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if safe.is_empty() {
+        return Err("Worksheet id is empty".to_string());
+    }
+    let dir = app.path().app_config_dir()
+        .map_err(|e| format!("Could not find config directory: {}", e))?
+        .join("versions");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(format!("{}.json", safe)))
+}
+
+pub fn load_history(app: &tauri::AppHandle, worksheet_id: &str) -> Result<VersionHistory, String> {
+    let path = history_path(app, worksheet_id)?;
+    if !path.exists() {
+        return Ok(VersionHistory::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid version history: {}", e))
+}
+
+pub fn save_history(app: &tauri::AppHandle, worksheet_id: &str, history: &VersionHistory) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(history).map_err(|e| e.to_string())?;
+    fs::write(history_path(app, worksheet_id)?, content).map_err(|e| e.to_string())
+}
+
+pub fn snapshot(app: &tauri::AppHandle, worksheet_id: &str, reason: &str, code: &str, params: Option<serde_json::Value>) -> Result<Option<WorksheetVersion>, String> {
+    let mut history = load_history(app, worksheet_id)?;
+    let version = history.record(reason, code, params);
+    if version.is_some() {
+        save_history(app, worksheet_id, &history)?;
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_skips_duplicates_and_is_bounded() {
+        let mut history = VersionHistory::default();
+        assert!(history.record("execute", "N<User>", None).is_some());
+        assert!(history.record("save", "N<User>", None).is_none());
+        for i in 0..MAX_VERSIONS + 5 {
+            history.record("execute", &format!("N<User>::RANGE(0, {})", i), None);
+        }
+        assert_eq!(history.versions.len(), MAX_VERSIONS);
+        assert_eq!(history.summaries()[0].id, MAX_VERSIONS as u64 + 6);
+        assert!(history.get(1).is_none());
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc", "a\nx\nc\nd");
+        let kinds: Vec<_> = diff.iter().map(|d| (d.kind.clone(), d.text.as_str())).collect();
+        assert_eq!(kinds, vec![
+            (DiffKind::Same, "a"),
+            (DiffKind::Removed, "b"),
+            (DiffKind::Added, "x"),
+            (DiffKind::Same, "c"),
+            (DiffKind::Added, "d"),
+        ]);
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{Manager, Monitor, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, Window};
+
+/// Never restore a window smaller than this (matches minWidth/minHeight in tauri.conf.json).
+const MIN_WIDTH: u32 = 800;
+const MIN_HEIGHT: u32 = 350;
+/// How much of the title bar must be on a monitor for a saved position to be reused.
+const MIN_VISIBLE: i32 = 100;
+
+/// Geometry of one window in physical pixels, keyed by window label in `window_state.json`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+    /// Name of the monitor the window was on when saved.
+    #[serde(default)]
+    pub monitor: Option<String>,
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+}
+
+fn default_zoom() -> f64 {
+    1.0
+}
+
+type WindowStates = HashMap<String, WindowState>;
+
+fn state_path<R: Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| format!("Could not find config directory: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join("window_state.json"))
+}
+
+fn load_states<R: Runtime>(app: &tauri::AppHandle<R>) -> WindowStates {
+    state_path(app).ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn save_states<R: Runtime>(app: &tauri::AppHandle<R>, states: &WindowStates) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(states).map_err(|e| e.to_string())?;
+    fs::write(state_path(app)?, content).map_err(|e| e.to_string())
+}
+
+fn update_state<R: Runtime, F>(app: &tauri::AppHandle<R>, label: &str, update: F) -> Result<(), String>
+where
+    F: FnOnce(Option<&WindowState>) -> Option<WindowState>,
+{
+    let mut states = load_states(app);
+    if let Some(state) = update(states.get(label)) {
+        states.insert(label.to_string(), state);
+        save_states(app, &states)?;
+    }
+    Ok(())
+}
+
+/// Records the window's geometry. While maximized only the flag is updated, so
+/// un-maximizing after the next launch returns to the previous normal size.
+pub fn save_window_state<R: Runtime>(window: &Window<R>) -> Result<(), String> {
+    let maximized = window.is_maximized().map_err(|e| e.to_string())?;
+    let minimized = window.is_minimized().unwrap_or(false);
+    let position = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.inner_size().map_err(|e| e.to_string())?;
+    let monitor = window.current_monitor().ok().flatten().and_then(|m| m.name().cloned());
+
+    update_state(window.app_handle(), window.label(), |previous| {
+        let zoom = previous.map(|p| p.zoom).unwrap_or_else(default_zoom);
+        match previous {
+            Some(prev) if maximized || minimized => Some(WindowState { maximized, zoom, ..prev.clone() }),
+            _ if minimized => None,
+            _ => Some(WindowState {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+                monitor,
+                zoom,
+            }),
+        }
+    })
+}
+
+/// True when the title bar area of the saved rectangle lands on `monitor`.
+fn is_visible_on(state: &WindowState, monitor: &Monitor) -> bool {
+    let pos = monitor.position();
+    let size = monitor.size();
+    let (left, top) = (pos.x, pos.y);
+    let (right, bottom) = (pos.x + size.width as i32, pos.y + size.height as i32);
+    state.x + MIN_VISIBLE.min(state.width as i32) <= right
+        && state.x + state.width as i32 - MIN_VISIBLE.min(state.width as i32) >= left
+        && state.y >= top
+        && state.y + MIN_VISIBLE / 2 <= bottom
+}
+
+/// Restores saved geometry, maximized state and zoom. If the saved monitor is gone or
+/// the window would be off-screen, the saved size is kept (clamped) and the window
+/// is centered on the primary monitor instead.
+pub fn restore_window_state<R: Runtime>(window: &WebviewWindow<R>) -> Result<(), String> {
+    let Some(state) = load_states(window.app_handle()).remove(window.label()) else {
+        return Ok(());
+    };
+    let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+
+    let target = monitors.iter()
+        .find(|m| state.monitor.is_some() && m.name() == state.monitor.as_ref() && is_visible_on(&state, m))
+        .or_else(|| monitors.iter().find(|m| is_visible_on(&state, m)));
+
+    match target {
+        Some(_) => {
+            window.set_size(PhysicalSize::new(state.width.max(MIN_WIDTH), state.height.max(MIN_HEIGHT))).map_err(|e| e.to_string())?;
+            window.set_position(PhysicalPosition::new(state.x, state.y)).map_err(|e| e.to_string())?;
+        }
+        None => {
+            let fallback = window.primary_monitor().ok().flatten().or_else(|| monitors.into_iter().next());
+            let (width, height) = match &fallback {
+                Some(m) => (state.width.min(m.size().width), state.height.min(m.size().height)),
+                None => (state.width, state.height),
+            };
+            window.set_size(PhysicalSize::new(width.max(MIN_WIDTH), height.max(MIN_HEIGHT))).map_err(|e| e.to_string())?;
+            window.center().map_err(|e| e.to_string())?;
+        }
+    }
+
+    if state.maximized {
+        window.maximize().map_err(|e| e.to_string())?;
+    }
+    if (state.zoom - 1.0).abs() > f64::EPSILON {
+        window.set_zoom(state.zoom).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Applies a webview zoom factor and remembers it for the window.
+pub fn set_zoom<R: Runtime>(window: &WebviewWindow<R>, zoom: f64) -> Result<(), String> {
+    let zoom = zoom.clamp(0.5, 3.0);
+    window.set_zoom(zoom).map_err(|e| e.to_string())?;
+    if !load_states(window.app_handle()).contains_key(window.label()) {
+        save_window_state(&window.as_ref().window())?;
+    }
+    update_state(window.app_handle(), window.label(), |previous| {
+        previous.map(|p| WindowState { zoom, ..p.clone() })
+    })
+}
@@ -0,0 +1,100 @@
+//! Checks a connection's `localPath` against the disk, since projects get moved and
+//! deleted while connections.json still points at them, and proposes where the project
+//! is now using the Docker mount of the instance serving the connection's port.
+
+use std::path::Path;
+use crate::config;
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum WorkspaceStatus {
+    Valid,
+    /// The connection has no `localPath`.
+    Unset,
+    /// The path no longer exists.
+    Missing,
+    /// The path exists but holds no helix.toml or helix.hx.
+    NotAProject,
+}
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceCheck {
+    pub connection_id: String,
+    pub path: Option<String>,
+    pub status: WorkspaceStatus,
+    /// Where the running instance's project is, when that is somewhere else.
+    pub suggestion: Option<String>,
+    /// Whether the suggestion was saved as the new `localPath`.
+    pub applied: bool,
+}
+
+pub fn status_of(path: Option<&str>) -> WorkspaceStatus {
+    match path.map(Path::new) {
+        None => WorkspaceStatus::Unset,
+        Some(path) if !path.is_dir() => WorkspaceStatus::Missing,
+        Some(path) if !config::is_project_dir(path) => WorkspaceStatus::NotAProject,
+        Some(_) => WorkspaceStatus::Valid,
+    }
+}
+
+/// `detected` unless it is where the connection already points.
+fn suggestion(path: Option<&str>, detected: Option<String>) -> Option<String> {
+    let same = |a: &str, b: &str| {
+        let canonical = |p: &str| Path::new(p).canonicalize().unwrap_or_else(|_| p.into());
+        canonical(a) == canonical(b)
+    };
+    detected.filter(|d| !path.is_some_and(|p| same(p, d)))
+}
+
+/// Re-checks the `localPath` of the connection `connection_id`. Local connections get the
+/// project Docker reports for their port as a suggestion; with `apply`, a suggestion
+/// replaces a path that is not valid.
+pub fn revalidate(app: &tauri::AppHandle, connection_id: &str, apply: bool) -> Result<WorkspaceCheck, String> {
+    let connections = config::load_connection_config(app.clone())?;
+    let connection = connections.connection(connection_id)
+        .ok_or_else(|| format!("No connection '{}'", connection_id))?;
+    let field = |k: &str| connection.get(k).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    let path = field("localPath").map(String::from);
+    let status = status_of(path.as_deref());
+    let detected = match field("type") {
+        Some("cloud") => None,
+        _ => config::try_detect_from_docker(field("port").unwrap_or("6969")),
+    };
+    let suggestion = suggestion(path.as_deref(), detected);
+
+    let applied = apply && status != WorkspaceStatus::Valid && suggestion.is_some();
+    if applied {
+        let new_path = suggestion.clone().unwrap_or_default();
+        config::update_connection_config(app, |config| {
+            let connection = config.connections.iter_mut()
+                .find(|c| c.get("id").and_then(|v| v.as_str()) == Some(connection_id))
+                .and_then(|c| c.as_object_mut())
+                .ok_or_else(|| format!("No connection '{}'", connection_id))?;
+            connection.insert("localPath".to_string(), new_path.into());
+            Ok(())
+        })?;
+    }
+    Ok(WorkspaceCheck { connection_id: connection_id.to_string(), path, status, suggestion, applied })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(status_of(None), WorkspaceStatus::Unset);
+        assert_eq!(status_of(Some(path)), WorkspaceStatus::NotAProject);
+        std::fs::write(dir.path().join("helix.toml"), "").unwrap();
+        assert_eq!(status_of(Some(path)), WorkspaceStatus::Valid);
+        let gone = dir.path().join("moved");
+        assert_eq!(status_of(gone.to_str()), WorkspaceStatus::Missing);
+
+        assert_eq!(suggestion(Some(path), Some(format!("{}/", path))), None);
+        assert_eq!(suggestion(gone.to_str(), Some(path.to_string())).as_deref(), Some(path));
+        assert_eq!(suggestion(None, None), None);
+    }
+}